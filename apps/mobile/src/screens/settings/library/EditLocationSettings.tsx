@@ -55,6 +55,7 @@ const EditLocationSettingsScreen = ({
 			sync_preview_media: data.syncPreviewMedia,
 			generate_preview_media: data.generatePreviewMedia,
 			hidden: data.hidden,
+			respect_ignore_files: null,
 			indexer_rules_ids: []
 		})
 	);
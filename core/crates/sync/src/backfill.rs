@@ -1,9 +1,9 @@
-use std::future::Future;
+use std::{collections::HashMap, future::Future, hash::Hash};
 
 use sd_prisma::{
 	prisma::{
-		file_path, label, label_on_object, location, media_data, object, tag, tag_on_object,
-		PrismaClient, SortOrder,
+		album, file_path, label, label_on_object, location, media_data, object, object_in_album,
+		object_in_space, space, space_link, tag, tag_on_object, tag_rule, PrismaClient, SortOrder,
 	},
 	prisma_sync,
 };
@@ -70,6 +70,10 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 													sync_preview_media
 												),
 												option_sync_entry!(l.hidden, hidden),
+												option_sync_entry!(
+													l.respect_ignore_files,
+													respect_ignore_files
+												),
 												option_sync_entry!(l.date_created, date_created),
 											],
 										),
@@ -248,13 +252,16 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 					db.tag()
 						.find_many(vec![tag::id::gt(cursor)])
 						.order_by(tag::id::order(SortOrder::Asc))
+						.include(tag::include!({ parent: select { pub_id } }))
 						.exec()
 				},
 				|tag| tag.id,
 				|tags| {
 					db.crdt_operation()
 						.create_many(
-							tags.into_iter()
+							// Tags are fetched in a single page, so this orders all of them
+							parents_first(tags, |t| t.id, |t| t.parent_id)
+								.into_iter()
 								.flat_map(|t| {
 									sync.shared_create(
 										prisma_sync::tag::SyncId { pub_id: t.pub_id },
@@ -263,6 +270,14 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 											[
 												t.name.map(|v| (tag::name::NAME, msgpack!(v))),
 												t.color.map(|v| (tag::color::NAME, msgpack!(v))),
+												t.parent.map(|p| {
+													(
+														tag::parent::NAME,
+														msgpack!(prisma_sync::tag::SyncId {
+															pub_id: p.pub_id
+														}),
+													)
+												}),
 												t.date_created.map(|v| {
 													(tag::date_created::NAME, msgpack!(v))
 												}),
@@ -281,6 +296,50 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 			)
 			.await?;
 
+			paginate(
+				|cursor| {
+					db.tag_rule()
+						.find_many(vec![tag_rule::id::gt(cursor)])
+						.order_by(tag_rule::id::order(SortOrder::Asc))
+						.include(tag_rule::include!({ tag: select { pub_id } }))
+						.exec()
+				},
+				|rule| rule.id,
+				|rules| {
+					db.crdt_operation()
+						.create_many(
+							rules
+								.into_iter()
+								.flat_map(|r| {
+									use tag_rule::*;
+
+									sync.shared_create(
+										prisma_sync::tag_rule::SyncId { pub_id: r.pub_id },
+										chain_optional_iter(
+											[],
+											[
+												option_sync_entry!(r.name, name),
+												option_sync_entry!(r.conditions, conditions),
+												option_sync_entry!(
+													r.tag.map(|t| prisma_sync::tag::SyncId {
+														pub_id: t.pub_id
+													}),
+													tag
+												),
+												option_sync_entry!(r.date_created, date_created),
+												option_sync_entry!(r.date_modified, date_modified),
+											],
+										),
+									)
+								})
+								.map(|o| crdt_op_unchecked_db(&o, instance_id))
+								.collect(),
+						)
+						.exec()
+				},
+			)
+			.await?;
+
 			paginate_relation(
 				|group_id, item_id| {
 					db.tag_on_object()
@@ -329,6 +388,253 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 			)
 			.await?;
 
+			paginate(
+				|cursor| {
+					db.album()
+						.find_many(vec![album::id::gt(cursor)])
+						.order_by(album::id::order(SortOrder::Asc))
+						.include(album::include!({ cover: select { pub_id } }))
+						.exec()
+				},
+				|album| album.id,
+				|albums| {
+					db.crdt_operation()
+						.create_many(
+							albums
+								.into_iter()
+								.flat_map(|a| {
+									use album::*;
+
+									sync.shared_create(
+										prisma_sync::album::SyncId { pub_id: a.pub_id },
+										chain_optional_iter(
+											[],
+											[
+												option_sync_entry!(a.name, name),
+												option_sync_entry!(a.is_hidden, is_hidden),
+												option_sync_entry!(
+													a.cover.map(|o| prisma_sync::object::SyncId {
+														pub_id: o.pub_id
+													}),
+													cover
+												),
+												option_sync_entry!(a.date_created, date_created),
+												option_sync_entry!(a.date_modified, date_modified),
+											],
+										),
+									)
+								})
+								.map(|o| crdt_op_unchecked_db(&o, instance_id))
+								.collect(),
+						)
+						.exec()
+				},
+			)
+			.await?;
+
+			paginate_relation(
+				|group_id, item_id| {
+					db.object_in_album()
+						.find_many(vec![
+							object_in_album::album_id::gt(group_id),
+							object_in_album::object_id::gt(item_id),
+						])
+						.order_by(object_in_album::album_id::order(SortOrder::Asc))
+						.order_by(object_in_album::object_id::order(SortOrder::Asc))
+						.include(object_in_album::include!({
+							album: select { pub_id }
+							object: select { pub_id }
+						}))
+						.exec()
+				},
+				|o_a| (o_a.album_id, o_a.object_id),
+				|objects_in_album| {
+					db.crdt_operation()
+						.create_many(
+							objects_in_album
+								.into_iter()
+								.flat_map(|o_a| {
+									sync.relation_create(
+										prisma_sync::object_in_album::SyncId {
+											album: prisma_sync::album::SyncId {
+												pub_id: o_a.album.pub_id,
+											},
+											object: prisma_sync::object::SyncId {
+												pub_id: o_a.object.pub_id,
+											},
+										},
+										chain_optional_iter(
+											[],
+											[
+												option_sync_entry!(
+													o_a.position,
+													object_in_album::position
+												),
+												option_sync_entry!(
+													o_a.date_created,
+													object_in_album::date_created
+												),
+											],
+										),
+									)
+								})
+								.map(|o| crdt_op_unchecked_db(&o, instance_id))
+								.collect(),
+						)
+						.exec()
+				},
+			)
+			.await?;
+
+			paginate(
+				|cursor| {
+					db.space()
+						.find_many(vec![space::id::gt(cursor)])
+						.order_by(space::id::order(SortOrder::Asc))
+						.exec()
+				},
+				|space| space.id,
+				|spaces| {
+					db.crdt_operation()
+						.create_many(
+							spaces
+								.into_iter()
+								.flat_map(|s| {
+									use space::*;
+
+									sync.shared_create(
+										prisma_sync::space::SyncId { pub_id: s.pub_id },
+										chain_optional_iter(
+											[],
+											[
+												option_sync_entry!(s.name, name),
+												option_sync_entry!(s.description, description),
+												option_sync_entry!(s.date_created, date_created),
+												option_sync_entry!(s.date_modified, date_modified),
+											],
+										),
+									)
+								})
+								.map(|o| crdt_op_unchecked_db(&o, instance_id))
+								.collect(),
+						)
+						.exec()
+				},
+			)
+			.await?;
+
+			paginate_relation(
+				|group_id, item_id| {
+					db.object_in_space()
+						.find_many(vec![
+							object_in_space::space_id::gt(group_id),
+							object_in_space::object_id::gt(item_id),
+						])
+						.order_by(object_in_space::space_id::order(SortOrder::Asc))
+						.order_by(object_in_space::object_id::order(SortOrder::Asc))
+						.include(object_in_space::include!({
+							space: select { pub_id }
+							object: select { pub_id }
+						}))
+						.exec()
+				},
+				|o_s| (o_s.space_id, o_s.object_id),
+				|objects_in_space| {
+					db.crdt_operation()
+						.create_many(
+							objects_in_space
+								.into_iter()
+								.flat_map(|o_s| {
+									use object_in_space::*;
+
+									sync.relation_create(
+										prisma_sync::object_in_space::SyncId {
+											space: prisma_sync::space::SyncId {
+												pub_id: o_s.space.pub_id,
+											},
+											object: prisma_sync::object::SyncId {
+												pub_id: o_s.object.pub_id,
+											},
+										},
+										chain_optional_iter(
+											[],
+											[
+												option_sync_entry!(o_s.x, x),
+												option_sync_entry!(o_s.y, y),
+												option_sync_entry!(o_s.width, width),
+												option_sync_entry!(o_s.height, height),
+												option_sync_entry!(o_s.z_index, z_index),
+												option_sync_entry!(o_s.date_created, date_created),
+											],
+										),
+									)
+								})
+								.map(|o| crdt_op_unchecked_db(&o, instance_id))
+								.collect(),
+						)
+						.exec()
+				},
+			)
+			.await?;
+
+			paginate(
+				|cursor| {
+					db.space_link()
+						.find_many(vec![space_link::id::gt(cursor)])
+						.order_by(space_link::id::order(SortOrder::Asc))
+						.include(space_link::include!({
+							space: select { pub_id }
+							from: select { pub_id }
+							to: select { pub_id }
+						}))
+						.exec()
+				},
+				|link| link.id,
+				|links| {
+					db.crdt_operation()
+						.create_many(
+							links
+								.into_iter()
+								.flat_map(|l| {
+									use space_link::*;
+
+									sync.shared_create(
+										prisma_sync::space_link::SyncId { pub_id: l.pub_id },
+										chain_optional_iter(
+											[],
+											[
+												option_sync_entry!(l.label, label),
+												option_sync_entry!(
+													l.space.map(|s| prisma_sync::space::SyncId {
+														pub_id: s.pub_id
+													}),
+													space
+												),
+												option_sync_entry!(
+													l.from.map(|o| prisma_sync::object::SyncId {
+														pub_id: o.pub_id
+													}),
+													from
+												),
+												option_sync_entry!(
+													l.to.map(|o| prisma_sync::object::SyncId {
+														pub_id: o.pub_id
+													}),
+													to
+												),
+												option_sync_entry!(l.date_created, date_created),
+											],
+										),
+									)
+								})
+								.map(|o| crdt_op_unchecked_db(&o, instance_id))
+								.collect(),
+						)
+						.exec()
+				},
+			)
+			.await?;
+
 			paginate(
 				|cursor| {
 					db.label()
@@ -409,6 +715,44 @@ pub async fn backfill_operations(db: &PrismaClient, sync: &crate::Manager, insta
 		.unwrap();
 }
 
+/// Orders items so each one comes after its parent, otherwise the receiving end would get children
+/// referencing parents it doesn't know about yet. Cycles are broken wherever they're entered.
+fn parents_first<T, K: Eq + Hash>(
+	items: Vec<T>,
+	key: impl Fn(&T) -> K,
+	parent: impl Fn(&T) -> Option<K>,
+) -> Vec<T> {
+	let index = items
+		.iter()
+		.enumerate()
+		.map(|(idx, item)| (key(item), idx))
+		.collect::<HashMap<_, _>>();
+
+	let mut emitted = vec![false; items.len()];
+	let mut order = Vec::with_capacity(items.len());
+
+	for start in 0..items.len() {
+		// Walks up to the first ancestor already emitted, then emits the way back down
+		let mut chain = vec![];
+		let mut current = Some(start);
+		while let Some(idx) = current.filter(|idx| !emitted[*idx] && !chain.contains(idx)) {
+			chain.push(idx);
+			current = parent(&items[idx]).and_then(|parent| index.get(&parent).copied());
+		}
+
+		for idx in chain.into_iter().rev() {
+			emitted[idx] = true;
+			order.push(idx);
+		}
+	}
+
+	let mut items = items.into_iter().map(Some).collect::<Vec<_>>();
+	order
+		.into_iter()
+		.filter_map(|idx| items[idx].take())
+		.collect()
+}
+
 async fn paginate<
 	T,
 	E: std::fmt::Debug,
@@ -456,3 +800,39 @@ async fn paginate_relation<
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parents_come_first() {
+		// (id, parent_id), with 1 reparented under 4 after being created
+		let tags = vec![
+			(1, Some(4)),
+			(2, Some(1)),
+			(3, None),
+			(4, Some(3)),
+			(5, Some(9)),
+		];
+
+		let ordered = parents_first(tags, |t| t.0, |t| t.1)
+			.into_iter()
+			.map(|t| t.0)
+			.collect::<Vec<_>>();
+
+		assert_eq!(ordered, [3, 4, 1, 2, 5]);
+	}
+
+	#[test]
+	fn cycles_keep_every_item() {
+		let tags = vec![(1, Some(2)), (2, Some(1)), (3, None)];
+
+		let ordered = parents_first(tags, |t| t.0, |t| t.1)
+			.into_iter()
+			.map(|t| t.0)
+			.collect::<Vec<_>>();
+
+		assert_eq!(ordered, [2, 1, 3]);
+	}
+}
@@ -0,0 +1,14 @@
+-- AlterTable
+ALTER TABLE "media_data" ADD COLUMN "duration" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "audio_codec" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "bitrate" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "sample_rate" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "channels" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "bit_depth" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "title" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "album" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "album_artist" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "genre" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "track_number" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "year" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "has_cover_art" BOOLEAN;
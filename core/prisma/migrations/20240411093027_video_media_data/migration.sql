@@ -0,0 +1,8 @@
+-- AlterTable
+ALTER TABLE "media_data" ADD COLUMN "fps" REAL;
+ALTER TABLE "media_data" ADD COLUMN "video_codec" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "video_streams" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "audio_streams" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "subtitle_streams" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "rotation" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "hdr_format" TEXT;
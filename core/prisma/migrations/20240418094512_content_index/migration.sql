@@ -0,0 +1,10 @@
+-- CreateTable
+-- Full-text index of file contents, managed through raw queries as Prisma doesn't support virtual tables.
+-- The rowid is the id of the indexed object.
+CREATE VIRTUAL TABLE "content_index" USING fts5("content", tokenize = 'unicode61 remove_diacritics 2');
+
+-- CreateTrigger
+CREATE TRIGGER "content_index_object_delete" AFTER DELETE ON "object"
+BEGIN
+    DELETE FROM "content_index" WHERE rowid = OLD."id";
+END;
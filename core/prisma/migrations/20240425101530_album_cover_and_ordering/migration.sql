@@ -0,0 +1,21 @@
+-- AlterTable
+ALTER TABLE "object_in_album" ADD COLUMN "position" INTEGER;
+
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_album" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "name" TEXT,
+    "is_hidden" BOOLEAN,
+    "cover_id" INTEGER,
+    "date_created" DATETIME,
+    "date_modified" DATETIME,
+    CONSTRAINT "album_cover_id_fkey" FOREIGN KEY ("cover_id") REFERENCES "object" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_album" ("date_created", "date_modified", "id", "is_hidden", "name", "pub_id") SELECT "date_created", "date_modified", "id", "is_hidden", "name", "pub_id" FROM "album";
+DROP TABLE "album";
+ALTER TABLE "new_album" RENAME TO "album";
+CREATE UNIQUE INDEX "album_pub_id_key" ON "album"("pub_id");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
@@ -0,0 +1,24 @@
+-- AlterTable
+ALTER TABLE "object_in_space" ADD COLUMN "x" REAL;
+ALTER TABLE "object_in_space" ADD COLUMN "y" REAL;
+ALTER TABLE "object_in_space" ADD COLUMN "width" REAL;
+ALTER TABLE "object_in_space" ADD COLUMN "height" REAL;
+ALTER TABLE "object_in_space" ADD COLUMN "z_index" INTEGER;
+ALTER TABLE "object_in_space" ADD COLUMN "date_created" DATETIME;
+
+-- CreateTable
+CREATE TABLE "space_link" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "label" TEXT,
+    "space_id" INTEGER,
+    "from_id" INTEGER,
+    "to_id" INTEGER,
+    "date_created" DATETIME,
+    CONSTRAINT "space_link_space_id_fkey" FOREIGN KEY ("space_id") REFERENCES "space" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "space_link_from_id_fkey" FOREIGN KEY ("from_id") REFERENCES "object" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "space_link_to_id_fkey" FOREIGN KEY ("to_id") REFERENCES "object" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "space_link_pub_id_key" ON "space_link"("pub_id");
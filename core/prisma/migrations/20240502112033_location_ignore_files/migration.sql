@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "location" ADD COLUMN "respect_ignore_files" BOOLEAN;
@@ -0,0 +1,721 @@
+use crate::{
+	api::locations::{object_with_file_paths, ExplorerItem, ThumbnailKey},
+	invalidate_query,
+	library::Library,
+	object::media::old_thumbnail::get_indexed_thumb_key,
+	Node,
+};
+
+use sd_cache::{Normalise, NormalisedResults};
+use sd_prisma::{
+	prisma::{album, file_path, object, object_in_album, PrismaClient, SortOrder},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::msgpack;
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use prisma_client_rust::QueryError;
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use uuid::Uuid;
+
+use super::{utils::library, Ctx, R};
+
+/// How many objects are used as thumbnails for albums without a usable cover
+const MAX_THUMBNAILS: usize = 4;
+
+#[derive(Serialize, Type, Debug)]
+pub struct AlbumWithThumbnails {
+	pub album: album::Data,
+	pub object_count: i32,
+	/// Thumbnail of the cover object, or of the first objects when the cover has none
+	pub thumbnails: Vec<ThumbnailKey>,
+}
+
+#[derive(Type, Deserialize, Debug)]
+pub struct AlbumObjectsArgs {
+	pub album_id: album::id::Type,
+	pub object_ids: Vec<object::id::Type>,
+}
+
+fn sync_id(album_pub_id: &[u8], object_pub_id: Vec<u8>) -> prisma_sync::object_in_album::SyncId {
+	prisma_sync::object_in_album::SyncId {
+		album: prisma_sync::album::SyncId {
+			pub_id: album_pub_id.to_vec(),
+		},
+		object: prisma_sync::object::SyncId {
+			pub_id: object_pub_id,
+		},
+	}
+}
+
+async fn find_album(
+	db: &PrismaClient,
+	album_id: album::id::Type,
+) -> Result<album::Data, rspc::Error> {
+	db.album()
+		.find_unique(album::id::equals(album_id))
+		.exec()
+		.await?
+		.ok_or_else(|| rspc::Error::new(ErrorCode::NotFound, "Album not found".to_string()))
+}
+
+/// Finds the objects whose thumbnails represent an album: the cover, or the first objects
+/// in the album when there's no cover or its thumbnail hasn't been generated on this node
+async fn album_thumbnails(
+	node: &Node,
+	library: &Library,
+	album: &album::Data,
+) -> Result<Vec<ThumbnailKey>, QueryError> {
+	if let Some(cover_id) = album.cover_id {
+		if let Some(cas_id) = thumbnail_cas_id(node, library, cover_id).await? {
+			return Ok(vec![get_indexed_thumb_key(&cas_id, library.id)]);
+		}
+	}
+
+	let object_ids = library
+		.db
+		.object_in_album()
+		.find_many(vec![object_in_album::album_id::equals(album.id)])
+		.order_by(object_in_album::position::order(SortOrder::Asc))
+		.select(object_in_album::select!({ object_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|object_in_album| object_in_album.object_id)
+		.filter(|object_id| Some(*object_id) != album.cover_id);
+
+	let mut thumbnails = Vec::with_capacity(MAX_THUMBNAILS);
+
+	for object_id in object_ids {
+		if thumbnails.len() == MAX_THUMBNAILS {
+			break;
+		}
+
+		if let Some(cas_id) = thumbnail_cas_id(node, library, object_id).await? {
+			thumbnails.push(get_indexed_thumb_key(&cas_id, library.id));
+		}
+	}
+
+	Ok(thumbnails)
+}
+
+/// The cas_id of a file path of the object whose thumbnail exists on this node, if any
+async fn thumbnail_cas_id(
+	node: &Node,
+	library: &Library,
+	object_id: object::id::Type,
+) -> Result<Option<String>, QueryError> {
+	let file_paths = library
+		.db
+		.file_path()
+		.find_many(vec![
+			file_path::object_id::equals(Some(object_id)),
+			file_path::cas_id::not(None),
+		])
+		.select(file_path::select!({ cas_id }))
+		.exec()
+		.await?;
+
+	for cas_id in file_paths
+		.into_iter()
+		.filter_map(|file_path| file_path.cas_id)
+	{
+		if matches!(library.thumbnail_exists(node, &cas_id).await, Ok(true)) {
+			return Ok(Some(cas_id));
+		}
+	}
+
+	Ok(None)
+}
+
+/// Appends objects to the end of an album, in the given order, skipping the ones already in it
+async fn add_objects(
+	Library { db, sync, .. }: &Library,
+	album: &album::Data,
+	object_ids: Vec<object::id::Type>,
+) -> Result<(), rspc::Error> {
+	if object_ids.is_empty() {
+		return Ok(());
+	}
+
+	let mut seen = db
+		.object_in_album()
+		.find_many(vec![
+			object_in_album::album_id::equals(album.id),
+			object_in_album::object_id::in_vec(object_ids.clone()),
+		])
+		.select(object_in_album::select!({ object_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|object_in_album| object_in_album.object_id)
+		.collect::<HashSet<_>>();
+
+	let objects = db
+		.object()
+		.find_many(vec![object::id::in_vec(object_ids.clone())])
+		.select(object::select!({ id pub_id }))
+		.exec()
+		.await?;
+
+	let mut position = db
+		.object_in_album()
+		.find_first(vec![object_in_album::album_id::equals(album.id)])
+		.order_by(object_in_album::position::order(SortOrder::Desc))
+		.select(object_in_album::select!({ position }))
+		.exec()
+		.await?
+		.and_then(|object_in_album| object_in_album.position)
+		.map_or(0, |position| position + 1);
+
+	let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+	let (sync_ops, db_creates): (Vec<_>, Vec<_>) = object_ids
+		.into_iter()
+		.filter(|object_id| seen.insert(*object_id))
+		.filter_map(|object_id| objects.iter().find(|object| object.id == object_id))
+		.map(|object| {
+			let object_position = position;
+			position += 1;
+
+			(
+				sync.relation_create(
+					sync_id(&album.pub_id, object.pub_id.clone()),
+					[
+						(object_in_album::position::NAME, msgpack!(object_position)),
+						(
+							object_in_album::date_created::NAME,
+							msgpack!(&date_created.to_rfc3339()),
+						),
+					],
+				),
+				object_in_album::CreateUnchecked {
+					album_id: album.id,
+					object_id: object.id,
+					_params: vec![
+						object_in_album::position::set(Some(object_position)),
+						object_in_album::date_created::set(Some(date_created)),
+					],
+				},
+			)
+		})
+		.unzip();
+
+	if db_creates.is_empty() {
+		return Ok(());
+	}
+
+	sync.write_ops(
+		db,
+		(
+			sync_ops.into_iter().flatten().collect(),
+			db.object_in_album().create_many(db_creates),
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// Clears the cover of an album, if there is one
+async fn clear_cover(
+	Library { db, sync, .. }: &Library,
+	album: &album::Data,
+) -> Result<(), QueryError> {
+	if album.cover_id.is_none() {
+		return Ok(());
+	}
+
+	sync.write_op(
+		db,
+		sync.shared_update(
+			prisma_sync::album::SyncId {
+				pub_id: album.pub_id.clone(),
+			},
+			album::cover::NAME,
+			// Nil clears the relation when other instances apply the update
+			msgpack!(None::<prisma_sync::object::SyncId>),
+		),
+		db.album().update(
+			album::id::equals(album.id),
+			vec![album::cover::disconnect()],
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(node, library), _: ()| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let albums = db
+						.album()
+						.find_many(vec![])
+						.order_by(album::name::order(SortOrder::Asc))
+						.exec()
+						.await?;
+
+					let mut items = Vec::with_capacity(albums.len());
+
+					for album in albums {
+						items.push(AlbumWithThumbnails {
+							object_count: db
+								.object_in_album()
+								.count(vec![object_in_album::album_id::equals(album.id)])
+								.exec()
+								.await? as i32,
+							thumbnails: album_thumbnails(&node, &library, &album).await?,
+							album,
+						});
+					}
+
+					Ok(items)
+				})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), album_id: album::id::Type| async move {
+					Ok(library
+						.db
+						.album()
+						.find_unique(album::id::equals(album_id))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("objects", {
+			// Objects of an album, in their manual order
+			R.with2(library())
+				.query(|(node, library), album_id: album::id::Type| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let object_ids = db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(album_id)])
+						.order_by(object_in_album::position::order(SortOrder::Asc))
+						.select(object_in_album::select!({ object_id }))
+						.exec()
+						.await?
+						.into_iter()
+						.map(|object_in_album| object_in_album.object_id)
+						.collect::<Vec<_>>();
+
+					let mut objects = db
+						.object()
+						.find_many(vec![object::id::in_vec(object_ids.clone())])
+						.include(object_with_file_paths::include())
+						.exec()
+						.await?;
+
+					objects.sort_by_key(|object| {
+						object_ids
+							.iter()
+							.position(|object_id| *object_id == object.id)
+					});
+
+					let mut items = Vec::with_capacity(objects.len());
+
+					for object in objects {
+						let cas_id = object
+							.file_paths
+							.iter()
+							.find_map(|file_path| file_path.cas_id.as_ref());
+
+						let thumbnail_exists_locally = if let Some(cas_id) = cas_id {
+							library.thumbnail_exists(&node, cas_id).await.map_err(|e| {
+								rspc::Error::with_cause(
+									ErrorCode::InternalServerError,
+									"Failed to check that thumbnail exists".to_string(),
+									e,
+								)
+							})?
+						} else {
+							false
+						};
+
+						items.push(ExplorerItem::Object {
+							thumbnail: cas_id
+								.filter(|_| thumbnail_exists_locally)
+								.map(|cas_id| get_indexed_thumb_key(cas_id, library.id)),
+							item: object,
+						});
+					}
+
+					let (nodes, items) = items.normalise(|item| item.id());
+
+					Ok(NormalisedResults { items, nodes })
+				})
+		})
+		.procedure("getForObject", {
+			R.with2(library())
+				.query(|(_, library), object_id: object::id::Type| async move {
+					Ok(library
+						.db
+						.album()
+						.find_many(vec![album::objects::some(vec![
+							object_in_album::object_id::equals(object_id),
+						])])
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize, Debug)]
+			pub struct AlbumCreateArgs {
+				pub name: String,
+				#[serde(default)]
+				#[specta(optional)]
+				pub object_ids: Vec<object::id::Type>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumCreateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = Uuid::new_v4().as_bytes().to_vec();
+					let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+					let album = sync
+						.write_ops(
+							db,
+							(
+								sync.shared_create(
+									prisma_sync::album::SyncId {
+										pub_id: pub_id.clone(),
+									},
+									[
+										(album::name::NAME, msgpack!(&args.name)),
+										(album::is_hidden::NAME, msgpack!(false)),
+										(
+											album::date_created::NAME,
+											msgpack!(&date_created.to_rfc3339()),
+										),
+									],
+								),
+								db.album().create(
+									pub_id,
+									vec![
+										album::name::set(Some(args.name)),
+										album::is_hidden::set(Some(false)),
+										album::date_created::set(Some(date_created)),
+									],
+								),
+							),
+						)
+						.await?;
+
+					add_objects(&library, &album, args.object_ids).await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.getForObject");
+
+					Ok(album)
+				})
+		})
+		.procedure("rename", {
+			#[derive(Type, Deserialize, Debug)]
+			pub struct AlbumRenameArgs {
+				pub id: album::id::Type,
+				pub name: String,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumRenameArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album = find_album(db, args.id).await?;
+					let date_modified: DateTime<FixedOffset> = Utc::now().into();
+
+					sync.write_ops(
+						db,
+						(
+							[
+								(album::name::NAME, msgpack!(&args.name)),
+								(
+									album::date_modified::NAME,
+									msgpack!(&date_modified.to_rfc3339()),
+								),
+							]
+							.into_iter()
+							.map(|(k, v)| {
+								sync.shared_update(
+									prisma_sync::album::SyncId {
+										pub_id: album.pub_id.clone(),
+									},
+									k,
+									v,
+								)
+							})
+							.collect(),
+							db.album().update(
+								album::id::equals(args.id),
+								vec![
+									album::name::set(Some(args.name)),
+									album::date_modified::set(Some(date_modified)),
+								],
+							),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.get");
+
+					Ok(())
+				})
+		})
+		.procedure("addObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let album = find_album(&library.db, args.album_id).await?;
+
+					add_objects(&library, &album, args.object_ids).await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.objects");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("removeObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album = find_album(db, args.album_id).await?;
+
+					let objects_in_album = db
+						.object_in_album()
+						.find_many(vec![
+							object_in_album::album_id::equals(args.album_id),
+							object_in_album::object_id::in_vec(args.object_ids),
+						])
+						.select(object_in_album::select!({ object_id object: select { pub_id } }))
+						.exec()
+						.await?;
+
+					if album.cover_id.map_or(false, |cover_id| {
+						objects_in_album
+							.iter()
+							.any(|object_in_album| object_in_album.object_id == cover_id)
+					}) {
+						clear_cover(&library, &album).await?;
+					}
+
+					let object_ids = objects_in_album
+						.iter()
+						.map(|object_in_album| object_in_album.object_id)
+						.collect();
+
+					sync.write_ops(
+						db,
+						(
+							objects_in_album
+								.into_iter()
+								.map(|object_in_album| {
+									sync.relation_delete(sync_id(
+										&album.pub_id,
+										object_in_album.object.pub_id,
+									))
+								})
+								.collect(),
+							db.object_in_album().delete_many(vec![
+								object_in_album::album_id::equals(args.album_id),
+								object_in_album::object_id::in_vec(object_ids),
+							]),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.objects");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("reorder", {
+			// `object_ids` are placed first, in the given order, followed by the other objects of
+			// the album in their current order
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album = find_album(db, args.album_id).await?;
+
+					let objects_in_album = db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(args.album_id)])
+						.order_by(object_in_album::position::order(SortOrder::Asc))
+						.select(object_in_album::select!({
+							object_id
+							position
+							object: select { pub_id }
+						}))
+						.exec()
+						.await?;
+
+					let mut seen = HashSet::new();
+
+					let (sync_ops, db_updates): (Vec<_>, Vec<_>) = args
+						.object_ids
+						.iter()
+						.filter_map(|object_id| {
+							objects_in_album
+								.iter()
+								.find(|object_in_album| object_in_album.object_id == *object_id)
+						})
+						.chain(objects_in_album.iter())
+						.filter(|object_in_album| seen.insert(object_in_album.object_id))
+						.zip(0..)
+						.filter(|(object_in_album, position)| {
+							object_in_album.position != Some(*position)
+						})
+						.map(|(object_in_album, position)| {
+							(
+								sync.relation_update(
+									sync_id(&album.pub_id, object_in_album.object.pub_id.clone()),
+									object_in_album::position::NAME,
+									msgpack!(position),
+								),
+								db.object_in_album().update(
+									object_in_album::album_id_object_id(
+										args.album_id,
+										object_in_album.object_id,
+									),
+									vec![object_in_album::position::set(Some(position))],
+								),
+							)
+						})
+						.unzip();
+
+					if !db_updates.is_empty() {
+						sync.write_ops(db, (sync_ops, db_updates)).await?;
+					}
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("setCover", {
+			#[derive(Type, Deserialize, Debug)]
+			pub struct AlbumSetCoverArgs {
+				pub id: album::id::Type,
+				pub object_id: Option<object::id::Type>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumSetCoverArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album = find_album(db, args.id).await?;
+
+					if let Some(object_id) = args.object_id {
+						let object = db
+							.object()
+							.find_first(vec![
+								object::id::equals(object_id),
+								object::albums::some(vec![object_in_album::album_id::equals(
+									args.id,
+								)]),
+							])
+							.select(object::select!({ pub_id }))
+							.exec()
+							.await?
+							.ok_or_else(|| {
+								rspc::Error::new(
+									ErrorCode::BadRequest,
+									"The cover must be an object in the album".to_string(),
+								)
+							})?;
+
+						sync.write_op(
+							db,
+							sync.shared_update(
+								prisma_sync::album::SyncId {
+									pub_id: album.pub_id.clone(),
+								},
+								album::cover::NAME,
+								msgpack!(prisma_sync::object::SyncId {
+									pub_id: object.pub_id
+								}),
+							),
+							db.album().update(
+								album::id::equals(args.id),
+								vec![album::cover::connect(object::id::equals(object_id))],
+							),
+						)
+						.await?;
+					} else {
+						clear_cover(&library, &album).await?;
+					}
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.get");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), album_id: album::id::Type| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album = find_album(db, album_id).await?;
+
+					let objects_in_album = db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(album_id)])
+						.select(object_in_album::select!({ object: select { pub_id } }))
+						.exec()
+						.await?;
+
+					sync.write_ops(
+						db,
+						(
+							objects_in_album
+								.into_iter()
+								.map(|object_in_album| {
+									sync.relation_delete(sync_id(
+										&album.pub_id,
+										object_in_album.object.pub_id,
+									))
+								})
+								.collect(),
+							db.object_in_album()
+								.delete_many(vec![object_in_album::album_id::equals(album_id)]),
+						),
+					)
+					.await?;
+
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::album::SyncId {
+							pub_id: album.pub_id,
+						}),
+						db.album().delete(album::id::equals(album_id)),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.getForObject");
+
+					Ok(())
+				})
+		})
+}
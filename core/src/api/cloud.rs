@@ -146,6 +146,7 @@ mod library {
 						crate::cloud::sync::receive::create_instance(
 							&library,
 							&node.libraries,
+							&node.config,
 							instance.uuid,
 							instance.identity,
 							instance.node_id,
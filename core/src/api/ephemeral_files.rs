@@ -5,17 +5,15 @@ use crate::{
 	object::{
 		fs::{error::FileSystemJobsError, find_available_filename_for_duplicate},
 		media::media_data_extractor::{
-			can_extract_media_data_for_image, extract_media_data, MediaDataError,
+			extract_media_data_by_extension, is_missing_media_data_error,
 		},
 	},
 };
 
-use sd_file_ext::extensions::ImageExtension;
 use sd_file_path_helper::IsolatedFilePathData;
-use sd_media_metadata::MediaMetadata;
 use sd_utils::error::FileIOError;
 
-use std::{ffi::OsStr, path::PathBuf, str::FromStr};
+use std::{ffi::OsStr, path::PathBuf};
 
 use async_recursion::async_recursion;
 use futures_concurrency::future::TryJoin;
@@ -42,21 +40,9 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					return Ok(None);
 				};
 
-				// TODO(fogodev): change this when we have media data for audio and videos
-				let image_extension = ImageExtension::from_str(extension).map_err(|e| {
-					error!("Failed to parse image extension: {e:#?}");
-					rspc::Error::new(ErrorCode::BadRequest, "Invalid image extension".to_string())
-				})?;
-
-				if !can_extract_media_data_for_image(&image_extension) {
-					return Ok(None);
-				}
-
-				match extract_media_data(full_path.clone()).await {
-					Ok(img_media_data) => Ok(Some(MediaMetadata::Image(Box::new(img_media_data)))),
-					Err(MediaDataError::MediaData(sd_media_metadata::Error::NoExifDataOnPath(
-						_,
-					))) => Ok(None),
+				match extract_media_data_by_extension(&full_path, extension).await {
+					Ok(maybe_media_data) => Ok(maybe_media_data),
+					Err(e) if is_missing_media_data_error(&e) => Ok(None),
 					Err(e) => Err(rspc::Error::with_cause(
 						ErrorCode::InternalServerError,
 						"Failed to extract media data".to_string(),
@@ -2,14 +2,26 @@ use crate::{
 	api::{locations::object_with_file_paths, utils::library},
 	invalidate_query,
 	library::Library,
-	location::{get_location_path_from_location_id, LocationError},
+	location::{
+		archive::ensure_location_is_not_archived, get_location_path_from_location_id, LocationError,
+	},
 	object::{
 		fs::{
-			error::FileSystemJobsError, find_available_filename_for_duplicate,
-			old_copy::OldFileCopierJobInit, old_cut::OldFileCutterJobInit,
-			old_delete::OldFileDeleterJobInit, old_erase::OldFileEraserJobInit,
+			archive::{OldArchiveCompressorJobInit, OldArchiveExtractorJobInit},
+			convert::{ImageConverterArgs, ImageConverterTask},
+			error::FileSystemJobsError,
+			find_available_filename_for_duplicate,
+			old_copy::OldFileCopierJobInit,
+			old_cut::OldFileCutterJobInit,
+			old_delete::OldFileDeleterJobInit,
+			old_erase::OldFileEraserJobInit,
+			reclaim::OldDuplicateReclaimerJobInit,
+			sync::OldFolderSyncJobInit,
+		},
+		media::{
+			media_data_audio_from_prisma_data, media_data_image_from_prisma_data,
+			media_data_video_from_prisma_data,
 		},
-		media::media_data_image_from_prisma_data,
 	},
 	old_job::Job,
 };
@@ -19,7 +31,6 @@ use sd_file_ext::kind::ObjectKind;
 use sd_file_path_helper::{
 	file_path_to_isolate, file_path_to_isolate_with_id, FilePathError, IsolatedFilePathData,
 };
-use sd_images::ConvertibleExtension;
 use sd_media_metadata::MediaMetadata;
 use sd_prisma::{
 	prisma::{file_path, location, object},
@@ -28,11 +39,7 @@ use sd_prisma::{
 use sd_sync::OperationFactory;
 use sd_utils::{db::maybe_missing, error::FileIOError, msgpack};
 
-use std::{
-	ffi::OsString,
-	path::{Path, PathBuf},
-	sync::Arc,
-};
+use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, FixedOffset, Utc};
 use futures::future::join_all;
@@ -40,7 +47,7 @@ use regex::Regex;
 use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use tokio::{fs, io, task::spawn_blocking};
+use tokio::{fs, io};
 use tracing::{error, warn};
 
 use super::{Ctx, R};
@@ -139,7 +146,13 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 										media_data_image_from_prisma_data(obj.media_data?).ok()?,
 									))
 								}
-								_ => return None, // TODO(brxken128): audio and video
+								Some(v) if v == ObjectKind::Audio as i32 => MediaMetadata::Audio(
+									Box::new(media_data_audio_from_prisma_data(obj.media_data?)),
+								),
+								Some(v) if v == ObjectKind::Video as i32 => MediaMetadata::Video(
+									Box::new(media_data_video_from_prisma_data(obj.media_data?)),
+								),
+								_ => return None,
 							})
 						})
 						.ok_or_else(|| {
@@ -278,6 +291,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				     sub_path,
 				     name,
 				 }: CreateFolderArgs| async move {
+					ensure_location_is_not_archived(&library.db, location_id).await?;
+
 					let mut path =
 						get_location_path_from_location_id(&library.db, location_id).await?;
 
@@ -406,7 +421,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 										.db
 										.location()
 										.find_unique(location::id::equals(args.location_id))
-										.select(location::select!({ path })),
+										.select(location::select!({ path is_archived })),
 									library
 										.db
 										.file_path()
@@ -415,8 +430,14 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 								))
 								.await?;
 
-							let location_path = maybe_location
-								.ok_or(LocationError::IdNotFound(args.location_id))?
+							let location = maybe_location
+								.ok_or(LocationError::IdNotFound(args.location_id))?;
+
+							if location.is_archived.unwrap_or(false) {
+								return Err(LocationError::Archived(args.location_id).into());
+							}
+
+							let location_path = location
 								.path
 								.ok_or(LocationError::MissingPath(args.location_id))?;
 
@@ -466,134 +487,12 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				})
 		})
 		.procedure("convertImage", {
-			#[derive(Type, Deserialize)]
-			struct ConvertImageArgs {
-				location_id: location::id::Type,
-				file_path_id: file_path::id::Type,
-				delete_src: bool, // if set, we delete the src image after
-				desired_extension: ConvertibleExtension,
-				quality_percentage: Option<i32>, // 1% - 125%
-			}
 			R.with2(library())
-				.mutation(|(_, library), args: ConvertImageArgs| async move {
-					// TODO:(fogodev) I think this will have to be a Job due to possibly being too much CPU Bound for rspc
-
-					let location_path =
-						get_location_path_from_location_id(&library.db, args.location_id).await?;
-
-					let isolated_path = IsolatedFilePathData::try_from(
-						library
-							.db
-							.file_path()
-							.find_unique(file_path::id::equals(args.file_path_id))
-							.select(file_path_to_isolate::select())
-							.exec()
-							.await?
-							.ok_or(LocationError::FilePath(FilePathError::IdNotFound(
-								args.file_path_id,
-							)))?,
-					)?;
-
-					let path = Path::new(&location_path).join(&isolated_path);
-
-					if let Err(e) = fs::metadata(&path).await {
-						if e.kind() == io::ErrorKind::NotFound {
-							return Err(LocationError::FilePath(FilePathError::NotFound(
-								path.into_boxed_path(),
-							))
-							.into());
-						} else {
-							return Err(FileIOError::from((
-								path,
-								e,
-								"Got an error trying to read metadata from image to convert",
-							))
-							.into());
-						}
-					}
-
-					args.quality_percentage.map(|x| x.clamp(1, 125));
-
-					let path = Arc::new(path);
-
-					let output_extension =
-						Arc::new(OsString::from(args.desired_extension.to_string()));
-
-					// TODO(fogodev): Refactor this if Rust get async scoped spawns someday
-					let inner_path = Arc::clone(&path);
-					let inner_output_extension = Arc::clone(&output_extension);
-					let image = spawn_blocking(move || {
-						sd_images::convert_image(inner_path.as_ref(), &inner_output_extension).map(
-							|mut image| {
-								if let Some(quality_percentage) = args.quality_percentage {
-									image = image.resize(
-										image.width()
-											* (quality_percentage as f32 / 100_f32) as u32,
-										image.height()
-											* (quality_percentage as f32 / 100_f32) as u32,
-										image::imageops::FilterType::Triangle,
-									);
-								}
-								image
-							},
-						)
-					})
-					.await
-					.map_err(|e| {
-						error!("{e:#?}");
-						rspc::Error::new(
-							ErrorCode::InternalServerError,
-							"Had an internal problem converting image".to_string(),
-						)
-					})??;
-
-					let output_path = path.with_extension(output_extension.as_ref());
-
-					if fs::metadata(&output_path)
-						.await
-						.map(|_| true)
-						.map_err(|e| {
-							FileIOError::from(
-							(
-								&output_path,
-								e,
-								"Got an error trying to check if the desired converted file already exists"
-							)
-						)
-						})? {
-						return Err(rspc::Error::new(
-							ErrorCode::Conflict,
-							"There is already a file with same name and extension in this directory"
-								.to_string(),
-						));
-					} else {
-						fs::write(&output_path, image.as_bytes())
-							.await
-							.map_err(|e| {
-								FileIOError::from((
-									output_path,
-									e,
-									"There was an error while writing the image to the output path",
-								))
-							})?;
-					}
-
-					if args.delete_src {
-						fs::remove_file(path.as_ref()).await.map_err(|e| {
-							// Let's also invalidate the query here, because we succeeded in converting the file
-							invalidate_query!(library, "search.paths");
-							invalidate_query!(library, "search.objects");
-
-							FileIOError::from((
-								path.as_ref(),
-								e,
-								"There was an error while deleting the source image",
-							))
-						})?;
-					}
-
-					invalidate_query!(library, "search.paths");
-					invalidate_query!(library, "search.objects");
+				.mutation(|(node, library), args: ImageConverterArgs| async move {
+					ImageConverterTask::new(node, library, args)
+						.await?
+						.dispatch()
+						.await;
 
 					Ok(())
 				})
@@ -628,6 +527,51 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.map_err(Into::into)
 				})
 		})
+		.procedure("compressFiles", {
+			R.with2(library()).mutation(
+				|(node, library), args: OldArchiveCompressorJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("extractArchive", {
+			R.with2(library()).mutation(
+				|(node, library), args: OldArchiveExtractorJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("planFolderSync", {
+			R.with2(library())
+				.query(|(_, library), args: OldFolderSyncJobInit| async move {
+					args.plan(&library.db).await.map_err(Into::into)
+				})
+		})
+		.procedure("syncFolders", {
+			R.with2(library())
+				.mutation(|(node, library), args: OldFolderSyncJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
+		.procedure("reclaimDuplicates", {
+			R.with2(library()).mutation(
+				|(node, library), args: OldDuplicateReclaimerJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
 		.procedure("renameFile", {
 			#[derive(Type, Deserialize)]
 			pub struct RenameOne {
@@ -812,6 +756,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 
 			R.with2(library()).mutation(
 				|(_, library), RenameFileArgs { location_id, kind }: RenameFileArgs| async move {
+					ensure_location_is_not_archived(&library.db, location_id).await?;
+
 					let location_path =
 						get_location_path_from_location_id(&library.db, location_id).await?;
 
@@ -1,8 +1,12 @@
 use crate::{
 	invalidate_query,
 	location::{
+		archive::{archive_location, reattach_location},
 		delete_location, find_location,
-		indexer::{rules::IndexerRuleCreateArgs, OldIndexerJobInit},
+		indexer::{
+			rules::{rule_set::RuleSet, IndexerRule, IndexerRuleCreateArgs},
+			OldIndexerJobInit,
+		},
 		light_scan_location, location_with_indexer_rules,
 		non_indexed::NonIndexedPathItem,
 		relink_location, scan_location, scan_location_sub_path, LocationCreateArgs, LocationError,
@@ -18,6 +22,7 @@ use sd_cache::{CacheNode, Model, Normalise, NormalisedResult, NormalisedResults,
 use sd_prisma::prisma::{
 	file_path, indexer_rule, indexer_rules_in_location, location, object, SortOrder,
 };
+use sd_utils::db::maybe_missing;
 
 use std::path::{Path, PathBuf};
 
@@ -243,6 +248,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub generate_preview_media: Option<bool>,
 				pub sync_preview_media: Option<bool>,
 				pub hidden: Option<bool>,
+				pub respect_ignore_files: Option<bool>,
 				pub date_created: Option<DateTime<FixedOffset>>,
 				pub instance_id: Option<i32>,
 				pub indexer_rules: Vec<Reference<indexer_rule::Data>>,
@@ -271,6 +277,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						generate_preview_media: value.generate_preview_media,
 						sync_preview_media: value.sync_preview_media,
 						hidden: value.hidden,
+						respect_ignore_files: value.respect_ignore_files,
 						date_created: value.date_created,
 						instance_id: value.instance_id,
 						indexer_rules: value
@@ -339,6 +346,31 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				},
 			)
 		})
+		.procedure("archive", {
+			R.with2(library()).mutation(
+				|(node, library), location_id: location::id::Type| async move {
+					archive_location(&node, &library, location_id)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("reattach", {
+			#[derive(Type, Deserialize)]
+			pub struct ReattachLocationArgs {
+				pub location_id: location::id::Type,
+				pub path: PathBuf,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library),
+				 ReattachLocationArgs { location_id, path }: ReattachLocationArgs| async move {
+					reattach_location(&node, &library, location_id, path)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
 		.procedure("relink", {
 			R.with2(library())
 				.mutation(|(_, library), location_path: PathBuf| async move {
@@ -595,6 +627,90 @@ fn mount_indexer_rule_routes() -> AlphaRouter<Ctx> {
 				Ok(NormalisedResults { items, nodes })
 			})
 		})
+		.procedure("export", {
+			R.with2(library())
+				.query(|(_, library), indexer_rule_ids: Vec<i32>| async move {
+					let rules = library
+						.db
+						.indexer_rule()
+						.find_many(if indexer_rule_ids.is_empty() {
+							vec![]
+						} else {
+							vec![indexer_rule::id::in_vec(indexer_rule_ids)]
+						})
+						.exec()
+						.await?
+						.iter()
+						.map(IndexerRule::try_from)
+						.collect::<Result<Vec<_>, _>>()?;
+
+					Ok(RuleSet::export(&rules).to_json()?)
+				})
+		})
+		.procedure("validate", {
+			R.query(|_, rule_set: String| async move {
+				let rule_set = RuleSet::from_json(&rule_set)?;
+				rule_set.validate()?;
+
+				Ok(rule_set)
+			})
+		})
+		.procedure("import", {
+			#[derive(Type, Deserialize)]
+			pub struct RuleSetImportArgs {
+				pub rule_set: String,
+				pub skip_existing: bool,
+			}
+
+			R.with2(library()).mutation(
+				|(_, library),
+				 RuleSetImportArgs {
+				     rule_set,
+				     skip_existing,
+				 }: RuleSetImportArgs| async move {
+					let created = RuleSet::from_json(&rule_set)?
+						.import(&library, skip_existing)
+						.await?;
+
+					if !created.is_empty() {
+						invalidate_query!(library, "locations.indexer_rules.list");
+					}
+
+					Ok(created.into_iter().map(|rule| rule.id).collect::<Vec<_>>())
+				},
+			)
+		})
+		.procedure("dryRun", {
+			#[derive(Type, Deserialize)]
+			pub struct RuleSetDryRunArgs {
+				pub location_id: location::id::Type,
+				pub rule_set: String,
+			}
+
+			R.with2(library()).query(
+				|(_, library),
+				 RuleSetDryRunArgs {
+				     location_id,
+				     rule_set,
+				 }: RuleSetDryRunArgs| async move {
+					let location = find_location(&library, location_id)
+						.select(location::select!({ path respect_ignore_files }))
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(location_id))?;
+
+					let location_path = maybe_missing(location.path, "location.path")?;
+
+					Ok(RuleSet::from_json(&rule_set)?
+						.dry_run(
+							location_id,
+							Path::new(&location_path),
+							location.respect_ignore_files.unwrap_or(false),
+						)
+						.await?)
+				},
+			)
+		})
 		// list indexer rules for location, returning the indexer rule
 		.procedure("listForLocation", {
 			R.with2(library())
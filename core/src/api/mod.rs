@@ -19,6 +19,7 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 use uuid::Uuid;
 
+mod albums;
 mod auth;
 mod backups;
 mod cloud;
@@ -36,6 +37,7 @@ pub mod notifications;
 mod p2p;
 mod preferences;
 pub(crate) mod search;
+mod spaces;
 mod sync;
 mod tags;
 pub mod utils;
@@ -66,6 +68,8 @@ pub enum CoreEvent {
 pub enum BackendFeature {
 	FilesOverP2P,
 	CloudSync,
+	/// Confirms `cas_id` matches with a full content hash before linking files to the same object
+	VerifiedDedup,
 }
 
 impl BackendFeature {
@@ -77,6 +81,9 @@ impl BackendFeature {
 			BackendFeature::CloudSync => {
 				node.cloud_sync_flag.store(true, Ordering::Relaxed);
 			}
+			BackendFeature::VerifiedDedup => {
+				node.verified_dedup_flag.store(true, Ordering::Relaxed);
+			}
 		}
 	}
 }
@@ -187,6 +194,9 @@ pub(crate) fn mount() -> Arc<Router> {
 					BackendFeature::CloudSync => {
 						node.cloud_sync_flag.store(enabled, Ordering::Relaxed);
 					}
+					BackendFeature::VerifiedDedup => {
+						node.verified_dedup_flag.store(enabled, Ordering::Relaxed);
+					}
 				}
 
 				invalidate_query!(node; node, "nodeState");
@@ -202,6 +212,8 @@ pub(crate) fn mount() -> Arc<Router> {
 		.merge("volumes.", volumes::mount())
 		.merge("tags.", tags::mount())
 		.merge("labels.", labels::mount())
+		.merge("albums.", albums::mount())
+		.merge("spaces.", spaces::mount())
 		// .merge("categories.", categories::mount())
 		// .merge("keys.", keys::mount())
 		.merge("locations.", locations::mount())
@@ -1,9 +1,14 @@
-use crate::p2p::{operations, ConnectionMethod, DiscoveryMethod, Header, P2PEvent, PeerMetadata};
+use crate::{
+	invalidate_query,
+	node::config::{NodeConfigError, PeerPermissions, TrustedPeer},
+	p2p::{operations, ConnectionMethod, DiscoveryMethod, Header, P2PEvent, PeerMetadata},
+};
 
 use sd_p2p::{PeerConnectionCandidate, RemoteIdentity};
+use sd_p2p_tunnel::PairingCode;
 
 use rspc::{alpha::AlphaRouter, ErrorCode};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use specta::Type;
 use std::path::PathBuf;
 use tokio::io::AsyncWriteExt;
@@ -120,6 +125,70 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				Ok(())
 			})
 		})
+		.procedure("startPairing", {
+			R.mutation(|node, _: ()| async move {
+				Ok(PairingPayload {
+					identity: node.p2p.p2p.remote_identity(),
+					code: node.p2p.start_pairing().to_string(),
+				})
+			})
+		})
+		.procedure("pair", {
+			R.mutation(|node, args: PairingPayload| async move {
+				let code = args
+					.code
+					.parse::<PairingCode>()
+					.map_err(|err| rspc::Error::new(ErrorCode::BadRequest, err.to_string()))?;
+
+				operations::pair(node.p2p.clone(), args.identity, code)
+					.await
+					.map_err(|err| rspc::Error::new(ErrorCode::BadRequest, err.to_string()))
+			})
+		})
+		.procedure("trustedPeers", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.config
+					.get()
+					.await
+					.trusted_peers
+					.into_iter()
+					.collect::<Vec<(RemoteIdentity, TrustedPeer)>>())
+			})
+		})
+		.procedure("setPeerPermissions", {
+			#[derive(Type, Deserialize)]
+			pub struct SetPeerPermissionsArgs {
+				identity: RemoteIdentity,
+				permissions: PeerPermissions,
+			}
+
+			R.mutation(|node, args: SetPeerPermissionsArgs| async move {
+				if !node
+					.p2p
+					.set_peer_permissions(args.identity, args.permissions)
+					.await
+					.map_err(config_error)?
+				{
+					return Err(untrusted_peer(&args.identity));
+				}
+
+				invalidate_query!(node; node, "p2p.trustedPeers");
+
+				Ok(())
+			})
+		})
+		.procedure("untrustPeer", {
+			R.mutation(|node, identity: RemoteIdentity| async move {
+				if !node.p2p.untrust(&identity).await.map_err(config_error)? {
+					return Err(untrusted_peer(&identity));
+				}
+
+				invalidate_query!(node; node, "p2p.trustedPeers");
+
+				Ok(())
+			})
+		})
 		.procedure("cancelSpacedrop", {
 			R.mutation(|node, id: Uuid| async move {
 				node.p2p.cancel_spacedrop(id).await;
@@ -128,3 +197,25 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			})
 		})
 }
+
+fn config_error(err: NodeConfigError) -> rspc::Error {
+	rspc::Error::with_cause(
+		ErrorCode::InternalServerError,
+		"Failed to update trusted peers".to_string(),
+		err,
+	)
+}
+
+fn untrusted_peer(identity: &RemoteIdentity) -> rspc::Error {
+	rspc::Error::new(
+		ErrorCode::NotFound,
+		format!("Peer '{identity}' isn't trusted, it must be paired first"),
+	)
+}
+
+/// What a node shows to be paired with, either as text or as a QR code of its JSON
+#[derive(Serialize, Deserialize, Type)]
+pub struct PairingPayload {
+	identity: RemoteIdentity,
+	code: String,
+}
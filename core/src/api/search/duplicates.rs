@@ -0,0 +1,145 @@
+use sd_prisma::prisma::{file_path, object, PrismaClient};
+
+use std::collections::HashMap;
+
+use prisma_client_rust::raw;
+use rspc::ErrorCode;
+use serde::Deserialize;
+use specta::Type;
+
+file_path::select!(file_path_for_duplicates {
+	id
+	object_id
+	size_in_bytes_bytes
+});
+
+/// Sizes are strings as Javascript numbers can't hold every `u64`
+#[derive(Deserialize, Type, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateSizeFilter {
+	#[specta(optional)]
+	pub min_size_in_bytes: Option<String>,
+	#[specta(optional)]
+	pub max_size_in_bytes: Option<String>,
+}
+
+impl DuplicateSizeFilter {
+	fn bounds(&self) -> Result<(u64, u64), rspc::Error> {
+		let parse = |size: &Option<String>, default| {
+			size.as_deref().map_or(Ok(default), |size| {
+				size.parse::<u64>().map_err(|e| {
+					rspc::Error::with_cause(
+						ErrorCode::BadRequest,
+						format!("Invalid size in bytes: {size}"),
+						e,
+					)
+				})
+			})
+		};
+
+		Ok((
+			parse(&self.min_size_in_bytes, 0)?,
+			parse(&self.max_size_in_bytes, u64::MAX)?,
+		))
+	}
+}
+
+/// Object whose file paths share the same content
+#[derive(Debug)]
+pub struct DuplicateGroup {
+	pub object_id: object::id::Type,
+	pub size_in_bytes: u64,
+	pub file_path_ids: Vec<file_path::id::Type>,
+}
+
+impl DuplicateGroup {
+	/// Bytes which would be freed by keeping a single copy
+	pub fn wasted_bytes(&self) -> u64 {
+		self.size_in_bytes * (self.file_path_ids.len() as u64 - 1)
+	}
+}
+
+/// Groups the file paths matching `params` by object, keeping only objects with more than one
+/// copy, ranked by wasted bytes
+pub async fn find_duplicate_groups(
+	db: &PrismaClient,
+	mut params: Vec<file_path::WhereParam>,
+	size_filter: &DuplicateSizeFilter,
+) -> Result<Vec<DuplicateGroup>, rspc::Error> {
+	let (min_size, max_size) = size_filter.bounds()?;
+
+	// Most files don't have copies, so we let the database find the ones that do instead of
+	// loading every file path. Filters can still leave a single copy of them, checked below.
+	params.extend([
+		file_path::object_id::in_vec(duplicated_object_ids(db).await?),
+		file_path::is_dir::equals(Some(false)),
+	]);
+
+	let mut groups = HashMap::<_, DuplicateGroup>::new();
+
+	for file_path in db
+		.file_path()
+		.find_many(params)
+		.select(file_path_for_duplicates::select())
+		.exec()
+		.await?
+	{
+		let Some(object_id) = file_path.object_id else {
+			continue;
+		};
+
+		let size_in_bytes = file_path
+			.size_in_bytes_bytes
+			.as_deref()
+			.and_then(|size| <[u8; 8]>::try_from(size).ok())
+			.map(u64::from_be_bytes)
+			.unwrap_or_default();
+
+		if size_in_bytes < min_size || size_in_bytes > max_size {
+			continue;
+		}
+
+		groups
+			.entry(object_id)
+			.or_insert_with(|| DuplicateGroup {
+				object_id,
+				size_in_bytes,
+				file_path_ids: vec![],
+			})
+			.file_path_ids
+			.push(file_path.id);
+	}
+
+	let mut groups = groups
+		.into_values()
+		.filter(|group| group.file_path_ids.len() > 1)
+		.collect::<Vec<_>>();
+
+	groups.sort_unstable_by(|one, two| {
+		two.wasted_bytes()
+			.cmp(&one.wasted_bytes())
+			.then(one.object_id.cmp(&two.object_id))
+	});
+
+	Ok(groups)
+}
+
+async fn duplicated_object_ids(db: &PrismaClient) -> Result<Vec<object::id::Type>, rspc::Error> {
+	#[derive(Deserialize)]
+	struct DuplicatedObject {
+		object_id: object::id::Type,
+	}
+
+	Ok(db
+		._query_raw::<DuplicatedObject>(raw!(
+			"SELECT object_id FROM file_path
+			WHERE object_id IS NOT NULL AND is_dir = 0
+			GROUP BY object_id
+			HAVING COUNT(*) > 1"
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|object| object.object_id)
+		.collect())
+}
@@ -1,10 +1,10 @@
-use crate::location::LocationError;
+use crate::{location::LocationError, object::content_index};
 
 use sd_file_path_helper::{check_file_path_exists, IsolatedFilePathData};
 use sd_prisma::prisma::{self, file_path};
 
 use chrono::{DateTime, FixedOffset, Utc};
-use prisma_client_rust::{OrderByQuery, PaginatedQuery, WhereQuery};
+use prisma_client_rust::{raw, OrderByQuery, PaginatedQuery, WhereQuery};
 use rspc::ErrorCode;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -69,6 +69,10 @@ pub enum FilePathFilterArgs {
 	ModifiedAt(Range<DateTime<Utc>>),
 	IndexedAt(Range<DateTime<Utc>>),
 	Hidden(bool),
+	/// Sizes are strings as Javascript numbers can't hold every `u64`
+	SizeInBytes(Range<String>),
+	/// Words or quoted phrases which must all appear in the file's contents
+	Content(String),
 }
 
 impl FilePathFilterArgs {
@@ -151,10 +155,59 @@ impl FilePathFilterArgs {
 			Self::Hidden(v) => {
 				vec![hidden::equals(Some(v))]
 			}
+			Self::SizeInBytes(v) => {
+				let (operator, size) = match &v {
+					Range::From(size) => (">=", size),
+					Range::To(size) => ("<=", size),
+				};
+				let size = size.parse::<u64>().map_err(|e| {
+					rspc::Error::with_cause(
+						ErrorCode::BadRequest,
+						format!("Invalid size in bytes: {size}"),
+						e,
+					)
+				})?;
+
+				// Sizes are stored as big endian bytes, which Prisma can only filter by equality,
+				// but SQLite compares blobs byte by byte so comparing them compares sizes. The
+				// size is formatted from an `u64`, so this is sql injection safe.
+				vec![id::in_vec(
+					file_path_ids(
+						db,
+						raw!(&format!(
+							"SELECT id FROM file_path
+							WHERE size_in_bytes_bytes {operator} X'{size:016X}'"
+						)),
+					)
+					.await?,
+				)]
+			}
+			Self::Content(v) if v.trim().is_empty() => vec![],
+			Self::Content(v) => vec![object_id::in_vec(
+				content_index::matching_objects(db, &v).await?,
+			)],
 		})
 	}
 }
 
+async fn file_path_ids(
+	db: &prisma::PrismaClient,
+	query: prisma_client_rust::Raw,
+) -> Result<Vec<file_path::id::Type>, rspc::Error> {
+	#[derive(Deserialize)]
+	struct FilePathId {
+		id: file_path::id::Type,
+	}
+
+	Ok(db
+		._query_raw::<FilePathId>(query)
+		.exec()
+		.await?
+		.into_iter()
+		.map(|file_path| file_path.id)
+		.collect())
+}
+
 #[derive(Deserialize, Type, Debug)]
 #[serde(rename_all = "camelCase")]
 pub enum FilePathObjectCursor {
@@ -1,5 +1,7 @@
-use sd_prisma::prisma::{self, media_data};
+use sd_prisma::prisma::{self, media_data, PrismaClient};
 
+use chrono::{DateTime, Utc};
+use prisma_client_rust::{raw, PrismaValue};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
@@ -9,12 +11,24 @@ use super::utils::*;
 #[serde(rename_all = "camelCase", tag = "field", content = "value")]
 pub enum MediaDataOrder {
 	EpochTime(SortOrder),
+	Duration(SortOrder),
+	Artist(SortOrder),
+	Album(SortOrder),
+	TrackNumber(SortOrder),
+	Bitrate(SortOrder),
+	Fps(SortOrder),
 }
 
 impl MediaDataOrder {
 	pub fn get_sort_order(&self) -> prisma::SortOrder {
 		(*match self {
-			Self::EpochTime(v) => v,
+			Self::EpochTime(v)
+			| Self::Duration(v)
+			| Self::Artist(v)
+			| Self::Album(v)
+			| Self::TrackNumber(v)
+			| Self::Bitrate(v)
+			| Self::Fps(v) => v,
 		})
 		.into()
 	}
@@ -24,6 +38,170 @@ impl MediaDataOrder {
 		use media_data::*;
 		match self {
 			Self::EpochTime(_) => epoch_time::order(dir),
+			Self::Duration(_) => duration::order(dir),
+			Self::Artist(_) => artist::order(dir),
+			Self::Album(_) => album::order(dir),
+			Self::TrackNumber(_) => track_number::order(dir),
+			Self::Bitrate(_) => bitrate::order(dir),
+			Self::Fps(_) => fps::order(dir),
 		}
 	}
 }
+
+/// Bounding box in degrees, wrapping around the antimeridian when `min_longitude` is greater than
+/// `max_longitude`
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoBoundingBox {
+	pub min_latitude: f64,
+	pub max_latitude: f64,
+	pub min_longitude: f64,
+	pub max_longitude: f64,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum MediaDataFilterArgs {
+	/// Width times height, in millions of pixels
+	Megapixels(Range<f64>),
+	CameraMake(TextMatch),
+	CameraModel(TextMatch),
+	/// When the photo or video was taken
+	TakenAt(Range<DateTime<Utc>>),
+	Location(GeoBoundingBox),
+	Artist(TextMatch),
+}
+
+impl MediaDataFilterArgs {
+	pub async fn into_params(
+		self,
+		db: &PrismaClient,
+	) -> Result<Vec<media_data::WhereParam>, rspc::Error> {
+		use media_data::*;
+
+		// Resolutions, camera data and locations are stored as JSON blobs, so we can only filter them
+		// with raw queries. Numbers come from deserialized `f64`s, so formatting them into the
+		// queries is sql injection safe.
+		Ok(match self {
+			Self::Megapixels(v) => {
+				let (operator, megapixels) = match v {
+					Range::From(v) => (">=", v),
+					Range::To(v) => ("<=", v),
+				};
+
+				vec![id::in_vec(
+					media_data_ids(
+						db,
+						raw!(&format!(
+							"SELECT id FROM media_data
+							WHERE json_extract(CAST(resolution AS TEXT), '$.width')
+								* json_extract(CAST(resolution AS TEXT), '$.height') {operator} {}",
+							megapixels * 1_000_000.0
+						)),
+					)
+					.await?,
+				)]
+			}
+			Self::CameraMake(v) => camera_data_param(db, "device_make", v).await?,
+			Self::CameraModel(v) => camera_data_param(db, "device_model", v).await?,
+			Self::TakenAt(v) => vec![match v {
+				Range::From(v) => epoch_time::gte(v.timestamp()),
+				Range::To(v) => epoch_time::lte(v.timestamp()),
+			}],
+			Self::Location(GeoBoundingBox {
+				min_latitude,
+				max_latitude,
+				min_longitude,
+				max_longitude,
+			}) => {
+				let longitude_operator = if min_longitude <= max_longitude {
+					"AND"
+				} else {
+					"OR"
+				};
+
+				vec![id::in_vec(
+					media_data_ids(
+						db,
+						raw!(&format!(
+							"SELECT id FROM media_data
+							WHERE json_extract(CAST(media_location AS TEXT), '$.latitude')
+									BETWEEN {min_latitude} AND {max_latitude}
+								AND (
+									json_extract(CAST(media_location AS TEXT), '$.longitude') >= {min_longitude}
+									{longitude_operator}
+									json_extract(CAST(media_location AS TEXT), '$.longitude') <= {max_longitude}
+								)"
+						)),
+					)
+					.await?,
+				)]
+			}
+			Self::Artist(v) => v
+				.into_param(
+					artist::contains,
+					artist::starts_with,
+					artist::ends_with,
+					|s| artist::equals(Some(s)),
+				)
+				.map(|v| vec![v])
+				.unwrap_or_default(),
+		})
+	}
+}
+
+async fn media_data_ids(
+	db: &PrismaClient,
+	query: prisma_client_rust::Raw,
+) -> Result<Vec<media_data::id::Type>, rspc::Error> {
+	#[derive(Deserialize)]
+	struct MediaDataId {
+		id: media_data::id::Type,
+	}
+
+	Ok(db
+		._query_raw::<MediaDataId>(query)
+		.exec()
+		.await?
+		.into_iter()
+		.map(|media_data| media_data.id)
+		.collect())
+}
+
+async fn camera_data_param(
+	db: &PrismaClient,
+	field: &str,
+	text_match: TextMatch,
+) -> Result<Vec<media_data::WhereParam>, rspc::Error> {
+	if text_match.is_empty() {
+		return Ok(vec![]);
+	}
+
+	let escape = |s: String| {
+		s.replace('\\', "\\\\")
+			.replace('%', "\\%")
+			.replace('_', "\\_")
+	};
+
+	let pattern = match text_match {
+		TextMatch::Contains(v) => format!("%{}%", escape(v)),
+		TextMatch::StartsWith(v) => format!("{}%", escape(v)),
+		TextMatch::EndsWith(v) => format!("%{}", escape(v)),
+		TextMatch::Equals(v) => escape(v),
+	};
+
+	// `field` is always one of our constants, so this is sql injection safe
+	Ok(vec![media_data::id::in_vec(
+		media_data_ids(
+			db,
+			raw!(
+				&format!(
+					"SELECT id FROM media_data
+					WHERE json_extract(CAST(camera_data AS TEXT), '$.{field}') LIKE {{}} ESCAPE '\\'"
+				),
+				PrismaValue::String(pattern)
+			),
+		)
+		.await?,
+	)])
+}
@@ -5,14 +5,20 @@ use crate::{
 	},
 	library::Library,
 	location::{non_indexed, LocationError},
-	object::media::old_thumbnail::get_indexed_thumb_key,
+	object::{
+		content_index::{self, SnippetPart},
+		media::old_thumbnail::{
+			get_indexed_thumb_key,
+			perceptual_hash::{cluster_similar, DEFAULT_MAX_DISTANCE},
+		},
+	},
 	util::{unsafe_streamed_query, BatchedStream},
 };
 
 use sd_cache::{CacheNode, Model, Normalise, Reference};
 use sd_prisma::prisma::{self, PrismaClient};
 
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use async_stream::stream;
 use futures::StreamExt;
@@ -21,9 +27,11 @@ use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
+pub mod duplicates;
 pub mod file_path;
 pub mod media_data;
 pub mod object;
+pub mod query;
 pub mod saved;
 mod utils;
 
@@ -62,7 +70,7 @@ impl SearchFilterArgs {
 	) -> Result<Vec<T>, rspc::Error> {
 		Ok(match self {
 			Self::FilePath(v) => file_path(v.into_params(db).await?),
-			Self::Object(v) => object(v.into_params()),
+			Self::Object(v) => object(v.into_params(db).await?),
 		})
 	}
 
@@ -83,6 +91,19 @@ impl SearchFilterArgs {
 	}
 }
 
+/// Appends the filters from a text query, if there is one
+async fn with_query_filters(
+	db: &PrismaClient,
+	mut filters: Vec<SearchFilterArgs>,
+	query: Option<String>,
+) -> Result<Vec<SearchFilterArgs>, rspc::Error> {
+	if let Some(query) = query {
+		filters.extend(query::parse_query(db, &query).await?);
+	}
+
+	Ok(filters)
+}
+
 pub fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("ephemeralPaths", {
@@ -100,6 +121,9 @@ pub fn mount() -> AlphaRouter<Ctx> {
 			struct EphemeralPathSearchArgs {
 				path: PathBuf,
 				with_hidden_files: bool,
+				#[serde(default)]
+				#[specta(optional)]
+				with_ignore_files: bool,
 				#[specta(optional)]
 				order: Option<EphemeralPathOrder>,
 			}
@@ -115,10 +139,16 @@ pub fn mount() -> AlphaRouter<Ctx> {
 				 EphemeralPathSearchArgs {
 				     path,
 				     with_hidden_files,
+				     with_ignore_files,
 				     order,
 				 }| async move {
-					let paths =
-						non_indexed::walk(path, with_hidden_files, node, library, |entries| {
+					let paths = non_indexed::walk(
+						path,
+						with_hidden_files,
+						with_ignore_files,
+						node,
+						library,
+						|entries| {
 							macro_rules! order_match {
 								($order:ident, [$(($variant:ident, |$i:ident| $func:expr)),+]) => {{
 									match $order {
@@ -150,8 +180,9 @@ pub fn mount() -> AlphaRouter<Ctx> {
 									]
 								)
 							}
-						})
-						.await?;
+						},
+					)
+					.await?;
 
 					let mut stream = BatchedStream::new(paths);
 					Ok(unsafe_streamed_query(stream! {
@@ -192,6 +223,9 @@ pub fn mount() -> AlphaRouter<Ctx> {
 				order_and_pagination: Option<file_path::OrderAndPagination>,
 				#[serde(default)]
 				filters: Vec<SearchFilterArgs>,
+				/// Text query like `kind:image ext:!raw`, parsed into more filters
+				#[specta(optional)]
+				query: Option<String>,
 				#[serde(default = "default_group_directories")]
 				group_directories: bool,
 			}
@@ -206,10 +240,13 @@ pub fn mount() -> AlphaRouter<Ctx> {
 				     take,
 				     order_and_pagination,
 				     filters,
+				     query,
 				     group_directories,
 				 }| async move {
 					let Library { db, .. } = library.as_ref();
 
+					let filters = with_query_filters(db, filters, query).await?;
+
 					let params = {
 						let mut params = Vec::new();
 
@@ -274,6 +311,25 @@ pub fn mount() -> AlphaRouter<Ctx> {
 				},
 			)
 		})
+		.procedure("parseQuery", {
+			#[derive(Serialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			enum ParsedSearchQuery {
+				Filters(Vec<SearchFilterArgs>),
+				Error { message: String, span: query::Span },
+			}
+
+			R.with2(library())
+				.query(|(_, library), query: String| async move {
+					match query::parse_query(&library.db, &query).await {
+						Ok(filters) => Ok(ParsedSearchQuery::Filters(filters)),
+						Err(query::SearchQueryError::Invalid { message, span }) => {
+							Ok(ParsedSearchQuery::Error { message, span })
+						}
+						Err(e) => Err(e.into()),
+					}
+				})
+		})
 		.procedure("pathsCount", {
 			#[derive(Deserialize, Type, Debug)]
 			#[serde(rename_all = "camelCase")]
@@ -311,6 +367,9 @@ pub fn mount() -> AlphaRouter<Ctx> {
 				order_and_pagination: Option<object::OrderAndPagination>,
 				#[serde(default)]
 				filters: Vec<SearchFilterArgs>,
+				/// Text query like `kind:image ext:!raw`, parsed into more filters
+				#[specta(optional)]
+				query: Option<String>,
 			}
 
 			R.with2(library()).query(
@@ -319,9 +378,12 @@ pub fn mount() -> AlphaRouter<Ctx> {
 				     take,
 				     order_and_pagination,
 				     filters,
+				     query,
 				 }| async move {
 					let Library { db, .. } = library.as_ref();
 
+					let filters = with_query_filters(db, filters, query).await?;
+
 					let take = take.max(MAX_TAKE);
 
 					let mut query = db
@@ -422,5 +484,376 @@ pub fn mount() -> AlphaRouter<Ctx> {
 						.await? as u32)
 				})
 		})
+		.procedure("duplicates", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct DuplicatesSearchArgs {
+				#[specta(optional)]
+				take: Option<u8>,
+				#[serde(default)]
+				skip: u32,
+				#[serde(default)]
+				filters: Vec<SearchFilterArgs>,
+				#[serde(default)]
+				size: duplicates::DuplicateSizeFilter,
+			}
+
+			#[derive(Serialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct DuplicateGroup {
+				object_id: prisma::object::id::Type,
+				size_in_bytes: String,
+				wasted_bytes: String,
+				file_paths: Vec<Reference<ExplorerItem>>,
+			}
+
+			#[derive(Serialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct DuplicatesSearchData {
+				groups: Vec<DuplicateGroup>,
+				total_groups: u32,
+				total_wasted_bytes: String,
+				nodes: Vec<CacheNode>,
+			}
+
+			R.with2(library()).query(
+				|(node, library),
+				 DuplicatesSearchArgs {
+				     take,
+				     skip,
+				     filters,
+				     size,
+				 }| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let params = {
+						let mut params = Vec::new();
+
+						for filter in filters {
+							params.extend(filter.into_file_path_params(db).await?);
+						}
+
+						params
+					};
+
+					let groups = duplicates::find_duplicate_groups(db, params, &size).await?;
+
+					let total_groups = groups.len() as u32;
+					let total_wasted_bytes = groups
+						.iter()
+						.map(duplicates::DuplicateGroup::wasted_bytes)
+						.sum::<u64>();
+
+					let groups = groups
+						.into_iter()
+						.skip(skip as usize)
+						.take(take.unwrap_or(MAX_TAKE).min(MAX_TAKE) as usize)
+						.collect::<Vec<_>>();
+
+					let mut file_paths = db
+						.file_path()
+						.find_many(vec![prisma::file_path::id::in_vec(
+							groups
+								.iter()
+								.flat_map(|group| group.file_path_ids.iter().copied())
+								.collect(),
+						)])
+						.include(file_path_with_object::include())
+						.exec()
+						.await?
+						.into_iter()
+						.map(|file_path| (file_path.id, file_path))
+						.collect::<HashMap<_, _>>();
+
+					let mut items = Vec::with_capacity(file_paths.len());
+					let mut group_lens = Vec::with_capacity(groups.len());
+
+					for group in &groups {
+						let len_before = items.len();
+
+						for file_path in group
+							.file_path_ids
+							.iter()
+							.filter_map(|id| file_paths.remove(id))
+						{
+							let thumbnail_exists_locally = if let Some(cas_id) = &file_path.cas_id {
+								library
+									.thumbnail_exists(&node, cas_id)
+									.await
+									.map_err(LocationError::from)?
+							} else {
+								false
+							};
+
+							items.push(ExplorerItem::Path {
+								thumbnail: file_path
+									.cas_id
+									.as_ref()
+									.filter(|_| thumbnail_exists_locally)
+									.map(|i| get_indexed_thumb_key(i, library.id)),
+								item: file_path,
+							});
+						}
+
+						group_lens.push(items.len() - len_before);
+					}
+
+					let (nodes, mut items) = items.normalise(|item| item.id());
+
+					Ok(DuplicatesSearchData {
+						groups: groups
+							.iter()
+							.zip(group_lens)
+							.map(|(group, len)| DuplicateGroup {
+								object_id: group.object_id,
+								size_in_bytes: group.size_in_bytes.to_string(),
+								wasted_bytes: group.wasted_bytes().to_string(),
+								file_paths: items.drain(..len).collect(),
+							})
+							.collect(),
+						total_groups,
+						total_wasted_bytes: total_wasted_bytes.to_string(),
+						nodes,
+					})
+				},
+			)
+		})
+		.procedure("similarObjects", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SimilarObjectsSearchArgs {
+				/// How many of the 64 perceptual hash bits can differ for objects to be similar
+				#[specta(optional)]
+				max_distance: Option<u32>,
+				#[specta(optional)]
+				take: Option<u8>,
+				#[serde(default)]
+				skip: u32,
+				#[serde(default)]
+				filters: Vec<SearchFilterArgs>,
+			}
+
+			#[derive(Serialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SimilarObjectsSearchData {
+				clusters: Vec<Vec<Reference<ExplorerItem>>>,
+				total_clusters: u32,
+				nodes: Vec<CacheNode>,
+			}
+
+			R.with2(library()).query(
+				|(node, library),
+				 SimilarObjectsSearchArgs {
+				     max_distance,
+				     take,
+				     skip,
+				     filters,
+				 }| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let params = {
+						let mut params = vec![prisma::object::perceptual_hash::not(None)];
+
+						for filter in filters {
+							params.extend(filter.into_object_params(db).await?);
+						}
+
+						params
+					};
+
+					let hashes = db
+						.object()
+						.find_many(params)
+						.select(prisma::object::select!({ id perceptual_hash }))
+						.exec()
+						.await?
+						.into_iter()
+						.filter_map(|object| {
+							object
+								.perceptual_hash
+								.and_then(|hash| <[u8; 8]>::try_from(hash).ok())
+								.map(|hash| (object.id, u64::from_be_bytes(hash)))
+						})
+						.collect::<Vec<_>>();
+
+					let clusters =
+						cluster_similar(&hashes, max_distance.unwrap_or(DEFAULT_MAX_DISTANCE));
+
+					let total_clusters = clusters.len() as u32;
+
+					let clusters = clusters
+						.into_iter()
+						.skip(skip as usize)
+						.take(take.unwrap_or(MAX_TAKE).min(MAX_TAKE) as usize)
+						.collect::<Vec<_>>();
+
+					let mut objects = db
+						.object()
+						.find_many(vec![prisma::object::id::in_vec(
+							clusters.iter().flatten().copied().collect(),
+						)])
+						.include(object_with_file_paths::include())
+						.exec()
+						.await?
+						.into_iter()
+						.map(|object| (object.id, object))
+						.collect::<HashMap<_, _>>();
+
+					let mut items = Vec::with_capacity(objects.len());
+					let mut cluster_lens = Vec::with_capacity(clusters.len());
+
+					for cluster in &clusters {
+						let len_before = items.len();
+
+						for object in cluster.iter().filter_map(|id| objects.remove(id)) {
+							let cas_id = object
+								.file_paths
+								.iter()
+								.find_map(|file_path| file_path.cas_id.as_ref());
+
+							let thumbnail_exists_locally = if let Some(cas_id) = cas_id {
+								library
+									.thumbnail_exists(&node, cas_id)
+									.await
+									.map_err(LocationError::from)?
+							} else {
+								false
+							};
+
+							items.push(ExplorerItem::Object {
+								thumbnail: cas_id
+									.filter(|_| thumbnail_exists_locally)
+									.map(|cas_id| get_indexed_thumb_key(cas_id, library.id)),
+								item: object,
+							});
+						}
+
+						cluster_lens.push(items.len() - len_before);
+					}
+
+					let (nodes, mut items) = items.normalise(|item| item.id());
+
+					Ok(SimilarObjectsSearchData {
+						clusters: cluster_lens
+							.into_iter()
+							.map(|len| items.drain(..len).collect())
+							.collect(),
+						total_clusters,
+						nodes,
+					})
+				},
+			)
+		})
+		.procedure("content", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct ContentSearchArgs {
+				query: String,
+				#[specta(optional)]
+				take: Option<u8>,
+				#[serde(default)]
+				filters: Vec<SearchFilterArgs>,
+			}
+
+			#[derive(Serialize, Type, Debug)]
+			struct ContentSearchItem {
+				item: Reference<ExplorerItem>,
+				/// Part of the contents around the match, with the matched words highlighted
+				snippet: Vec<SnippetPart>,
+			}
+
+			#[derive(Serialize, Type, Debug)]
+			struct ContentSearchData {
+				items: Vec<ContentSearchItem>,
+				nodes: Vec<CacheNode>,
+			}
+
+			R.with2(library()).query(
+				|(node, library),
+				 ContentSearchArgs {
+				     query,
+				     take,
+				     filters,
+				 }| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let matches =
+						content_index::search(db, &query, content_index::MAX_MATCHES).await?;
+
+					let params = {
+						let mut params = vec![prisma::object::id::in_vec(
+							matches
+								.iter()
+								.map(|content_match| content_match.object_id)
+								.collect(),
+						)];
+
+						for filter in filters {
+							params.extend(filter.into_object_params(db).await?);
+						}
+
+						params
+					};
+
+					let mut objects = db
+						.object()
+						.find_many(params)
+						.include(object_with_file_paths::include())
+						.exec()
+						.await?
+						.into_iter()
+						.map(|object| (object.id, object))
+						.collect::<HashMap<_, _>>();
+
+					let take = take.unwrap_or(MAX_TAKE).min(MAX_TAKE) as usize;
+					let mut items = Vec::with_capacity(take);
+					let mut snippets = Vec::with_capacity(take);
+
+					// Matches are sorted by relevance, so we keep their order
+					for content_match in matches {
+						if items.len() == take {
+							break;
+						}
+
+						let Some(object) = objects.remove(&content_match.object_id) else {
+							continue;
+						};
+
+						let cas_id = object
+							.file_paths
+							.iter()
+							.find_map(|file_path| file_path.cas_id.as_ref());
+
+						let thumbnail_exists_locally = if let Some(cas_id) = cas_id {
+							library
+								.thumbnail_exists(&node, cas_id)
+								.await
+								.map_err(LocationError::from)?
+						} else {
+							false
+						};
+
+						items.push(ExplorerItem::Object {
+							thumbnail: cas_id
+								.filter(|_| thumbnail_exists_locally)
+								.map(|cas_id| get_indexed_thumb_key(cas_id, library.id)),
+							item: object,
+						});
+						snippets.push(content_match.snippet);
+					}
+
+					let (nodes, items) = items.normalise(|item| item.id());
+
+					Ok(ContentSearchData {
+						items: items
+							.into_iter()
+							.zip(snippets)
+							.map(|(item, snippet)| ContentSearchItem { item, snippet })
+							.collect(),
+						nodes,
+					})
+				},
+			)
+		})
 		.merge("saved.", saved::mount())
 }
@@ -1,6 +1,9 @@
 // use crate::library::Category;
+use crate::object::tag::with_descendants;
 
-use sd_prisma::prisma::{self, label_on_object, object, tag_on_object};
+use sd_prisma::prisma::{
+	self, label_on_object, object, object_in_album, tag_on_object, PrismaClient,
+};
 
 use chrono::{DateTime, FixedOffset};
 use prisma_client_rust::{not, or, OrderByQuery, PaginatedQuery, WhereQuery};
@@ -113,15 +116,22 @@ pub enum ObjectFilterArgs {
 	Hidden(ObjectHiddenFilter),
 	Kind(InOrNotIn<i32>),
 	Tags(InOrNotIn<i32>),
+	/// Like `Tags`, but also matching the tags nested under the given ones
+	TagsWithDescendants(InOrNotIn<i32>),
 	Labels(InOrNotIn<i32>),
+	Albums(InOrNotIn<i32>),
 	DateAccessed(Range<chrono::DateTime<FixedOffset>>),
+	MediaData(MediaDataFilterArgs),
 }
 
 impl ObjectFilterArgs {
-	pub fn into_params(self) -> Vec<object::WhereParam> {
+	pub async fn into_params(
+		self,
+		db: &PrismaClient,
+	) -> Result<Vec<object::WhereParam>, rspc::Error> {
 		use object::*;
 
-		match self {
+		Ok(match self {
 			Self::Favorite(v) => vec![favorite::equals(Some(v))],
 			Self::Hidden(v) => v.to_param().map(|v| vec![v]).unwrap_or_default(),
 			Self::Tags(v) => v
@@ -131,6 +141,16 @@ impl ObjectFilterArgs {
 				)
 				.map(|v| vec![v])
 				.unwrap_or_default(),
+			Self::TagsWithDescendants(v) => match v {
+				InOrNotIn::In(v) => InOrNotIn::In(with_descendants(db, v).await?),
+				InOrNotIn::NotIn(v) => InOrNotIn::NotIn(with_descendants(db, v).await?),
+			}
+			.into_param(
+				|v| tags::some(vec![tag_on_object::tag_id::in_vec(v)]),
+				|v| tags::none(vec![tag_on_object::tag_id::in_vec(v)]),
+			)
+			.map(|v| vec![v])
+			.unwrap_or_default(),
 			Self::Labels(v) => v
 				.into_param(
 					|v| labels::some(vec![label_on_object::label_id::in_vec(v)]),
@@ -138,6 +158,13 @@ impl ObjectFilterArgs {
 				)
 				.map(|v| vec![v])
 				.unwrap_or_default(),
+			Self::Albums(v) => v
+				.into_param(
+					|v| albums::some(vec![object_in_album::album_id::in_vec(v)]),
+					|v| albums::none(vec![object_in_album::album_id::in_vec(v)]),
+				)
+				.map(|v| vec![v])
+				.unwrap_or_default(),
 			Self::Kind(v) => v
 				.into_param(kind::in_vec, kind::not_in_vec)
 				.map(|v| vec![v])
@@ -151,7 +178,8 @@ impl ObjectFilterArgs {
 					},
 				]
 			}
-		}
+			Self::MediaData(v) => vec![media_data::is(v.into_params(db).await?)],
+		})
 	}
 }
 
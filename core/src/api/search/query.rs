@@ -0,0 +1,784 @@
+//! A small query language so clients don't need to build [`SearchFilterArgs`] by hand, like:
+//! `kind:image tag:"Travel" size:>10MB modified:2024-01..2024-06 ext:!raw "beach"`
+//!
+//! Terms without a key match file names. Values can be quoted, lists are comma separated
+//! (`ext:jpg,png`) and a leading `!` excludes them (`ext:!raw`). Sizes and dates accept `>`, `>=`,
+//! `<`, `<=` and `from..to` ranges, where either side can be left open. Photos can also be
+//! filtered by megapixels (`mp:>20`), camera (`make:`, `model:`), capture date (`taken:`) and a
+//! bounding box of coordinates (`geo:38.5,-9.5,39,-9`), `tagtree:` matches tags along with
+//! every tag nested under them and `album:` the objects in the named albums.
+
+use sd_file_ext::kind::ObjectKind;
+use sd_prisma::prisma::{album, label, location, tag, PrismaClient};
+use sd_utils::parse_size;
+
+use chrono::{DateTime, Duration, Months, NaiveDate, TimeZone, Utc};
+use prisma_client_rust::QueryError;
+use rspc::ErrorCode;
+use serde::Serialize;
+use specta::Type;
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+use super::{
+	media_data::{GeoBoundingBox, MediaDataFilterArgs},
+	FilePathFilterArgs, InOrNotIn, ObjectFilterArgs, Range, SearchFilterArgs, TextMatch,
+};
+
+/// Position in the query, counted in characters
+#[derive(Serialize, Type, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+}
+
+impl Span {
+	const fn new(start: usize, end: usize) -> Self {
+		Self { start, end }
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum SearchQueryError {
+	#[error("{message} at {}..{}", .span.start, .span.end)]
+	Invalid { message: String, span: Span },
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+}
+
+impl From<SearchQueryError> for rspc::Error {
+	fn from(e: SearchQueryError) -> Self {
+		match e {
+			SearchQueryError::Invalid { .. } => {
+				rspc::Error::with_cause(ErrorCode::BadRequest, e.to_string(), e)
+			}
+			SearchQueryError::Database(e) => e.into(),
+		}
+	}
+}
+
+/// Filters referencing tags, labels, albums or locations by name, which we can only turn into ids with
+/// the database at hand
+#[derive(Debug)]
+struct NamedFilter {
+	kind: NamedKind,
+	names: InOrNotIn<String>,
+	span: Span,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NamedKind {
+	Tag,
+	TagTree,
+	Label,
+	Album,
+	Location,
+}
+
+impl NamedKind {
+	const fn name(self) -> &'static str {
+		match self {
+			Self::Tag | Self::TagTree => "tag",
+			Self::Label => "label",
+			Self::Album => "album",
+			Self::Location => "location",
+		}
+	}
+}
+
+#[derive(Default, Debug)]
+pub struct SearchQuery {
+	filters: Vec<SearchFilterArgs>,
+	named_filters: Vec<NamedFilter>,
+}
+
+impl SearchQuery {
+	pub fn parse(query: &str) -> Result<Self, SearchQueryError> {
+		let mut parsed = Self::default();
+
+		for token in tokenize(query)? {
+			parsed.push(token)?;
+		}
+
+		Ok(parsed)
+	}
+
+	/// Parses a query, or looks for the whole text in names if it isn't one. Searches are saved
+	/// with the text typed in the search bar, which was always a name before queries existed.
+	pub fn parse_or_name(query: &str) -> Self {
+		Self::parse(query).unwrap_or_else(|_| Self::name(query))
+	}
+
+	fn name(query: &str) -> Self {
+		Self {
+			filters: vec![SearchFilterArgs::FilePath(FilePathFilterArgs::Name(
+				TextMatch::Contains(query.to_string()),
+			))],
+			named_filters: vec![],
+		}
+	}
+
+	/// Looks up the tags, labels, albums and locations referenced by name, failing on unknown names
+	pub async fn resolve(
+		self,
+		db: &PrismaClient,
+	) -> Result<Vec<SearchFilterArgs>, SearchQueryError> {
+		let Self {
+			mut filters,
+			named_filters,
+		} = self;
+
+		for NamedFilter { kind, names, span } in named_filters {
+			let (InOrNotIn::In(list) | InOrNotIn::NotIn(list)) = &names;
+
+			let found = match kind {
+				NamedKind::Tag | NamedKind::TagTree => db
+					.tag()
+					.find_many(vec![tag::name::in_vec(list.clone())])
+					.select(tag::select!({ id name }))
+					.exec()
+					.await?
+					.into_iter()
+					.map(|tag| (tag.id, tag.name))
+					.collect::<Vec<_>>(),
+				NamedKind::Label => db
+					.label()
+					.find_many(vec![label::name::in_vec(list.clone())])
+					.select(label::select!({ id name }))
+					.exec()
+					.await?
+					.into_iter()
+					.map(|label| (label.id, Some(label.name)))
+					.collect(),
+				NamedKind::Album => db
+					.album()
+					.find_many(vec![album::name::in_vec(list.clone())])
+					.select(album::select!({ id name }))
+					.exec()
+					.await?
+					.into_iter()
+					.map(|album| (album.id, album.name))
+					.collect(),
+				NamedKind::Location => db
+					.location()
+					.find_many(vec![location::name::in_vec(list.clone())])
+					.select(location::select!({ id name }))
+					.exec()
+					.await?
+					.into_iter()
+					.map(|location| (location.id, location.name))
+					.collect(),
+			};
+
+			if let Some(missing) = list
+				.iter()
+				.find(|name| !found.iter().any(|(_, found)| found.as_ref() == Some(*name)))
+			{
+				return Err(SearchQueryError::Invalid {
+					message: format!("Unknown {} `{missing}`", kind.name()),
+					span,
+				});
+			}
+
+			let ids = found.into_iter().map(|(id, _)| id).collect();
+			let ids = match names {
+				InOrNotIn::In(_) => InOrNotIn::In(ids),
+				InOrNotIn::NotIn(_) => InOrNotIn::NotIn(ids),
+			};
+
+			filters.push(match kind {
+				NamedKind::Tag => SearchFilterArgs::Object(ObjectFilterArgs::Tags(ids)),
+				NamedKind::TagTree => {
+					SearchFilterArgs::Object(ObjectFilterArgs::TagsWithDescendants(ids))
+				}
+				NamedKind::Label => SearchFilterArgs::Object(ObjectFilterArgs::Labels(ids)),
+				NamedKind::Album => SearchFilterArgs::Object(ObjectFilterArgs::Albums(ids)),
+				NamedKind::Location => {
+					SearchFilterArgs::FilePath(FilePathFilterArgs::Locations(ids))
+				}
+			});
+		}
+
+		Ok(filters)
+	}
+
+	fn push(&mut self, token: Token) -> Result<(), SearchQueryError> {
+		use FilePathFilterArgs as FP;
+		use MediaDataFilterArgs as MD;
+		use ObjectFilterArgs as O;
+
+		let Some((key, key_span)) = &token.key else {
+			self.filters
+				.push(SearchFilterArgs::FilePath(FP::Name(TextMatch::Contains(
+					token.value,
+				))));
+			return Ok(());
+		};
+
+		if token.value.is_empty() && !token.quoted {
+			return Err(SearchQueryError::Invalid {
+				message: format!("Missing value for `{key}`"),
+				span: *key_span,
+			});
+		}
+
+		match key.as_str() {
+			"name" => self
+				.filters
+				.push(SearchFilterArgs::FilePath(FP::Name(TextMatch::Contains(
+					token.without_prefix()?.to_string(),
+				)))),
+			"content" => self.filters.push(SearchFilterArgs::FilePath(FP::Content(
+				token.without_prefix()?.to_string(),
+			))),
+			"ext" | "extension" => self.filters.push(SearchFilterArgs::FilePath(FP::Extension(
+				token.in_or_not_in(|ext| Ok(ext.trim_start_matches('.').to_lowercase()))?,
+			))),
+			"kind" => self
+				.filters
+				.push(SearchFilterArgs::Object(O::Kind(token.in_or_not_in(
+					|name| {
+						ObjectKind::iter()
+							.find(|kind| kind.to_string().eq_ignore_ascii_case(name))
+							.map(|kind| kind as i32)
+							.ok_or_else(|| format!("Unknown kind `{name}`"))
+					},
+				)?))),
+			"tag" | "tags" => self.push_named(NamedKind::Tag, &token)?,
+			"tagtree" => self.push_named(NamedKind::TagTree, &token)?,
+			"label" | "labels" => self.push_named(NamedKind::Label, &token)?,
+			"album" | "albums" => self.push_named(NamedKind::Album, &token)?,
+			"in" | "location" => self.push_named(NamedKind::Location, &token)?,
+			"size" => {
+				let (from, to) = token.bounds(
+					"size",
+					|value| parse_size(value).map(|size| (size, size)),
+					|size| size.saturating_add(1),
+					|size| size.saturating_sub(1),
+				)?;
+
+				self.filters.extend(
+					[from.map(Range::From), to.map(Range::To)]
+						.into_iter()
+						.flatten()
+						.map(|range| {
+							SearchFilterArgs::FilePath(FP::SizeInBytes(match range {
+								Range::From(size) => Range::From(size.to_string()),
+								Range::To(size) => Range::To(size.to_string()),
+							}))
+						}),
+				);
+			}
+			"created" | "modified" | "indexed" | "accessed" | "taken" => {
+				let (from, to) = token.bounds(
+					"date",
+					parse_date,
+					|date| date + Duration::nanoseconds(1),
+					|date| date - Duration::nanoseconds(1),
+				)?;
+
+				self.filters.extend(
+					[from.map(Range::From), to.map(Range::To)]
+						.into_iter()
+						.flatten()
+						.map(|range| match key.as_str() {
+							"created" => SearchFilterArgs::FilePath(FP::CreatedAt(range)),
+							"modified" => SearchFilterArgs::FilePath(FP::ModifiedAt(range)),
+							"indexed" => SearchFilterArgs::FilePath(FP::IndexedAt(range)),
+							"taken" => SearchFilterArgs::Object(O::MediaData(MD::TakenAt(range))),
+							_ => SearchFilterArgs::Object(O::DateAccessed(match range {
+								Range::From(date) => Range::From(date.into()),
+								Range::To(date) => Range::To(date.into()),
+							})),
+						}),
+				);
+			}
+			"mp" | "megapixels" => {
+				let (from, to) = token.bounds(
+					"megapixel count",
+					|value| value.parse::<f64>().ok().map(|mp| (mp, mp)),
+					|mp| mp,
+					|mp| mp,
+				)?;
+
+				self.filters.extend(
+					[from.map(Range::From), to.map(Range::To)]
+						.into_iter()
+						.flatten()
+						.map(|range| SearchFilterArgs::Object(O::MediaData(MD::Megapixels(range)))),
+				);
+			}
+			"make" => self
+				.filters
+				.push(SearchFilterArgs::Object(O::MediaData(MD::CameraMake(
+					TextMatch::Contains(token.without_prefix()?.to_string()),
+				)))),
+			"model" | "camera" => {
+				self.filters
+					.push(SearchFilterArgs::Object(O::MediaData(MD::CameraModel(
+						TextMatch::Contains(token.without_prefix()?.to_string()),
+					))))
+			}
+			"artist" => self
+				.filters
+				.push(SearchFilterArgs::Object(O::MediaData(MD::Artist(
+					TextMatch::Contains(token.without_prefix()?.to_string()),
+				)))),
+			"geo" => self
+				.filters
+				.push(SearchFilterArgs::Object(O::MediaData(MD::Location(
+					token.bounding_box()?,
+				)))),
+			"hidden" => self
+				.filters
+				.push(SearchFilterArgs::FilePath(FP::Hidden(token.boolean()?))),
+			"favorite" | "fav" => self
+				.filters
+				.push(SearchFilterArgs::Object(O::Favorite(token.boolean()?))),
+			_ => {
+				return Err(SearchQueryError::Invalid {
+					message: format!("Unknown filter `{key}`"),
+					span: *key_span,
+				})
+			}
+		}
+
+		Ok(())
+	}
+
+	fn push_named(&mut self, kind: NamedKind, token: &Token) -> Result<(), SearchQueryError> {
+		self.named_filters.push(NamedFilter {
+			kind,
+			names: token.in_or_not_in(|name| Ok(name.to_string()))?,
+			span: token.span,
+		});
+
+		Ok(())
+	}
+}
+
+/// Parses a query and resolves it into filters
+pub async fn parse_query(
+	db: &PrismaClient,
+	query: &str,
+) -> Result<Vec<SearchFilterArgs>, SearchQueryError> {
+	SearchQuery::parse(query)?.resolve(db).await
+}
+
+/// Filters of the text saved along a search, see [`SearchQuery::parse_or_name`]
+pub async fn parse_saved_query(
+	db: &PrismaClient,
+	query: &str,
+) -> Result<Vec<SearchFilterArgs>, QueryError> {
+	match SearchQuery::parse_or_name(query).resolve(db).await {
+		Ok(filters) => Ok(filters),
+		// Like text that isn't a query, a name which doesn't exist anymore is looked for in names
+		Err(SearchQueryError::Invalid { .. }) => Ok(SearchQuery::name(query).filters),
+		Err(SearchQueryError::Database(e)) => Err(e),
+	}
+}
+
+#[derive(Debug)]
+struct Token {
+	key: Option<(String, Span)>,
+	/// Operator before the value, like `!` or `>=`, only allowed after a key
+	prefix: String,
+	value: String,
+	quoted: bool,
+	/// Span of the prefix and value
+	span: Span,
+}
+
+impl Token {
+	fn error(&self, message: impl Into<String>) -> SearchQueryError {
+		SearchQueryError::Invalid {
+			message: message.into(),
+			span: self.span,
+		}
+	}
+
+	fn unexpected_prefix(&self) -> SearchQueryError {
+		self.error(format!("Unexpected `{}`", self.prefix))
+	}
+
+	fn without_prefix(&self) -> Result<&str, SearchQueryError> {
+		if self.prefix.is_empty() {
+			Ok(&self.value)
+		} else {
+			Err(self.unexpected_prefix())
+		}
+	}
+
+	fn in_or_not_in<T>(
+		&self,
+		parse: impl Fn(&str) -> Result<T, String>,
+	) -> Result<InOrNotIn<T>, SearchQueryError> {
+		let items = if self.quoted {
+			vec![self.value.as_str()]
+		} else {
+			self.value
+				.split(',')
+				.filter(|item| !item.is_empty())
+				.collect()
+		}
+		.into_iter()
+		.map(|item| parse(item).map_err(|message| self.error(message)))
+		.collect::<Result<Vec<_>, _>>()?;
+
+		match self.prefix.as_str() {
+			"" => Ok(InOrNotIn::In(items)),
+			"!" => Ok(InOrNotIn::NotIn(items)),
+			_ => Err(self.unexpected_prefix()),
+		}
+	}
+
+	fn boolean(&self) -> Result<bool, SearchQueryError> {
+		match self.without_prefix()?.to_lowercase().as_str() {
+			"true" | "yes" => Ok(true),
+			"false" | "no" => Ok(false),
+			value => Err(self.error(format!("Expected `true` or `false`, found `{value}`"))),
+		}
+	}
+
+	/// Bounding boxes are written as `min_latitude,min_longitude,max_latitude,max_longitude`
+	fn bounding_box(&self) -> Result<GeoBoundingBox, SearchQueryError> {
+		let coordinates = self
+			.without_prefix()?
+			.split(',')
+			.map(|coordinate| coordinate.trim().parse::<f64>())
+			.collect::<Result<Vec<_>, _>>();
+
+		match coordinates.as_deref() {
+			Ok(&[min_latitude, min_longitude, max_latitude, max_longitude]) => Ok(GeoBoundingBox {
+				min_latitude,
+				max_latitude,
+				min_longitude,
+				max_longitude,
+			}),
+			_ => Err(self.error(
+				"Expected `min_latitude,min_longitude,max_latitude,max_longitude` coordinates",
+			)),
+		}
+	}
+
+	/// Inclusive bounds of a comparison, where `parse_value` gives the first and last values a
+	/// single value stands for, like the first and last instants of `2024-01`
+	fn bounds<T: Copy>(
+		&self,
+		what: &str,
+		parse_value: impl Fn(&str) -> Option<(T, T)>,
+		after: impl Fn(T) -> T,
+		before: impl Fn(T) -> T,
+	) -> Result<(Option<T>, Option<T>), SearchQueryError> {
+		let parse = |value: &str| {
+			parse_value(value).ok_or_else(|| self.error(format!("Invalid {what} `{value}`")))
+		};
+
+		Ok(match self.prefix.as_str() {
+			"" | "=" => {
+				if let Some((from, to)) = self.value.split_once("..") {
+					if from.is_empty() && to.is_empty() {
+						return Err(self.error(format!("Missing {what} range bounds")));
+					}
+
+					(
+						(!from.is_empty())
+							.then(|| parse(from).map(|(first, _)| first))
+							.transpose()?,
+						(!to.is_empty())
+							.then(|| parse(to).map(|(_, last)| last))
+							.transpose()?,
+					)
+				} else {
+					let (first, last) = parse(&self.value)?;
+					(Some(first), Some(last))
+				}
+			}
+			">" => (Some(after(parse(&self.value)?.1)), None),
+			">=" => (Some(parse(&self.value)?.0), None),
+			"<" => (None, Some(before(parse(&self.value)?.0))),
+			"<=" => (None, Some(parse(&self.value)?.1)),
+			_ => return Err(self.unexpected_prefix()),
+		})
+	}
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, SearchQueryError> {
+	let chars = query.chars().collect::<Vec<_>>();
+	let mut tokens = vec![];
+	let mut pos = 0;
+
+	while pos < chars.len() {
+		if chars[pos].is_whitespace() {
+			pos += 1;
+			continue;
+		}
+
+		let key_len = chars[pos..]
+			.iter()
+			.take_while(|c| c.is_ascii_alphabetic())
+			.count();
+
+		let key = if key_len > 0 && chars.get(pos + key_len) == Some(&':') {
+			let key = (
+				chars[pos..pos + key_len]
+					.iter()
+					.collect::<String>()
+					.to_ascii_lowercase(),
+				Span::new(pos, pos + key_len),
+			);
+			pos += key_len + 1;
+			Some(key)
+		} else {
+			None
+		};
+
+		let value_start = pos;
+
+		let prefix_len = if key.is_some() {
+			chars[pos..]
+				.iter()
+				.take_while(|c| matches!(c, '!' | '<' | '>' | '='))
+				.count()
+		} else {
+			0
+		};
+		let prefix = chars[pos..pos + prefix_len].iter().collect();
+		pos += prefix_len;
+
+		let (value, quoted) = if chars.get(pos) == Some(&'"') {
+			let quote_start = pos;
+			let mut value = String::new();
+			pos += 1;
+
+			loop {
+				match chars.get(pos) {
+					None => {
+						return Err(SearchQueryError::Invalid {
+							message: "Unterminated quote".into(),
+							span: Span::new(quote_start, chars.len()),
+						})
+					}
+					Some('"') => {
+						pos += 1;
+						break;
+					}
+					Some('\\') if matches!(chars.get(pos + 1), Some('"' | '\\')) => {
+						value.push(chars[pos + 1]);
+						pos += 2;
+					}
+					Some(c) => {
+						value.push(*c);
+						pos += 1;
+					}
+				}
+			}
+
+			if chars.get(pos).map_or(false, |c| !c.is_whitespace()) {
+				return Err(SearchQueryError::Invalid {
+					message: "Expected a space after the closing quote".into(),
+					span: Span::new(pos, pos + 1),
+				});
+			}
+
+			(value, true)
+		} else {
+			let len = chars[pos..]
+				.iter()
+				.take_while(|c| !c.is_whitespace())
+				.count();
+			let value = chars[pos..pos + len].iter().collect();
+			pos += len;
+
+			(value, false)
+		};
+
+		tokens.push(Token {
+			key,
+			prefix,
+			value,
+			quoted,
+			span: Span::new(value_start, pos),
+		});
+	}
+
+	Ok(tokens)
+}
+
+/// Dates like `2024`, `2024-03` or `2024-03-15`, in UTC, standing for every instant in them
+fn parse_date(value: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+	let mut parts = value.splitn(3, '-');
+
+	let year = parts.next()?.parse::<i32>().ok()?;
+	let month = parts.next().map(str::parse::<u32>).transpose().ok()?;
+	let day = parts.next().map(str::parse::<u32>).transpose().ok()?;
+
+	let first_day = NaiveDate::from_ymd_opt(year, month.unwrap_or(1), day.unwrap_or(1))?;
+	let next_first_day = match (month, day) {
+		(None, _) => first_day.checked_add_months(Months::new(12))?,
+		(Some(_), None) => first_day.checked_add_months(Months::new(1))?,
+		(Some(_), Some(_)) => first_day.succ_opt()?,
+	};
+
+	Some((
+		Utc.from_utc_datetime(&first_day.and_hms_opt(0, 0, 0)?),
+		Utc.from_utc_datetime(&next_first_day.and_hms_opt(0, 0, 0)?) - Duration::nanoseconds(1),
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn date(value: &str) -> DateTime<Utc> {
+		DateTime::parse_from_rfc3339(value).unwrap().into()
+	}
+
+	#[test]
+	fn full_query() {
+		let SearchQuery {
+			filters,
+			named_filters,
+		} = SearchQuery::parse(
+			r#"kind:image tag:"Travel" size:>10MB modified:2024-01..2024-06 ext:!raw "beach""#,
+		)
+		.unwrap();
+
+		assert!(matches!(
+			&filters[..],
+			[
+				SearchFilterArgs::Object(ObjectFilterArgs::Kind(InOrNotIn::In(kinds))),
+				SearchFilterArgs::FilePath(FilePathFilterArgs::SizeInBytes(Range::From(size))),
+				SearchFilterArgs::FilePath(FilePathFilterArgs::ModifiedAt(Range::From(from))),
+				SearchFilterArgs::FilePath(FilePathFilterArgs::ModifiedAt(Range::To(to))),
+				SearchFilterArgs::FilePath(FilePathFilterArgs::Extension(InOrNotIn::NotIn(exts))),
+				SearchFilterArgs::FilePath(FilePathFilterArgs::Name(TextMatch::Contains(name))),
+			] if kinds == &[ObjectKind::Image as i32]
+				&& size == "10000001"
+				&& *from == date("2024-01-01T00:00:00Z")
+				&& *to == date("2024-06-30T23:59:59.999999999Z")
+				&& exts == &["raw"]
+				&& name == "beach"
+		));
+
+		assert!(matches!(
+			&named_filters[..],
+			[NamedFilter {
+				kind: NamedKind::Tag,
+				names: InOrNotIn::In(names),
+				span: Span { start: 15, end: 23 },
+			}] if names == &["Travel"]
+		));
+	}
+
+	#[test]
+	fn tag_trees() {
+		let SearchQuery { named_filters, .. } = SearchQuery::parse("tagtree:!Projects").unwrap();
+
+		assert!(matches!(
+			&named_filters[..],
+			[NamedFilter {
+				kind: NamedKind::TagTree,
+				names: InOrNotIn::NotIn(names),
+				..
+			}] if names == &["Projects"]
+		));
+	}
+
+	#[test]
+	fn albums() {
+		let SearchQuery { named_filters, .. } =
+			SearchQuery::parse("album:Summer,\"Road trip\"").unwrap();
+
+		assert!(matches!(
+			&named_filters[..],
+			[NamedFilter {
+				kind: NamedKind::Album,
+				names: InOrNotIn::In(names),
+				..
+			}] if names == &["Summer", "Road trip"]
+		));
+	}
+
+	#[test]
+	fn saved_text() {
+		for text in ["notes: draft", r#"my "quoted file"#, "size:huge"] {
+			let SearchQuery {
+				filters,
+				named_filters,
+			} = SearchQuery::parse_or_name(text);
+
+			assert!(matches!(
+				&filters[..],
+				[SearchFilterArgs::FilePath(FilePathFilterArgs::Name(TextMatch::Contains(name)))]
+					if name == text
+			));
+			assert!(named_filters.is_empty());
+		}
+
+		let SearchQuery { filters, .. } = SearchQuery::parse_or_name("kind:image");
+		assert!(matches!(
+			&filters[..],
+			[SearchFilterArgs::Object(ObjectFilterArgs::Kind(_))]
+		));
+	}
+
+	#[test]
+	fn media_data() {
+		let SearchQuery { filters, .. } =
+			SearchQuery::parse("mp:>=20 camera:X-T5 geo:38.5,-9.5,39,-9").unwrap();
+
+		assert!(matches!(
+			&filters[..],
+			[
+				SearchFilterArgs::Object(ObjectFilterArgs::MediaData(
+					MediaDataFilterArgs::Megapixels(Range::From(megapixels))
+				)),
+				SearchFilterArgs::Object(ObjectFilterArgs::MediaData(
+					MediaDataFilterArgs::CameraModel(TextMatch::Contains(model))
+				)),
+				SearchFilterArgs::Object(ObjectFilterArgs::MediaData(
+					MediaDataFilterArgs::Location(GeoBoundingBox {
+						min_latitude,
+						max_longitude,
+						..
+					})
+				)),
+			] if *megapixels == 20.0 && model == "X-T5" && *min_latitude == 38.5 && *max_longitude == -9.0
+		));
+
+		assert!(SearchQuery::parse("geo:1,2,3").is_err());
+	}
+
+	#[test]
+	fn dates() {
+		assert_eq!(
+			parse_date("2024"),
+			Some((
+				date("2024-01-01T00:00:00Z"),
+				date("2024-12-31T23:59:59.999999999Z")
+			))
+		);
+		assert_eq!(
+			parse_date("2024-02-29").map(|(first, _)| first),
+			Some(date("2024-02-29T00:00:00Z"))
+		);
+		assert_eq!(parse_date("2023-02-29"), None);
+		assert_eq!(parse_date("yesterday"), None);
+	}
+
+	#[test]
+	fn errors() {
+		let span = |query| match SearchQuery::parse(query) {
+			Err(SearchQueryError::Invalid { span, .. }) => Some(span),
+			_ => None,
+		};
+
+		assert_eq!(span(r#"name:"unterminated"#), Some(Span::new(5, 18)));
+		assert_eq!(span("colour:red"), Some(Span::new(0, 6)));
+		assert_eq!(span("size:>lots"), Some(Span::new(5, 10)));
+		assert_eq!(span("kind:image,potato"), Some(Span::new(5, 17)));
+		assert_eq!(span("ext:"), Some(Span::new(0, 3)));
+		assert_eq!(span("été:x"), None);
+	}
+}
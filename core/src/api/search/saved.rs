@@ -1,17 +1,204 @@
-use crate::{api::utils::library, invalidate_query, library::Library};
+use crate::{
+	api::{
+		locations::{file_path_with_object, ExplorerItem},
+		utils::{library, InvalidateOperationEvent},
+		CoreEvent,
+	},
+	invalidate_query,
+	library::Library,
+	location::LocationError,
+	object::{
+		fs::{find_available_filename_for_duplicate, old_copy::OldFileCopierJobInit},
+		media::old_thumbnail::get_indexed_thumb_key,
+	},
+	old_job::Job,
+	Node,
+};
 
-use sd_prisma::{prisma::saved_search, prisma_sync};
+use sd_cache::{CacheNode, Normalise, Reference};
+use sd_prisma::{
+	prisma::{file_path, location, saved_search, PrismaClient},
+	prisma_sync,
+};
 use sd_sync::{option_sync_db_entry, sync_db_entry, OperationFactory};
-use sd_utils::chain_optional_iter;
+use sd_utils::{chain_optional_iter, error::FileIOError};
+
+use std::{
+	collections::{BTreeMap, HashSet},
+	path::PathBuf,
+	time::Duration,
+};
 
 use chrono::{DateTime, FixedOffset, Utc};
-use rspc::alpha::AlphaRouter;
+use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{de::IgnoredAny, Deserialize, Serialize};
 use specta::Type;
+use tokio::{
+	fs, io,
+	sync::broadcast::error::{RecvError, TryRecvError},
+	time::sleep,
+};
 use tracing::error;
 use uuid::Uuid;
 
-use super::{Ctx, R};
+use super::{query, Ctx, SearchFilterArgs, R};
+
+/// How long to wait for a burst of changes, like the ones from an indexer job, to settle before
+/// re-evaluating a saved search
+const CHANGES_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Invalidated queries which may change the results of a saved search
+const CHANGES_KEYS: [&str; 8] = [
+	"search.paths",
+	"search.objects",
+	"search.saved.get",
+	"search.saved.list",
+	"tags.getForObject",
+	"tags.getWithObjects",
+	"labels.getForObject",
+	"labels.getWithObjects",
+];
+
+/// Loads the filters of a saved search, along with the ones from its text query
+pub(crate) async fn saved_search_filters(
+	db: &PrismaClient,
+	search_id: saved_search::id::Type,
+) -> Result<Vec<SearchFilterArgs>, rspc::Error> {
+	let search = db
+		.saved_search()
+		.find_unique(saved_search::id::equals(search_id))
+		.select(saved_search::select!({ filters search }))
+		.exec()
+		.await?
+		.ok_or_else(|| rspc::Error::new(ErrorCode::NotFound, "search not found".into()))?;
+
+	let mut filters = search
+		.filters
+		.as_deref()
+		.map(serde_json::from_str::<Vec<SearchFilterArgs>>)
+		.transpose()
+		.map_err(|e| {
+			rspc::Error::with_cause(
+				ErrorCode::InternalServerError,
+				"saved search has invalid filters".into(),
+				e,
+			)
+		})?
+		.unwrap_or_default();
+
+	if let Some(search) = &search.search {
+		filters.extend(query::parse_saved_query(db, search).await?);
+	}
+
+	Ok(filters)
+}
+
+/// Params matching the file paths currently in a saved search, so it can be used as a virtual folder
+pub(crate) async fn saved_search_file_path_params(
+	db: &PrismaClient,
+	search_id: saved_search::id::Type,
+) -> Result<Vec<file_path::WhereParam>, rspc::Error> {
+	let mut params = vec![];
+
+	for filter in saved_search_filters(db, search_id).await? {
+		params.extend(filter.into_file_path_params(db).await?);
+	}
+
+	Ok(params)
+}
+
+#[derive(Serialize, Type, Debug)]
+struct SavedSearchChanges {
+	added: Vec<Reference<ExplorerItem>>,
+	removed: Vec<file_path::id::Type>,
+	nodes: Vec<CacheNode>,
+}
+
+/// Re-evaluates a saved search, updating `matched` and returning what changed since the last time,
+/// or `None` if the saved search was deleted
+async fn saved_search_changes(
+	node: &Node,
+	library: &Library,
+	search_id: saved_search::id::Type,
+	matched: &mut HashSet<file_path::id::Type>,
+) -> Result<Option<SavedSearchChanges>, rspc::Error> {
+	let Library { db, .. } = library;
+
+	if db
+		.saved_search()
+		.count(vec![saved_search::id::equals(search_id)])
+		.exec()
+		.await?
+		== 0
+	{
+		return Ok(None);
+	}
+
+	let current = db
+		.file_path()
+		.find_many(saved_search_file_path_params(db, search_id).await?)
+		.select(file_path::select!({ id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|file_path| file_path.id)
+		.collect::<HashSet<_>>();
+
+	let removed = matched.difference(&current).copied().collect::<Vec<_>>();
+	let added = current.difference(matched).copied().collect::<Vec<_>>();
+
+	*matched = current;
+
+	let file_paths = if added.is_empty() {
+		vec![]
+	} else {
+		db.file_path()
+			.find_many(vec![file_path::id::in_vec(added)])
+			.include(file_path_with_object::include())
+			.exec()
+			.await?
+	};
+
+	let mut items = Vec::with_capacity(file_paths.len());
+
+	for file_path in file_paths {
+		let thumbnail_exists_locally = if let Some(cas_id) = &file_path.cas_id {
+			library
+				.thumbnail_exists(node, cas_id)
+				.await
+				.map_err(LocationError::from)?
+		} else {
+			false
+		};
+
+		items.push(ExplorerItem::Path {
+			thumbnail: file_path
+				.cas_id
+				.as_ref()
+				.filter(|_| thumbnail_exists_locally)
+				.map(|i| get_indexed_thumb_key(i, library.id)),
+			item: file_path,
+		})
+	}
+
+	let (nodes, added) = items.normalise(|item| item.id());
+
+	Ok(Some(SavedSearchChanges {
+		added,
+		removed,
+		nodes,
+	}))
+}
+
+fn affects_saved_searches(event: &CoreEvent) -> bool {
+	match event {
+		CoreEvent::InvalidateOperation(InvalidateOperationEvent::Single(event)) => {
+			CHANGES_KEYS.contains(&event.key)
+		}
+		CoreEvent::InvalidateOperation(InvalidateOperationEvent::All) => true,
+		_ => false,
+	}
+}
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
@@ -33,6 +220,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 
 				|(_, library), args: Args| async move {
 					let Library { db, sync, .. } = library.as_ref();
+
 					let pub_id = Uuid::new_v4().as_bytes().to_vec();
 					let date_created: DateTime<FixedOffset> = Utc::now().into();
 
@@ -106,6 +294,196 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					.await?)
 			})
 		})
+		.procedure("changes", {
+			// Live results of a saved search
+			// - the first update has every matched file path in `added`
+			// - following updates are deltas, sent whenever indexing, tagging or labeling changes the results
+			// - the subscription ends when the saved search is deleted
+			R.with2(library()).subscription(
+				|(node, library), search_id: saved_search::id::Type| async move {
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+					let mut matched = HashSet::new();
+
+					async_stream::stream! {
+						let mut first = true;
+
+						'changes: loop {
+							match saved_search_changes(&node, &library, search_id, &mut matched).await {
+								Ok(Some(changes)) => {
+									if first || !changes.added.is_empty() || !changes.removed.is_empty() {
+										yield changes;
+									}
+								}
+								Ok(None) => break,
+								Err(e) => {
+									error!("Failed to evaluate saved search {search_id}: {e:#?}");
+									break;
+								}
+							}
+
+							first = false;
+
+							loop {
+								match event_bus_rx.recv().await {
+									Ok(event) if affects_saved_searches(&event) => break,
+									Ok(_) => {}
+									// We missed events, so we re-evaluate to be safe
+									Err(RecvError::Lagged(_)) => break,
+									Err(RecvError::Closed) => break 'changes,
+								}
+							}
+
+							sleep(CHANGES_DEBOUNCE).await;
+
+							// Everything that happened while we waited is covered by a single re-evaluation
+							while !matches!(
+								event_bus_rx.try_recv(),
+								Err(TryRecvError::Empty | TryRecvError::Closed)
+							) {}
+						}
+					}
+				},
+			)
+		})
+		.procedure("copy", {
+			#[derive(Type, Deserialize, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SavedSearchCopyArgs {
+				id: saved_search::id::Type,
+				target_location_id: location::id::Type,
+				target_location_relative_directory_path: PathBuf,
+			}
+
+			// Copies everything currently matched by a saved search, with a job per source location
+			R.with2(library()).mutation(
+				|(node, library),
+				 SavedSearchCopyArgs {
+				     id,
+				     target_location_id,
+				     target_location_relative_directory_path,
+				 }: SavedSearchCopyArgs| async move {
+					let file_paths = library
+						.db
+						.file_path()
+						.find_many(saved_search_file_path_params(&library.db, id).await?)
+						.select(file_path::select!({ id location_id }))
+						.exec()
+						.await?;
+
+					let mut by_location = BTreeMap::<_, Vec<_>>::new();
+					for file_path in file_paths {
+						if let Some(location_id) = file_path.location_id {
+							by_location
+								.entry(location_id)
+								.or_default()
+								.push(file_path.id);
+						}
+					}
+
+					for (source_location_id, sources_file_path_ids) in by_location {
+						Job::new(OldFileCopierJobInit {
+							source_location_id,
+							target_location_id,
+							sources_file_path_ids,
+							target_location_relative_directory_path:
+								target_location_relative_directory_path.clone(),
+						})
+						.spawn(&node, &library)
+						.await?;
+					}
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("export", {
+			#[derive(Type, Deserialize, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SavedSearchExportArgs {
+				id: saved_search::id::Type,
+				target_dir: PathBuf,
+			}
+
+			// Copies the files currently matched by a saved search on this device into a directory,
+			// which doesn't have to be in a location. They're written side by side, so clashing
+			// names get a ` (n)` suffix.
+			R.with2(library())
+				.mutation(|(_, library), args: SavedSearchExportArgs| async move {
+					let SavedSearchExportArgs { id, target_dir } = args;
+
+					match fs::metadata(&target_dir).await {
+						Ok(metadata) if metadata.is_dir() => {}
+						Ok(_) => {
+							return Err(rspc::Error::new(
+								ErrorCode::BadRequest,
+								"Target is not a directory".to_string(),
+							))
+						}
+						Err(e) => {
+							let e =
+								FileIOError::from((&target_dir, e, "Target directory not found"));
+							return Err(rspc::Error::with_cause(
+								ErrorCode::BadRequest,
+								e.to_string(),
+								e,
+							));
+						}
+					}
+
+					let file_path_ids = library
+						.db
+						.file_path()
+						.find_many(
+							saved_search_file_path_params(&library.db, id)
+								.await?
+								.into_iter()
+								.chain([file_path::is_dir::equals(Some(false))])
+								.collect(),
+						)
+						.select(file_path::select!({ id }))
+						.exec()
+						.await?
+						.into_iter()
+						.map(|file_path| file_path.id)
+						.collect();
+
+					// Files from locations on other devices have no path here, so they're skipped
+					for source in library
+						.get_file_paths(file_path_ids)
+						.await?
+						.into_values()
+						.flatten()
+					{
+						let Some(name) = source.file_name() else {
+							continue;
+						};
+
+						let mut target = target_dir.join(name);
+						match fs::metadata(&target).await {
+							Ok(_) => {
+								target = find_available_filename_for_duplicate(&target).await?
+							}
+							Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+							Err(e) => {
+								return Err(FileIOError::from((
+									target,
+									e,
+									"Failed to get target file metadata",
+								))
+								.into())
+							}
+						}
+
+						fs::copy(&source, &target).await.map_err(|e| {
+							FileIOError::from((&source, e, "Failed to export file"))
+						})?;
+					}
+
+					invalidate_query!(library, "search.ephemeralPaths");
+
+					Ok(())
+				})
+		})
 		.procedure("update", {
 			R.with2(library()).mutation({
 				saved_search::partial_unchecked!(Args {
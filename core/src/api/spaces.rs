@@ -0,0 +1,744 @@
+use crate::{
+	api::locations::{object_with_file_paths, ExplorerItem},
+	invalidate_query,
+	library::Library,
+	object::media::old_thumbnail::get_indexed_thumb_key,
+};
+
+use sd_cache::{Normalise, NormalisedResults};
+use sd_prisma::{
+	prisma::{object, object_in_space, space, space_link, PrismaClient, SortOrder},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::msgpack;
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use prisma_client_rust::or;
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use uuid::Uuid;
+
+use super::{utils::library, Ctx, R};
+
+object_in_space::select!(space_item {
+	object_id
+	x
+	y
+	width
+	height
+	z_index
+});
+
+#[derive(Serialize, Type, Debug)]
+pub struct SpaceWithLayout {
+	pub space: space::Data,
+	pub items: Vec<space_item::Data>,
+	pub links: Vec<space_link::Data>,
+}
+
+#[derive(Type, Deserialize, Debug)]
+pub struct SpaceObjectsArgs {
+	pub space_id: space::id::Type,
+	pub object_ids: Vec<object::id::Type>,
+}
+
+/// Layout of an item on the canvas, fields left as `None` are kept as they are
+#[derive(Type, Deserialize, Debug)]
+pub struct SpaceItemLayout {
+	pub object_id: object::id::Type,
+	#[serde(default)]
+	#[specta(optional)]
+	pub x: Option<f64>,
+	#[serde(default)]
+	#[specta(optional)]
+	pub y: Option<f64>,
+	#[serde(default)]
+	#[specta(optional)]
+	pub width: Option<f64>,
+	#[serde(default)]
+	#[specta(optional)]
+	pub height: Option<f64>,
+	#[serde(default)]
+	#[specta(optional)]
+	pub z_index: Option<i32>,
+}
+
+fn sync_id(space_pub_id: &[u8], object_pub_id: Vec<u8>) -> prisma_sync::object_in_space::SyncId {
+	prisma_sync::object_in_space::SyncId {
+		space: prisma_sync::space::SyncId {
+			pub_id: space_pub_id.to_vec(),
+		},
+		object: prisma_sync::object::SyncId {
+			pub_id: object_pub_id,
+		},
+	}
+}
+
+async fn find_space(
+	db: &PrismaClient,
+	space_id: space::id::Type,
+) -> Result<space::Data, rspc::Error> {
+	db.space()
+		.find_unique(space::id::equals(space_id))
+		.exec()
+		.await?
+		.ok_or_else(|| rspc::Error::new(ErrorCode::NotFound, "Space not found".to_string()))
+}
+
+/// Deletes links along with their sync operations
+async fn delete_links(
+	Library { db, sync, .. }: &Library,
+	links: Vec<space_link::Data>,
+) -> Result<(), rspc::Error> {
+	if links.is_empty() {
+		return Ok(());
+	}
+
+	let (sync_ops, ids): (Vec<_>, Vec<_>) = links
+		.into_iter()
+		.map(|link| {
+			(
+				sync.shared_delete(prisma_sync::space_link::SyncId {
+					pub_id: link.pub_id,
+				}),
+				link.id,
+			)
+		})
+		.unzip();
+
+	sync.write_ops(
+		db,
+		(
+			sync_ops,
+			db.space_link()
+				.delete_many(vec![space_link::id::in_vec(ids)]),
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library
+					.db
+					.space()
+					.find_many(vec![])
+					.order_by(space::date_created::order(SortOrder::Asc))
+					.exec()
+					.await?)
+			})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), space_id: space::id::Type| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let Some(space) = db
+						.space()
+						.find_unique(space::id::equals(space_id))
+						.exec()
+						.await?
+					else {
+						return Ok(None);
+					};
+
+					let (items, links) = db
+						._batch((
+							db.object_in_space()
+								.find_many(vec![object_in_space::space_id::equals(space_id)])
+								.order_by(object_in_space::z_index::order(SortOrder::Asc))
+								.select(space_item::select()),
+							db.space_link()
+								.find_many(vec![space_link::space_id::equals(Some(space_id))]),
+						))
+						.await?;
+
+					Ok(Some(SpaceWithLayout {
+						space,
+						items,
+						links,
+					}))
+				})
+		})
+		.procedure("objects", {
+			R.with2(library())
+				.query(|(node, library), space_id: space::id::Type| async move {
+					let objects = library
+						.db
+						.object()
+						.find_many(vec![object::spaces::some(vec![
+							object_in_space::space_id::equals(space_id),
+						])])
+						.include(object_with_file_paths::include())
+						.exec()
+						.await?;
+
+					let mut items = Vec::with_capacity(objects.len());
+
+					for object in objects {
+						let cas_id = object
+							.file_paths
+							.iter()
+							.find_map(|file_path| file_path.cas_id.as_ref());
+
+						let thumbnail_exists_locally = if let Some(cas_id) = cas_id {
+							library.thumbnail_exists(&node, cas_id).await.map_err(|e| {
+								rspc::Error::with_cause(
+									ErrorCode::InternalServerError,
+									"Failed to check that thumbnail exists".to_string(),
+									e,
+								)
+							})?
+						} else {
+							false
+						};
+
+						items.push(ExplorerItem::Object {
+							thumbnail: cas_id
+								.filter(|_| thumbnail_exists_locally)
+								.map(|cas_id| get_indexed_thumb_key(cas_id, library.id)),
+							item: object,
+						});
+					}
+
+					let (nodes, items) = items.normalise(|item| item.id());
+
+					Ok(NormalisedResults { items, nodes })
+				})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize, Debug)]
+			pub struct SpaceCreateArgs {
+				pub name: String,
+				#[serde(default)]
+				#[specta(optional)]
+				pub description: Option<String>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceCreateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = Uuid::new_v4().as_bytes().to_vec();
+					let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+					let space = sync
+						.write_ops(
+							db,
+							(
+								sync.shared_create(
+									prisma_sync::space::SyncId {
+										pub_id: pub_id.clone(),
+									},
+									[
+										(space::name::NAME, msgpack!(&args.name)),
+										(space::description::NAME, msgpack!(&args.description)),
+										(
+											space::date_created::NAME,
+											msgpack!(&date_created.to_rfc3339()),
+										),
+									],
+								),
+								db.space().create(
+									pub_id,
+									vec![
+										space::name::set(Some(args.name)),
+										space::description::set(args.description),
+										space::date_created::set(Some(date_created)),
+									],
+								),
+							),
+						)
+						.await?;
+
+					invalidate_query!(library, "spaces.list");
+
+					Ok(space)
+				})
+		})
+		.procedure("update", {
+			#[derive(Type, Deserialize, Debug)]
+			pub struct SpaceUpdateArgs {
+				pub id: space::id::Type,
+				#[serde(default)]
+				#[specta(optional)]
+				pub name: Option<String>,
+				/// An empty description clears it
+				#[serde(default)]
+				#[specta(optional)]
+				pub description: Option<String>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceUpdateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space = find_space(db, args.id).await?;
+					let date_modified: DateTime<FixedOffset> = Utc::now().into();
+
+					let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+						args.name.map(|name| {
+							(
+								(space::name::NAME, msgpack!(&name)),
+								space::name::set(Some(name)),
+							)
+						}),
+						args.description.map(|description| {
+							let description = Some(description).filter(|d| !d.is_empty());
+
+							(
+								(space::description::NAME, msgpack!(&description)),
+								space::description::set(description),
+							)
+						}),
+					]
+					.into_iter()
+					.flatten()
+					.chain([(
+						(
+							space::date_modified::NAME,
+							msgpack!(&date_modified.to_rfc3339()),
+						),
+						space::date_modified::set(Some(date_modified)),
+					)])
+					.unzip();
+
+					sync.write_ops(
+						db,
+						(
+							sync_params
+								.into_iter()
+								.map(|(k, v)| {
+									sync.shared_update(
+										prisma_sync::space::SyncId {
+											pub_id: space.pub_id.clone(),
+										},
+										k,
+										v,
+									)
+								})
+								.collect(),
+							db.space().update(space::id::equals(args.id), db_params),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.list");
+					invalidate_query!(library, "spaces.get");
+
+					Ok(())
+				})
+		})
+		.procedure("addObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space = find_space(db, args.space_id).await?;
+
+					let mut seen = db
+						.object_in_space()
+						.find_many(vec![
+							object_in_space::space_id::equals(args.space_id),
+							object_in_space::object_id::in_vec(args.object_ids.clone()),
+						])
+						.select(object_in_space::select!({ object_id }))
+						.exec()
+						.await?
+						.into_iter()
+						.map(|object_in_space| object_in_space.object_id)
+						.collect::<HashSet<_>>();
+
+					let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+					let (sync_ops, db_creates): (Vec<_>, Vec<_>) = db
+						.object()
+						.find_many(vec![object::id::in_vec(args.object_ids)])
+						.select(object::select!({ id pub_id }))
+						.exec()
+						.await?
+						.into_iter()
+						.filter(|object| seen.insert(object.id))
+						.map(|object| {
+							(
+								sync.relation_create(
+									sync_id(&space.pub_id, object.pub_id),
+									[(
+										object_in_space::date_created::NAME,
+										msgpack!(&date_created.to_rfc3339()),
+									)],
+								),
+								object_in_space::CreateUnchecked {
+									space_id: space.id,
+									object_id: object.id,
+									_params: vec![object_in_space::date_created::set(Some(
+										date_created,
+									))],
+								},
+							)
+						})
+						.unzip();
+
+					if !db_creates.is_empty() {
+						sync.write_ops(
+							db,
+							(
+								sync_ops.into_iter().flatten().collect(),
+								db.object_in_space().create_many(db_creates),
+							),
+						)
+						.await?;
+					}
+
+					invalidate_query!(library, "spaces.get");
+					invalidate_query!(library, "spaces.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("removeObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space = find_space(db, args.space_id).await?;
+
+					let objects_in_space = db
+						.object_in_space()
+						.find_many(vec![
+							object_in_space::space_id::equals(args.space_id),
+							object_in_space::object_id::in_vec(args.object_ids),
+						])
+						.select(object_in_space::select!({ object_id object: select { pub_id } }))
+						.exec()
+						.await?;
+
+					let object_ids = objects_in_space
+						.iter()
+						.map(|object_in_space| object_in_space.object_id)
+						.collect::<Vec<_>>();
+
+					// Links can't point at objects that are no longer in the space
+					let links = db
+						.space_link()
+						.find_many(vec![
+							space_link::space_id::equals(Some(args.space_id)),
+							or![
+								space_link::from_id::in_vec(object_ids.clone()),
+								space_link::to_id::in_vec(object_ids.clone()),
+							],
+						])
+						.exec()
+						.await?;
+
+					delete_links(&library, links).await?;
+
+					sync.write_ops(
+						db,
+						(
+							objects_in_space
+								.into_iter()
+								.map(|object_in_space| {
+									sync.relation_delete(sync_id(
+										&space.pub_id,
+										object_in_space.object.pub_id,
+									))
+								})
+								.collect(),
+							db.object_in_space().delete_many(vec![
+								object_in_space::space_id::equals(args.space_id),
+								object_in_space::object_id::in_vec(object_ids),
+							]),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.get");
+					invalidate_query!(library, "spaces.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("updateLayout", {
+			#[derive(Type, Deserialize, Debug)]
+			pub struct SpaceLayoutArgs {
+				pub space_id: space::id::Type,
+				pub items: Vec<SpaceItemLayout>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceLayoutArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space = find_space(db, args.space_id).await?;
+
+					let objects = db
+						.object_in_space()
+						.find_many(vec![
+							object_in_space::space_id::equals(args.space_id),
+							object_in_space::object_id::in_vec(
+								args.items.iter().map(|item| item.object_id).collect(),
+							),
+						])
+						.select(object_in_space::select!({ object_id object: select { pub_id } }))
+						.exec()
+						.await?;
+
+					let mut sync_ops = vec![];
+					let mut db_updates = vec![];
+
+					for item in args.items {
+						let Some(object_in_space) = objects
+							.iter()
+							.find(|object_in_space| object_in_space.object_id == item.object_id)
+						else {
+							return Err(rspc::Error::new(
+								ErrorCode::BadRequest,
+								format!("Object {} is not in the space", item.object_id),
+							));
+						};
+
+						let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+							item.x.map(|x| {
+								(
+									(object_in_space::x::NAME, msgpack!(x)),
+									object_in_space::x::set(Some(x)),
+								)
+							}),
+							item.y.map(|y| {
+								(
+									(object_in_space::y::NAME, msgpack!(y)),
+									object_in_space::y::set(Some(y)),
+								)
+							}),
+							item.width.map(|width| {
+								(
+									(object_in_space::width::NAME, msgpack!(width)),
+									object_in_space::width::set(Some(width)),
+								)
+							}),
+							item.height.map(|height| {
+								(
+									(object_in_space::height::NAME, msgpack!(height)),
+									object_in_space::height::set(Some(height)),
+								)
+							}),
+							item.z_index.map(|z_index| {
+								(
+									(object_in_space::z_index::NAME, msgpack!(z_index)),
+									object_in_space::z_index::set(Some(z_index)),
+								)
+							}),
+						]
+						.into_iter()
+						.flatten()
+						.unzip();
+
+						if db_params.is_empty() {
+							continue;
+						}
+
+						sync_ops.extend(sync_params.into_iter().map(|(k, v)| {
+							sync.relation_update(
+								sync_id(&space.pub_id, object_in_space.object.pub_id.clone()),
+								k,
+								v,
+							)
+						}));
+
+						db_updates.push(db.object_in_space().update(
+							object_in_space::space_id_object_id(args.space_id, item.object_id),
+							db_params,
+						));
+					}
+
+					if !db_updates.is_empty() {
+						sync.write_ops(db, (sync_ops, db_updates)).await?;
+					}
+
+					invalidate_query!(library, "spaces.get");
+
+					Ok(())
+				})
+		})
+		.procedure("createLink", {
+			#[derive(Type, Deserialize, Debug)]
+			pub struct SpaceLinkCreateArgs {
+				pub space_id: space::id::Type,
+				pub from_object_id: object::id::Type,
+				pub to_object_id: object::id::Type,
+				#[serde(default)]
+				#[specta(optional)]
+				pub label: Option<String>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceLinkCreateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space = find_space(db, args.space_id).await?;
+
+					let objects = db
+						.object()
+						.find_many(vec![
+							object::id::in_vec(vec![args.from_object_id, args.to_object_id]),
+							object::spaces::some(vec![object_in_space::space_id::equals(
+								args.space_id,
+							)]),
+						])
+						.select(object::select!({ id pub_id }))
+						.exec()
+						.await?;
+
+					let find_object = |object_id| {
+						objects
+							.iter()
+							.find(|object| object.id == object_id)
+							.ok_or_else(|| {
+								rspc::Error::new(
+									ErrorCode::BadRequest,
+									format!("Object {object_id} is not in the space"),
+								)
+							})
+					};
+
+					let from = find_object(args.from_object_id)?;
+					let to = find_object(args.to_object_id)?;
+
+					let pub_id = Uuid::new_v4().as_bytes().to_vec();
+					let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+					let link = sync
+						.write_ops(
+							db,
+							(
+								sync.shared_create(
+									prisma_sync::space_link::SyncId {
+										pub_id: pub_id.clone(),
+									},
+									[
+										(space_link::label::NAME, msgpack!(&args.label)),
+										(
+											space_link::space::NAME,
+											msgpack!(prisma_sync::space::SyncId {
+												pub_id: space.pub_id
+											}),
+										),
+										(
+											space_link::from::NAME,
+											msgpack!(prisma_sync::object::SyncId {
+												pub_id: from.pub_id.clone()
+											}),
+										),
+										(
+											space_link::to::NAME,
+											msgpack!(prisma_sync::object::SyncId {
+												pub_id: to.pub_id.clone()
+											}),
+										),
+										(
+											space_link::date_created::NAME,
+											msgpack!(&date_created.to_rfc3339()),
+										),
+									],
+								),
+								db.space_link().create(
+									pub_id,
+									vec![
+										space_link::label::set(args.label),
+										space_link::space::connect(space::id::equals(space.id)),
+										space_link::from::connect(object::id::equals(from.id)),
+										space_link::to::connect(object::id::equals(to.id)),
+										space_link::date_created::set(Some(date_created)),
+									],
+								),
+							),
+						)
+						.await?;
+
+					invalidate_query!(library, "spaces.get");
+
+					Ok(link)
+				})
+		})
+		.procedure("deleteLink", {
+			R.with2(library())
+				.mutation(|(_, library), link_id: space_link::id::Type| async move {
+					let link = library
+						.db
+						.space_link()
+						.find_unique(space_link::id::equals(link_id))
+						.exec()
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(ErrorCode::NotFound, "Link not found".to_string())
+						})?;
+
+					delete_links(&library, vec![link]).await?;
+
+					invalidate_query!(library, "spaces.get");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), space_id: space::id::Type| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space = find_space(db, space_id).await?;
+
+					let (links, objects_in_space) = db
+						._batch((
+							db.space_link()
+								.find_many(vec![space_link::space_id::equals(Some(space_id))]),
+							db.object_in_space()
+								.find_many(vec![object_in_space::space_id::equals(space_id)])
+								.select(object_in_space::select!({ object: select { pub_id } })),
+						))
+						.await?;
+
+					delete_links(&library, links).await?;
+
+					sync.write_ops(
+						db,
+						(
+							objects_in_space
+								.into_iter()
+								.map(|object_in_space| {
+									sync.relation_delete(sync_id(
+										&space.pub_id,
+										object_in_space.object.pub_id,
+									))
+								})
+								.collect(),
+							db.object_in_space()
+								.delete_many(vec![object_in_space::space_id::equals(space_id)]),
+						),
+					)
+					.await?;
+
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::space::SyncId {
+							pub_id: space.pub_id,
+						}),
+						db.space().delete(space::id::equals(space_id)),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.list");
+					invalidate_query!(library, "spaces.get");
+
+					Ok(())
+				})
+		})
+}
@@ -1,8 +1,16 @@
-use crate::{invalidate_query, library::Library, object::tag::TagCreateArgs};
+use crate::{
+	api::search::saved::saved_search_file_path_params,
+	invalidate_query,
+	library::Library,
+	object::tag::{
+		rules::{conditions_from_rule, TagRuleCondition, TagRuleCreateArgs},
+		with_descendants, TagCreateArgs,
+	},
+};
 
 use sd_cache::{CacheNode, Normalise, NormalisedResult, NormalisedResults, Reference};
 use sd_prisma::{
-	prisma::{file_path, object, tag, tag_on_object},
+	prisma::{file_path, object, saved_search, tag, tag_on_object, tag_rule},
 	prisma_sync,
 };
 use sd_sync::OperationFactory;
@@ -11,7 +19,6 @@ use sd_utils::{msgpack, uuid_to_bytes};
 use std::collections::BTreeMap;
 
 use chrono::{DateTime, Utc};
-use itertools::{Either, Itertools};
 use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -117,6 +124,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			enum Target {
 				Object(object::id::Type),
 				FilePath(file_path::id::Type),
+				/// Everything currently matched by a saved search
+				SavedSearch(saved_search::id::Type),
 			}
 
 			#[derive(Debug, Type, Deserialize)]
@@ -141,33 +150,40 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 							rspc::Error::new(ErrorCode::NotFound, "Tag not found".to_string())
 						})?;
 
-					let (objects, file_paths) = db
-						._batch({
-							let (objects, file_paths): (Vec<_>, Vec<_>) = args
-								.targets
-								.into_iter()
-								.partition_map(|target| match target {
-									Target::Object(id) => Either::Left(id),
-									Target::FilePath(id) => Either::Right(id),
-								});
-
-							(
-								db.object()
-									.find_many(vec![object::id::in_vec(objects)])
-									.select(object::select!({
-										id
-										pub_id
-									})),
+					let (mut objects, mut file_paths) = (vec![], vec![]);
+					for target in args.targets {
+						match target {
+							Target::Object(id) => objects.push(id),
+							Target::FilePath(id) => file_paths.push(id),
+							Target::SavedSearch(id) => file_paths.extend(
 								db.file_path()
-									.find_many(vec![file_path::id::in_vec(file_paths)])
-									.select(file_path::select!({
-										id
-										pub_id
-										is_dir
-										object: select { id pub_id }
-									})),
-							)
-						})
+									.find_many(saved_search_file_path_params(db, id).await?)
+									.select(file_path::select!({ id }))
+									.exec()
+									.await?
+									.into_iter()
+									.map(|file_path| file_path.id),
+							),
+						}
+					}
+
+					let (objects, file_paths) = db
+						._batch((
+							db.object()
+								.find_many(vec![object::id::in_vec(objects)])
+								.select(object::select!({
+									id
+									pub_id
+								})),
+							db.file_path()
+								.find_many(vec![file_path::id::in_vec(file_paths)])
+								.select(file_path::select!({
+									id
+									pub_id
+									is_dir
+									object: select { id pub_id }
+								})),
+						))
 						.await?;
 
 					macro_rules! sync_id {
@@ -356,6 +372,152 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
+		.procedure("setParent", {
+			#[derive(Type, Deserialize)]
+			pub struct TagSetParentArgs {
+				pub id: tag::id::Type,
+				pub parent_id: Option<tag::id::Type>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: TagSetParentArgs| async move {
+					let Library { sync, db, .. } = library.as_ref();
+
+					let tag = db
+						.tag()
+						.find_unique(tag::id::equals(args.id))
+						.select(tag::select!({ pub_id }))
+						.exec()
+						.await?
+						.ok_or(rspc::Error::new(
+							ErrorCode::NotFound,
+							"Error finding tag in db".into(),
+						))?;
+
+					let parent = if let Some(parent_id) = args.parent_id {
+						if with_descendants(db, vec![args.id])
+							.await?
+							.contains(&parent_id)
+						{
+							return Err(rspc::Error::new(
+								ErrorCode::BadRequest,
+								"A tag can't be nested under itself or its descendants".into(),
+							));
+						}
+
+						Some(
+							db.tag()
+								.find_unique(tag::id::equals(parent_id))
+								.select(tag::select!({ pub_id }))
+								.exec()
+								.await?
+								.ok_or(rspc::Error::new(
+									ErrorCode::NotFound,
+									"Error finding parent tag in db".into(),
+								))?,
+						)
+					} else {
+						None
+					};
+
+					sync.write_op(
+						db,
+						sync.shared_update(
+							prisma_sync::tag::SyncId {
+								pub_id: tag.pub_id.clone(),
+							},
+							tag::parent::NAME,
+							msgpack!(parent.map(|parent| prisma_sync::tag::SyncId {
+								pub_id: parent.pub_id
+							})),
+						),
+						db.tag().update(
+							tag::id::equals(args.id),
+							vec![
+								args.parent_id
+									.map(|parent_id| {
+										tag::parent::connect(tag::id::equals(parent_id))
+									})
+									.unwrap_or_else(tag::parent::disconnect),
+								tag::date_modified::set(Some(Utc::now().into())),
+							],
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "tags.list");
+
+					Ok(())
+				})
+		})
+		.procedure("listRules", {
+			#[derive(Serialize, Type)]
+			pub struct TagRuleItem {
+				pub id: tag_rule::id::Type,
+				pub name: Option<String>,
+				pub tag_id: Option<tag::id::Type>,
+				pub conditions: Vec<TagRuleCondition>,
+			}
+
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				library
+					.db
+					.tag_rule()
+					.find_many(vec![])
+					.exec()
+					.await?
+					.into_iter()
+					.map(|rule| {
+						Ok(TagRuleItem {
+							conditions: conditions_from_rule(&rule)?,
+							id: rule.id,
+							name: rule.name,
+							tag_id: rule.tag_id,
+						})
+					})
+					.collect::<Result<Vec<_>, rspc::Error>>()
+			})
+		})
+		.procedure("createRule", {
+			R.with2(library())
+				.mutation(|(_, library), args: TagRuleCreateArgs| async move {
+					args.create(&library).await?;
+
+					invalidate_query!(library, "tags.listRules");
+
+					Ok(())
+				})
+		})
+		.procedure("deleteRule", {
+			R.with2(library())
+				.mutation(|(_, library), rule_id: tag_rule::id::Type| async move {
+					let Library { sync, db, .. } = library.as_ref();
+
+					let rule = db
+						.tag_rule()
+						.find_unique(tag_rule::id::equals(rule_id))
+						.select(tag_rule::select!({ pub_id }))
+						.exec()
+						.await?
+						.ok_or(rspc::Error::new(
+							ErrorCode::NotFound,
+							"Error finding tag rule in db".into(),
+						))?;
+
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::tag_rule::SyncId {
+							pub_id: rule.pub_id,
+						}),
+						db.tag_rule().delete(tag_rule::id::equals(rule_id)),
+					)
+					.await?;
+
+					invalidate_query!(library, "tags.listRules");
+
+					Ok(())
+				})
+		})
 		.procedure(
 			"delete",
 			R.with2(library())
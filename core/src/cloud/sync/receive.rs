@@ -1,5 +1,6 @@
 use crate::{
 	library::{Libraries, Library},
+	node::config,
 	Node,
 };
 
@@ -10,7 +11,7 @@ use sd_p2p::{IdentityOrRemoteIdentity, RemoteIdentity};
 use sd_prisma::prisma::{cloud_crdt_operation, instance, PrismaClient, SortOrder};
 use sd_sync::CRDTOperation;
 use sd_utils::uuid_to_bytes;
-use tracing::info;
+use tracing::{error, info};
 
 use std::{
 	collections::{hash_map::Entry, HashMap},
@@ -150,6 +151,7 @@ pub async fn run_actor(
 						create_instance(
 							&library,
 							&libraries,
+							&node.config,
 							collection.instance_uuid,
 							instance.identity,
 							instance.node_id,
@@ -212,11 +214,20 @@ fn crdt_op_db(op: &CRDTOperation) -> cloud_crdt_operation::Create {
 pub async fn create_instance(
 	library: &Arc<Library>,
 	libraries: &Libraries,
+	node_config: &config::Manager,
 	uuid: Uuid,
 	identity: RemoteIdentity,
 	node_id: Uuid,
 	metadata: HashMap<String, String>,
 ) -> prisma_client_rust::Result<()> {
+	let joined = library
+		.db
+		.instance()
+		.find_unique(instance::pub_id::equals(uuid_to_bytes(uuid)))
+		.exec()
+		.await?
+		.is_none();
+
 	library
 		.db
 		.instance()
@@ -239,6 +250,18 @@ pub async fn create_instance(
 
 	library.sync.timestamps.write().await.insert(uuid, NTP64(0));
 
+	// The instance just joined the library, so its node can sync it once it advertises the instance
+	if joined {
+		if let Err(err) = node_config
+			.write(|c| {
+				c.sync_instances.insert(identity, library.id);
+			})
+			.await
+		{
+			error!("Failed to allow the new instance '{uuid}' to sync: {err:?}");
+		}
+	}
+
 	// Called again so the new instances are picked up
 	libraries.update_instances(library.clone()).await;
 
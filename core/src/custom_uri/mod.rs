@@ -1,7 +1,12 @@
 use crate::{
-	api::{utils::InvalidateOperationEvent, CoreEvent},
+	api::{
+		search::saved::saved_search_file_path_params, utils::InvalidateOperationEvent, CoreEvent,
+	},
 	library::Library,
-	object::media::old_thumbnail::WEBP_EXTENSION,
+	object::{
+		fs::archive::{read_archive_entry, ArchivePath},
+		media::old_thumbnail::WEBP_EXTENSION,
+	},
 	p2p::operations,
 	util::InfallibleResponse,
 	Node,
@@ -12,7 +17,7 @@ use hyper::{header, upgrade::OnUpgrade};
 use sd_file_ext::text::is_text;
 use sd_file_path_helper::{file_path_to_handle_custom_uri, IsolatedFilePathData};
 use sd_p2p::{IdentityOrRemoteIdentity, RemoteIdentity, P2P};
-use sd_prisma::prisma::{file_path, location};
+use sd_prisma::prisma::{file_path, location, saved_search};
 use sd_utils::db::maybe_missing;
 
 use std::{
@@ -26,7 +31,7 @@ use std::{
 };
 
 use axum::{
-	body::{self, Body, BoxBody, Full},
+	body::{self, Body, BoxBody, Full, StreamBody},
 	extract::{self, State},
 	http::{HeaderMap, HeaderValue, Request, Response, StatusCode},
 	middleware,
@@ -34,11 +39,16 @@ use axum::{
 	routing::get,
 	Router,
 };
+use futures::{stream, StreamExt};
 use mini_moka::sync::Cache;
+use serde::Serialize;
 use tokio::{
 	fs::{self, File},
 	io::{self, copy_bidirectional, AsyncReadExt, AsyncSeekExt, SeekFrom},
+	sync::{mpsc, oneshot},
+	task::spawn_blocking,
 };
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, warn};
 use uuid::Uuid;
 
@@ -59,6 +69,17 @@ struct CacheValue {
 	serve_from: ServeFrom,
 }
 
+/// A file matched by a saved search, served by its `/file` url
+#[derive(Serialize)]
+struct SavedSearchEntry {
+	id: file_path::id::Type,
+	location_id: location::id::Type,
+	name: Option<String>,
+	extension: Option<String>,
+	is_dir: bool,
+	url: String,
+}
+
 const MAX_TEXT_READ_LENGTH: usize = 10 * 1024; // 10KB
 
 #[derive(Debug, Clone)]
@@ -304,13 +325,78 @@ pub fn base_router() -> Router<LocalState> {
 				},
 			),
 		)
+		.route(
+			"/saved-search/:lib_id/:search_id",
+			get(
+				|State(state): State<LocalState>,
+				 extract::Path((lib_id, search_id)): extract::Path<(String, String)>| async move {
+					let library_id = Uuid::from_str(&lib_id).map_err(bad_request)?;
+					let search_id = search_id
+						.parse::<saved_search::id::Type>()
+						.map_err(bad_request)?;
+
+					let library = state
+						.node
+						.libraries
+						.get_library(&library_id)
+						.await
+						.ok_or_else(|| not_found(()))?;
+
+					let file_paths = library
+						.db
+						.file_path()
+						.find_many(
+							saved_search_file_path_params(&library.db, search_id)
+								.await
+								.map_err(not_found)?,
+						)
+						.select(file_path::select!({ id location_id name extension is_dir }))
+						.exec()
+						.await
+						.map_err(internal_server_error)?;
+
+					let entries = file_paths
+						.into_iter()
+						.filter_map(|file_path| {
+							let location_id = file_path.location_id?;
+
+							Some(SavedSearchEntry {
+								id: file_path.id,
+								location_id,
+								name: file_path.name,
+								extension: file_path.extension,
+								is_dir: file_path.is_dir.unwrap_or_default(),
+								url: format!("/file/{library_id}/{location_id}/{}", file_path.id),
+							})
+						})
+						.collect::<Vec<_>>();
+
+					Ok::<_, Response<BoxBody>>(
+						InfallibleResponse::builder()
+							.header("Content-Type", HeaderValue::from_static("application/json"))
+							.body(body::boxed(Full::from(
+								serde_json::to_vec(&entries).map_err(internal_server_error)?,
+							))),
+					)
+				},
+			),
+		)
 		.route(
 			"/local-file-by-path/:path",
 			get(
 				|extract::Path(path): extract::Path<String>, request: Request<Body>| async move {
 					let path = PathBuf::from(path);
 
-					let metadata = fs::metadata(&path).await.map_err(internal_server_error)?;
+					let metadata = match fs::metadata(&path).await {
+						Ok(metadata) => metadata,
+						// Files inside archives are streamed without extracting the archive
+						Err(e) => {
+							return match ArchivePath::find(&path).await {
+								Some(archive) => serve_archive_entry(archive).await,
+								None => Err(internal_server_error(e)),
+							};
+						}
+					};
 					(!metadata.is_dir())
 						.then_some(())
 						.ok_or_else(|| not_found(()))?;
@@ -406,6 +492,58 @@ pub fn router(node: Arc<Node>) -> Router<()> {
 		.with_state(with_state(node))
 }
 
+/// Streams a single file from inside an archive. Range requests aren't supported, as we can't
+/// seek inside compressed entries.
+async fn serve_archive_entry(archive: ArchivePath) -> Result<Response<BoxBody>, Response<BoxBody>> {
+	let ext = archive
+		.inner_path
+		.extension()
+		.and_then(OsStr::to_str)
+		.map(str::to_lowercase);
+
+	let (size_tx, size_rx) = oneshot::channel();
+	let (chunks_tx, mut chunks_rx) = mpsc::channel(4);
+
+	spawn_blocking(move || {
+		if let Err(e) = read_archive_entry(&archive, size_tx, &chunks_tx) {
+			warn!("Failed to read archive entry: {e:#?}");
+		}
+	});
+
+	// The size is only sent if the entry exists
+	let size = size_rx.await.map_err(not_found)?;
+	let first_chunk = chunks_rx
+		.recv()
+		.await
+		.transpose()
+		.map_err(internal_server_error)?
+		.unwrap_or_default();
+
+	let mime_type = match ext {
+		None => "text/plain".to_string(),
+		Some(ext) => match mime_type_from_ext(&ext) {
+			"text/plain" => {
+				let text_buf = &first_chunk[..min(first_chunk.len(), MAX_TEXT_READ_LENGTH)];
+				text_mime_type(&ext, text_buf, text_buf.len() as u64 == size)?
+			}
+			mime_type => mime_type.to_string(),
+		},
+	};
+
+	Ok(InfallibleResponse::builder()
+		.header(
+			"Content-Type",
+			HeaderValue::from_str(&mime_type).map_err(|err| {
+				error!("Error converting mime-type into header value: {}", err);
+				internal_server_error(())
+			})?,
+		)
+		.header(header::CONTENT_LENGTH, HeaderValue::from(size))
+		.body(body::boxed(StreamBody::new(
+			stream::once(async move { Ok(first_chunk) }).chain(ReceiverStream::new(chunks_rx)),
+		))))
+}
+
 // TODO: This should possibly be determined from magic bytes when the file is indexed and stored it in the DB on the file path
 async fn infer_the_mime_type(
 	ext: &str,
@@ -413,7 +551,32 @@ async fn infer_the_mime_type(
 	metadata: &Metadata,
 ) -> Result<String, Response<BoxBody>> {
 	let ext = ext.to_lowercase();
-	let mime_type = match ext.as_str() {
+	let mime_type = mime_type_from_ext(&ext);
+	if mime_type != "text/plain" {
+		return Ok(mime_type.to_string());
+	}
+
+	let mut text_buf = vec![
+		0;
+		min(
+			metadata.len().try_into().unwrap_or(usize::MAX),
+			MAX_TEXT_READ_LENGTH
+		)
+	];
+	if !text_buf.is_empty() {
+		file.read_exact(&mut text_buf)
+			.await
+			.map_err(internal_server_error)?;
+		file.seek(SeekFrom::Start(0))
+			.await
+			.map_err(internal_server_error)?;
+	}
+
+	text_mime_type(&ext, &text_buf, text_buf.len() == (metadata.len() as usize))
+}
+
+fn mime_type_from_ext(ext: &str) -> &'static str {
+	match ext {
 		// AAC audio
 		"aac" => "audio/aac",
 		// Musical Instrument Digital Interface (MIDI)
@@ -490,59 +653,45 @@ async fn infer_the_mime_type(
 		// AVC in HEIF images sequence (animated)
 		"avcs" => "image/avcs",
 		_ => "text/plain",
-	};
+	}
+}
 
-	Ok(if mime_type == "text/plain" {
-		let mut text_buf = vec![
-			0;
-			min(
-				metadata.len().try_into().unwrap_or(usize::MAX),
-				MAX_TEXT_READ_LENGTH
-			)
-		];
-		if !text_buf.is_empty() {
-			file.read_exact(&mut text_buf)
-				.await
-				.map_err(internal_server_error)?;
-			file.seek(SeekFrom::Start(0))
-				.await
-				.map_err(internal_server_error)?;
+fn text_mime_type(
+	ext: &str,
+	text_buf: &[u8],
+	is_whole_file: bool,
+) -> Result<String, Response<BoxBody>> {
+	let charset = is_text(text_buf, is_whole_file).unwrap_or("");
+
+	// Only browser recognized types, everything else should be text/plain
+	// https://www.iana.org/assignments/media-types/media-types.xhtml#table-text
+	let mime_type = match ext {
+		// HyperText Markup Language
+		"html" | "htm" => "text/html",
+		// Cascading Style Sheets
+		"css" => "text/css",
+		// Javascript
+		"js" | "mjs" => "text/javascript",
+		// Comma-separated values
+		"csv" => "text/csv",
+		// Markdown
+		"md" | "markdown" => "text/markdown",
+		// Rich text format
+		"rtf" => "text/rtf",
+		// Web Video Text Tracks
+		"vtt" => "text/vtt",
+		// Extensible Markup Language
+		"xml" => "text/xml",
+		// Text
+		"txt" => "text/plain",
+		_ => {
+			if charset.is_empty() {
+				// "TODO: This filetype is not supported because of the missing mime type!",
+				return Err(not_implemented(()));
+			};
+			"text/plain"
 		}
+	};
 
-		let charset = is_text(&text_buf, text_buf.len() == (metadata.len() as usize)).unwrap_or("");
-
-		// Only browser recognized types, everything else should be text/plain
-		// https://www.iana.org/assignments/media-types/media-types.xhtml#table-text
-		let mime_type = match ext.as_str() {
-			// HyperText Markup Language
-			"html" | "htm" => "text/html",
-			// Cascading Style Sheets
-			"css" => "text/css",
-			// Javascript
-			"js" | "mjs" => "text/javascript",
-			// Comma-separated values
-			"csv" => "text/csv",
-			// Markdown
-			"md" | "markdown" => "text/markdown",
-			// Rich text format
-			"rtf" => "text/rtf",
-			// Web Video Text Tracks
-			"vtt" => "text/vtt",
-			// Extensible Markup Language
-			"xml" => "text/xml",
-			// Text
-			"txt" => "text/plain",
-			_ => {
-				if charset.is_empty() {
-					// "TODO: This filetype is not supported because of the missing mime type!",
-					return Err(not_implemented(()));
-				};
-				mime_type
-			}
-		};
-
-		format!("{mime_type}; charset={charset}")
-	} else {
-		mime_type.to_string()
-	})
+	Ok(format!("{mime_type}; charset={charset}"))
 }
@@ -3,11 +3,12 @@
 use crate::{
 	api::{CoreEvent, Router},
 	location::LocationManagerError,
-	object::media::old_thumbnail::old_actor::OldThumbnailer,
+	object::{fs::error::FileSystemJobsError, media::old_thumbnail::old_actor::OldThumbnailer},
 };
 
 #[cfg(feature = "ai")]
 use sd_ai::old_image_labeler::{DownloadModelError, OldImageLabeler, YoloV8};
+use sd_task_system::TaskSystem;
 
 use api::notifications::{Notification, NotificationData, NotificationId};
 use chrono::{DateTime, Utc};
@@ -59,6 +60,8 @@ pub struct Node {
 	pub config: Arc<config::Manager>,
 	pub libraries: Arc<library::Libraries>,
 	pub old_jobs: Arc<old_job::OldJobs>,
+	/// Runs the tasks that moved off the old job system, only file system ones for now
+	pub task_system: TaskSystem<FileSystemJobsError>,
 	pub locations: location::Locations,
 	pub p2p: Arc<p2p::P2PManager>,
 	pub event_bus: (broadcast::Sender<CoreEvent>, broadcast::Receiver<CoreEvent>),
@@ -66,6 +69,7 @@ pub struct Node {
 	pub thumbnailer: OldThumbnailer,
 	pub files_over_p2p_flag: Arc<AtomicBool>,
 	pub cloud_sync_flag: Arc<AtomicBool>,
+	pub verified_dedup_flag: Arc<AtomicBool>,
 	pub env: Arc<env::Env>,
 	pub http: reqwest::Client,
 	#[cfg(feature = "ai")]
@@ -97,6 +101,10 @@ impl Node {
 		// This error is ignored because it's throwing on mobile despite the folder existing.
 		let _ = fs::create_dir_all(&data_dir).await;
 
+		if let Err(e) = location::non_indexed::remove_archive_previews(data_dir).await {
+			warn!("Failed to remove archive previews left by the last run: {e:#?}");
+		}
+
 		let event_bus = broadcast::channel(1024);
 		let config = config::Manager::new(data_dir.to_path_buf())
 			.await
@@ -122,6 +130,7 @@ impl Node {
 		let node = Arc::new(Node {
 			data_dir: data_dir.to_path_buf(),
 			old_jobs,
+			task_system: TaskSystem::new(),
 			locations,
 			notifications: notifications::Notifications::new(),
 			p2p,
@@ -137,6 +146,7 @@ impl Node {
 			libraries,
 			files_over_p2p_flag: Arc::new(AtomicBool::new(false)),
 			cloud_sync_flag: Arc::new(AtomicBool::new(false)),
+			verified_dedup_flag: Arc::new(AtomicBool::new(false)),
 			http: reqwest::Client::new(),
 			env,
 			#[cfg(feature = "ai")]
@@ -254,6 +264,7 @@ impl Node {
 	pub async fn shutdown(&self) {
 		info!("Spacedrive shutting down...");
 		self.thumbnailer.shutdown().await;
+		self.task_system.shutdown().await;
 		self.old_jobs.shutdown().await;
 		self.p2p.shutdown().await;
 		#[cfg(feature = "ai")]
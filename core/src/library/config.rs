@@ -1,5 +1,5 @@
 use crate::{
-	node::config::NodeConfig,
+	node::config::{self, NodeConfigError},
 	util::version_manager::{Kind, ManagedVersion, VersionManager, VersionManagerError},
 };
 
@@ -21,7 +21,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 use specta::Type;
 use thiserror::Error;
 use tokio::fs;
-use tracing::error;
+use tracing::{error, warn};
 use uuid::Uuid;
 
 use super::name::LibraryName;
@@ -70,10 +70,11 @@ pub enum LibraryConfigVersion {
 	V7 = 7,
 	V8 = 8,
 	V9 = 9,
+	V10 = 10,
 }
 
 impl ManagedVersion<LibraryConfigVersion> for LibraryConfig {
-	const LATEST_VERSION: LibraryConfigVersion = LibraryConfigVersion::V9;
+	const LATEST_VERSION: LibraryConfigVersion = LibraryConfigVersion::V10;
 
 	const KIND: Kind = Kind::Json("version");
 
@@ -102,10 +103,12 @@ impl LibraryConfig {
 
 	pub(crate) async fn load(
 		path: impl AsRef<Path>,
-		node_config: &NodeConfig,
+		library_id: Uuid,
+		node_config: &config::Manager,
 		db: &PrismaClient,
 	) -> Result<Self, LibraryConfigError> {
 		let path = path.as_ref();
+		let node_id = node_config.get().await.id;
 
 		VersionManager::<Self, LibraryConfigVersion>::migrate_and_load(
 			path,
@@ -172,7 +175,7 @@ impl LibraryConfig {
 						db.node()
 							.update_many(
 								vec![],
-								vec![node::pub_id::set(node_config.id.as_bytes().to_vec())],
+								vec![node::pub_id::set(node_id.as_bytes().to_vec())],
 							)
 							.exec()
 							.await?;
@@ -184,7 +187,7 @@ impl LibraryConfig {
 						)
 						.map_err(VersionManagerError::SerdeJson)?;
 
-						config.insert(String::from("node_id"), json!(node_config.id.to_string()));
+						config.insert(String::from("node_id"), json!(node_id.to_string()));
 
 						fs::write(
 							path,
@@ -267,7 +270,7 @@ impl LibraryConfig {
 							identity: node
 								.and_then(|n| n.identity.clone())
 								.unwrap_or_else(|| Identity::new().to_bytes()),
-							node_id: node_config.id.as_bytes().to_vec(),
+							node_id: node_id.as_bytes().to_vec(),
 							last_seen: now,
 							date_created: node.map(|n| n.date_created).unwrap_or_else(|| now),
 							_params: vec![],
@@ -390,6 +393,35 @@ impl LibraryConfig {
 						.await?;
 					}
 
+					(LibraryConfigVersion::V9, LibraryConfigVersion::V10) => {
+						// Instances used to sync as soon as they joined the library, now the node
+						// running them must be allowed to. We only know the instances' identities,
+						// so they're handed over to their node once it advertises them.
+						let mut remote_identities = vec![];
+						for instance in db.instance().find_many(vec![]).exec().await? {
+							match IdentityOrRemoteIdentity::from_bytes(&instance.identity) {
+								Ok(IdentityOrRemoteIdentity::RemoteIdentity(identity)) => {
+									remote_identities.push(identity);
+								}
+								// Only our own instance has its private key
+								Ok(IdentityOrRemoteIdentity::Identity(_)) => {}
+								Err(e) => warn!("Skipping instance with invalid identity: {e:?}"),
+							}
+						}
+
+						if !remote_identities.is_empty() {
+							node_config
+								.write(|c| {
+									c.sync_instances.extend(
+										remote_identities
+											.into_iter()
+											.map(|identity| (identity, library_id)),
+									)
+								})
+								.await?;
+						}
+					}
+
 					_ => {
 						error!("Library config version is not handled: {:?}", current);
 						return Err(VersionManagerError::UnexpectedMigration {
@@ -430,4 +462,6 @@ pub enum LibraryConfigError {
 	VersionManager(#[from] VersionManagerError<LibraryConfigVersion>),
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
+	#[error(transparent)]
+	NodeConfig(#[from] NodeConfigError),
 }
@@ -408,8 +408,8 @@ impl Libraries {
 			create.to_query(&db).exec().await?;
 		}
 
+		let config = LibraryConfig::load(config_path, id, &node.config, &db).await?;
 		let node_config = node.config.get().await;
-		let config = LibraryConfig::load(config_path, &node_config, &db).await?;
 
 		let instances = db.instance().find_many(vec![]).exec().await?;
 
@@ -610,6 +610,7 @@ impl Libraries {
 										if let Err(err) = cloud::sync::receive::create_instance(
 											&library,
 											&node.libraries,
+											&node.config,
 											instance.uuid,
 											instance.identity,
 											instance.node_id,
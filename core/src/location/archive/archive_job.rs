@@ -0,0 +1,590 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::{
+		find_location, location_with_indexer_rules, metadata::SpacedriveLocationMetadataFile,
+		normalize_path, scan_location, LocationError,
+	},
+	object::{cas::generate_cas_id, media::old_thumbnail::get_indexed_thumbnail_path},
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_file_path_helper::IsolatedFilePathData;
+use sd_prisma::{
+	prisma::{file_path, location, PrismaClient, SortOrder},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{db::maybe_missing, msgpack, uuid_to_bytes};
+
+use std::{
+	borrow::Cow,
+	hash::Hash,
+	path::{Path, PathBuf},
+};
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::fs;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::{archived_file_paths_filters, is_archived, CHUNK_SIZE, MIN_REATTACH_MATCH_PERCENTAGE};
+
+file_path::select!(file_path_to_reattach {
+	id
+	materialized_path
+	is_dir
+	name
+	extension
+	cas_id
+	size_in_bytes_bytes
+});
+
+/// `OldLocationArchiverJobInit` marks a location as archived, after checking how much of its
+/// preview media is available, so it can be browsed without the location's files.
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct OldLocationArchiverJobInit {
+	pub location_id: location::id::Type,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldLocationArchiverJobRunMetadata {
+	cursor: file_path::id::Type,
+	total_files: usize,
+	files_with_thumbnail: usize,
+}
+
+impl JobRunMetadata for OldLocationArchiverJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.total_files += new_data.total_files;
+		self.files_with_thumbnail += new_data.files_with_thumbnail;
+		self.cursor = new_data.cursor;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldLocationArchiverJobInit {
+	type Data = ();
+	type Step = ();
+	type RunMetadata = OldLocationArchiverJobRunMetadata;
+
+	const NAME: &'static str = "location_archiver";
+	const IS_BATCHED: bool = true;
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let location = find_location(&ctx.library, init.location_id)
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(init.location_id))?;
+
+		if is_archived(&location) {
+			return Err(LocationError::Archived(init.location_id).into());
+		}
+
+		// Must fill in the data, otherwise the job will not run
+		*data = Some(());
+
+		let (file_paths_count, cursor) = count_and_first_id(db, init.location_id).await?;
+
+		ctx.progress(vec![
+			JobReportUpdate::TaskCount(file_paths_count),
+			JobReportUpdate::Message(format!(
+				"Checking preview media of {file_paths_count} files"
+			)),
+		]);
+
+		Ok((
+			OldLocationArchiverJobRunMetadata {
+				cursor,
+				..Default::default()
+			},
+			vec![(); file_paths_count.div_ceil(CHUNK_SIZE)],
+		)
+			.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step_number, .. }: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+		let Library { db, id, .. } = &*ctx.library;
+
+		let file_paths = get_archived_file_paths(db, init.location_id, run_metadata.cursor).await?;
+
+		let Some(last) = file_paths.last() else {
+			return Err(JobError::EarlyFinish {
+				name: <Self as StatefulJob>::NAME.to_string(),
+				reason: "Expected file paths not returned from database query for this chunk"
+					.to_string(),
+			});
+		};
+
+		let cursor = last.id + 1;
+
+		let files_with_thumbnail = join_all(file_paths.iter().filter_map(|file_path| {
+			file_path
+				.cas_id
+				.as_deref()
+				.map(|cas_id| fs::try_exists(get_indexed_thumbnail_path(&ctx.node, cas_id, *id)))
+		}))
+		.await
+		.into_iter()
+		.filter(|res| matches!(res, Ok(true)))
+		.count();
+
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(
+			step_number * CHUNK_SIZE + file_paths.len(),
+		)]);
+
+		Ok(OldLocationArchiverJobRunMetadata {
+			cursor,
+			total_files: file_paths.len(),
+			files_with_thumbnail,
+		}
+		.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let Library { db, sync, .. } = &*ctx.library;
+
+		let location = find_location(&ctx.library, init.location_id)
+			.select(location::select!({ pub_id }))
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(init.location_id))?;
+
+		sync.write_op(
+			db,
+			sync.shared_update(
+				prisma_sync::location::SyncId {
+					pub_id: location.pub_id,
+				},
+				location::is_archived::NAME,
+				msgpack!(true),
+			),
+			db.location().update(
+				location::id::equals(init.location_id),
+				vec![location::is_archived::set(Some(true))],
+			),
+		)
+		.await?;
+
+		// Archived locations are never watched, as their files are expected to be gone
+		ctx.node
+			.locations
+			.remove(init.location_id, ctx.library.clone())
+			.await
+			.map_err(LocationError::from)?;
+
+		info!(
+			"Archived location {}, {} of {} files have preview media",
+			init.location_id, run_metadata.files_with_thumbnail, run_metadata.total_files
+		);
+
+		invalidate_query!(ctx.library, "locations.list");
+		invalidate_query!(ctx.library, "locations.get");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+/// `OldLocationReattacherJobInit` checks that the files found at `path` match the archived index
+/// of a location by their `cas_id`, and if enough of them do, moves the location to `path` and
+/// makes it writable again, rescanning it to pick up any changes made while it was archived.
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct OldLocationReattacherJobInit {
+	pub location_id: location::id::Type,
+	pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldLocationReattacherJobData {
+	location_path: String,
+	location_name: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldLocationReattacherJobRunMetadata {
+	cursor: file_path::id::Type,
+	total_files: usize,
+	matched_files: usize,
+}
+
+impl JobRunMetadata for OldLocationReattacherJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.total_files += new_data.total_files;
+		self.matched_files += new_data.matched_files;
+		self.cursor = new_data.cursor;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldLocationReattacherJobInit {
+	type Data = OldLocationReattacherJobData;
+	type Step = ();
+	type RunMetadata = OldLocationReattacherJobRunMetadata;
+
+	const NAME: &'static str = "location_reattacher";
+	const IS_BATCHED: bool = true;
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let location = find_location(&ctx.library, init.location_id)
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(init.location_id))?;
+
+		if !is_archived(&location) {
+			return Err(LocationError::NotArchived(init.location_id).into());
+		}
+
+		let (location_path, location_name) = normalize_path(&init.path)
+			.map_err(|_| LocationError::DirectoryNotFound(init.path.as_path().into()))?;
+
+		if !fs::metadata(&location_path)
+			.await
+			.map_err(|_| LocationError::DirectoryNotFound(init.path.as_path().into()))?
+			.is_dir()
+		{
+			return Err(LocationError::NotDirectory(init.path.as_path().into()).into());
+		}
+
+		if db
+			.location()
+			.count(vec![
+				location::path::equals(Some(location_path.clone())),
+				location::id::not(init.location_id),
+			])
+			.exec()
+			.await? > 0
+		{
+			return Err(LocationError::LocationAlreadyExists(init.path.as_path().into()).into());
+		}
+
+		*data = Some(OldLocationReattacherJobData {
+			location_path,
+			location_name,
+		});
+
+		let (file_paths_count, cursor) = count_and_first_id(db, init.location_id).await?;
+
+		ctx.progress(vec![
+			JobReportUpdate::TaskCount(file_paths_count),
+			JobReportUpdate::Message(format!(
+				"Matching {file_paths_count} archived files against {}",
+				init.path.display()
+			)),
+		]);
+
+		Ok((
+			OldLocationReattacherJobRunMetadata {
+				cursor,
+				..Default::default()
+			},
+			vec![(); file_paths_count.div_ceil(CHUNK_SIZE)],
+		)
+			.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step_number, .. }: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		let file_paths =
+			get_archived_file_paths(&ctx.library.db, init.location_id, run_metadata.cursor).await?;
+
+		let Some(last) = file_paths.last() else {
+			return Err(JobError::EarlyFinish {
+				name: <Self as StatefulJob>::NAME.to_string(),
+				reason: "Expected file paths not returned from database query for this chunk"
+					.to_string(),
+			});
+		};
+
+		let cursor = last.id + 1;
+
+		let location_path = Path::new(&data.location_path);
+
+		let matched_files =
+			join_all(file_paths.iter().map(|file_path| {
+				matches_archived_file(init.location_id, location_path, file_path)
+			}))
+			.await
+			.into_iter()
+			.filter(|matched| *matched)
+			.count();
+
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(
+			step_number * CHUNK_SIZE + file_paths.len(),
+		)]);
+
+		Ok(OldLocationReattacherJobRunMetadata {
+			cursor,
+			total_files: file_paths.len(),
+			matched_files,
+		}
+		.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let library = &ctx.library;
+		let Library { db, sync, .. } = &**library;
+
+		let OldLocationReattacherJobData {
+			location_path,
+			location_name,
+		} = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		if run_metadata.matched_files * 100
+			< run_metadata.total_files * MIN_REATTACH_MATCH_PERCENTAGE
+		{
+			return Err(LocationError::ArchiveMismatch {
+				path: init.path.as_path().into(),
+				matched: run_metadata.matched_files,
+				total: run_metadata.total_files,
+			}
+			.into());
+		}
+
+		let location = find_location(library, init.location_id)
+			.select(location::select!({ pub_id }))
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(init.location_id))?;
+
+		let instance_id = library.config().await.instance_id;
+
+		// The disk may now be plugged into this instance, so we take ownership of the location
+		sync.write_ops(
+			db,
+			(
+				[
+					(location::path::NAME, msgpack!(location_path)),
+					(location::is_archived::NAME, msgpack!(false)),
+					(
+						location::instance::NAME,
+						msgpack!(prisma_sync::instance::SyncId {
+							pub_id: uuid_to_bytes(sync.instance)
+						}),
+					),
+				]
+				.into_iter()
+				.map(|(field, value)| {
+					sync.shared_update(
+						prisma_sync::location::SyncId {
+							pub_id: location.pub_id.clone(),
+						},
+						field,
+						value,
+					)
+				})
+				.collect(),
+				db.location().update(
+					location::id::equals(init.location_id),
+					vec![
+						location::path::set(Some(location_path.clone())),
+						location::is_archived::set(Some(false)),
+						location::instance_id::set(Some(instance_id)),
+					],
+				),
+			),
+		)
+		.await?;
+
+		let location_pub_id =
+			Uuid::from_slice(&location.pub_id).map_err(|_| JobError::MissingData {
+				value: "location.pub_id".to_string(),
+			})?;
+
+		match SpacedriveLocationMetadataFile::try_load(location_path)
+			.await
+			.map_err(LocationError::from)?
+		{
+			Some(metadata)
+				if metadata.location_path(library.id) == Some(Path::new(location_path)) => {}
+			Some(mut metadata) if metadata.has_library(library.id) => metadata
+				.relink(library.id, location_path)
+				.await
+				.map_err(LocationError::from)?,
+			Some(mut metadata) => metadata
+				.add_library(
+					library.id,
+					location_pub_id,
+					location_path,
+					location_name.clone(),
+				)
+				.await
+				.map_err(LocationError::from)?,
+			None => SpacedriveLocationMetadataFile::create_and_save(
+				library.id,
+				location_pub_id,
+				location_path,
+				location_name.clone(),
+			)
+			.await
+			.map_err(LocationError::from)?,
+		}
+
+		ctx.node
+			.locations
+			.add(init.location_id, library.clone())
+			.await
+			.map_err(LocationError::from)?;
+
+		debug!(
+			"Reattached location {} to {location_path}, rescanning it",
+			init.location_id
+		);
+
+		scan_location(
+			&ctx.node,
+			library,
+			find_location(library, init.location_id)
+				.include(location_with_indexer_rules::include())
+				.exec()
+				.await?
+				.ok_or(LocationError::IdNotFound(init.location_id))?,
+		)
+		.await
+		.map_err(LocationError::from)?;
+
+		invalidate_query!(library, "locations.list");
+		invalidate_query!(library, "locations.get");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+async fn count_and_first_id(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+) -> Result<(usize, file_path::id::Type), prisma_client_rust::QueryError> {
+	let (count, first) = db
+		._batch((
+			db.file_path()
+				.count(archived_file_paths_filters(location_id, None)),
+			db.file_path()
+				.find_first(archived_file_paths_filters(location_id, None))
+				.order_by(file_path::id::order(SortOrder::Asc))
+				.select(file_path::select!({ id })),
+		))
+		.await?;
+
+	Ok((
+		count as usize,
+		first.map(|file_path| file_path.id).unwrap_or_default(),
+	))
+}
+
+async fn get_archived_file_paths(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+	cursor: file_path::id::Type,
+) -> Result<Vec<file_path_to_reattach::Data>, prisma_client_rust::QueryError> {
+	db.file_path()
+		.find_many(archived_file_paths_filters(location_id, Some(cursor)))
+		.order_by(file_path::id::order(SortOrder::Asc))
+		.take(CHUNK_SIZE as i64)
+		.select(file_path_to_reattach::select())
+		.exec()
+		.await
+}
+
+async fn matches_archived_file(
+	location_id: location::id::Type,
+	location_path: &Path,
+	file_path: &file_path_to_reattach::Data,
+) -> bool {
+	let (
+		Ok(materialized_path),
+		Ok(is_dir),
+		Ok(name),
+		Ok(extension),
+		Ok(cas_id),
+		Ok(size_in_bytes_bytes),
+	) = (
+		maybe_missing(&file_path.materialized_path, "file_path.materialized_path"),
+		maybe_missing(file_path.is_dir, "file_path.is_dir"),
+		maybe_missing(&file_path.name, "file_path.name"),
+		maybe_missing(&file_path.extension, "file_path.extension"),
+		maybe_missing(&file_path.cas_id, "file_path.cas_id"),
+		maybe_missing(
+			&file_path.size_in_bytes_bytes,
+			"file_path.size_in_bytes_bytes",
+		),
+	)
+	else {
+		return false;
+	};
+
+	let Ok(size) = <[u8; 8]>::try_from(size_in_bytes_bytes.as_slice()).map(u64::from_be_bytes)
+	else {
+		return false;
+	};
+
+	let full_path = location_path.join(IsolatedFilePathData::from_db_data(
+		location_id,
+		is_dir,
+		Cow::Borrowed(materialized_path),
+		Cow::Borrowed(name),
+		Cow::Borrowed(extension),
+	));
+
+	// Checking the size first, so we don't bother hashing files that obviously changed
+	match fs::metadata(&full_path).await {
+		Ok(metadata) if metadata.len() == size => generate_cas_id(&full_path, size)
+			.await
+			.is_ok_and(|new_cas_id| &new_cas_id == cas_id),
+		_ => false,
+	}
+}
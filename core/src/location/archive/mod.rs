@@ -0,0 +1,139 @@
+use crate::{
+	library::Library,
+	location::{find_location, LocationError},
+	object::media::OldMediaProcessorJobInit,
+	old_job::JobBuilder,
+	Node,
+};
+
+use sd_prisma::prisma::{file_path, location, PrismaClient};
+
+use std::{path::PathBuf, sync::Arc};
+
+use serde_json::json;
+use uuid::Uuid;
+
+mod archive_job;
+
+pub use archive_job::{OldLocationArchiverJobInit, OldLocationReattacherJobInit};
+
+const CHUNK_SIZE: usize = 100;
+
+/// Percentage of the archived files that must be found with the same `cas_id` on the new path
+/// for us to consider it the same location
+const MIN_REATTACH_MATCH_PERCENTAGE: usize = 90;
+
+/// Archives a location, keeping its whole index, media data and preview media in the library,
+/// so it stays searchable after the disk is unplugged or deleted. Archived locations are
+/// read-only until they're reattached to a new path with [`reattach_location`].
+///
+/// If the location is online, we run the media processor first, so any missing thumbnails and
+/// media data are generated while we still have access to the files.
+pub async fn archive_location(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_id: location::id::Type,
+) -> Result<(), LocationError> {
+	let location = find_location(library, location_id)
+		.exec()
+		.await?
+		.ok_or(LocationError::IdNotFound(location_id))?;
+
+	if is_archived(&location) {
+		return Err(LocationError::Archived(location_id));
+	}
+
+	let is_online = match Uuid::from_slice(&location.pub_id) {
+		Ok(pub_id) => node.locations.is_online(&pub_id).await,
+		Err(_) => false,
+	};
+
+	if is_online {
+		JobBuilder::new(OldMediaProcessorJobInit {
+			location: location.clone(),
+			sub_path: None,
+			regenerate_thumbnails: false,
+			regenerate_labels: false,
+		})
+		.with_action("archive_location")
+		.with_metadata(json!({ "location": location }))
+		.build()
+		.queue_next(OldLocationArchiverJobInit { location_id })
+		.spawn(node, library)
+		.await
+	} else {
+		JobBuilder::new(OldLocationArchiverJobInit { location_id })
+			.with_action("archive_location")
+			.with_metadata(json!({ "location": location }))
+			.build()
+			.spawn(node, library)
+			.await
+	}
+	.map_err(Into::into)
+}
+
+/// Reattaches an archived location to `path`, which can be the old path of the location once its
+/// disk is plugged back in, or a completely new one. The files found at `path` are matched against
+/// the archived index by their `cas_id`, and the location is only reattached if enough of them match.
+pub async fn reattach_location(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_id: location::id::Type,
+	path: PathBuf,
+) -> Result<(), LocationError> {
+	let location = find_location(library, location_id)
+		.exec()
+		.await?
+		.ok_or(LocationError::IdNotFound(location_id))?;
+
+	if !is_archived(&location) {
+		return Err(LocationError::NotArchived(location_id));
+	}
+
+	JobBuilder::new(OldLocationReattacherJobInit { location_id, path })
+		.with_action("reattach_location")
+		.with_metadata(json!({ "location": location }))
+		.build()
+		.spawn(node, library)
+		.await
+		.map_err(Into::into)
+}
+
+fn is_archived(location: &location::Data) -> bool {
+	location.is_archived.unwrap_or(false)
+}
+
+/// Archived locations are read-only, so this must be checked before touching any of their files
+pub async fn ensure_location_is_not_archived(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+) -> Result<(), LocationError> {
+	let location = db
+		.location()
+		.find_unique(location::id::equals(location_id))
+		.select(location::select!({ is_archived }))
+		.exec()
+		.await?
+		.ok_or(LocationError::IdNotFound(location_id))?;
+
+	if location.is_archived.unwrap_or(false) {
+		Err(LocationError::Archived(location_id))
+	} else {
+		Ok(())
+	}
+}
+
+/// Only files with a `cas_id` can be matched on reattach, and are the ones with preview media
+fn archived_file_paths_filters(
+	location_id: location::id::Type,
+	file_path_id: Option<file_path::id::Type>,
+) -> Vec<file_path::WhereParam> {
+	sd_utils::chain_optional_iter(
+		[
+			file_path::location_id::equals(Some(location_id)),
+			file_path::is_dir::equals(Some(false)),
+			file_path::cas_id::not(None),
+		],
+		[file_path_id.map(file_path::id::gte)],
+	)
+}
@@ -1,3 +1,5 @@
+use crate::old_job::JobManagerError;
+
 use sd_file_path_helper::FilePathError;
 use sd_prisma::prisma::location;
 use sd_utils::{
@@ -51,6 +53,19 @@ pub enum LocationError {
 	NestedLocation(Box<Path>),
 	#[error(transparent)]
 	NonUtf8Path(#[from] NonUtf8PathError),
+	#[error("location is archived and can't be modified <id='{0}'>")]
+	Archived(location::id::Type),
+	#[error("location is not archived <id='{0}'>")]
+	NotArchived(location::id::Type),
+	#[error(
+		"path doesn't match the archived location, found {matched} of {total} files <path='{}'>",
+		.path.display()
+	)]
+	ArchiveMismatch {
+		path: Box<Path>,
+		matched: usize,
+		total: usize,
+	},
 
 	// Internal Errors
 	#[error(transparent)]
@@ -75,6 +90,8 @@ pub enum LocationError {
 	MissingPath(location::id::Type),
 	#[error("missing-field: {0}")]
 	MissingField(#[from] MissingFieldError),
+	#[error(transparent)]
+	JobManager(#[from] JobManagerError),
 }
 
 impl From<LocationError> for rspc::Error {
@@ -91,9 +108,12 @@ impl From<LocationError> for rspc::Error {
 			}
 
 			// User's fault errors
-			NotDirectory(_) | NestedLocation(_) | LocationAlreadyExists(_) => {
-				Self::with_cause(ErrorCode::BadRequest, err.to_string(), err)
-			}
+			NotDirectory(_)
+			| NestedLocation(_)
+			| LocationAlreadyExists(_)
+			| Archived(_)
+			| NotArchived(_)
+			| ArchiveMismatch { .. } => Self::with_cause(ErrorCode::BadRequest, err.to_string(), err),
 
 			// Custom error message is used to differentiate these errors in the frontend
 			// TODO: A better solution would be for rspc to support sending custom data alongside errors
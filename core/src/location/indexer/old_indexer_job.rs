@@ -39,7 +39,7 @@ use super::{
 	execute_indexer_save_step, execute_indexer_update_step, iso_file_path_factory,
 	old_walk::{keep_walking, walk, ToWalkEntry, WalkResult},
 	remove_non_existing_file_paths, reverse_update_directories_sizes,
-	rules::IndexerRule,
+	rules::{ignore_files::IgnoreFilesCache, IndexerRule},
 	IndexerError, OldIndexerJobSaveStep, OldIndexerJobUpdateStep,
 };
 
@@ -208,6 +208,10 @@ impl StatefulJob for OldIndexerJobInit {
 		} = walk(
 			&to_walk_path,
 			&indexer_rules,
+			&IgnoreFilesCache::new(
+				Some(location_path.to_path_buf()),
+				init.location.respect_ignore_files.unwrap_or(false),
+			),
 			update_notifier_fn(ctx),
 			file_paths_db_fetcher_fn!(&db),
 			to_remove_db_fetcher_fn!(location_id, &db),
@@ -396,6 +400,10 @@ impl StatefulJob for OldIndexerJobInit {
 				} = keep_walking(
 					to_walk_entry,
 					&data.indexer_rules,
+					&IgnoreFilesCache::new(
+						Some(location_path.to_path_buf()),
+						init.location.respect_ignore_files.unwrap_or(false),
+					),
 					update_notifier_fn(ctx),
 					file_paths_db_fetcher_fn!(&db),
 					to_remove_db_fetcher_fn!(location_id, &db),
@@ -29,8 +29,10 @@ use tracing::{debug, error};
 
 use super::{
 	execute_indexer_save_step, iso_file_path_factory, location_with_indexer_rules,
-	old_walk::walk_single_dir, remove_non_existing_file_paths, rules::IndexerRule, IndexerError,
-	OldIndexerJobSaveStep,
+	old_walk::walk_single_dir,
+	remove_non_existing_file_paths,
+	rules::{ignore_files::IgnoreFilesCache, IndexerRule},
+	IndexerError, OldIndexerJobSaveStep,
 };
 
 /// BATCH_SIZE is the number of files to index at each step, writing the chunk of files metadata in the database.
@@ -80,6 +82,10 @@ pub async fn old_shallow(
 		walk_single_dir(
 			&to_walk_path,
 			&indexer_rules,
+			&IgnoreFilesCache::new(
+				Some(location_path.to_path_buf()),
+				location.respect_ignore_files.unwrap_or(false),
+			),
 			|_, _| {},
 			file_paths_db_fetcher_fn!(&db),
 			to_remove_db_fetcher_fn!(location_id, &db),
@@ -18,7 +18,7 @@ use tracing::trace;
 use uuid::Uuid;
 
 use super::{
-	rules::{IndexerRule, RuleKind},
+	rules::{ignore_files::IgnoreFilesCache, IndexerRule, RuleKind},
 	IndexerError,
 };
 
@@ -119,6 +119,7 @@ where
 pub(super) async fn walk<FilePathDBFetcherFut, ToRemoveDbFetcherFut>(
 	root: impl AsRef<Path>,
 	indexer_rules: &[IndexerRule],
+	ignore_files: &IgnoreFilesCache,
 	mut update_notifier: impl FnMut(&Path, usize),
 	file_paths_db_fetcher: impl Fn(Vec<file_path::WhereParam>) -> FilePathDBFetcherFut,
 	to_remove_db_fetcher: impl Fn(
@@ -153,7 +154,6 @@ where
 	let mut paths_buffer = HashSet::with_capacity(WALKER_PATHS_BUFFER_INITIAL_CAPACITY);
 	let mut paths_and_sizes = HashMap::with_capacity(TO_WALK_QUEUE_INITIAL_CAPACITY);
 	let mut to_remove = vec![];
-
 	while let Some(entry) = to_walk.pop_front() {
 		let (entry_size, current_to_remove) = inner_walk_single_dir(
 			root,
@@ -167,6 +167,7 @@ where
 				paths_buffer: &mut paths_buffer,
 				maybe_to_walk: Some(&mut to_walk),
 				errors: &mut errors,
+				ignore_files,
 			},
 		)
 		.await;
@@ -200,6 +201,7 @@ where
 pub(super) async fn keep_walking<FilePathDBFetcherFut, ToRemoveDbFetcherFut>(
 	to_walk_entry: &ToWalkEntry,
 	indexer_rules: &[IndexerRule],
+	ignore_files: &IgnoreFilesCache,
 	mut update_notifier: impl FnMut(&Path, usize),
 	file_paths_db_fetcher: impl Fn(Vec<file_path::WhereParam>) -> FilePathDBFetcherFut,
 	to_remove_db_fetcher: impl Fn(
@@ -224,7 +226,6 @@ where
 	let mut indexed_paths = HashSet::with_capacity(WALK_SINGLE_DIR_PATHS_BUFFER_INITIAL_CAPACITY);
 	let mut paths_buffer = HashSet::with_capacity(WALK_SINGLE_DIR_PATHS_BUFFER_INITIAL_CAPACITY);
 	let mut errors = vec![];
-
 	let (to_walk_entry_size, to_remove) = inner_walk_single_dir(
 		to_walk_entry.path.clone(),
 		to_walk_entry,
@@ -237,6 +238,7 @@ where
 			paths_buffer: &mut paths_buffer,
 			maybe_to_walk: Some(&mut to_keep_walking),
 			errors: &mut errors,
+			ignore_files,
 		},
 	)
 	.await;
@@ -265,6 +267,7 @@ where
 pub(super) async fn walk_single_dir<FilePathDBFetcherFut, ToRemoveDbFetcherFut>(
 	root: impl AsRef<Path>,
 	indexer_rules: &[IndexerRule],
+	ignore_files: &IgnoreFilesCache,
 	mut update_notifier: impl FnMut(&Path, usize) + '_,
 	file_paths_db_fetcher: impl Fn(Vec<file_path::WhereParam>) -> FilePathDBFetcherFut,
 	to_remove_db_fetcher: impl Fn(
@@ -305,7 +308,6 @@ where
 
 	let mut paths_buffer = HashSet::with_capacity(WALK_SINGLE_DIR_PATHS_BUFFER_INITIAL_CAPACITY);
 	let mut errors = vec![];
-
 	let (root_size, to_remove) = inner_walk_single_dir(
 		root,
 		&ToWalkEntry {
@@ -322,6 +324,7 @@ where
 			paths_buffer: &mut paths_buffer,
 			maybe_to_walk: None,
 			errors: &mut errors,
+			ignore_files,
 		},
 	)
 	.await;
@@ -427,6 +430,7 @@ struct WorkingTable<'a> {
 	paths_buffer: &'a mut HashSet<WalkingEntry>,
 	maybe_to_walk: Option<&'a mut VecDeque<ToWalkEntry>>,
 	errors: &'a mut Vec<IndexerError>,
+	ignore_files: &'a IgnoreFilesCache,
 }
 
 async fn inner_walk_single_dir<ToRemoveDbFetcherFut>(
@@ -448,6 +452,7 @@ async fn inner_walk_single_dir<ToRemoveDbFetcherFut>(
 		paths_buffer,
 		mut maybe_to_walk,
 		errors,
+		ignore_files,
 	}: WorkingTable<'_>,
 ) -> (u64, Vec<file_path_pub_and_cas_ids::Data>)
 where
@@ -509,22 +514,15 @@ where
 			accept_by_children_dir
 		);
 
-		let Ok(rules_per_kind) = IndexerRule::apply_all(indexer_rules, &current_path)
+		let Ok(rules_per_kind) = IndexerRule::apply_all(indexer_rules, &current_path, ignore_files)
 			.await
 			.map_err(|e| errors.push(e.into()))
 		else {
 			continue 'entries;
 		};
 
-		if rules_per_kind
-			.get(&RuleKind::RejectFilesByGlob)
-			.map_or(false, |reject_results| {
-				reject_results.iter().any(|reject| !reject)
-			}) {
-			trace!(
-				"Path {} rejected by `RuleKind::RejectFilesByGlob`",
-				current_path.display()
-			);
+		if let Some(kind) = RuleKind::rejected_by(&rules_per_kind) {
+			trace!("Path {} rejected by `{kind:?}`", current_path.display());
 			continue 'entries;
 		}
 
@@ -543,6 +541,20 @@ where
 
 		let is_dir = metadata.is_dir();
 
+		// Ignored directories are skipped along with everything in them, like git does
+		let Ok(is_ignored) = ignore_files
+			.is_ignored_by_default_files(&current_path, is_dir)
+			.await
+			.map_err(|e| errors.push(e.into()))
+		else {
+			continue 'entries;
+		};
+
+		if is_ignored {
+			trace!("Path {} ignored by ignore files", current_path.display());
+			continue 'entries;
+		}
+
 		if is_dir {
 			// If it is a directory, first we check if we must reject it and its children entirely
 			if rules_per_kind
@@ -585,13 +597,9 @@ where
 			}
 		}
 
-		if rules_per_kind
-			.get(&RuleKind::AcceptFilesByGlob)
-			.map_or(false, |accept_rules| {
-				accept_rules.iter().all(|accept| !accept)
-			}) {
+		if let Some(kind) = RuleKind::not_accepted_by(&rules_per_kind) {
 			trace!(
-				"Path {} reject because it didn't passed in any AcceptFilesByGlob rules",
+				"Path {} reject because it didn't passed in any `{kind:?}` rules",
 				current_path.display()
 			);
 			continue 'entries;
@@ -822,6 +830,7 @@ mod tests {
 		let walk_result = walk(
 			root_path.to_path_buf(),
 			&[],
+			&IgnoreFilesCache::new(Some(root_path.to_path_buf()), false),
 			|_, _| {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
@@ -887,6 +896,7 @@ mod tests {
 		let walk_result = walk(
 			root_path.to_path_buf(),
 			only_photos_rule,
+			&IgnoreFilesCache::new(Some(root_path.to_path_buf()), false),
 			|_, _| {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
@@ -961,6 +971,7 @@ mod tests {
 		let walk_result = walk(
 			root_path.to_path_buf(),
 			git_repos,
+			&IgnoreFilesCache::new(Some(root_path.to_path_buf()), false),
 			|_, _| {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
@@ -1053,6 +1064,85 @@ mod tests {
 		let walk_result = walk(
 			root_path.to_path_buf(),
 			git_repos_no_deps_no_build_dirs,
+			&IgnoreFilesCache::new(Some(root_path.to_path_buf()), false),
+			|_, _| {},
+			|_| async { Ok(vec![]) },
+			|_, _| async { Ok(vec![]) },
+			|path, is_dir| {
+				IsolatedFilePathData::new(0, root_path, path, is_dir).map_err(Into::into)
+			},
+			420,
+		)
+		.await
+		.unwrap();
+
+		if !walk_result.errors.is_empty() {
+			panic!("errors: {:#?}", walk_result.errors);
+		}
+
+		let actual = walk_result.walked.collect::<HashSet<_>>();
+
+		if actual != expected {
+			panic!("difference: {:#?}", expected.difference(&actual));
+		}
+	}
+
+	#[tokio::test]
+	// #[traced_test]
+	async fn respecting_ignore_files() {
+		let root = prepare_location().await;
+		let root_path = root.path();
+
+		fs::write(root_path.join("rust_project/.gitignore"), "target/\n")
+			.await
+			.unwrap();
+		fs::write(
+			root_path.join("inner/node_project/.sdignore"),
+			"node_modules/\n",
+		)
+		.await
+		.unwrap();
+
+		let metadata = FilePathMetadata {
+			inode: 0,
+			size_in_bytes: 0,
+			created_at: Utc::now(),
+			modified_at: Utc::now(),
+			hidden: false,
+		};
+
+		let f = |path, is_dir| IsolatedFilePathData::new(0, root_path, path, is_dir).unwrap();
+		let pub_id = Uuid::new_v4();
+		let maybe_object_id = None;
+
+		#[rustfmt::skip]
+		let expected = [
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("rust_project"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("rust_project/.git"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("rust_project/.gitignore"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("rust_project/Cargo.toml"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("rust_project/src"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("rust_project/src/main.rs"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("inner"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("inner/node_project"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("inner/node_project/.git"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("inner/node_project/.sdignore"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("inner/node_project/package.json"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("inner/node_project/src"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("inner/node_project/src/App.tsx"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("photos"), true), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("photos/photo1.png"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("photos/photo2.jpg"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("photos/photo3.jpeg"), false), metadata },
+			WalkedEntry { pub_id, maybe_object_id, iso_file_path: f(root_path.join("photos/text.txt"), false), metadata },
+		]
+		.into_iter()
+		.collect::<HashSet<_>>();
+
+		let walk_result = walk(
+			root_path.to_path_buf(),
+			&[],
+			&IgnoreFilesCache::new(Some(root_path.to_path_buf()), true),
 			|_, _| {},
 			|_| async { Ok(vec![]) },
 			|_, _| async { Ok(vec![]) },
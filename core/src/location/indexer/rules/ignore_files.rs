@@ -0,0 +1,284 @@
+//! `.gitignore` semantics for indexer rules and walkers: every directory from the entry up to the
+//! repository root can have ignore files, and the closest one with a matching pattern decides, so
+//! nested ignore files can both ignore more and whitelist (`!pattern`) what their parents ignored.
+
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use tokio::{fs, sync::Mutex};
+use tracing::debug;
+
+use super::{IndexerRuleError, RuleKind};
+
+/// Directory that stops the lookup of ignore files in parent directories, as git does
+const REPOSITORY_MARKER: &str = ".git";
+
+pub(super) fn validate_ignore_file_names(names: &[String]) -> Result<(), IndexerRuleError> {
+	if names.is_empty() {
+		return Err(IndexerRuleError::InvalidRuleParameter {
+			kind: RuleKind::RejectFilesByIgnoreFiles,
+			parameter: String::new(),
+			reason: "at least one ignore file name is required",
+		});
+	}
+
+	if let Some(name) = names
+		.iter()
+		.find(|name| name.is_empty() || name.contains(['/', '\\']))
+	{
+		return Err(IndexerRuleError::InvalidRuleParameter {
+			kind: RuleKind::RejectFilesByIgnoreFiles,
+			parameter: name.clone(),
+			reason: "ignore files are names, not paths",
+		});
+	}
+
+	Ok(())
+}
+
+/// Names of the ignore files honoured by locations respecting ignore files and by ephemeral walks
+/// asking for it, `.sdignore` being for patterns only meant for Spacedrive
+pub const DEFAULT_IGNORE_FILES: [&str; 2] = [".gitignore", ".sdignore"];
+
+struct IgnoreDir {
+	matcher: Option<Gitignore>,
+	is_repository_root: bool,
+}
+
+impl IgnoreDir {
+	/// Builds a matcher out of the ignore files present in `dir`, later names taking precedence
+	async fn read(dir: &Path, names: &[String]) -> Result<Self, IndexerRuleError> {
+		let mut builder = GitignoreBuilder::new(dir);
+		let mut found = false;
+
+		for name in names {
+			let ignore_file = dir.join(name);
+
+			if fs::try_exists(&ignore_file)
+				.await
+				.map_err(|e| IndexerRuleError::IgnoreFileIO(FileIOError::from((&ignore_file, e))))?
+			{
+				// Invalid lines are skipped, the same way git does
+				if let Some(e) = builder.add(&ignore_file) {
+					debug!("Skipping invalid lines of {}: {e}", ignore_file.display());
+				}
+				found = true;
+			}
+		}
+
+		let matcher = if found {
+			Some(
+				builder
+					.build()
+					.map_err(|e| IndexerRuleError::IgnoreFile(e.to_string()))?,
+			)
+		} else {
+			None
+		};
+
+		let is_repository_root = fs::try_exists(dir.join(REPOSITORY_MARKER))
+			.await
+			.map_err(|e| IndexerRuleError::IgnoreFileIO(FileIOError::from((dir, e))))?;
+
+		Ok(Self {
+			matcher,
+			is_repository_root,
+		})
+	}
+}
+
+/// Ignore files read so far, cached by directory so each one is parsed once per walk instead of
+/// once per entry below it
+struct IgnoreFiles {
+	names: Vec<String>,
+	root: Option<PathBuf>,
+	dirs: HashMap<PathBuf, IgnoreDir>,
+}
+
+impl IgnoreFiles {
+	fn new(names: Vec<String>, root: Option<PathBuf>) -> Self {
+		Self {
+			names,
+			root,
+			dirs: HashMap::new(),
+		}
+	}
+
+	/// Whether `path` is ignored by the ignore files in its parent directories, the closest one
+	/// with a matching pattern deciding
+	async fn is_ignored(&mut self, path: &Path, is_dir: bool) -> Result<bool, IndexerRuleError> {
+		for dir in path.ancestors().skip(1) {
+			if !self.dirs.contains_key(dir) {
+				let ignore_dir = IgnoreDir::read(dir, &self.names).await?;
+				self.dirs.insert(dir.to_path_buf(), ignore_dir);
+			}
+
+			let Some(ignore_dir) = self.dirs.get(dir) else {
+				continue;
+			};
+
+			if let Some(matcher) = &ignore_dir.matcher {
+				let matched = matcher.matched_path_or_any_parents(path, is_dir);
+
+				if matched.is_ignore() {
+					return Ok(true);
+				}
+
+				if matched.is_whitelist() {
+					return Ok(false);
+				}
+			}
+
+			if ignore_dir.is_repository_root || self.root.as_deref() == Some(dir) {
+				break;
+			}
+		}
+
+		Ok(false)
+	}
+}
+
+/// Ignore files of a whole walk, for [`DEFAULT_IGNORE_FILES`] when the walk respects them and for
+/// every `RejectFilesByIgnoreFiles` rule applied during it, each set of names having its own cache
+#[derive(Default)]
+pub struct IgnoreFilesCache {
+	root: Option<PathBuf>,
+	respect_default_files: bool,
+	per_names: Mutex<HashMap<Vec<String>, IgnoreFiles>>,
+}
+
+impl IgnoreFilesCache {
+	/// Ignore files are looked up from each entry up to the closest repository root, and no
+	/// further than `root` when walking a location, so its parents can't hide it
+	pub fn new(root: Option<PathBuf>, respect_default_files: bool) -> Self {
+		Self {
+			root,
+			respect_default_files,
+			per_names: Mutex::default(),
+		}
+	}
+
+	/// Whether `path` is ignored by [`DEFAULT_IGNORE_FILES`], always `false` when the walk doesn't
+	/// respect them
+	pub async fn is_ignored_by_default_files(
+		&self,
+		path: &Path,
+		is_dir: bool,
+	) -> Result<bool, IndexerRuleError> {
+		if !self.respect_default_files {
+			return Ok(false);
+		}
+
+		let names = DEFAULT_IGNORE_FILES
+			.into_iter()
+			.map(str::to_string)
+			.collect::<Vec<_>>();
+
+		self.is_ignored(&names, path, is_dir).await
+	}
+
+	pub(super) async fn is_ignored(
+		&self,
+		names: &[String],
+		path: &Path,
+		is_dir: bool,
+	) -> Result<bool, IndexerRuleError> {
+		self.per_names
+			.lock()
+			.await
+			.entry(names.to_vec())
+			.or_insert_with(|| IgnoreFiles::new(names.to_vec(), self.root.clone()))
+			.is_ignored(path, is_dir)
+			.await
+	}
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+	use super::*;
+
+	use tempfile::tempdir;
+
+	#[tokio::test]
+	async fn nested_ignore_files() {
+		let root = tempdir().unwrap();
+		let root = root.path();
+		let mut ignore_files =
+			IgnoreFiles::new(vec![".gitignore".to_string(), ".ignore".to_string()], None);
+
+		fs::create_dir_all(root.join(".git")).await.unwrap();
+		fs::create_dir_all(root.join("app/target/debug"))
+			.await
+			.unwrap();
+		fs::create_dir_all(root.join("app/logs")).await.unwrap();
+
+		fs::write(root.join(".gitignore"), "target/\n*.log\n")
+			.await
+			.unwrap();
+		fs::write(root.join("app/.ignore"), "!important.log\n")
+			.await
+			.unwrap();
+
+		assert!(ignore_files
+			.is_ignored(&root.join("app/target"), true)
+			.await
+			.unwrap());
+		assert!(ignore_files
+			.is_ignored(&root.join("app/target/debug/app"), false)
+			.await
+			.unwrap());
+		assert!(ignore_files
+			.is_ignored(&root.join("app/logs/today.log"), false)
+			.await
+			.unwrap());
+		assert!(!ignore_files
+			.is_ignored(&root.join("app/logs/important.log"), false)
+			.await
+			.unwrap());
+		assert!(!ignore_files
+			.is_ignored(&root.join("app/src/main.rs"), false)
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn stops_at_root() {
+		let parent = tempdir().unwrap();
+		let parent = parent.path();
+		let root = parent.join("location");
+		let names = [".gitignore".to_string()];
+
+		fs::create_dir_all(&root).await.unwrap();
+		fs::write(parent.join(".gitignore"), "*.txt\n")
+			.await
+			.unwrap();
+
+		let file = root.join("notes.txt");
+
+		assert!(IgnoreFilesCache::new(None, false)
+			.is_ignored(&names, &file, false)
+			.await
+			.unwrap());
+		assert!(!IgnoreFilesCache::new(Some(root.clone()), true)
+			.is_ignored(&names, &file, false)
+			.await
+			.unwrap());
+		assert!(!IgnoreFilesCache::new(None, false)
+			.is_ignored_by_default_files(&file, false)
+			.await
+			.unwrap());
+	}
+
+	#[test]
+	fn names() {
+		assert!(validate_ignore_file_names(&[".gitignore".to_string()]).is_ok());
+		assert!(validate_ignore_file_names(&[]).is_err());
+		assert!(validate_ignore_file_names(&["../.gitignore".to_string()]).is_err());
+	}
+}
@@ -0,0 +1,504 @@
+//! Rules matching on what we know about an entry besides its path: extended attributes, size,
+//! modification date and, on unix, ownership and permission bits.
+
+use sd_utils::{error::FileIOError, parse_size};
+
+use std::{fmt, fs::Metadata, path::Path, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::OnceCell, task::spawn_blocking};
+
+use super::{IndexerRuleError, RuleKind};
+
+/// An extended attribute that must be present, optionally with a given value: `user.xdg.origin.url`
+/// or `user.project=spacedrive`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XattrMatcher {
+	pub name: String,
+	pub value: Option<Vec<u8>>,
+}
+
+/// An inclusive range of file sizes in bytes, like `1MB..`, `..10KiB` or `100..2048`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeRange {
+	pub min: Option<u64>,
+	pub max: Option<u64>,
+}
+
+/// An inclusive range of ages in seconds since the last modification, like `..7d` for files
+/// modified in the last week or `1y..` for files untouched for a year
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgeRange {
+	pub min: Option<u64>,
+	pub max: Option<u64>,
+}
+
+/// Ownership and permission bits, only available on unix: `uid:1000`, `gid:20` or `mode:002`,
+/// with the mode in octal and matching when all of its bits are set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PermissionMatcher {
+	Uid(u32),
+	Gid(u32),
+	Mode(u32),
+}
+
+fn invalid(kind: RuleKind, parameter: &str, reason: &'static str) -> IndexerRuleError {
+	IndexerRuleError::InvalidRuleParameter {
+		kind,
+		parameter: parameter.to_string(),
+		reason,
+	}
+}
+
+impl XattrMatcher {
+	pub fn parse(kind: RuleKind, parameter: &str) -> Result<Self, IndexerRuleError> {
+		let (name, value) = parameter
+			.split_once('=')
+			.map_or((parameter, None), |(name, value)| {
+				(name, Some(value.as_bytes().to_vec()))
+			});
+
+		if name.is_empty() {
+			return Err(invalid(kind, parameter, "missing attribute name"));
+		}
+
+		Ok(Self {
+			name: name.to_string(),
+			value,
+		})
+	}
+
+	fn matches(&self, path: &Path) -> Result<bool, IndexerRuleError> {
+		#[cfg(target_family = "unix")]
+		{
+			let value = match xattr::get(path, &self.name) {
+				Ok(value) => value,
+				Err(e) if is_absent_xattr(&e) => None,
+				Err(e) => {
+					return Err(IndexerRuleError::MetadataFileIO(FileIOError::from((
+						path, e,
+					))))
+				}
+			};
+
+			Ok(match (value, &self.value) {
+				(Some(value), Some(expected)) => &value == expected,
+				(Some(_), None) => true,
+				(None, _) => false,
+			})
+		}
+
+		#[cfg(not(target_family = "unix"))]
+		{
+			let _ = path;
+			Ok(false)
+		}
+	}
+}
+
+/// File systems without extended attributes (FAT, exFAT, some network mounts) fail with
+/// `ENOTSUP`, their entries just don't have the attribute
+#[cfg(target_family = "unix")]
+fn is_absent_xattr(e: &std::io::Error) -> bool {
+	e.raw_os_error()
+		.is_some_and(|code| [libc::ENOTSUP, libc::EOPNOTSUPP, libc::ENODATA].contains(&code))
+}
+
+impl fmt::Display for XattrMatcher {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.value {
+			Some(value) => write!(f, "{}={}", self.name, String::from_utf8_lossy(value)),
+			None => write!(f, "{}", self.name),
+		}
+	}
+}
+
+/// Parses `min..max` ranges where either side can be left open, a single value standing for
+/// itself
+fn parse_range(
+	kind: RuleKind,
+	parameter: &str,
+	parse_value: impl Fn(&str) -> Option<u64>,
+) -> Result<(Option<u64>, Option<u64>), IndexerRuleError> {
+	let parse_bound = |bound: &str| {
+		let bound = bound.trim();
+		if bound.is_empty() {
+			Ok(None)
+		} else {
+			parse_value(bound)
+				.map(Some)
+				.ok_or_else(|| invalid(kind, parameter, "invalid value"))
+		}
+	};
+
+	let (min, max) = match parameter.split_once("..") {
+		Some((min, max)) => (parse_bound(min)?, parse_bound(max)?),
+		None => {
+			let value = parse_bound(parameter)?;
+			(value, value)
+		}
+	};
+
+	match (min, max) {
+		(None, None) => Err(invalid(kind, parameter, "both sides of the range are open")),
+		(Some(min), Some(max)) if min > max => Err(invalid(
+			kind,
+			parameter,
+			"the start of the range is after its end",
+		)),
+		bounds => Ok(bounds),
+	}
+}
+
+/// Writes a range back in the format `parse_range` reads, each bound with the largest unit
+/// dividing it
+fn format_range(
+	f: &mut fmt::Formatter<'_>,
+	min: Option<u64>,
+	max: Option<u64>,
+	units: &[(&str, u64)],
+) -> fmt::Result {
+	let format_bound = |value: u64| {
+		units
+			.iter()
+			.find(|(_, multiplier)| value != 0 && value % multiplier == 0)
+			.map_or_else(
+				|| value.to_string(),
+				|(unit, multiplier)| format!("{}{unit}", value / multiplier),
+			)
+	};
+
+	match (min, max) {
+		(Some(min), Some(max)) if min == max => write!(f, "{}", format_bound(min)),
+		(min, max) => write!(
+			f,
+			"{}..{}",
+			min.map(format_bound).unwrap_or_default(),
+			max.map(format_bound).unwrap_or_default()
+		),
+	}
+}
+
+fn contains(min: Option<u64>, max: Option<u64>, value: u64) -> bool {
+	min.map_or(true, |min| value >= min) && max.map_or(true, |max| value <= max)
+}
+
+/// Units used when writing sizes back, from the largest
+const SIZE_UNITS: [(&str, u64); 8] = [
+	("TiB", 1 << 40),
+	("TB", 1_000_000_000_000),
+	("GiB", 1 << 30),
+	("GB", 1_000_000_000),
+	("MiB", 1 << 20),
+	("MB", 1_000_000),
+	("KiB", 1 << 10),
+	("KB", 1_000),
+];
+
+/// Units used when writing ages back, from the largest
+const AGE_UNITS: [(&str, u64); 5] = [
+	("y", 365 * 24 * 60 * 60),
+	("w", 7 * 24 * 60 * 60),
+	("d", 24 * 60 * 60),
+	("h", 60 * 60),
+	("m", 60),
+];
+
+fn parse_age(value: &str) -> Option<u64> {
+	let unit_start = value
+		.find(|c: char| !c.is_ascii_digit())
+		.unwrap_or(value.len());
+	let (number, unit) = value.split_at(unit_start);
+
+	let multiplier: u64 = match unit {
+		"" | "s" => 1,
+		"m" => 60,
+		"h" => 60 * 60,
+		"d" => 24 * 60 * 60,
+		"w" => 7 * 24 * 60 * 60,
+		"y" => 365 * 24 * 60 * 60,
+		_ => return None,
+	};
+
+	number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+impl SizeRange {
+	pub fn parse(kind: RuleKind, parameter: &str) -> Result<Self, IndexerRuleError> {
+		parse_range(kind, parameter, parse_size).map(|(min, max)| Self { min, max })
+	}
+
+	fn matches(&self, metadata: &Metadata) -> bool {
+		contains(self.min, self.max, metadata.len())
+	}
+}
+
+impl fmt::Display for SizeRange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		format_range(f, self.min, self.max, &SIZE_UNITS)
+	}
+}
+
+impl AgeRange {
+	pub fn parse(kind: RuleKind, parameter: &str) -> Result<Self, IndexerRuleError> {
+		parse_range(kind, parameter, parse_age).map(|(min, max)| Self { min, max })
+	}
+
+	fn matches(&self, metadata: &Metadata, now: SystemTime) -> bool {
+		// Files without a modification date or modified in the future are considered brand new
+		let age = metadata
+			.modified()
+			.ok()
+			.and_then(|modified| now.duration_since(modified).ok())
+			.map_or(0, |age| age.as_secs());
+
+		contains(self.min, self.max, age)
+	}
+}
+
+impl fmt::Display for AgeRange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		format_range(f, self.min, self.max, &AGE_UNITS)
+	}
+}
+
+impl PermissionMatcher {
+	pub fn parse(kind: RuleKind, parameter: &str) -> Result<Self, IndexerRuleError> {
+		let (key, value) = parameter
+			.split_once(':')
+			.ok_or_else(|| invalid(kind, parameter, "expected `uid:`, `gid:` or `mode:`"))?;
+
+		match key {
+			"uid" => value.parse().map(Self::Uid),
+			"gid" => value.parse().map(Self::Gid),
+			"mode" => u32::from_str_radix(value.trim_start_matches("0o"), 8).map(Self::Mode),
+			_ => {
+				return Err(invalid(
+					kind,
+					parameter,
+					"expected `uid:`, `gid:` or `mode:`",
+				))
+			}
+		}
+		.map_err(|_| invalid(kind, parameter, "invalid number"))
+	}
+
+	fn matches(&self, metadata: &Metadata) -> bool {
+		#[cfg(target_family = "unix")]
+		{
+			use std::os::unix::fs::MetadataExt;
+
+			match *self {
+				Self::Uid(uid) => metadata.uid() == uid,
+				Self::Gid(gid) => metadata.gid() == gid,
+				Self::Mode(mode) => metadata.mode() & mode == mode,
+			}
+		}
+
+		#[cfg(not(target_family = "unix"))]
+		{
+			let _ = metadata;
+			false
+		}
+	}
+}
+
+impl fmt::Display for PermissionMatcher {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Uid(uid) => write!(f, "uid:{uid}"),
+			Self::Gid(gid) => write!(f, "gid:{gid}"),
+			Self::Mode(mode) => write!(f, "mode:{mode:03o}"),
+		}
+	}
+}
+
+/// What the rules of this module need to know about an entry, fetched at most once however many
+/// rules are applied to it
+pub(super) struct EntryMetadata {
+	metadata: OnceCell<Metadata>,
+	now: SystemTime,
+}
+
+impl EntryMetadata {
+	pub(super) fn new() -> Self {
+		Self {
+			metadata: OnceCell::new(),
+			now: SystemTime::now(),
+		}
+	}
+
+	async fn get(&self, path: &Path) -> Result<&Metadata, IndexerRuleError> {
+		self.metadata
+			.get_or_try_init(|| async {
+				fs::symlink_metadata(path)
+					.await
+					.map_err(|e| IndexerRuleError::MetadataFileIO(FileIOError::from((path, e))))
+			})
+			.await
+	}
+
+	pub(super) async fn is_dir(&self, path: &Path) -> Result<bool, IndexerRuleError> {
+		self.get(path).await.map(Metadata::is_dir)
+	}
+
+	/// Extended attributes are checked on directories too, so a rejected directory is skipped
+	/// along with everything in it
+	pub(super) async fn any_xattr(
+		&self,
+		path: &Path,
+		matchers: &[XattrMatcher],
+	) -> Result<bool, IndexerRuleError> {
+		let path = path.to_path_buf();
+		let matchers = matchers.to_vec();
+
+		// Reading extended attributes is a blocking syscall, so it's kept off the async runtime
+		spawn_blocking(move || {
+			for matcher in &matchers {
+				if matcher.matches(&path)? {
+					return Ok(true);
+				}
+			}
+
+			Ok(false)
+		})
+		.await?
+	}
+
+	/// Sizes only make sense for files, directories never match
+	pub(super) async fn any_size(
+		&self,
+		path: &Path,
+		ranges: &[SizeRange],
+	) -> Result<bool, IndexerRuleError> {
+		let metadata = self.get(path).await?;
+
+		Ok(!metadata.is_dir() && ranges.iter().any(|range| range.matches(metadata)))
+	}
+
+	/// Ages are only checked for files, directories never match
+	pub(super) async fn any_age(
+		&self,
+		path: &Path,
+		ranges: &[AgeRange],
+	) -> Result<bool, IndexerRuleError> {
+		let metadata = self.get(path).await?;
+
+		Ok(!metadata.is_dir() && ranges.iter().any(|range| range.matches(metadata, self.now)))
+	}
+
+	pub(super) async fn any_permission(
+		&self,
+		path: &Path,
+		matchers: &[PermissionMatcher],
+	) -> Result<bool, IndexerRuleError> {
+		let metadata = self.get(path).await?;
+
+		Ok(matchers.iter().any(|matcher| matcher.matches(metadata)))
+	}
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ranges() {
+		let kind = RuleKind::AcceptFilesBySize;
+
+		assert_eq!(
+			SizeRange::parse(kind, "1MB..").unwrap(),
+			SizeRange {
+				min: Some(1_000_000),
+				max: None
+			}
+		);
+		assert_eq!(
+			SizeRange::parse(kind, "..10KiB").unwrap(),
+			SizeRange {
+				min: None,
+				max: Some(10 << 10)
+			}
+		);
+		assert_eq!(
+			SizeRange::parse(kind, "2048").unwrap(),
+			SizeRange {
+				min: Some(2048),
+				max: Some(2048)
+			}
+		);
+		assert!(SizeRange::parse(kind, "..").is_err());
+		assert!(SizeRange::parse(kind, "2MB..1MB").is_err());
+		assert!(SizeRange::parse(kind, "1 parsec..").is_err());
+
+		assert_eq!(
+			AgeRange::parse(RuleKind::RejectFilesByModifiedAge, "1y..").unwrap(),
+			AgeRange {
+				min: Some(365 * 24 * 60 * 60),
+				max: None
+			}
+		);
+	}
+
+	#[test]
+	fn matchers() {
+		let kind = RuleKind::AcceptFilesByXattr;
+
+		assert_eq!(
+			XattrMatcher::parse(kind, "user.project=spacedrive").unwrap(),
+			XattrMatcher {
+				name: "user.project".to_string(),
+				value: Some(b"spacedrive".to_vec())
+			}
+		);
+		assert!(XattrMatcher::parse(kind, "=value").is_err());
+
+		#[cfg(target_family = "unix")]
+		{
+			use std::io::Error;
+
+			for code in [libc::ENOTSUP, libc::EOPNOTSUPP, libc::ENODATA] {
+				assert!(is_absent_xattr(&Error::from_raw_os_error(code)));
+			}
+			assert!(!is_absent_xattr(&Error::from_raw_os_error(libc::EACCES)));
+		}
+
+		let kind = RuleKind::RejectFilesByPermissions;
+
+		assert_eq!(
+			PermissionMatcher::parse(kind, "mode:0o002").unwrap(),
+			PermissionMatcher::Mode(0o002)
+		);
+		assert_eq!(
+			PermissionMatcher::parse(kind, "uid:1000").unwrap(),
+			PermissionMatcher::Uid(1000)
+		);
+		assert!(PermissionMatcher::parse(kind, "mode:9").is_err());
+		assert!(PermissionMatcher::parse(kind, "owner:me").is_err());
+	}
+
+	#[test]
+	fn display_round_trip() {
+		for parameter in ["1MB..", "..10KiB", "1500", "2GiB..3GiB"] {
+			let range = SizeRange::parse(RuleKind::AcceptFilesBySize, parameter).unwrap();
+			assert_eq!(range.to_string(), parameter);
+			assert_eq!(
+				SizeRange::parse(RuleKind::AcceptFilesBySize, &range.to_string()).unwrap(),
+				range
+			);
+		}
+
+		let range = AgeRange::parse(RuleKind::RejectFilesByModifiedAge, "..7d").unwrap();
+		assert_eq!(range.to_string(), "..1w");
+
+		for parameter in ["uid:1000", "gid:20", "mode:002"] {
+			let matcher =
+				PermissionMatcher::parse(RuleKind::RejectFilesByPermissions, parameter).unwrap();
+			assert_eq!(matcher.to_string(), parameter);
+		}
+
+		let matcher = XattrMatcher::parse(RuleKind::AcceptFilesByXattr, "user.tag=a=b").unwrap();
+		assert_eq!(matcher.to_string(), "user.tag=a=b");
+	}
+}
@@ -24,15 +24,36 @@ use tokio::fs;
 use tracing::debug;
 use uuid::Uuid;
 
+pub mod ignore_files;
+pub mod metadata;
+pub mod rule_set;
 pub mod seed;
 
+use ignore_files::IgnoreFilesCache;
+use metadata::{AgeRange, EntryMetadata, PermissionMatcher, SizeRange, XattrMatcher};
+
 #[derive(Error, Debug)]
 pub enum IndexerRuleError {
 	// User errors
 	#[error("invalid indexer rule kind integer: {0}")]
 	InvalidRuleKindInt(i32),
+	#[error("invalid parameter `{parameter}` for {kind:?} rule: {reason}")]
+	InvalidRuleParameter {
+		kind: RuleKind,
+		parameter: String,
+		reason: &'static str,
+	},
 	#[error("glob builder error: {0}")]
 	Glob(#[from] globset::Error),
+	#[error("invalid rule set: {0}")]
+	RuleSetFormat(#[from] serde_json::Error),
+	#[error("unsupported rule set version: {0}")]
+	UnsupportedRuleSetVersion(u32),
+	#[error("invalid rule `{name}` in rule set: {source}")]
+	InvalidRuleSetEntry {
+		name: String,
+		source: Box<IndexerRuleError>,
+	},
 	#[error(transparent)]
 	NonUtf8Path(#[from] NonUtf8PathError),
 
@@ -45,6 +66,14 @@ pub enum IndexerRuleError {
 	AcceptByItsChildrenFileIO(FileIOError),
 	#[error("reject by its children file I/O error: {0}")]
 	RejectByItsChildrenFileIO(FileIOError),
+	#[error("file metadata I/O error: {0}")]
+	MetadataFileIO(FileIOError),
+	#[error("ignore file I/O error: {0}")]
+	IgnoreFileIO(FileIOError),
+	#[error("ignore file error: {0}")]
+	IgnoreFile(String),
+	#[error("indexer rule task error: {0}")]
+	Task(#[from] tokio::task::JoinError),
 	#[error("database error: {0}")]
 	Database(#[from] prisma_client_rust::QueryError),
 	#[error("missing-field: {0}")]
@@ -55,7 +84,11 @@ impl From<IndexerRuleError> for rspc::Error {
 	fn from(err: IndexerRuleError) -> Self {
 		match err {
 			IndexerRuleError::InvalidRuleKindInt(_)
+			| IndexerRuleError::InvalidRuleParameter { .. }
 			| IndexerRuleError::Glob(_)
+			| IndexerRuleError::RuleSetFormat(_)
+			| IndexerRuleError::UnsupportedRuleSetVersion(_)
+			| IndexerRuleError::InvalidRuleSetEntry { .. }
 			| IndexerRuleError::NonUtf8Path(_) => {
 				rspc::Error::with_cause(ErrorCode::BadRequest, err.to_string(), err)
 			}
@@ -73,6 +106,10 @@ impl From<IndexerRuleError> for rspc::Error {
 ///
 /// In case of `RuleKind::AcceptIfChildrenDirectoriesArePresent` or `RuleKind::RejectIfChildrenDirectoriesArePresent` the
 /// `parameters` field must be a vector of strings containing the names of the directories.
+///
+/// The remaining kinds take the formats described in the `metadata` module: extended attributes as
+/// `name` or `name=value`, sizes and modification ages as `min..max` ranges (`1MB..`, `..30d`),
+/// permissions as `uid:1000`, `gid:20` or `mode:002`, and ignore files by their names (`.gitignore`).
 #[derive(Type, Deserialize)]
 pub struct IndexerRuleCreateArgs {
 	pub name: String,
@@ -100,24 +137,7 @@ impl IndexerRuleCreateArgs {
 			&self
 				.rules
 				.into_iter()
-				.map(|(kind, parameters)| match kind {
-					RuleKind::AcceptFilesByGlob => {
-						RulePerKind::new_accept_files_by_globs_str(parameters)
-					}
-					RuleKind::RejectFilesByGlob => {
-						RulePerKind::new_reject_files_by_globs_str(parameters)
-					}
-					RuleKind::AcceptIfChildrenDirectoriesArePresent => {
-						Ok(RulePerKind::AcceptIfChildrenDirectoriesArePresent(
-							parameters.into_iter().collect(),
-						))
-					}
-					RuleKind::RejectIfChildrenDirectoriesArePresent => {
-						Ok(RulePerKind::RejectIfChildrenDirectoriesArePresent(
-							parameters.into_iter().collect(),
-						))
-					}
-				})
+				.map(|(kind, parameters)| RulePerKind::new(kind, parameters))
 				.collect::<Result<Vec<_>, _>>()?,
 		)?;
 
@@ -156,12 +176,61 @@ pub enum RuleKind {
 	RejectFilesByGlob = 1,
 	AcceptIfChildrenDirectoriesArePresent = 2,
 	RejectIfChildrenDirectoriesArePresent = 3,
+	AcceptFilesByXattr = 4,
+	RejectFilesByXattr = 5,
+	AcceptFilesBySize = 6,
+	RejectFilesBySize = 7,
+	AcceptFilesByModifiedAge = 8,
+	RejectFilesByModifiedAge = 9,
+	AcceptFilesByPermissions = 10,
+	RejectFilesByPermissions = 11,
+	RejectFilesByIgnoreFiles = 12,
 }
 
 impl RuleKind {
 	pub const fn variant_count() -> usize {
 		// TODO: Use https://doc.rust-lang.org/std/mem/fn.variant_count.html if it ever gets stabilized
-		4
+		13
+	}
+
+	/// Kinds rejecting a path as soon as one of their rules fails, along with everything in it
+	/// for directories
+	const REJECTING: [Self; 6] = [
+		Self::RejectFilesByGlob,
+		Self::RejectFilesByXattr,
+		Self::RejectFilesBySize,
+		Self::RejectFilesByModifiedAge,
+		Self::RejectFilesByPermissions,
+		Self::RejectFilesByIgnoreFiles,
+	];
+
+	/// Kinds only accepting a path if one of their rules passes, directories not accepted are
+	/// still walked
+	const ACCEPTING: [Self; 5] = [
+		Self::AcceptFilesByGlob,
+		Self::AcceptFilesByXattr,
+		Self::AcceptFilesBySize,
+		Self::AcceptFilesByModifiedAge,
+		Self::AcceptFilesByPermissions,
+	];
+
+	/// Returns the first kind rejecting a path given the results of `IndexerRule::apply_all`
+	pub fn rejected_by(rules_per_kind: &HashMap<Self, Vec<bool>>) -> Option<Self> {
+		Self::REJECTING.into_iter().find(|kind| {
+			rules_per_kind
+				.get(kind)
+				.map_or(false, |results| results.iter().any(|passed| !passed))
+		})
+	}
+
+	/// Returns the first kind that didn't accept a path given the results of
+	/// `IndexerRule::apply_all`
+	pub fn not_accepted_by(rules_per_kind: &HashMap<Self, Vec<bool>>) -> Option<Self> {
+		Self::ACCEPTING.into_iter().find(|kind| {
+			rules_per_kind
+				.get(kind)
+				.map_or(false, |results| results.iter().all(|passed| !passed))
+		})
 	}
 }
 
@@ -172,18 +241,129 @@ impl RuleKind {
 ///
 /// In case of `ParametersPerKind::AcceptIfChildrenDirectoriesArePresent` or `ParametersPerKind::RejectIfChildrenDirectoriesArePresent`
 /// first we change the data structure to a vector, then we serialize it.
+///
+/// The other kinds store their parameters already parsed, see the `metadata` module.
 #[derive(Debug)]
 pub enum RulePerKind {
-	// TODO: Add an indexer rule that filter files based on their extended attributes
-	// https://learn.microsoft.com/en-us/windows/win32/fileio/file-attribute-constants
-	// https://en.wikipedia.org/wiki/Extended_file_attributes
 	AcceptFilesByGlob(Vec<Glob>, GlobSet),
 	RejectFilesByGlob(Vec<Glob>, GlobSet),
 	AcceptIfChildrenDirectoriesArePresent(HashSet<String>),
 	RejectIfChildrenDirectoriesArePresent(HashSet<String>),
+	AcceptFilesByXattr(Vec<XattrMatcher>),
+	RejectFilesByXattr(Vec<XattrMatcher>),
+	AcceptFilesBySize(Vec<SizeRange>),
+	RejectFilesBySize(Vec<SizeRange>),
+	AcceptFilesByModifiedAge(Vec<AgeRange>),
+	RejectFilesByModifiedAge(Vec<AgeRange>),
+	AcceptFilesByPermissions(Vec<PermissionMatcher>),
+	RejectFilesByPermissions(Vec<PermissionMatcher>),
+	RejectFilesByIgnoreFiles(Vec<String>),
 }
 
 impl RulePerKind {
+	pub fn new(kind: RuleKind, parameters: Vec<String>) -> Result<Self, IndexerRuleError> {
+		fn parse_all<T>(
+			kind: RuleKind,
+			parameters: &[String],
+			parse: impl Fn(RuleKind, &str) -> Result<T, IndexerRuleError>,
+		) -> Result<Vec<T>, IndexerRuleError> {
+			parameters
+				.iter()
+				.map(|parameter| parse(kind, parameter))
+				.collect()
+		}
+
+		match kind {
+			RuleKind::AcceptFilesByGlob => Self::new_accept_files_by_globs_str(parameters),
+			RuleKind::RejectFilesByGlob => Self::new_reject_files_by_globs_str(parameters),
+			RuleKind::AcceptIfChildrenDirectoriesArePresent => Ok(
+				Self::AcceptIfChildrenDirectoriesArePresent(parameters.into_iter().collect()),
+			),
+			RuleKind::RejectIfChildrenDirectoriesArePresent => Ok(
+				Self::RejectIfChildrenDirectoriesArePresent(parameters.into_iter().collect()),
+			),
+			RuleKind::AcceptFilesByXattr => {
+				parse_all(kind, &parameters, XattrMatcher::parse).map(Self::AcceptFilesByXattr)
+			}
+			RuleKind::RejectFilesByXattr => {
+				parse_all(kind, &parameters, XattrMatcher::parse).map(Self::RejectFilesByXattr)
+			}
+			RuleKind::AcceptFilesBySize => {
+				parse_all(kind, &parameters, SizeRange::parse).map(Self::AcceptFilesBySize)
+			}
+			RuleKind::RejectFilesBySize => {
+				parse_all(kind, &parameters, SizeRange::parse).map(Self::RejectFilesBySize)
+			}
+			RuleKind::AcceptFilesByModifiedAge => {
+				parse_all(kind, &parameters, AgeRange::parse).map(Self::AcceptFilesByModifiedAge)
+			}
+			RuleKind::RejectFilesByModifiedAge => {
+				parse_all(kind, &parameters, AgeRange::parse).map(Self::RejectFilesByModifiedAge)
+			}
+			RuleKind::AcceptFilesByPermissions => {
+				parse_all(kind, &parameters, PermissionMatcher::parse)
+					.map(Self::AcceptFilesByPermissions)
+			}
+			RuleKind::RejectFilesByPermissions => {
+				parse_all(kind, &parameters, PermissionMatcher::parse)
+					.map(Self::RejectFilesByPermissions)
+			}
+			RuleKind::RejectFilesByIgnoreFiles => {
+				ignore_files::validate_ignore_file_names(&parameters)?;
+				Ok(Self::RejectFilesByIgnoreFiles(parameters))
+			}
+		}
+	}
+
+	/// The inverse of [`RulePerKind::new`], giving back the kind and parameters of this rule
+	pub fn kind_and_parameters(&self) -> (RuleKind, Vec<String>) {
+		fn to_strings<T: ToString>(items: &[T]) -> Vec<String> {
+			items.iter().map(ToString::to_string).collect()
+		}
+
+		fn sorted(names: &HashSet<String>) -> Vec<String> {
+			let mut names = names.iter().cloned().collect::<Vec<_>>();
+			names.sort();
+			names
+		}
+
+		match self {
+			Self::AcceptFilesByGlob(globs, _) => (RuleKind::AcceptFilesByGlob, to_strings(globs)),
+			Self::RejectFilesByGlob(globs, _) => (RuleKind::RejectFilesByGlob, to_strings(globs)),
+			Self::AcceptIfChildrenDirectoriesArePresent(children) => (
+				RuleKind::AcceptIfChildrenDirectoriesArePresent,
+				sorted(children),
+			),
+			Self::RejectIfChildrenDirectoriesArePresent(children) => (
+				RuleKind::RejectIfChildrenDirectoriesArePresent,
+				sorted(children),
+			),
+			Self::AcceptFilesByXattr(matchers) => {
+				(RuleKind::AcceptFilesByXattr, to_strings(matchers))
+			}
+			Self::RejectFilesByXattr(matchers) => {
+				(RuleKind::RejectFilesByXattr, to_strings(matchers))
+			}
+			Self::AcceptFilesBySize(ranges) => (RuleKind::AcceptFilesBySize, to_strings(ranges)),
+			Self::RejectFilesBySize(ranges) => (RuleKind::RejectFilesBySize, to_strings(ranges)),
+			Self::AcceptFilesByModifiedAge(ranges) => {
+				(RuleKind::AcceptFilesByModifiedAge, to_strings(ranges))
+			}
+			Self::RejectFilesByModifiedAge(ranges) => {
+				(RuleKind::RejectFilesByModifiedAge, to_strings(ranges))
+			}
+			Self::AcceptFilesByPermissions(matchers) => {
+				(RuleKind::AcceptFilesByPermissions, to_strings(matchers))
+			}
+			Self::RejectFilesByPermissions(matchers) => {
+				(RuleKind::RejectFilesByPermissions, to_strings(matchers))
+			}
+			Self::RejectFilesByIgnoreFiles(names) => {
+				(RuleKind::RejectFilesByIgnoreFiles, names.clone())
+			}
+		}
+	}
+
 	fn new_files_by_globs_str_and_kind(
 		globs_str: impl IntoIterator<Item = impl AsRef<str>>,
 		kind_fn: impl Fn(Vec<Glob>, GlobSet) -> Self,
@@ -245,6 +425,67 @@ impl Serialize for RulePerKind {
 					"RejectIfChildrenDirectoriesArePresent",
 					children,
 				),
+			RulePerKind::AcceptFilesByXattr(ref parameters) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					4,
+					"AcceptFilesByXattr",
+					parameters,
+				),
+			RulePerKind::RejectFilesByXattr(ref parameters) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					5,
+					"RejectFilesByXattr",
+					parameters,
+				),
+			RulePerKind::AcceptFilesBySize(ref parameters) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				6,
+				"AcceptFilesBySize",
+				parameters,
+			),
+			RulePerKind::RejectFilesBySize(ref parameters) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				7,
+				"RejectFilesBySize",
+				parameters,
+			),
+			RulePerKind::AcceptFilesByModifiedAge(ref parameters) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					8,
+					"AcceptFilesByModifiedAge",
+					parameters,
+				),
+			RulePerKind::RejectFilesByModifiedAge(ref parameters) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					9,
+					"RejectFilesByModifiedAge",
+					parameters,
+				),
+			RulePerKind::AcceptFilesByPermissions(ref parameters) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					10,
+					"AcceptFilesByPermissions",
+					parameters,
+				),
+			RulePerKind::RejectFilesByPermissions(ref parameters) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					11,
+					"RejectFilesByPermissions",
+					parameters,
+				),
+			RulePerKind::RejectFilesByIgnoreFiles(ref parameters) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					12,
+					"RejectFilesByIgnoreFiles",
+					parameters,
+				),
 		}
 	}
 }
@@ -259,6 +500,15 @@ impl<'de> Deserialize<'de> for RulePerKind {
 			"RejectFilesByGlob",
 			"AcceptIfChildrenDirectoriesArePresent",
 			"RejectIfChildrenDirectoriesArePresent",
+			"AcceptFilesByXattr",
+			"RejectFilesByXattr",
+			"AcceptFilesBySize",
+			"RejectFilesBySize",
+			"AcceptFilesByModifiedAge",
+			"RejectFilesByModifiedAge",
+			"AcceptFilesByPermissions",
+			"RejectFilesByPermissions",
+			"RejectFilesByIgnoreFiles",
 		];
 
 		enum Fields {
@@ -266,6 +516,15 @@ impl<'de> Deserialize<'de> for RulePerKind {
 			RejectFilesByGlob,
 			AcceptIfChildrenDirectoriesArePresent,
 			RejectIfChildrenDirectoriesArePresent,
+			AcceptFilesByXattr,
+			RejectFilesByXattr,
+			AcceptFilesBySize,
+			RejectFilesBySize,
+			AcceptFilesByModifiedAge,
+			RejectFilesByModifiedAge,
+			AcceptFilesByPermissions,
+			RejectFilesByPermissions,
+			RejectFilesByIgnoreFiles,
 		}
 
 		struct FieldsVisitor;
@@ -278,7 +537,8 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					"`AcceptFilesByGlob` \
 				or `RejectFilesByGlob` \
 				or `AcceptIfChildrenDirectoriesArePresent` \
-				or `RejectIfChildrenDirectoriesArePresent`",
+				or `RejectIfChildrenDirectoriesArePresent` \
+				or one of the file metadata rule kinds",
 				)
 			}
 
@@ -291,9 +551,18 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					1 => Ok(Fields::RejectFilesByGlob),
 					2 => Ok(Fields::AcceptIfChildrenDirectoriesArePresent),
 					3 => Ok(Fields::RejectIfChildrenDirectoriesArePresent),
+					4 => Ok(Fields::AcceptFilesByXattr),
+					5 => Ok(Fields::RejectFilesByXattr),
+					6 => Ok(Fields::AcceptFilesBySize),
+					7 => Ok(Fields::RejectFilesBySize),
+					8 => Ok(Fields::AcceptFilesByModifiedAge),
+					9 => Ok(Fields::RejectFilesByModifiedAge),
+					10 => Ok(Fields::AcceptFilesByPermissions),
+					11 => Ok(Fields::RejectFilesByPermissions),
+					12 => Ok(Fields::RejectFilesByIgnoreFiles),
 					_ => Err(de::Error::invalid_value(
 						de::Unexpected::Unsigned(value),
-						&"variant index 0 <= i < 3",
+						&"variant index 0 <= i < 13",
 					)),
 				}
 			}
@@ -310,6 +579,15 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					"RejectIfChildrenDirectoriesArePresent" => {
 						Ok(Fields::RejectIfChildrenDirectoriesArePresent)
 					}
+					"AcceptFilesByXattr" => Ok(Fields::AcceptFilesByXattr),
+					"RejectFilesByXattr" => Ok(Fields::RejectFilesByXattr),
+					"AcceptFilesBySize" => Ok(Fields::AcceptFilesBySize),
+					"RejectFilesBySize" => Ok(Fields::RejectFilesBySize),
+					"AcceptFilesByModifiedAge" => Ok(Fields::AcceptFilesByModifiedAge),
+					"RejectFilesByModifiedAge" => Ok(Fields::RejectFilesByModifiedAge),
+					"AcceptFilesByPermissions" => Ok(Fields::AcceptFilesByPermissions),
+					"RejectFilesByPermissions" => Ok(Fields::RejectFilesByPermissions),
+					"RejectFilesByIgnoreFiles" => Ok(Fields::RejectFilesByIgnoreFiles),
 					_ => Err(de::Error::unknown_variant(value, VARIANTS)),
 				}
 			}
@@ -326,6 +604,15 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					b"RejectIfChildrenDirectoriesArePresent" => {
 						Ok(Fields::RejectIfChildrenDirectoriesArePresent)
 					}
+					b"AcceptFilesByXattr" => Ok(Fields::AcceptFilesByXattr),
+					b"RejectFilesByXattr" => Ok(Fields::RejectFilesByXattr),
+					b"AcceptFilesBySize" => Ok(Fields::AcceptFilesBySize),
+					b"RejectFilesBySize" => Ok(Fields::RejectFilesBySize),
+					b"AcceptFilesByModifiedAge" => Ok(Fields::AcceptFilesByModifiedAge),
+					b"RejectFilesByModifiedAge" => Ok(Fields::RejectFilesByModifiedAge),
+					b"AcceptFilesByPermissions" => Ok(Fields::AcceptFilesByPermissions),
+					b"RejectFilesByPermissions" => Ok(Fields::RejectFilesByPermissions),
+					b"RejectFilesByIgnoreFiles" => Ok(Fields::RejectFilesByIgnoreFiles),
 					_ => Err(de::Error::unknown_variant(
 						&String::from_utf8_lossy(bytes),
 						VARIANTS,
@@ -411,6 +698,42 @@ impl<'de> Deserialize<'de> for RulePerKind {
 						reject_if_children_directories_are_present,
 					)
 					.map(Self::Value::RejectIfChildrenDirectoriesArePresent),
+					(Fields::AcceptFilesByXattr, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<XattrMatcher>>(parameters)
+							.map(Self::Value::AcceptFilesByXattr)
+					}
+					(Fields::RejectFilesByXattr, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<XattrMatcher>>(parameters)
+							.map(Self::Value::RejectFilesByXattr)
+					}
+					(Fields::AcceptFilesBySize, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<SizeRange>>(parameters)
+							.map(Self::Value::AcceptFilesBySize)
+					}
+					(Fields::RejectFilesBySize, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<SizeRange>>(parameters)
+							.map(Self::Value::RejectFilesBySize)
+					}
+					(Fields::AcceptFilesByModifiedAge, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<AgeRange>>(parameters)
+							.map(Self::Value::AcceptFilesByModifiedAge)
+					}
+					(Fields::RejectFilesByModifiedAge, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<AgeRange>>(parameters)
+							.map(Self::Value::RejectFilesByModifiedAge)
+					}
+					(Fields::AcceptFilesByPermissions, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<PermissionMatcher>>(parameters)
+							.map(Self::Value::AcceptFilesByPermissions)
+					}
+					(Fields::RejectFilesByPermissions, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<PermissionMatcher>>(parameters)
+							.map(Self::Value::RejectFilesByPermissions)
+					}
+					(Fields::RejectFilesByIgnoreFiles, parameters) => {
+						de::VariantAccess::newtype_variant::<Vec<String>>(parameters)
+							.map(Self::Value::RejectFilesByIgnoreFiles)
+					}
 				})
 			}
 		}
@@ -427,7 +750,14 @@ impl<'de> Deserialize<'de> for RulePerKind {
 }
 
 impl RulePerKind {
-	async fn apply(&self, source: impl AsRef<Path>) -> Result<(RuleKind, bool), IndexerRuleError> {
+	async fn apply(
+		&self,
+		source: impl AsRef<Path>,
+		metadata: &EntryMetadata,
+		ignore_files: &IgnoreFilesCache,
+	) -> Result<(RuleKind, bool), IndexerRuleError> {
+		let source = source.as_ref();
+
 		match self {
 			RulePerKind::AcceptIfChildrenDirectoriesArePresent(children) => {
 				accept_dir_for_its_children(source, children)
@@ -448,6 +778,45 @@ impl RulePerKind {
 				RuleKind::RejectFilesByGlob,
 				reject_by_glob(source, reject_glob_set),
 			)),
+
+			RulePerKind::AcceptFilesByXattr(matchers) => Ok((
+				RuleKind::AcceptFilesByXattr,
+				metadata.any_xattr(source, matchers).await?,
+			)),
+			RulePerKind::RejectFilesByXattr(matchers) => Ok((
+				RuleKind::RejectFilesByXattr,
+				!metadata.any_xattr(source, matchers).await?,
+			)),
+			RulePerKind::AcceptFilesBySize(ranges) => Ok((
+				RuleKind::AcceptFilesBySize,
+				metadata.any_size(source, ranges).await?,
+			)),
+			RulePerKind::RejectFilesBySize(ranges) => Ok((
+				RuleKind::RejectFilesBySize,
+				!metadata.any_size(source, ranges).await?,
+			)),
+			RulePerKind::AcceptFilesByModifiedAge(ranges) => Ok((
+				RuleKind::AcceptFilesByModifiedAge,
+				metadata.any_age(source, ranges).await?,
+			)),
+			RulePerKind::RejectFilesByModifiedAge(ranges) => Ok((
+				RuleKind::RejectFilesByModifiedAge,
+				!metadata.any_age(source, ranges).await?,
+			)),
+			RulePerKind::AcceptFilesByPermissions(matchers) => Ok((
+				RuleKind::AcceptFilesByPermissions,
+				metadata.any_permission(source, matchers).await?,
+			)),
+			RulePerKind::RejectFilesByPermissions(matchers) => Ok((
+				RuleKind::RejectFilesByPermissions,
+				!metadata.any_permission(source, matchers).await?,
+			)),
+			RulePerKind::RejectFilesByIgnoreFiles(names) => Ok((
+				RuleKind::RejectFilesByIgnoreFiles,
+				!ignore_files
+					.is_ignored(names, source, metadata.is_dir(source).await?)
+					.await?,
+			)),
 		}
 	}
 }
@@ -466,25 +835,43 @@ impl IndexerRule {
 	pub async fn apply(
 		&self,
 		source: impl AsRef<Path>,
+		ignore_files: &IgnoreFilesCache,
 	) -> Result<Vec<(RuleKind, bool)>, IndexerRuleError> {
-		try_join_all(self.rules.iter().map(|rule| rule.apply(source.as_ref()))).await
+		let source = source.as_ref();
+		let metadata = EntryMetadata::new();
+
+		try_join_all(
+			self.rules
+				.iter()
+				.map(|rule| rule.apply(source, &metadata, ignore_files)),
+		)
+		.await
 	}
 
 	pub async fn apply_all(
 		rules: &[IndexerRule],
 		source: impl AsRef<Path>,
+		ignore_files: &IgnoreFilesCache,
 	) -> Result<HashMap<RuleKind, Vec<bool>>, IndexerRuleError> {
-		try_join_all(rules.iter().map(|rule| rule.apply(source.as_ref())))
-			.await
-			.map(|results| {
-				results.into_iter().flatten().fold(
-					HashMap::<_, Vec<_>>::with_capacity(RuleKind::variant_count()),
-					|mut map, (kind, result)| {
-						map.entry(kind).or_default().push(result);
-						map
-					},
-				)
-			})
+		let source = source.as_ref();
+		let metadata = EntryMetadata::new();
+
+		try_join_all(
+			rules
+				.iter()
+				.flat_map(|rule| &rule.rules)
+				.map(|rule| rule.apply(source, &metadata, ignore_files)),
+		)
+		.await
+		.map(|results| {
+			results.into_iter().fold(
+				HashMap::<_, Vec<_>>::with_capacity(RuleKind::variant_count()),
+				|mut map, (kind, result)| {
+					map.entry(kind).or_default().push(result);
+					map
+				},
+			)
+		})
 	}
 }
 
@@ -638,7 +1025,7 @@ mod tests {
 
 	async fn check_rule(indexer_rule: &IndexerRule, path: impl AsRef<Path>) -> bool {
 		indexer_rule
-			.apply(path)
+			.apply(path, &IgnoreFilesCache::default())
 			.await
 			.unwrap()
 			.into_iter()
@@ -813,6 +1200,42 @@ mod tests {
 					RulePerKind::RejectIfChildrenDirectoriesArePresent(self_childrens),
 					RulePerKind::RejectIfChildrenDirectoriesArePresent(other_childrens),
 				) => self_childrens == other_childrens,
+				(
+					RulePerKind::AcceptFilesByXattr(self_parameters),
+					RulePerKind::AcceptFilesByXattr(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::RejectFilesByXattr(self_parameters),
+					RulePerKind::RejectFilesByXattr(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::AcceptFilesBySize(self_parameters),
+					RulePerKind::AcceptFilesBySize(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::RejectFilesBySize(self_parameters),
+					RulePerKind::RejectFilesBySize(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::AcceptFilesByModifiedAge(self_parameters),
+					RulePerKind::AcceptFilesByModifiedAge(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::RejectFilesByModifiedAge(self_parameters),
+					RulePerKind::RejectFilesByModifiedAge(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::AcceptFilesByPermissions(self_parameters),
+					RulePerKind::AcceptFilesByPermissions(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::RejectFilesByPermissions(self_parameters),
+					RulePerKind::RejectFilesByPermissions(other_parameters),
+				) => self_parameters == other_parameters,
+				(
+					RulePerKind::RejectFilesByIgnoreFiles(self_parameters),
+					RulePerKind::RejectFilesByIgnoreFiles(other_parameters),
+				) => self_parameters == other_parameters,
 				_ => false,
 			}
 		}
@@ -852,4 +1275,73 @@ mod tests {
 
 		assert_eq!(actual, expected);
 	}
+
+	#[test]
+	fn serde_metadata_rules() {
+		let actual = IndexerRule::new(
+			"Small recent files".to_string(),
+			false,
+			[
+				(RuleKind::AcceptFilesBySize, vec!["..10MB".to_string()]),
+				(RuleKind::RejectFilesByModifiedAge, vec!["1y..".to_string()]),
+				(
+					RuleKind::RejectFilesByXattr,
+					vec!["user.skip=1".to_string()],
+				),
+				(
+					RuleKind::RejectFilesByPermissions,
+					vec!["mode:002".to_string()],
+				),
+				(
+					RuleKind::RejectFilesByIgnoreFiles,
+					vec![".gitignore".to_string()],
+				),
+			]
+			.into_iter()
+			.map(|(kind, parameters)| RulePerKind::new(kind, parameters).unwrap())
+			.collect(),
+		);
+
+		let expected =
+			rmp_serde::from_slice::<IndexerRule>(&rmp_serde::to_vec_named(&actual).unwrap())
+				.unwrap();
+
+		assert_eq!(actual, expected);
+	}
+
+	#[tokio::test]
+	async fn test_metadata_rules() {
+		let root = tempdir().unwrap();
+
+		let small = root.path().join("small.txt");
+		let big = root.path().join("big.bin");
+		let dir = root.path().join("dir");
+
+		fs::write(&small, b"tiny").await.unwrap();
+		fs::write(&big, vec![0; 4096]).await.unwrap();
+		fs::create_dir(&dir).await.unwrap();
+
+		let rules = &[IndexerRule::new(
+			"no big files".to_string(),
+			false,
+			vec![
+				RulePerKind::new(RuleKind::RejectFilesBySize, vec!["1KiB..".to_string()]).unwrap(),
+			],
+		)];
+
+		let rejected_by = |path| async move {
+			RuleKind::rejected_by(
+				&IndexerRule::apply_all(rules, path, &IgnoreFilesCache::default())
+					.await
+					.unwrap(),
+			)
+		};
+
+		assert_eq!(rejected_by(&small).await, None);
+		assert_eq!(rejected_by(&big).await, Some(RuleKind::RejectFilesBySize));
+		// Directories have no meaningful size, so they're never rejected by it
+		assert_eq!(rejected_by(&dir).await, None);
+
+		assert!(RulePerKind::new(RuleKind::AcceptFilesBySize, vec!["big".to_string()]).is_err());
+	}
 }
@@ -0,0 +1,289 @@
+//! Rule sets, a versioned and human readable format to move indexer rules between libraries and
+//! nodes. Each rule is written with the same kinds and parameters `IndexerRuleCreateArgs` takes:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "rules": [
+//!     {
+//!       "name": "No build outputs",
+//!       "rules": [
+//!         { "kind": "RejectFilesByGlob", "parameters": ["**/target", "**/node_modules"] },
+//!         { "kind": "RejectFilesBySize", "parameters": ["1GB.."] }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+
+use crate::library::Library;
+
+use sd_prisma::prisma::{indexer_rule, location};
+
+use std::{collections::HashSet, path::Path};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::{
+	super::{iso_file_path_factory, old_walk::walk, IndexerError},
+	ignore_files::IgnoreFilesCache,
+	IndexerRule, IndexerRuleCreateArgs, IndexerRuleError, RuleKind, RulePerKind,
+};
+
+/// Version written by exports, bumped on breaking changes to the format
+pub const RULE_SET_VERSION: u32 = 1;
+
+/// Maximum number of paths walked by each pass of a dry run
+const DRY_RUN_LIMIT: u64 = 100_000;
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct RuleSet {
+	pub version: u32,
+	pub rules: Vec<RuleSetEntry>,
+}
+
+/// A single indexer rule, named as it will show up in the library importing it
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct RuleSetEntry {
+	pub name: String,
+	pub rules: Vec<RuleSetRule>,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct RuleSetRule {
+	pub kind: RuleKind,
+	#[serde(default)]
+	#[specta(optional)]
+	pub parameters: Vec<String>,
+}
+
+/// How many paths of a location the indexer would index with a rule set and how many it would skip
+#[derive(Serialize, Type, Debug)]
+pub struct RuleSetDryRun {
+	pub accepted: u32,
+	pub rejected: u32,
+	/// The location has more paths than a dry run walks, so counts only cover part of it
+	pub truncated: bool,
+}
+
+impl RuleSet {
+	pub fn export(rules: &[IndexerRule]) -> Self {
+		Self {
+			version: RULE_SET_VERSION,
+			rules: rules
+				.iter()
+				.map(|rule| RuleSetEntry {
+					name: rule.name.clone(),
+					rules: rule
+						.rules
+						.iter()
+						.map(|rule| {
+							let (kind, parameters) = rule.kind_and_parameters();
+							RuleSetRule { kind, parameters }
+						})
+						.collect(),
+				})
+				.collect(),
+		}
+	}
+
+	pub fn from_json(json: &str) -> Result<Self, IndexerRuleError> {
+		let rule_set = serde_json::from_str::<Self>(json)?;
+
+		if rule_set.version == 0 || rule_set.version > RULE_SET_VERSION {
+			return Err(IndexerRuleError::UnsupportedRuleSetVersion(
+				rule_set.version,
+			));
+		}
+
+		Ok(rule_set)
+	}
+
+	pub fn to_json(&self) -> Result<String, IndexerRuleError> {
+		serde_json::to_string_pretty(self).map_err(Into::into)
+	}
+
+	/// Parses every rule of the set, failing on the first invalid one
+	pub fn validate(&self) -> Result<Vec<IndexerRule>, IndexerRuleError> {
+		let now = Utc::now();
+
+		self.rules
+			.iter()
+			.map(|entry| {
+				entry
+					.rules
+					.iter()
+					.map(|rule| RulePerKind::new(rule.kind, rule.parameters.clone()))
+					.collect::<Result<Vec<_>, _>>()
+					.map(|rules| IndexerRule {
+						id: None,
+						name: entry.name.clone(),
+						default: false,
+						rules,
+						date_created: now,
+						date_modified: now,
+					})
+					.map_err(|e| IndexerRuleError::InvalidRuleSetEntry {
+						name: entry.name.clone(),
+						source: Box::new(e),
+					})
+			})
+			.collect()
+	}
+
+	/// Creates the rules of the set in the library once all of them are known to be valid, skipping
+	/// the ones named like an existing rule if `skip_existing` is set
+	pub async fn import(
+		self,
+		library: &Library,
+		skip_existing: bool,
+	) -> Result<Vec<indexer_rule::Data>, IndexerRuleError> {
+		self.validate()?;
+
+		let existing_names = if skip_existing {
+			library
+				.db
+				.indexer_rule()
+				.find_many(vec![indexer_rule::name::in_vec(
+					self.rules.iter().map(|entry| entry.name.clone()).collect(),
+				)])
+				.select(indexer_rule::select!({ name }))
+				.exec()
+				.await?
+				.into_iter()
+				.filter_map(|rule| rule.name)
+				.collect()
+		} else {
+			HashSet::new()
+		};
+
+		let mut created = Vec::with_capacity(self.rules.len());
+
+		for entry in self.rules {
+			if existing_names.contains(&entry.name) {
+				continue;
+			}
+
+			let args = IndexerRuleCreateArgs {
+				name: entry.name,
+				dry_run: false,
+				rules: entry
+					.rules
+					.into_iter()
+					.map(|rule| (rule.kind, rule.parameters))
+					.collect(),
+			};
+
+			if let Some(rule) = args.create(library).await? {
+				created.push(rule);
+			}
+		}
+
+		Ok(created)
+	}
+
+	/// Walks the location once with the rule set and once without any rule, the difference being
+	/// what the rule set rejects. Whether the location respects ignore files applies to both walks.
+	pub async fn dry_run(
+		&self,
+		location_id: location::id::Type,
+		location_path: &Path,
+		respect_ignore_files: bool,
+	) -> Result<RuleSetDryRun, IndexerError> {
+		let rules = self.validate()?;
+
+		let (accepted, accepted_truncated) =
+			count_indexed(location_id, location_path, &rules, respect_ignore_files).await?;
+		let (total, total_truncated) =
+			count_indexed(location_id, location_path, &[], respect_ignore_files).await?;
+
+		Ok(RuleSetDryRun {
+			accepted,
+			rejected: total.saturating_sub(accepted),
+			truncated: accepted_truncated || total_truncated,
+		})
+	}
+}
+
+/// Counts the paths the walker would index, as if none of them were in the database yet
+async fn count_indexed(
+	location_id: location::id::Type,
+	location_path: &Path,
+	rules: &[IndexerRule],
+	respect_ignore_files: bool,
+) -> Result<(u32, bool), IndexerError> {
+	let walk_result = walk(
+		location_path,
+		rules,
+		&IgnoreFilesCache::new(Some(location_path.to_path_buf()), respect_ignore_files),
+		|_, _| {},
+		|_| async { Ok(vec![]) },
+		|_, _| async { Ok(vec![]) },
+		iso_file_path_factory(location_id, location_path),
+		DRY_RUN_LIMIT,
+	)
+	.await?;
+
+	Ok((
+		walk_result.walked.count() as u32,
+		!walk_result.to_walk.is_empty(),
+	))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+	use super::*;
+
+	use super::super::seed::{no_git, no_hidden, no_os_protected};
+
+	#[test]
+	fn export_round_trip() {
+		let rules = [
+			IndexerRule::from(no_os_protected()),
+			IndexerRule::from(no_hidden()),
+			IndexerRule::from(no_git()),
+		];
+
+		let json = RuleSet::export(&rules).to_json().unwrap();
+		let imported = RuleSet::from_json(&json).unwrap().validate().unwrap();
+
+		assert_eq!(imported.len(), rules.len());
+		for (imported, rule) in imported.iter().zip(&rules) {
+			assert_eq!(imported.name, rule.name);
+			assert_eq!(imported.rules, rule.rules);
+		}
+	}
+
+	#[test]
+	fn invalid_rule_sets() {
+		assert!(matches!(
+			RuleSet::from_json(r#"{ "version": 2, "rules": [] }"#),
+			Err(IndexerRuleError::UnsupportedRuleSetVersion(2))
+		));
+		assert!(matches!(
+			RuleSet::from_json(
+				r#"{ "version": 1, "rules": [{ "name": "x", "rules": [{ "kind": "Nope" }] }] }"#
+			),
+			Err(IndexerRuleError::RuleSetFormat(_))
+		));
+
+		let rule_set = RuleSet::from_json(
+			r#"{
+				"version": 1,
+				"rules": [{
+					"name": "Big files",
+					"rules": [{ "kind": "RejectFilesBySize", "parameters": ["2MB..1MB"] }]
+				}]
+			}"#,
+		)
+		.unwrap();
+
+		assert!(matches!(
+			rule_set.validate(),
+			Err(IndexerRuleError::InvalidRuleSetEntry { name, .. }) if name == "Big files"
+		));
+	}
+}
@@ -30,6 +30,12 @@ pub(super) async fn check_online(
 ) -> Result<bool, LocationManagerError> {
 	let pub_id = Uuid::from_slice(&location.pub_id)?;
 
+	// Archived locations are expected to have their files gone, so we never watch them
+	if location.is_archived.unwrap_or(false) {
+		node.locations.remove_online(&pub_id).await;
+		return Ok(false);
+	}
+
 	let location_path = maybe_missing(&location.path, "location.path").map(Path::new)?;
 
 	// TODO(N): This isn't gonna work with removable media and this will likely permanently break if the DB is restored from a backup.
@@ -1,5 +1,6 @@
 use crate::{
 	library::{Library, LibraryManagerEvent},
+	location::indexer::rules::IndexerRuleError,
 	old_job::JobManagerError,
 	Node,
 };
@@ -103,6 +104,8 @@ pub enum LocationManagerError {
 	JobManager(#[from] JobManagerError),
 	#[error("missing-field")]
 	MissingField(#[from] MissingFieldError),
+	#[error("Indexer rules error: (error: {0})")]
+	IndexerRules(#[from] IndexerRuleError),
 
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
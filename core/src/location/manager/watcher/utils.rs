@@ -3,13 +3,18 @@ use crate::{
 	library::Library,
 	location::{
 		create_file_path, delete_directory, find_location,
-		indexer::reverse_update_directories_sizes, location_with_indexer_rules,
-		manager::LocationManagerError, scan_location_sub_path, update_location_size,
+		indexer::{
+			reverse_update_directories_sizes,
+			rules::{ignore_files::IgnoreFilesCache, IndexerRule, RuleKind},
+		},
+		location_with_indexer_rules,
+		manager::LocationManagerError,
+		scan_location_sub_path, update_location_size,
 	},
 	object::{
+		content_index,
 		media::{
-			media_data_extractor::{can_extract_media_data_for_image, extract_media_data},
-			media_data_image_to_query_params,
+			media_data_extractor::extract_media_data_by_extension, media_data_to_query_params,
 			old_thumbnail::get_indexed_thumbnail_path,
 		},
 		old_file_identifier::FileMetadata,
@@ -26,7 +31,10 @@ use sd_file_path_helper::{
 	IsolatedFilePathData, MetadataExt,
 };
 use sd_prisma::{
-	prisma::{file_path, location, media_data, object},
+	prisma::{
+		file_path, indexer_rule, indexer_rules_in_location, location, media_data, object,
+		PrismaClient,
+	},
 	prisma_sync,
 };
 use sd_sync::OperationFactory;
@@ -74,6 +82,76 @@ pub(super) fn check_event(event: &Event, ignore_paths: &HashSet<PathBuf>) -> boo
 	})
 }
 
+/// The watcher doesn't go through the walker, so we check the location's indexer rules here to
+/// avoid adding paths that the next scan would remove. Rules about children directories need the
+/// whole directory to be read, so those are left to the walker, and directories are only checked
+/// against rejecting rules as the walker still walks directories that accepting rules skip.
+async fn accepted_by_indexer_rules(
+	location_id: location::id::Type,
+	path: &Path,
+	is_dir: bool,
+	db: &PrismaClient,
+) -> Result<bool, LocationManagerError> {
+	let rules = db
+		.indexer_rule()
+		.find_many(vec![indexer_rule::locations::some(vec![
+			indexer_rules_in_location::location_id::equals(location_id),
+		])])
+		.exec()
+		.await?
+		.iter()
+		.map(IndexerRule::try_from)
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let location = db
+		.location()
+		.find_unique(location::id::equals(location_id))
+		.select(location::select!({ path respect_ignore_files }))
+		.exec()
+		.await?;
+
+	let ignore_files = IgnoreFilesCache::new(
+		location
+			.as_ref()
+			.and_then(|location| location.path.as_ref())
+			.map(PathBuf::from),
+		location
+			.and_then(|location| location.respect_ignore_files)
+			.unwrap_or(false),
+	);
+
+	let rules_per_kind = IndexerRule::apply_all(&rules, path, &ignore_files).await?;
+
+	let failed_kind = RuleKind::rejected_by(&rules_per_kind).or_else(|| {
+		if is_dir {
+			None
+		} else {
+			RuleKind::not_accepted_by(&rules_per_kind)
+		}
+	});
+
+	if let Some(kind) = failed_kind {
+		trace!(
+			"Watcher ignoring {} because of a `{kind:?}` indexer rule",
+			path.display()
+		);
+		return Ok(false);
+	}
+
+	if ignore_files
+		.is_ignored_by_default_files(path, is_dir)
+		.await?
+	{
+		trace!(
+			"Watcher ignoring {} because of ignore files",
+			path.display()
+		);
+		return Ok(false);
+	}
+
+	Ok(true)
+}
+
 pub(super) async fn create_dir(
 	location_id: location::id::Type,
 	path: impl AsRef<Path>,
@@ -81,14 +159,18 @@ pub(super) async fn create_dir(
 	node: &Arc<Node>,
 	library: &Arc<Library>,
 ) -> Result<(), LocationManagerError> {
+	let path = path.as_ref();
+
+	if !accepted_by_indexer_rules(location_id, path, true, &library.db).await? {
+		return Ok(());
+	}
+
 	let location = find_location(library, location_id)
 		.include(location_with_indexer_rules::include())
 		.exec()
 		.await?
 		.ok_or(LocationManagerError::MissingLocation(location_id))?;
 
-	let path = path.as_ref();
-
 	let location_path = maybe_missing(&location.path, "location.path")?;
 
 	trace!(
@@ -173,6 +255,10 @@ async fn inner_create_file(
 		path.display()
 	);
 
+	if !accepted_by_indexer_rules(location_id, path, false, db).await? {
+		return Ok(());
+	}
+
 	let iso_file_path = IsolatedFilePathData::new(location_id, location_path, path, false)?;
 	let iso_file_path_parts = iso_file_path.to_parts();
 	let extension = iso_file_path_parts.extension.to_string();
@@ -331,42 +417,45 @@ async fn inner_create_file(
 				}
 			});
 		}
+	}
 
-		// TODO: Currently we only extract media data for images, remove this if later
-		if matches!(kind, ObjectKind::Image) {
-			if let Ok(image_extension) = ImageExtension::from_str(&extension) {
-				if can_extract_media_data_for_image(&image_extension) {
-					if let Ok(media_data) = extract_media_data(path)
-						.await
-						.map_err(|e| error!("Failed to extract media data: {e:#?}"))
-					{
-						let (sync_params, db_params) = media_data_image_to_query_params(media_data);
-
-						sync.write_ops(
-							db,
-							(
-								sync.shared_create(
-									prisma_sync::media_data::SyncId {
-										object: prisma_sync::object::SyncId {
-											pub_id: object_pub_id.clone(),
-										},
-									},
-									sync_params,
-								),
-								db.media_data().upsert(
-									media_data::object_id::equals(object_id),
-									media_data::create(
-										object::id::equals(object_id),
-										db_params.clone(),
-									),
-									db_params,
-								),
-							),
-						)
-						.await?;
-					}
-				}
-			}
+	if !extension.is_empty()
+		&& matches!(
+			kind,
+			ObjectKind::Image | ObjectKind::Audio | ObjectKind::Video
+		) {
+		if let Some((sync_params, db_params)) = extract_media_data_by_extension(path, &extension)
+			.await
+			.map_err(|e| error!("Failed to extract media data: {e:#?}"))
+			.ok()
+			.flatten()
+			.map(media_data_to_query_params)
+		{
+			sync.write_ops(
+				db,
+				(
+					sync.shared_create(
+						prisma_sync::media_data::SyncId {
+							object: prisma_sync::object::SyncId {
+								pub_id: object_pub_id.clone(),
+							},
+						},
+						sync_params,
+					),
+					db.media_data().upsert(
+						media_data::object_id::equals(object_id),
+						media_data::create(object::id::equals(object_id), db_params.clone()),
+						db_params,
+					),
+				),
+			)
+			.await?;
+		}
+	}
+
+	if content_index::is_indexable(kind) {
+		if let Err(e) = content_index::index_file(db, object_id, path).await {
+			error!("Failed to index file contents in the watcher: {e:#?}");
 		}
 	}
 
@@ -567,7 +656,7 @@ async fn inner_update_file(
 		if let Some(ref object) = file_path.object {
 			let int_kind = kind as i32;
 
-			if db
+			let object_id = if db
 				.file_path()
 				.count(vec![file_path::object_id::equals(Some(object.id))])
 				.exec()
@@ -590,33 +679,36 @@ async fn inner_update_file(
 					)
 					.await?;
 				}
+
+				object.id
 			} else {
 				let pub_id = uuid_to_bytes(Uuid::new_v4());
 				let date_created: DateTime<FixedOffset> =
 					DateTime::<Local>::from(fs_metadata.created_or_now()).into();
 
-				sync.write_ops(
-					db,
-					(
-						sync.shared_create(
-							prisma_sync::object::SyncId {
-								pub_id: pub_id.clone(),
-							},
-							[
-								(object::date_created::NAME, msgpack!(date_created)),
-								(object::kind::NAME, msgpack!(int_kind)),
-							],
-						),
-						db.object().create(
-							pub_id.to_vec(),
-							vec![
-								object::date_created::set(Some(date_created)),
-								object::kind::set(Some(int_kind)),
-							],
+				let new_object = sync
+					.write_ops(
+						db,
+						(
+							sync.shared_create(
+								prisma_sync::object::SyncId {
+									pub_id: pub_id.clone(),
+								},
+								[
+									(object::date_created::NAME, msgpack!(date_created)),
+									(object::kind::NAME, msgpack!(int_kind)),
+								],
+							),
+							db.object().create(
+								pub_id.to_vec(),
+								vec![
+									object::date_created::set(Some(date_created)),
+									object::kind::set(Some(int_kind)),
+								],
+							),
 						),
-					),
-				)
-				.await?;
+					)
+					.await?;
 
 				sync.write_op(
 					db,
@@ -635,7 +727,9 @@ async fn inner_update_file(
 					),
 				)
 				.await?;
-			}
+
+				new_object.id
+			};
 
 			if let Some(old_cas_id) = &file_path.cas_id {
 				// if this file had a thumbnail previously, we update it to match the new content
@@ -717,6 +811,12 @@ async fn inner_update_file(
 					}
 				}
 			}
+
+			if content_index::is_indexable(kind) {
+				if let Err(e) = content_index::index_file(db, object_id, full_path).await {
+					error!("Failed to index file contents in the watcher: {e:#?}");
+				}
+			}
 		}
 
 		invalidate_query!(library, "search.paths");
@@ -40,6 +40,7 @@ use tokio::{fs, io, time::Instant};
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+pub mod archive;
 mod error;
 pub mod indexer;
 mod manager;
@@ -276,6 +277,7 @@ pub struct LocationUpdateArgs {
 	generate_preview_media: Option<bool>,
 	sync_preview_media: Option<bool>,
 	hidden: Option<bool>,
+	respect_ignore_files: Option<bool>,
 	indexer_rules_ids: Vec<i32>,
 	path: Option<String>,
 }
@@ -290,6 +292,11 @@ impl LocationUpdateArgs {
 			.await?
 			.ok_or(LocationError::IdNotFound(self.id))?;
 
+		// Archived locations can only be moved to a new path by reattaching them
+		if self.path.is_some() && location.is_archived.unwrap_or(false) {
+			return Err(LocationError::Archived(self.id));
+		}
+
 		let name = self.name.clone();
 
 		let (sync_params, db_params): (Vec<_>, Vec<_>) = [
@@ -319,6 +326,12 @@ impl LocationUpdateArgs {
 					location::hidden::set(Some(v)),
 				)
 			}),
+			self.respect_ignore_files.map(|v| {
+				(
+					(location::respect_ignore_files::NAME, msgpack!(v)),
+					location::respect_ignore_files::set(Some(v)),
+				)
+			}),
 			self.path.clone().map(|v| {
 				(
 					(location::path::NAME, msgpack!(v)),
@@ -450,6 +463,11 @@ pub async fn scan_location(
 		return Ok(());
 	}
 
+	// Archived locations keep their index as it was, until they get reattached
+	if location.is_archived.unwrap_or(false) {
+		return Ok(());
+	}
+
 	let location_base_data = location::Data::from(&location);
 
 	JobBuilder::new(OldIndexerJobInit {
@@ -487,6 +505,11 @@ pub async fn scan_location_sub_path(
 		return Ok(());
 	}
 
+	// Archived locations keep their index as it was, until they get reattached
+	if location.is_archived.unwrap_or(false) {
+		return Ok(());
+	}
+
 	let location_base_data = location::Data::from(&location);
 
 	JobBuilder::new(OldIndexerJobInit {
@@ -527,10 +550,14 @@ pub async fn light_scan_location(
 		return Ok(());
 	}
 
+	if location.is_archived.unwrap_or(false) {
+		return Err(LocationError::Archived(location.id).into());
+	}
+
 	let location_base_data = location::Data::from(&location);
 
 	indexer::old_shallow(&location, &sub_path, &node, &library).await?;
-	old_file_identifier::old_shallow(&location_base_data, &sub_path, &library).await?;
+	old_file_identifier::old_shallow(&location_base_data, &sub_path, &node, &library).await?;
 	old_media_processor::old_shallow(
 		&location_base_data,
 		&sub_path,
@@ -881,6 +908,7 @@ impl From<location_with_indexer_rules::Data> for location::Data {
 			generate_preview_media: data.generate_preview_media,
 			sync_preview_media: data.sync_preview_media,
 			hidden: data.hidden,
+			respect_ignore_files: data.respect_ignore_files,
 			date_created: data.date_created,
 			file_paths: None,
 			indexer_rules: None,
@@ -904,6 +932,7 @@ impl From<&location_with_indexer_rules::Data> for location::Data {
 			generate_preview_media: data.generate_preview_media,
 			sync_preview_media: data.sync_preview_media,
 			hidden: data.hidden,
+			respect_ignore_files: data.respect_ignore_files,
 			date_created: data.date_created,
 			file_paths: None,
 			indexer_rules: None,
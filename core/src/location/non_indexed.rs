@@ -3,14 +3,23 @@ use crate::{
 	library::Library,
 	object::{
 		cas::generate_cas_id,
-		media::old_thumbnail::{get_ephemeral_thumb_key, BatchToProcess, GenerateThumbnailArgs},
+		fs::{
+			archive::{
+				extract_archive_entries_to, list_archive_directory, remove_stale_previews,
+				ArchivePath,
+			},
+			error::FileSystemJobsError,
+		},
+		media::old_thumbnail::{
+			get_ephemeral_thumb_key, get_ephemeral_thumbnail_path, BatchToProcess,
+			GenerateThumbnailArgs,
+		},
 	},
 	Node,
 };
 
-use futures::Stream;
 use itertools::Either;
-use sd_file_ext::{extensions::Extension, kind::ObjectKind};
+use sd_file_ext::{extensions::Extension, kind::ObjectKind, magic::ExtensionPossibility};
 use sd_file_path_helper::{path_is_hidden, MetadataExt};
 use sd_prisma::prisma::location;
 use sd_utils::{chain_optional_iter, error::FileIOError};
@@ -20,6 +29,7 @@ use std::{
 	io::ErrorKind,
 	path::{Path, PathBuf},
 	sync::Arc,
+	time::Duration,
 };
 
 use chrono::{DateTime, Utc};
@@ -27,12 +37,17 @@ use rspc::ErrorCode;
 use serde::Serialize;
 use specta::Type;
 use thiserror::Error;
-use tokio::{io, sync::mpsc, task::JoinError};
+use tokio::{
+	fs, io,
+	sync::mpsc,
+	task::{spawn_blocking, JoinError},
+};
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, span, warn, Level};
 
 use super::{
 	indexer::rules::{
+		ignore_files::IgnoreFilesCache,
 		seed::{no_hidden, no_os_protected},
 		IndexerRule, RuleKind,
 	},
@@ -53,6 +68,9 @@ pub enum NonIndexedLocationError {
 	#[error("error joining tokio task: {0}")]
 	TaskJoinError(#[from] JoinError),
 
+	#[error("archive error: {0}")]
+	Archive(#[from] FileSystemJobsError),
+
 	#[error("receiver shutdown error")]
 	SendError,
 }
@@ -66,7 +84,8 @@ impl<T> From<mpsc::error::SendError<T>> for NonIndexedLocationError {
 impl From<NonIndexedLocationError> for rspc::Error {
 	fn from(err: NonIndexedLocationError) -> Self {
 		match err {
-			NonIndexedLocationError::NotFound(_) => {
+			NonIndexedLocationError::NotFound(_)
+			| NonIndexedLocationError::Archive(FileSystemJobsError::FilePathNotFound(_)) => {
 				rspc::Error::with_cause(ErrorCode::NotFound, err.to_string(), err)
 			}
 			_ => rspc::Error::with_cause(ErrorCode::InternalServerError, err.to_string(), err),
@@ -97,17 +116,29 @@ pub struct NonIndexedPathItem {
 	pub hidden: bool,
 }
 
+/// Files inside archives bigger than this don't get thumbnails, as we have to extract them first
+const MAX_ARCHIVE_PREVIEW_SIZE: u64 = 64 * 1024 * 1024;
+/// Files extracted from archives to generate thumbnails are removed after this long
+const ARCHIVE_PREVIEWS_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+const ARCHIVE_PREVIEWS_DIR_NAME: &str = "archive_previews";
+
+type WalkStream =
+	ReceiverStream<Result<ExplorerItem, Either<rspc::Error, NonIndexedLocationError>>>;
+
 // #[instrument(name = "non_indexed::walk", skip(sort_fn))]
 pub async fn walk(
 	path: PathBuf,
 	with_hidden_files: bool,
+	with_ignore_files: bool,
 	node: Arc<Node>,
 	library: Arc<Library>,
 	sort_fn: impl FnOnce(&mut Vec<Entry>) + Send,
-) -> Result<
-	impl Stream<Item = Result<ExplorerItem, Either<rspc::Error, NonIndexedLocationError>>> + Send,
-	NonIndexedLocationError,
-> {
+) -> Result<WalkStream, NonIndexedLocationError> {
+	// Paths going through an archive are browsed as virtual directories
+	if let Some(archive) = ArchivePath::find(&path).await {
+		return walk_archive(archive, with_hidden_files, node, sort_fn).await;
+	}
+
 	let mut entries = get_all_entries(path.clone()).await?;
 
 	{
@@ -127,6 +158,8 @@ pub async fn walk(
 			[IndexerRule::from(no_os_protected())],
 			[(!with_hidden_files).then(|| IndexerRule::from(no_hidden()))],
 		);
+		// There's no location to stop at, so ignore files are read up to the repository root
+		let ignore_files = IgnoreFilesCache::new(None, with_ignore_files);
 
 		let mut thumbnails_to_generate = vec![];
 		// Generating thumbnails for PDFs is kinda slow, so we're leaving them for last in the batch
@@ -145,7 +178,7 @@ pub async fn walk(
 				}
 			};
 
-			match IndexerRule::apply_all(&rules, &entry_path).await {
+			match IndexerRule::apply_all(&rules, &entry_path, &ignore_files).await {
 				Ok(rule_results) => {
 					// No OS Protected and No Hidden rules, must always be from this kind, should panic otherwise
 					if rule_results[&RuleKind::RejectFilesByGlob]
@@ -161,8 +194,20 @@ pub async fn walk(
 				}
 			};
 
-			if entry.metadata.is_dir() {
-				directories.push((entry_path, name, entry.metadata));
+			match ignore_files
+				.is_ignored_by_default_files(Path::new(&entry_path), entry.is_dir)
+				.await
+			{
+				Ok(true) => continue,
+				Ok(false) => {}
+				Err(e) => {
+					tx.send(Err(Either::Left(e.into()))).await?;
+					continue;
+				}
+			}
+
+			if entry.is_dir {
+				directories.push((entry_path, name, entry));
 			} else {
 				let path = Path::new(&entry_path);
 
@@ -201,7 +246,7 @@ pub async fn walk(
 
 				let thumbnail_key = if should_generate_thumbnail {
 					if let Ok(cas_id) =
-						generate_cas_id(&path, entry.metadata.len())
+						generate_cas_id(&path, entry.size_in_bytes)
 							.await
 							.map_err(|e| {
 								tx.send(Err(Either::Left(
@@ -233,15 +278,15 @@ pub async fn walk(
 				tx.send(Ok(ExplorerItem::NonIndexedPath {
 					thumbnail: thumbnail_key,
 					item: NonIndexedPathItem {
-						hidden: path_is_hidden(Path::new(&entry_path), &entry.metadata),
+						hidden: entry.hidden,
 						path: entry_path,
 						name,
 						extension,
 						kind: kind as i32,
 						is_dir: false,
-						date_created: entry.metadata.created_or_now().into(),
-						date_modified: entry.metadata.modified_or_now().into(),
-						size_in_bytes_bytes: entry.metadata.len().to_be_bytes().to_vec(),
+						date_created: entry.date_created,
+						date_modified: entry.date_modified,
+						size_in_bytes_bytes: entry.size_in_bytes.to_be_bytes().to_vec(),
 					},
 				}))
 				.await?;
@@ -278,7 +323,7 @@ pub async fn walk(
 			})
 			.collect::<HashMap<_, _>>();
 
-		for (directory, name, metadata) in directories {
+		for (directory, name, entry) in directories {
 			if let Some(location) = locations.remove(&directory) {
 				tx.send(Ok(ExplorerItem::Location { item: location }))
 					.await?;
@@ -286,15 +331,15 @@ pub async fn walk(
 				tx.send(Ok(ExplorerItem::NonIndexedPath {
 					thumbnail: None,
 					item: NonIndexedPathItem {
-						hidden: path_is_hidden(Path::new(&directory), &metadata),
+						hidden: entry.hidden,
 						path: directory,
 						name,
 						extension: String::new(),
 						kind: ObjectKind::Folder as i32,
 						is_dir: true,
-						date_created: metadata.created_or_now().into(),
-						date_modified: metadata.modified_or_now().into(),
-						size_in_bytes_bytes: metadata.len().to_be_bytes().to_vec(),
+						date_created: entry.date_created,
+						date_modified: entry.date_modified,
+						size_in_bytes_bytes: entry.size_in_bytes.to_be_bytes().to_vec(),
 					},
 				}))
 				.await?;
@@ -317,13 +362,227 @@ pub async fn walk(
 	Ok(ReceiverStream::new(rx))
 }
 
+/// Removes every file extracted from archives to generate thumbnails. The thumbnailer doesn't tell
+/// us when it's done with them, so besides removing the stale ones on every walk, we start each
+/// run of the node from an empty directory.
+pub async fn remove_archive_previews(data_dir: impl AsRef<Path>) -> Result<(), FileIOError> {
+	let previews_dir = data_dir.as_ref().join(ARCHIVE_PREVIEWS_DIR_NAME);
+
+	match fs::remove_dir_all(&previews_dir).await {
+		Ok(()) => Ok(()),
+		Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+		Err(e) => Err(FileIOError::from((previews_dir, e))),
+	}
+}
+
+/// Lists a directory inside an archive, without extracting it. Thumbnails are generated from
+/// copies of the entries extracted to the node's data directory.
+async fn walk_archive(
+	archive: ArchivePath,
+	with_hidden_files: bool,
+	node: Arc<Node>,
+	sort_fn: impl FnOnce(&mut Vec<Entry>) + Send,
+) -> Result<WalkStream, NonIndexedLocationError> {
+	let archive_date_modified: DateTime<Utc> = fs::metadata(&archive.archive_path)
+		.await
+		.map_err(|e| (&archive.archive_path, e))?
+		.modified_or_now()
+		.into();
+
+	let mut entries = spawn_blocking({
+		let archive = archive.clone();
+		move || list_archive_directory(&archive)
+	})
+	.await??
+	.into_iter()
+	.map(|entry| Entry {
+		path: archive.join(&entry.name),
+		hidden: entry.name.starts_with('.'),
+		is_dir: entry.is_dir,
+		size_in_bytes: entry.size_in_bytes,
+		// Archives don't store creation dates
+		date_created: entry.date_modified.unwrap_or(archive_date_modified),
+		date_modified: entry.date_modified.unwrap_or(archive_date_modified),
+		name: entry.name,
+	})
+	.filter(|entry| with_hidden_files || !entry.hidden)
+	.collect::<Vec<_>>();
+
+	{
+		let span = span!(Level::INFO, "sort_fn");
+		let _enter = span.enter();
+
+		sort_fn(&mut entries);
+	}
+
+	let (tx, rx) = mpsc::channel(128);
+
+	tokio::spawn(async move {
+		let previews_dir = node.config.data_directory().join(ARCHIVE_PREVIEWS_DIR_NAME);
+		let mut previews_to_extract = HashMap::new();
+		let mut thumbnails_to_generate = vec![];
+
+		for entry in entries {
+			let Some(entry_path) = entry.path.to_str().map(str::to_string) else {
+				warn!(
+					"Skipping archive entry with non UTF-8 path: {}",
+					entry.path.display()
+				);
+				continue;
+			};
+
+			let path = Path::new(&entry_path);
+
+			let (name, extension, kind) = if entry.is_dir {
+				(entry.name.clone(), String::new(), ObjectKind::Folder)
+			} else {
+				let name = path
+					.file_stem()
+					.and_then(|s| s.to_str().map(str::to_string))
+					.unwrap_or_default();
+
+				let extension = path
+					.extension()
+					.and_then(|s| s.to_str().map(str::to_string))
+					.unwrap_or_default();
+
+				// We can't check the magic bytes without extracting the entry, so we go with
+				// the first candidate on conflicting extensions
+				let kind = Extension::from_str(&extension)
+					.and_then(|possibility| match possibility {
+						ExtensionPossibility::Known(extension) => Some(extension),
+						ExtensionPossibility::Conflicts(extensions) => {
+							extensions.into_iter().next()
+						}
+					})
+					.map(Into::into)
+					.unwrap_or(ObjectKind::Unknown);
+
+				(name, extension, kind)
+			};
+
+			let should_generate_thumbnail = {
+				#[cfg(feature = "ffmpeg")]
+				{
+					matches!(
+						kind,
+						ObjectKind::Image | ObjectKind::Video | ObjectKind::Document
+					)
+				}
+
+				#[cfg(not(feature = "ffmpeg"))]
+				{
+					matches!(kind, ObjectKind::Image | ObjectKind::Document)
+				}
+			};
+
+			let thumbnail_key = (should_generate_thumbnail
+				&& entry.size_in_bytes <= MAX_ARCHIVE_PREVIEW_SIZE)
+				.then(|| {
+					let cas_id = archive_entry_cas_id(&entry, archive_date_modified);
+
+					// No need to extract entries which already have thumbnails
+					if !get_ephemeral_thumbnail_path(&node, &cas_id).exists() {
+						let preview_path = previews_dir.join(&cas_id).with_extension(&extension);
+
+						previews_to_extract.insert(
+							archive
+								.inner_path
+								.join(entry.path.file_name().unwrap_or_default()),
+							preview_path.clone(),
+						);
+						thumbnails_to_generate.push(GenerateThumbnailArgs::new(
+							extension.clone(),
+							cas_id.clone(),
+							preview_path,
+						));
+					}
+
+					get_ephemeral_thumb_key(&cas_id)
+				});
+
+			tx.send(Ok(ExplorerItem::NonIndexedPath {
+				thumbnail: thumbnail_key,
+				item: NonIndexedPathItem {
+					hidden: entry.hidden,
+					path: entry_path,
+					name,
+					extension,
+					kind: kind as i32,
+					is_dir: entry.is_dir,
+					date_created: entry.date_created,
+					date_modified: entry.date_modified,
+					size_in_bytes_bytes: entry.size_in_bytes.to_be_bytes().to_vec(),
+				},
+			}))
+			.await?;
+		}
+
+		if previews_to_extract.is_empty() {
+			return Ok(());
+		}
+
+		let extracted = spawn_blocking({
+			let previews_dir = previews_dir.clone();
+			let archive_path = archive.archive_path.clone();
+			move || {
+				if let Err(e) = remove_stale_previews(&previews_dir, ARCHIVE_PREVIEWS_MAX_AGE) {
+					warn!("Failed to remove stale archive previews: {e:#?}");
+				}
+
+				std::fs::create_dir_all(&previews_dir)
+					.map_err(|e| FileIOError::from((&previews_dir, e)).into())
+					.and_then(|()| {
+						extract_archive_entries_to(
+							&archive_path,
+							archive.format,
+							previews_to_extract,
+							MAX_ARCHIVE_PREVIEW_SIZE,
+						)
+					})
+			}
+		})
+		.await?;
+
+		if let Err(e) = extracted {
+			error!("Failed to extract archive entries to generate thumbnails: {e:#?}");
+		}
+
+		// Entries we failed to extract just fail to generate their thumbnails, like unreadable files
+		node.thumbnailer
+			.new_ephemeral_thumbnails_batch(BatchToProcess::new(
+				thumbnails_to_generate,
+				false,
+				false,
+			))
+			.await;
+
+		Ok::<_, NonIndexedLocationError>(())
+	});
+
+	Ok(ReceiverStream::new(rx))
+}
+
+/// Archive entries can't be sampled like regular files without extracting them, so we identify
+/// them by their location and modification date instead
+fn archive_entry_cas_id(entry: &Entry, archive_date_modified: DateTime<Utc>) -> String {
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(entry.path.as_os_str().as_encoded_bytes());
+	hasher.update(&archive_date_modified.timestamp().to_le_bytes());
+	hasher.update(&entry.size_in_bytes.to_le_bytes());
+
+	hasher.finalize().to_hex()[..16].to_string()
+}
+
 #[derive(Debug)]
 pub struct Entry {
 	path: PathBuf,
 	name: String,
-	// size_in_bytes: u64,
-	// date_created:
-	metadata: std::fs::Metadata,
+	is_dir: bool,
+	hidden: bool,
+	size_in_bytes: u64,
+	date_created: DateTime<Utc>,
+	date_modified: DateTime<Utc>,
 }
 
 impl Entry {
@@ -332,15 +591,15 @@ impl Entry {
 	}
 
 	pub fn size_in_bytes(&self) -> u64 {
-		self.metadata.len()
+		self.size_in_bytes
 	}
 
 	pub fn date_created(&self) -> DateTime<Utc> {
-		self.metadata.created_or_now().into()
+		self.date_created
 	}
 
 	pub fn date_modified(&self) -> DateTime<Utc> {
-		self.metadata.modified_or_now().into()
+		self.date_modified
 	}
 }
 
@@ -360,10 +619,13 @@ pub async fn get_all_entries(path: PathBuf) -> Result<Vec<Entry>, NonIndexedLoca
 		let mut entries = Vec::new();
 		for entry in dir {
 			let entry = entry.map_err(|e| (path, e))?;
+			let entry_path = entry.path();
+			let metadata = entry.metadata().map_err(|e| (path, e))?;
 
 			// We must not keep `entry` around as we will quickly hit the OS limit on open file descriptors
 			entries.push(Entry {
-				path: entry.path(),
+				hidden: path_is_hidden(&entry_path, &metadata),
+				path: entry_path,
 				name: entry
 					.file_name()
 					.to_str()
@@ -374,7 +636,10 @@ pub async fn get_all_entries(path: PathBuf) -> Result<Vec<Entry>, NonIndexedLoca
 						)
 					})?
 					.to_string(),
-				metadata: entry.metadata().map_err(|e| (path, e))?,
+				is_dir: metadata.is_dir(),
+				size_in_bytes: metadata.len(),
+				date_created: metadata.created_or_now().into(),
+				date_modified: metadata.modified_or_now().into(),
 			});
 		}
 
@@ -4,14 +4,16 @@ use crate::{
 	util::version_manager::{Kind, ManagedVersion, VersionManager, VersionManagerError},
 };
 
-use sd_p2p::Identity;
+use sd_p2p::{Identity, RemoteIdentity};
 use sd_utils::error::FileIOError;
 
 use std::{
+	collections::{HashMap, HashSet},
 	path::{Path, PathBuf},
 	sync::Arc,
 };
 
+use chrono::{DateTime, Utc};
 use int_enum::IntEnum;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
@@ -72,6 +74,14 @@ pub struct NodeConfig {
 	pub p2p_ipv6_port: Port,
 	#[serde(default)]
 	pub p2p_discovery: P2PDiscoveryState,
+	/// Peers which paired with this node, the only ones allowed to open tunnels to it
+	#[serde(default)]
+	pub trusted_peers: HashMap<RemoteIdentity, TrustedPeer>,
+	/// Library instances allowed to sync before we knew which node runs them, by their identity.
+	/// They're handed over to that node once it advertises them, see
+	/// [`NodeConfig::claim_sync_instance`].
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub sync_instances: HashMap<RemoteIdentity, Uuid>,
 	/// Feature flags enabled on the node
 	#[serde(default)]
 	pub features: Vec<BackendFeature>,
@@ -88,6 +98,56 @@ pub struct NodeConfig {
 	version: NodeConfigVersion,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct TrustedPeer {
+	pub paired_at: DateTime<Utc>,
+	#[serde(default)]
+	pub permissions: PeerPermissions,
+}
+
+/// What a trusted peer is allowed to do with this node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct PeerPermissions {
+	/// Send files to this node with Spacedrop
+	pub spacedrop: bool,
+	/// Folder its Spacedrops are saved to without asking, if any
+	pub spacedrop_auto_accept: Option<String>,
+	/// Query this node over rspc and stream files from it
+	pub remote_rspc: bool,
+	/// Libraries it can sync with this node
+	pub sync_libraries: HashSet<Uuid>,
+}
+
+impl Default for PeerPermissions {
+	fn default() -> Self {
+		Self {
+			spacedrop: true,
+			spacedrop_auto_accept: None,
+			remote_rspc: true,
+			sync_libraries: HashSet::new(),
+		}
+	}
+}
+
+/// Something a peer can ask this node to do, see [`PeerPermissions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerCapability {
+	Spacedrop,
+	RemoteRspc,
+	Sync(Uuid),
+}
+
+impl PeerPermissions {
+	pub fn allows(&self, capability: PeerCapability) -> bool {
+		match capability {
+			PeerCapability::Spacedrop => self.spacedrop,
+			PeerCapability::RemoteRspc => self.remote_rspc,
+			PeerCapability::Sync(library_id) => self.sync_libraries.contains(&library_id),
+		}
+	}
+}
+
 mod identity_serde {
 	use sd_p2p::Identity;
 	use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -156,6 +216,8 @@ impl ManagedVersion<NodeConfigVersion> for NodeConfig {
 			p2p_ipv4_port: Port::Random,
 			p2p_ipv6_port: Port::Random,
 			p2p_discovery: P2PDiscoveryState::Everyone,
+			trusted_peers: HashMap::new(),
+			sync_instances: HashMap::new(),
 			version: Self::LATEST_VERSION,
 			features: vec![],
 			notifications: vec![],
@@ -168,6 +230,40 @@ impl ManagedVersion<NodeConfigVersion> for NodeConfig {
 }
 
 impl NodeConfig {
+	/// Trusts a peer we just paired with to sync `libraries`, keeping its permissions if it was
+	/// trusted already
+	pub fn trust(&mut self, identity: RemoteIdentity, libraries: impl IntoIterator<Item = Uuid>) {
+		let paired_at = Utc::now();
+		self.trusted_peers
+			.entry(identity)
+			.and_modify(|peer| peer.paired_at = paired_at)
+			.or_insert_with(|| TrustedPeer {
+				paired_at,
+				permissions: PeerPermissions::default(),
+			})
+			.permissions
+			.sync_libraries
+			.extend(libraries);
+	}
+
+	/// Hands `instance` over to the node `identity` advertising it for `library_id`, so that node
+	/// can sync the library if the instance was allowed to. Returns whether it was.
+	pub fn claim_sync_instance(
+		&mut self,
+		identity: RemoteIdentity,
+		library_id: Uuid,
+		instance: &RemoteIdentity,
+	) -> bool {
+		if self.sync_instances.get(instance) != Some(&library_id) {
+			return false;
+		}
+
+		self.sync_instances.remove(instance);
+		self.trust(identity, [library_id]);
+
+		true
+	}
+
 	pub async fn load(path: impl AsRef<Path>) -> Result<Self, NodeConfigError> {
 		let path = path.as_ref();
 		VersionManager::<Self, NodeConfigVersion>::migrate_and_load(
@@ -383,3 +479,63 @@ pub enum NodeConfigError {
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config() -> NodeConfig {
+		NodeConfig::from_latest_version().expect("the latest version has a default")
+	}
+
+	#[test]
+	fn pairing_allows_syncing_shared_libraries() {
+		let mut config = config();
+		let peer = Identity::default().to_remote_identity();
+		let (shared, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+		config.trust(peer, [shared]);
+
+		let permissions = &config.trusted_peers[&peer].permissions;
+		assert!(permissions.allows(PeerCapability::Sync(shared)));
+		assert!(!permissions.allows(PeerCapability::Sync(other)));
+
+		// Pairing again keeps what the user allowed since
+		config
+			.trusted_peers
+			.get_mut(&peer)
+			.unwrap()
+			.permissions
+			.spacedrop = false;
+		config.trust(peer, [other]);
+
+		let permissions = &config.trusted_peers[&peer].permissions;
+		assert!(!permissions.allows(PeerCapability::Spacedrop));
+		assert!(permissions.allows(PeerCapability::Sync(shared)));
+		assert!(permissions.allows(PeerCapability::Sync(other)));
+	}
+
+	#[test]
+	fn instances_are_claimed_by_the_node_advertising_them() {
+		let mut config = config();
+		let node = Identity::default().to_remote_identity();
+		let instance = Identity::default().to_remote_identity();
+		let library_id = Uuid::new_v4();
+
+		config.sync_instances.insert(instance, library_id);
+
+		// Advertising it for another library doesn't count
+		assert!(!config.claim_sync_instance(node, Uuid::new_v4(), &instance));
+		assert!(!config.trusted_peers.contains_key(&node));
+
+		assert!(config.claim_sync_instance(node, library_id, &instance));
+		assert!(config.trusted_peers[&node]
+			.permissions
+			.allows(PeerCapability::Sync(library_id)));
+		assert!(!config.trusted_peers.contains_key(&instance));
+
+		// An instance is only handed over once
+		let other_node = Identity::default().to_remote_identity();
+		assert!(!config.claim_sync_instance(other_node, library_id, &instance));
+	}
+}
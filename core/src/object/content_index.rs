@@ -0,0 +1,397 @@
+//! Full-text index of the contents of text files and documents.
+//!
+//! The index lives in the `content_index` FTS5 virtual table, created by a raw migration as Prisma
+//! doesn't support virtual tables, so it is only accessed through raw queries. Its `rowid` is the
+//! id of the indexed object and a trigger removes the entries of deleted objects.
+
+use sd_file_ext::{kind::ObjectKind, text::is_text};
+use sd_prisma::prisma::{object, PrismaClient};
+use sd_utils::error::FileIOError;
+
+use std::path::{Path, PathBuf};
+
+use prisma_client_rust::{raw, PrismaValue, QueryError};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	fs::File,
+	io::AsyncReadExt,
+	task::{spawn_blocking, JoinError},
+};
+use tracing::{trace, warn};
+
+/// We only index the beginning of big files, to keep the index at a reasonable size
+pub const MAX_INDEXED_BYTES: usize = 1024 * 1024;
+
+/// Ranked matches come with snippets, which are slow to build, so we only take the most relevant
+/// ones. Filtering by contents uses [`matching_objects`] instead, which isn't capped.
+pub const MAX_MATCHES: u32 = 1000;
+
+const SNIPPET_TOKENS: u8 = 16;
+const HIGHLIGHT_START: char = '\u{2}';
+const HIGHLIGHT_END: char = '\u{3}';
+
+#[derive(Error, Debug)]
+pub enum ContentIndexError {
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error("failed to extract text from pdf: {0}")]
+	Pdf(#[from] sd_images::Error),
+	#[error("failed to execute text extraction task: {0}")]
+	Task(#[from] JoinError),
+}
+
+pub const fn is_indexable(kind: ObjectKind) -> bool {
+	matches!(
+		kind,
+		ObjectKind::Text | ObjectKind::Code | ObjectKind::Document | ObjectKind::Config
+	)
+}
+
+/// Reads the text from the beginning of a file, returning `None` if it isn't text
+pub async fn extract_text(path: impl AsRef<Path>) -> Result<Option<String>, ContentIndexError> {
+	let path = path.as_ref();
+
+	if path
+		.extension()
+		.map_or(false, |extension| extension.eq_ignore_ascii_case("pdf"))
+	{
+		let path = path.to_path_buf();
+		return Ok(Some(
+			spawn_blocking(move || sd_images::extract_pdf_text(&path, MAX_INDEXED_BYTES)).await??,
+		));
+	}
+
+	let mut data = Vec::with_capacity(4096);
+	File::open(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?
+		.take(MAX_INDEXED_BYTES as u64)
+		.read_to_end(&mut data)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?;
+
+	Ok(is_text(&data, data.len() == MAX_INDEXED_BYTES).map(|charset| decode_text(&data, charset)))
+}
+
+fn decode_text(data: &[u8], charset: &str) -> String {
+	let text = match charset {
+		"utf-16le" | "utf-16be" => String::from_utf16_lossy(
+			&data
+				.chunks_exact(2)
+				.map(|unit| {
+					if charset == "utf-16le" {
+						u16::from_le_bytes([unit[0], unit[1]])
+					} else {
+						u16::from_be_bytes([unit[0], unit[1]])
+					}
+				})
+				.collect::<Vec<_>>(),
+		),
+		"utf-32le" | "utf-32be" => data
+			.chunks_exact(4)
+			.filter_map(|unit| {
+				char::from_u32(if charset == "utf-32le" {
+					u32::from_le_bytes([unit[0], unit[1], unit[2], unit[3]])
+				} else {
+					u32::from_be_bytes([unit[0], unit[1], unit[2], unit[3]])
+				})
+			})
+			.collect(),
+		"iso-8859-1" => data.iter().map(|&byte| char::from(byte)).collect(),
+		_ => String::from_utf8_lossy(data).into_owned(),
+	};
+
+	text.trim_start_matches('\u{feff}').to_string()
+}
+
+/// Replaces the indexed contents of an object
+pub async fn index_object(
+	db: &PrismaClient,
+	object_id: object::id::Type,
+	text: String,
+) -> Result<(), QueryError> {
+	remove_objects(db, &[object_id]).await?;
+
+	db._execute_raw(raw!(
+		"INSERT INTO content_index (rowid, content) VALUES ({}, {})",
+		PrismaValue::Int(object_id as i64),
+		PrismaValue::String(text)
+	))
+	.exec()
+	.await
+	.map(|_| ())
+}
+
+pub async fn remove_objects(
+	db: &PrismaClient,
+	object_ids: &[object::id::Type],
+) -> Result<(), QueryError> {
+	if object_ids.is_empty() {
+		return Ok(());
+	}
+
+	// We have no data coming from the user, so this is sql injection safe
+	db._execute_raw(raw!(&format!(
+		"DELETE FROM content_index WHERE rowid IN ({})",
+		object_ids
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join(",")
+	)))
+	.exec()
+	.await
+	.map(|_| ())
+}
+
+/// Extracts and indexes the text of a file, replacing the previous contents of its object
+pub async fn index_file(
+	db: &PrismaClient,
+	object_id: object::id::Type,
+	path: impl AsRef<Path>,
+) -> Result<(), ContentIndexError> {
+	let path = path.as_ref();
+
+	if let Some(text) = extract_text(path).await? {
+		trace!("Indexing contents of {}", path.display());
+		index_object(db, object_id, text).await?;
+	} else {
+		remove_objects(db, &[object_id]).await?;
+	}
+
+	Ok(())
+}
+
+/// Indexes the files of objects which aren't in the index yet, returning how many were indexed
+pub async fn index_new_objects(
+	db: &PrismaClient,
+	files: Vec<(object::id::Type, PathBuf)>,
+) -> Result<usize, ContentIndexError> {
+	if files.is_empty() {
+		return Ok(0);
+	}
+
+	#[derive(Deserialize)]
+	struct IndexedObject {
+		object_id: object::id::Type,
+	}
+
+	// We have no data coming from the user, so this is sql injection safe
+	let mut indexed = db
+		._query_raw::<IndexedObject>(raw!(&format!(
+			"SELECT rowid AS object_id FROM content_index WHERE rowid IN ({})",
+			files
+				.iter()
+				.map(|(object_id, _)| object_id.to_string())
+				.collect::<Vec<_>>()
+				.join(",")
+		)))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|indexed| indexed.object_id)
+		.collect::<Vec<_>>();
+
+	let mut total_indexed = 0;
+
+	for (object_id, path) in files {
+		if indexed.contains(&object_id) {
+			continue;
+		}
+
+		// A file we can't read shouldn't stop the others from being indexed
+		match extract_text(&path).await {
+			Ok(Some(text)) => {
+				index_object(db, object_id, text).await?;
+				indexed.push(object_id);
+				total_indexed += 1;
+			}
+			Ok(None) => {}
+			Err(e) => warn!("Failed to extract text to index {}: {e:#?}", path.display()),
+		}
+	}
+
+	Ok(total_indexed)
+}
+
+#[derive(Serialize, Type, Clone, Debug, PartialEq, Eq)]
+pub struct SnippetPart {
+	pub text: String,
+	pub highlighted: bool,
+}
+
+#[derive(Debug)]
+pub struct ContentMatch {
+	pub object_id: object::id::Type,
+	/// Lower is better, as given by SQLite's bm25
+	pub rank: f64,
+	pub snippet: Vec<SnippetPart>,
+}
+
+/// Finds the objects whose contents match the words in `query`, up to `limit` best matches first
+pub async fn search(
+	db: &PrismaClient,
+	query: &str,
+	limit: u32,
+) -> Result<Vec<ContentMatch>, QueryError> {
+	#[derive(Deserialize)]
+	struct RawContentMatch {
+		object_id: object::id::Type,
+		rank: f64,
+		snippet: String,
+	}
+
+	let Some(query) = fts_query(query) else {
+		return Ok(vec![]);
+	};
+
+	Ok(db
+		._query_raw::<RawContentMatch>(raw!(
+			&format!(
+				"SELECT
+					rowid AS object_id,
+					bm25(content_index) AS rank,
+					snippet(content_index, 0, '{HIGHLIGHT_START}', '{HIGHLIGHT_END}', '…', {SNIPPET_TOKENS}) AS snippet
+				FROM content_index
+				WHERE content_index MATCH {{}}
+				ORDER BY rank
+				LIMIT {limit}"
+			),
+			PrismaValue::String(query)
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|raw| ContentMatch {
+			object_id: raw.object_id,
+			rank: raw.rank,
+			snippet: split_snippet(&raw.snippet),
+		})
+		.collect())
+}
+
+/// Ids of every object whose contents match the words in `query`, for filtering other queries
+pub async fn matching_objects(
+	db: &PrismaClient,
+	query: &str,
+) -> Result<Vec<object::id::Type>, QueryError> {
+	#[derive(Deserialize)]
+	struct MatchingObject {
+		object_id: object::id::Type,
+	}
+
+	let Some(query) = fts_query(query) else {
+		return Ok(vec![]);
+	};
+
+	Ok(db
+		._query_raw::<MatchingObject>(raw!(
+			"SELECT rowid AS object_id FROM content_index WHERE content_index MATCH {}",
+			PrismaValue::String(query)
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|matching| matching.object_id)
+		.collect())
+}
+
+/// Turns free text into a FTS5 query matching all its words and quoted phrases, so users don't
+/// need to know the FTS5 syntax and can't break the query with special characters
+fn fts_query(text: &str) -> Option<String> {
+	let mut terms = vec![];
+
+	for (idx, chunk) in text.split('"').enumerate() {
+		// Odd chunks were between quotes
+		if idx % 2 == 1 {
+			if !chunk.trim().is_empty() {
+				terms.push(chunk.trim());
+			}
+		} else {
+			terms.extend(chunk.split_whitespace());
+		}
+	}
+
+	(!terms.is_empty()).then(|| {
+		terms
+			.into_iter()
+			.map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+			.collect::<Vec<_>>()
+			.join(" ")
+	})
+}
+
+fn split_snippet(snippet: &str) -> Vec<SnippetPart> {
+	let mut parts = vec![];
+
+	for (idx, chunk) in snippet.split(HIGHLIGHT_START).enumerate() {
+		let (highlighted, rest) = if idx == 0 {
+			("", chunk)
+		} else {
+			chunk.split_once(HIGHLIGHT_END).unwrap_or((chunk, ""))
+		};
+
+		if !highlighted.is_empty() {
+			parts.push(SnippetPart {
+				text: highlighted.to_string(),
+				highlighted: true,
+			});
+		}
+
+		if !rest.is_empty() {
+			parts.push(SnippetPart {
+				text: rest.to_string(),
+				highlighted: false,
+			});
+		}
+	}
+
+	parts
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn queries() {
+		assert_eq!(fts_query("  "), None);
+		assert_eq!(
+			fts_query(r#"beach "sunset over sea" AND-NOT*"#).as_deref(),
+			Some(r#""beach" "sunset over sea" "AND-NOT*""#)
+		);
+		assert_eq!(
+			fts_query(r#"unclosed "quote"#).as_deref(),
+			Some(r#""unclosed" "quote""#)
+		);
+	}
+
+	#[test]
+	fn snippets() {
+		let part = |text: &str, highlighted| SnippetPart {
+			text: text.to_string(),
+			highlighted,
+		};
+
+		assert_eq!(
+			split_snippet("…a \u{2}beach\u{3} with \u{2}sun\u{3}"),
+			vec![
+				part("…a ", false),
+				part("beach", true),
+				part(" with ", false),
+				part("sun", true),
+			]
+		);
+	}
+
+	#[test]
+	fn decoding() {
+		assert_eq!(decode_text("\u{feff}olá".as_bytes(), "utf-8"), "olá");
+		assert_eq!(decode_text(&[0x6f, 0x6c, 0xe1], "iso-8859-1"), "olá");
+		assert_eq!(decode_text(&[0, 0x6f, 0, 0x6c], "utf-16be"), "ol");
+	}
+}
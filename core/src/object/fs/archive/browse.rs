@@ -0,0 +1,452 @@
+use crate::object::fs::error::FileSystemJobsError;
+
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	ffi::OsStr,
+	fs::{self, File, OpenOptions},
+	io::{self, BufReader, Read},
+	path::{Component, Path, PathBuf},
+	time::Duration,
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use tokio::sync::{mpsc, oneshot};
+use zip::{read::ZipFile, ZipArchive};
+
+use super::{is_enclosed, split_archive_name, tar_reader, ArchiveFormat};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// An archive found in a path, and the path of an entry inside of it
+#[derive(Debug, Clone)]
+pub struct ArchivePath {
+	pub archive_path: PathBuf,
+	pub format: ArchiveFormat,
+	/// Path inside the archive, empty for its root
+	pub inner_path: PathBuf,
+}
+
+impl ArchivePath {
+	/// Finds the archive in a virtual path like `/photos/2023.zip/summer/beach.jpg`, which points to
+	/// an entry inside of it. Paths that don't go through an archive return `None`.
+	pub async fn find(path: impl AsRef<Path>) -> Option<Self> {
+		let path = path.as_ref();
+
+		for ancestor in path.ancestors() {
+			let Some((_, format)) = ancestor
+				.file_name()
+				.and_then(OsStr::to_str)
+				.and_then(split_archive_name)
+			else {
+				continue;
+			};
+
+			if tokio::fs::metadata(ancestor)
+				.await
+				.is_ok_and(|metadata| metadata.is_file())
+			{
+				let inner_path = path
+					.strip_prefix(ancestor)
+					.expect("We got the archive path from the path ancestors")
+					.to_path_buf();
+
+				return is_enclosed(&inner_path).then(|| Self {
+					archive_path: ancestor.to_path_buf(),
+					format,
+					inner_path: normalize_entry_path(&inner_path),
+				});
+			}
+		}
+
+		None
+	}
+
+	/// Virtual path of a child of this path, to be browsed or requested later
+	pub fn join(&self, name: &str) -> PathBuf {
+		self.archive_path.join(&self.inner_path).join(name)
+	}
+}
+
+#[derive(Debug)]
+pub struct ArchiveDirectoryEntry {
+	pub name: String,
+	pub is_dir: bool,
+	pub size_in_bytes: u64,
+	pub date_modified: Option<DateTime<Utc>>,
+}
+
+struct EntryMetadata {
+	path: PathBuf,
+	is_dir: bool,
+	size_in_bytes: u64,
+	date_modified: Option<DateTime<Utc>>,
+}
+
+/// Lists the direct children of a directory inside the archive. Lots of archives don't have
+/// entries for their directories, so we also infer them from the paths of the files inside them.
+pub fn list_archive_directory(
+	archive: &ArchivePath,
+) -> Result<Vec<ArchiveDirectoryEntry>, FileSystemJobsError> {
+	let mut children = BTreeMap::new();
+	let mut found_directory = archive.inner_path.as_os_str().is_empty();
+
+	for entry in read_entries_metadata(archive)? {
+		let Ok(relative_path) = entry.path.strip_prefix(&archive.inner_path) else {
+			continue;
+		};
+
+		let mut components = relative_path.components();
+		let Some(name) = components
+			.next()
+			.and_then(|component| component.as_os_str().to_str())
+		else {
+			// The entry of the directory we're listing
+			found_directory |= entry.is_dir;
+			continue;
+		};
+
+		found_directory = true;
+
+		if components.next().is_none() {
+			children.insert(
+				name.to_string(),
+				ArchiveDirectoryEntry {
+					name: name.to_string(),
+					is_dir: entry.is_dir,
+					size_in_bytes: entry.size_in_bytes,
+					date_modified: entry.date_modified,
+				},
+			);
+		} else {
+			// An implicit directory, unless we find its own entry later
+			children
+				.entry(name.to_string())
+				.or_insert_with(|| ArchiveDirectoryEntry {
+					name: name.to_string(),
+					is_dir: true,
+					size_in_bytes: 0,
+					date_modified: None,
+				});
+		}
+	}
+
+	if found_directory {
+		Ok(children.into_values().collect())
+	} else {
+		Err(FileSystemJobsError::FilePathNotFound(
+			archive
+				.archive_path
+				.join(&archive.inner_path)
+				.into_boxed_path(),
+		))
+	}
+}
+
+/// Reads a single file from the archive, so it can be streamed without extracting the archive.
+/// Its size is sent as soon as we find it, followed by its contents in chunks.
+pub fn read_archive_entry(
+	archive: &ArchivePath,
+	size_tx: oneshot::Sender<u64>,
+	chunks_tx: &mpsc::Sender<io::Result<Bytes>>,
+) -> Result<(), FileSystemJobsError> {
+	let mut size_tx = Some(size_tx);
+
+	visit_entries(archive, |metadata, reader| {
+		if metadata.is_dir || metadata.path != archive.inner_path {
+			return Ok(true);
+		}
+
+		if let Some(size_tx) = size_tx.take() {
+			// The request was dropped if nobody is listening anymore
+			if size_tx.send(metadata.size_in_bytes).is_err() {
+				return Ok(false);
+			}
+		}
+
+		let mut buf = vec![0; CHUNK_SIZE];
+		loop {
+			let chunk = match reader.read(&mut buf) {
+				Ok(0) => return Ok(false),
+				Ok(read) => Ok(Bytes::copy_from_slice(&buf[..read])),
+				Err(e) => Err(e),
+			};
+			let failed = chunk.is_err();
+
+			if chunks_tx.blocking_send(chunk).is_err() || failed {
+				return Ok(false);
+			}
+		}
+	})?;
+
+	if size_tx.is_some() {
+		Err(FileSystemJobsError::FilePathNotFound(
+			archive
+				.archive_path
+				.join(&archive.inner_path)
+				.into_boxed_path(),
+		))
+	} else {
+		Ok(())
+	}
+}
+
+/// Extracts some files from the archive to the given paths, skipping the ones bigger than
+/// `max_size`. Used to generate thumbnails for files inside archives, as the thumbnailer needs
+/// them on disk.
+pub fn extract_archive_entries_to(
+	archive_path: &Path,
+	format: ArchiveFormat,
+	mut targets: HashMap<PathBuf, PathBuf>,
+	max_size: u64,
+) -> Result<(), FileSystemJobsError> {
+	let archive = ArchivePath {
+		archive_path: archive_path.to_path_buf(),
+		format,
+		inner_path: PathBuf::new(),
+	};
+
+	visit_entries(&archive, |metadata, reader| {
+		if metadata.is_dir || metadata.size_in_bytes > max_size {
+			return Ok(true);
+		}
+
+		if let Some(target_path) = targets.remove(&metadata.path) {
+			// Never writing through whatever was left at the target path, like a symlink
+			fs::remove_file(&target_path)
+				.or_else(|e| {
+					if e.kind() == io::ErrorKind::NotFound {
+						Ok(())
+					} else {
+						Err(e)
+					}
+				})
+				.and_then(|()| {
+					OpenOptions::new()
+						.write(true)
+						.create_new(true)
+						.open(&target_path)
+				})
+				.and_then(|mut file| io::copy(reader, &mut file))
+				.map_err(|e| FileIOError::from((target_path, e)))?;
+		}
+
+		Ok(!targets.is_empty())
+	})
+}
+
+fn read_entries_metadata(archive: &ArchivePath) -> Result<Vec<EntryMetadata>, FileSystemJobsError> {
+	let io_error = |e: io::Error| FileIOError::from((&archive.archive_path, e));
+	let zip_error = |e: zip::result::ZipError| FileSystemJobsError::Zip {
+		path: archive.archive_path.clone().into_boxed_path(),
+		error: e,
+	};
+
+	let file = BufReader::new(File::open(&archive.archive_path).map_err(io_error)?);
+
+	match archive.format {
+		ArchiveFormat::Zip => {
+			let mut zip = ZipArchive::new(file).map_err(zip_error)?;
+
+			// Raw entries, so we don't bother with decompression just to list them
+			(0..zip.len())
+				.filter_map(|idx| {
+					zip.by_index_raw(idx)
+						.map(|entry| zip_entry_metadata(&entry))
+						.map_err(zip_error)
+						.transpose()
+				})
+				.collect()
+		}
+
+		ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => {
+			let mut entries_metadata = Vec::new();
+			let mut archive =
+				tar::Archive::new(tar_reader(file, archive.format).map_err(io_error)?);
+
+			for entry in archive.entries().map_err(io_error)? {
+				if let Some(metadata) = tar_entry_metadata(&entry.map_err(io_error)?) {
+					entries_metadata.push(metadata);
+				}
+			}
+
+			Ok(entries_metadata)
+		}
+	}
+}
+
+/// Visits the entries of the archive in order, while the visitor returns `true`
+fn visit_entries(
+	archive: &ArchivePath,
+	mut visitor: impl FnMut(EntryMetadata, &mut dyn Read) -> Result<bool, FileSystemJobsError>,
+) -> Result<(), FileSystemJobsError> {
+	let io_error = |e: io::Error| FileIOError::from((&archive.archive_path, e));
+	let zip_error = |e: zip::result::ZipError| FileSystemJobsError::Zip {
+		path: archive.archive_path.clone().into_boxed_path(),
+		error: e,
+	};
+
+	let file = BufReader::new(File::open(&archive.archive_path).map_err(io_error)?);
+
+	match archive.format {
+		ArchiveFormat::Zip => {
+			let mut zip = ZipArchive::new(file).map_err(zip_error)?;
+
+			for idx in 0..zip.len() {
+				let mut entry = match zip.by_index(idx) {
+					Ok(entry) => entry,
+					// Entries using compression methods we don't support are skipped
+					Err(zip::result::ZipError::UnsupportedArchive(_)) => continue,
+					Err(e) => return Err(zip_error(e)),
+				};
+
+				if let Some(metadata) = zip_entry_metadata(&entry) {
+					if !visitor(metadata, &mut entry)? {
+						break;
+					}
+				}
+			}
+		}
+
+		ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => {
+			let mut archive =
+				tar::Archive::new(tar_reader(file, archive.format).map_err(io_error)?);
+
+			for entry in archive.entries().map_err(io_error)? {
+				let mut entry = entry.map_err(io_error)?;
+
+				if let Some(metadata) = tar_entry_metadata(&entry) {
+					if !visitor(metadata, &mut entry)? {
+						break;
+					}
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Entries with paths escaping the archive are left out
+fn zip_entry_metadata(entry: &ZipFile<'_>) -> Option<EntryMetadata> {
+	let path = entry.enclosed_name().filter(|path| is_enclosed(path))?;
+	let date_modified = entry.last_modified();
+
+	Some(EntryMetadata {
+		path: normalize_entry_path(path),
+		is_dir: entry.is_dir(),
+		size_in_bytes: entry.size(),
+		// Zip archives store dates in local time, without the timezone
+		date_modified: NaiveDate::from_ymd_opt(
+			date_modified.year().into(),
+			date_modified.month().into(),
+			date_modified.day().into(),
+		)
+		.and_then(|date| {
+			date.and_hms_opt(
+				date_modified.hour().into(),
+				date_modified.minute().into(),
+				date_modified.second().into(),
+			)
+		})
+		.map(|date_time| Utc.from_utc_datetime(&date_time)),
+	})
+}
+
+/// Links and entries with paths escaping the archive are left out
+fn tar_entry_metadata<R: Read>(entry: &tar::Entry<'_, R>) -> Option<EntryMetadata> {
+	let entry_type = entry.header().entry_type();
+	if entry_type.is_symlink() || entry_type.is_hard_link() {
+		return None;
+	}
+
+	let path = entry.path().ok().filter(|path| is_enclosed(path))?;
+
+	Some(EntryMetadata {
+		path: normalize_entry_path(&path),
+		is_dir: entry_type.is_dir(),
+		size_in_bytes: entry.size(),
+		// Archives can claim any modification time, the ones we can't represent are left out
+		date_modified: entry
+			.header()
+			.mtime()
+			.ok()
+			.and_then(|mtime| i64::try_from(mtime).ok())
+			.and_then(|mtime| DateTime::<Utc>::from_timestamp(mtime, 0)),
+	})
+}
+
+/// Archives can have paths like `./docs/`, which we compare as `docs`
+fn normalize_entry_path(path: &Path) -> PathBuf {
+	path.components()
+		.filter(|component| matches!(component, Component::Normal(_)))
+		.collect()
+}
+
+/// Removes files left behind by previous calls to [`extract_archive_entries_to`]
+pub fn remove_stale_previews(previews_dir: &Path, max_age: Duration) -> Result<(), FileIOError> {
+	let read_dir = match fs::read_dir(previews_dir) {
+		Ok(read_dir) => read_dir,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(FileIOError::from((previews_dir, e))),
+	};
+
+	for entry in read_dir {
+		let entry = entry.map_err(|e| FileIOError::from((previews_dir, e)))?;
+		let path = entry.path();
+
+		let is_stale = entry
+			.metadata()
+			.and_then(|metadata| metadata.modified())
+			.map_err(|e| FileIOError::from((&path, e)))?
+			.elapsed()
+			.is_ok_and(|age| age > max_age);
+
+		if is_stale {
+			fs::remove_file(&path).map_err(|e| FileIOError::from((&path, e)))?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn entry_paths() {
+		assert_eq!(
+			normalize_entry_path(Path::new("./docs/notes/")),
+			PathBuf::from("docs/notes")
+		);
+		assert_eq!(normalize_entry_path(Path::new("")), PathBuf::new());
+	}
+
+	#[test]
+	fn tar_modification_times() {
+		let mut builder = tar::Builder::new(vec![]);
+		for (name, mtime) in [("old.txt", 1_700_000_000), ("future.txt", u64::MAX >> 1)] {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(0);
+			header.set_mtime(mtime);
+			header.set_cksum();
+			builder.append_data(&mut header, name, io::empty()).unwrap();
+		}
+		let data = builder.into_inner().unwrap();
+
+		let mut archive = tar::Archive::new(data.as_slice());
+		let dates = archive
+			.entries()
+			.unwrap()
+			.map(|entry| tar_entry_metadata(&entry.unwrap()).unwrap().date_modified)
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			dates,
+			[DateTime::<Utc>::from_timestamp(1_700_000_000, 0), None]
+		);
+	}
+}
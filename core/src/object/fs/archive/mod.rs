@@ -0,0 +1,1121 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::{
+		archive::ensure_location_is_not_archived, find_location,
+		get_location_path_from_location_id, light_scan_location, location_with_indexer_rules,
+		scan_location_sub_path, LocationError,
+	},
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunMetadata, JobStepOutput,
+		StatefulJob, WorkerContext,
+	},
+};
+
+use sd_prisma::prisma::{file_path, location};
+use sd_utils::{
+	db::maybe_missing,
+	error::{FileIOError, NonUtf8PathError},
+};
+
+use std::{
+	ffi::OsStr,
+	fs::{File, OpenOptions},
+	hash::Hash,
+	io::{self, BufReader, Read, Seek, SeekFrom, Write},
+	ops::Range,
+	path::{Component, Path, PathBuf},
+};
+
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::{fs, task::spawn_blocking};
+use tracing::{error, trace};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{append_digit_to_filename, error::FileSystemJobsError, get_many_files_datas, FileData};
+
+mod browse;
+
+pub use browse::{
+	extract_archive_entries_to, list_archive_directory, read_archive_entry, remove_stale_previews,
+	ArchiveDirectoryEntry, ArchivePath,
+};
+
+/// Amount of entries written or extracted on each step, which is how often these jobs can be
+/// paused and resumed
+const ENTRIES_PER_STEP: usize = 100;
+
+/// `tar` marks the end of an archive with two empty 512 bytes blocks
+const TAR_END_OF_ARCHIVE_LEN: usize = 1024;
+
+const ARCHIVE_SUFFIXES: [(&str, ArchiveFormat); 6] = [
+	(".zip", ArchiveFormat::Zip),
+	(".tar", ArchiveFormat::Tar),
+	(".tar.gz", ArchiveFormat::TarGz),
+	(".tgz", ArchiveFormat::TarGz),
+	(".tar.zst", ArchiveFormat::TarZst),
+	(".tzst", ArchiveFormat::TarZst),
+];
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum ArchiveFormat {
+	Zip,
+	Tar,
+	TarGz,
+	TarZst,
+}
+
+impl ArchiveFormat {
+	#[must_use]
+	pub const fn extension(&self) -> &'static str {
+		match self {
+			Self::Zip => "zip",
+			Self::Tar => "tar",
+			Self::TarGz => "tar.gz",
+			Self::TarZst => "tar.zst",
+		}
+	}
+}
+
+/// Splits an archive file name into its stem and format, as `tar` archives have a double extension
+fn split_archive_name(name: &str) -> Option<(&str, ArchiveFormat)> {
+	ARCHIVE_SUFFIXES.iter().find_map(|(suffix, format)| {
+		let stem_len = name.len().checked_sub(suffix.len())?;
+		(stem_len > 0
+			&& name.is_char_boundary(stem_len)
+			&& name[stem_len..].eq_ignore_ascii_case(suffix))
+		.then(|| (&name[..stem_len], *format))
+	})
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct OldArchiveCompressorJobInit {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	/// Name of the archive without extension, defaults to the name of the first selected file
+	pub name: Option<String>,
+	pub format: ArchiveFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldArchiveCompressorJobData {
+	archive_path: PathBuf,
+	/// Materialized path of the directory where the archive is created
+	sub_path: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveEntry {
+	source_path: PathBuf,
+	/// Path of the entry inside the archive, always using `/` as separator
+	name: String,
+	is_dir: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldArchiveCompressorJobStep {
+	entries: Vec<ArchiveEntry>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldArchiveCompressorJobRunMetadata {
+	/// Length of the archive after the last completed step, anything written after it belongs to
+	/// an interrupted step and is discarded when resuming
+	archive_len: u64,
+	archived: usize,
+}
+
+impl JobRunMetadata for OldArchiveCompressorJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.archive_len += new_data.archive_len;
+		self.archived += new_data.archived;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldArchiveCompressorJobInit {
+	type Data = OldArchiveCompressorJobData;
+	type Step = OldArchiveCompressorJobStep;
+	type RunMetadata = OldArchiveCompressorJobRunMetadata;
+
+	const NAME: &'static str = "archive_compressor";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		ensure_location_is_not_archived(db, init.location_id).await?;
+
+		let location_path = get_location_path_from_location_id(db, init.location_id)
+			.await
+			.map_err(FileSystemJobsError::from)?;
+
+		let files_datas = get_many_files_datas(db, &location_path, &init.file_path_ids).await?;
+
+		let Some(FileData {
+			file_path: first_file_path,
+			full_path: first_full_path,
+		}) = files_datas.first()
+		else {
+			return Err(JobError::EarlyFinish {
+				name: <Self as StatefulJob>::NAME.to_string(),
+				reason: "No files were selected to be archived".to_string(),
+			});
+		};
+
+		let name = match &init.name {
+			Some(name)
+				if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." =>
+			{
+				return Err(FileSystemJobsError::InvalidArchiveName(name.clone()).into());
+			}
+			Some(name) => name.clone(),
+			None => maybe_missing(&first_file_path.name, "file_path.name")?.clone(),
+		};
+
+		// The archive is created alongside the selected files
+		let parent_path = first_full_path.parent().ok_or_else(|| {
+			FileSystemJobsError::MissingParentPath(first_full_path.clone().into_boxed_path())
+		})?;
+
+		let archive_path =
+			find_available_path(parent_path, &name, Some(init.format.extension())).await?;
+
+		let sub_path = maybe_missing(
+			&first_file_path.materialized_path,
+			"file_path.materialized_path",
+		)?
+		.clone();
+
+		let mut entries = Vec::new();
+		let mut errors = Vec::new();
+
+		for FileData {
+			file_path,
+			full_path,
+		} in files_datas
+		{
+			let Some(name) = full_path.file_name().and_then(OsStr::to_str) else {
+				errors.push(NonUtf8PathError(full_path.into_boxed_path()).to_string());
+				continue;
+			};
+
+			if maybe_missing(file_path.is_dir, "file_path.is_dir")? {
+				let name = name.to_string();
+				collect_directory_entries(
+					full_path,
+					name,
+					&archive_path,
+					&mut entries,
+					&mut errors,
+				)
+				.await?;
+			} else {
+				entries.push(ArchiveEntry {
+					name: name.to_string(),
+					source_path: full_path,
+					is_dir: false,
+				});
+			}
+		}
+
+		*data = Some(OldArchiveCompressorJobData {
+			archive_path,
+			sub_path,
+		});
+
+		Ok((
+			Default::default(),
+			entries
+				.into_iter()
+				.chunks(ENTRIES_PER_STEP)
+				.into_iter()
+				.map(|chunk| OldArchiveCompressorJobStep {
+					entries: chunk.collect(),
+				})
+				.collect(),
+			errors.into(),
+		)
+			.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: OldArchiveCompressorJobStep { entries },
+			..
+		}: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		if let Some(entry) = entries.first() {
+			ctx.progress_msg(format!("Compressing {}", entry.name));
+		}
+
+		let archive_path = data.archive_path.clone();
+		let archive_len = run_metadata.archive_len;
+		let format = init.format;
+		let archived = entries.len();
+
+		let (new_archive_len, errors) = spawn_blocking({
+			let entries = entries.clone();
+			move || append_to_archive(&archive_path, archive_len, format, &entries)
+		})
+		.await??;
+
+		Ok((
+			OldArchiveCompressorJobRunMetadata {
+				archive_len: new_archive_len - archive_len,
+				archived: archived - errors.len(),
+			},
+			errors.into(),
+		)
+			.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		spawn_blocking({
+			let archive_path = data.archive_path.clone();
+			let archive_len = run_metadata.archive_len;
+			let format = init.format;
+			move || finish_archive(&archive_path, archive_len, format)
+		})
+		.await??;
+
+		let location = find_location(&ctx.library, init.location_id)
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(init.location_id))?;
+
+		if let Err(e) = light_scan_location(
+			ctx.node.clone(),
+			ctx.library.clone(),
+			location,
+			&data.sub_path,
+		)
+		.await
+		{
+			error!(
+				"Failed to index archive {}: {e:#?}",
+				data.archive_path.display()
+			);
+		}
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct OldArchiveExtractorJobInit {
+	pub location_id: location::id::Type,
+	pub file_path_id: file_path::id::Type,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldArchiveExtractorJobData {
+	archive_path: PathBuf,
+	format: ArchiveFormat,
+	target_path: PathBuf,
+	/// Path of the target directory relative to the location root, to index it at the end
+	target_sub_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldArchiveExtractorJobStep {
+	entries: Range<usize>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldArchiveExtractorJobRunMetadata {
+	extracted: usize,
+}
+
+impl JobRunMetadata for OldArchiveExtractorJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.extracted += new_data.extracted;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldArchiveExtractorJobInit {
+	type Data = OldArchiveExtractorJobData;
+	type Step = OldArchiveExtractorJobStep;
+	type RunMetadata = OldArchiveExtractorJobRunMetadata;
+
+	const NAME: &'static str = "archive_extractor";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		ensure_location_is_not_archived(db, init.location_id).await?;
+
+		let location_path = get_location_path_from_location_id(db, init.location_id)
+			.await
+			.map_err(FileSystemJobsError::from)?;
+
+		let FileData {
+			full_path: archive_path,
+			..
+		} = get_many_files_datas(db, &location_path, &[init.file_path_id])
+			.await?
+			.pop()
+			.ok_or(FileSystemJobsError::FilePathIdNotFound(init.file_path_id))?;
+
+		let (stem, format) = archive_path
+			.file_name()
+			.and_then(OsStr::to_str)
+			.and_then(split_archive_name)
+			.ok_or_else(|| {
+				FileSystemJobsError::UnsupportedArchive(archive_path.clone().into_boxed_path())
+			})?;
+
+		// Extracting into a new directory named after the archive, so we never overwrite anything
+		let parent_path = archive_path.parent().ok_or_else(|| {
+			FileSystemJobsError::MissingParentPath(archive_path.clone().into_boxed_path())
+		})?;
+
+		let target_path = find_available_path(parent_path, stem, None).await?;
+
+		let entries_count = spawn_blocking({
+			let archive_path = archive_path.clone();
+			move || count_archive_entries(&archive_path, format)
+		})
+		.await??;
+
+		fs::create_dir(&target_path).await.map_err(|e| {
+			FileSystemJobsError::CreateFileOrFolder(FileIOError::from((&target_path, e)))
+		})?;
+
+		let target_sub_path = target_path
+			.strip_prefix(&location_path)
+			.expect(
+				"We got the target path from the archive's directory, so it's inside the location",
+			)
+			.to_path_buf();
+
+		*data = Some(OldArchiveExtractorJobData {
+			archive_path,
+			format,
+			target_path,
+			target_sub_path,
+		});
+
+		let steps = match format {
+			ArchiveFormat::Zip => (0..entries_count)
+				.step_by(ENTRIES_PER_STEP)
+				.map(|start| OldArchiveExtractorJobStep {
+					entries: start..(start + ENTRIES_PER_STEP).min(entries_count),
+				})
+				.collect(),
+
+			// Tar archives can't be randomly accessed, so splitting them in steps would mean
+			// decompressing everything before each step again, we extract them in a single pass
+			ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => {
+				vec![OldArchiveExtractorJobStep {
+					entries: 0..entries_count,
+				}]
+			}
+		};
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: OldArchiveExtractorJobStep { entries },
+			..
+		}: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		ctx.progress_msg(format!(
+			"Extracting {}",
+			data.archive_path
+				.file_name()
+				.unwrap_or(data.archive_path.as_os_str())
+				.to_string_lossy()
+		));
+
+		let (extracted, errors) = spawn_blocking({
+			let archive_path = data.archive_path.clone();
+			let format = data.format;
+			let target_path = data.target_path.clone();
+			let entries = entries.clone();
+			move || extract_entries(&archive_path, format, &target_path, entries)
+		})
+		.await??;
+
+		Ok((
+			OldArchiveExtractorJobRunMetadata { extracted },
+			errors.into(),
+		)
+			.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		let location = find_location(&ctx.library, init.location_id)
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(init.location_id))?;
+
+		if let Err(e) =
+			scan_location_sub_path(&ctx.node, &ctx.library, location, &data.target_sub_path).await
+		{
+			error!(
+				"Failed to index extracted files at {}: {e:#?}",
+				data.target_path.display()
+			);
+		}
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+/// Like [`super::find_available_filename_for_duplicate`], but supporting double extensions
+async fn find_available_path(
+	parent_path: &Path,
+	name: &str,
+	extension: Option<&str>,
+) -> Result<PathBuf, FileSystemJobsError> {
+	let mut candidate = parent_path.join(extension.map_or_else(
+		|| name.to_string(),
+		|extension| format!("{name}.{extension}"),
+	));
+
+	for i in 1..u32::MAX {
+		match fs::metadata(&candidate).await {
+			Ok(_) => {
+				candidate = parent_path.to_path_buf();
+				append_digit_to_filename(&mut candidate, name, extension, i);
+			}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(candidate),
+			Err(e) => return Err(FileIOError::from((candidate, e)).into()),
+		}
+	}
+
+	Err(FileSystemJobsError::FailedToFindAvailableName(
+		candidate.into_boxed_path(),
+	))
+}
+
+async fn collect_directory_entries(
+	dir_path: PathBuf,
+	dir_name: String,
+	archive_path: &Path,
+	entries: &mut Vec<ArchiveEntry>,
+	errors: &mut Vec<String>,
+) -> Result<(), FileSystemJobsError> {
+	let mut to_walk = vec![(dir_path, dir_name)];
+
+	while let Some((dir_path, dir_name)) = to_walk.pop() {
+		entries.push(ArchiveEntry {
+			source_path: dir_path.clone(),
+			name: dir_name.clone(),
+			is_dir: true,
+		});
+
+		let mut read_dir = fs::read_dir(&dir_path)
+			.await
+			.map_err(|e| FileIOError::from((&dir_path, e)))?;
+
+		while let Some(children_entry) = read_dir
+			.next_entry()
+			.await
+			.map_err(|e| FileIOError::from((&dir_path, e)))?
+		{
+			let children_path = children_entry.path();
+
+			if children_path == archive_path {
+				continue;
+			}
+
+			let Some(children_name) = children_entry
+				.file_name()
+				.to_str()
+				.map(|children_name| format!("{dir_name}/{children_name}"))
+			else {
+				errors.push(NonUtf8PathError(children_path.into_boxed_path()).to_string());
+				continue;
+			};
+
+			// Symlinks aren't followed, so we can't end up walking in circles
+			if children_entry
+				.file_type()
+				.await
+				.map_err(|e| FileIOError::from((&children_path, e)))?
+				.is_dir()
+			{
+				to_walk.push((children_path, children_name));
+			} else {
+				entries.push(ArchiveEntry {
+					source_path: children_path,
+					name: children_name,
+					is_dir: false,
+				});
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Appends the entries to the archive, returning its new length and the entries that were skipped
+fn append_to_archive(
+	archive_path: &Path,
+	archive_len: u64,
+	format: ArchiveFormat,
+	entries: &[ArchiveEntry],
+) -> Result<(u64, Vec<String>), FileSystemJobsError> {
+	let io_error = |e: io::Error| FileIOError::from((archive_path, e));
+
+	let archive = open_archive_at(archive_path, archive_len)?;
+
+	let mut errors = Vec::new();
+
+	let mut archive = match format {
+		ArchiveFormat::Zip => {
+			let mut zip = if archive_len == 0 {
+				ZipWriter::new(archive)
+			} else {
+				ZipWriter::new_append(archive).map_err(|e| FileSystemJobsError::Zip {
+					path: archive_path.into(),
+					error: e,
+				})?
+			};
+
+			append_to_zip(&mut zip, entries, &mut errors)
+				.and_then(|()| zip.finish())
+				.map_err(|e| FileSystemJobsError::Zip {
+					path: archive_path.into(),
+					error: e,
+				})?
+		}
+
+		ArchiveFormat::Tar => append_to_tar(archive, entries, &mut errors).map_err(io_error)?,
+
+		ArchiveFormat::TarGz => append_to_tar(
+			GzEncoder::new(archive, Compression::default()),
+			entries,
+			&mut errors,
+		)
+		.and_then(GzEncoder::finish)
+		.map_err(io_error)?,
+
+		ArchiveFormat::TarZst => zstd::stream::write::Encoder::new(archive, 0)
+			.and_then(|encoder| append_to_tar(encoder, entries, &mut errors))
+			.and_then(zstd::stream::write::Encoder::finish)
+			.map_err(io_error)?,
+	};
+
+	let new_archive_len = archive.stream_position().map_err(io_error)?;
+
+	trace!(
+		"Appended {} entries to archive {}",
+		entries.len() - errors.len(),
+		archive_path.display()
+	);
+
+	Ok((new_archive_len, errors))
+}
+
+fn append_to_zip(
+	zip: &mut ZipWriter<File>,
+	entries: &[ArchiveEntry],
+	errors: &mut Vec<String>,
+) -> zip::result::ZipResult<()> {
+	let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+	for entry in entries {
+		if entry.is_dir {
+			zip.add_directory(entry.name.as_str(), options)?;
+			continue;
+		}
+
+		// Files that vanished since the job started are skipped instead of failing the whole job
+		let (mut file, len) = match File::open(&entry.source_path)
+			.and_then(|file| file.metadata().map(|metadata| (file, metadata.len())))
+		{
+			Ok(file_and_len) => file_and_len,
+			Err(e) => {
+				errors.push(FileIOError::from((&entry.source_path, e)).to_string());
+				continue;
+			}
+		};
+
+		zip.start_file(
+			entry.name.as_str(),
+			options.large_file(len >= u64::from(u32::MAX)),
+		)?;
+
+		io::copy(&mut file, zip)?;
+	}
+
+	Ok(())
+}
+
+fn append_to_tar<W: Write>(
+	writer: W,
+	entries: &[ArchiveEntry],
+	errors: &mut Vec<String>,
+) -> io::Result<W> {
+	let mut builder = tar::Builder::new(WithoutEndOfArchive::new(writer));
+
+	// Storing symlinks as they are instead of the files they point to
+	builder.follow_symlinks(false);
+
+	for entry in entries {
+		// Files that vanished since the job started are skipped instead of failing the whole job
+		if let Err(e) = std::fs::symlink_metadata(&entry.source_path) {
+			errors.push(FileIOError::from((&entry.source_path, e)).to_string());
+			continue;
+		}
+
+		// Directories are appended without their contents, which are entries of their own
+		builder.append_path_with_name(&entry.source_path, &entry.name)?;
+	}
+
+	builder.into_inner().map(WithoutEndOfArchive::into_inner)
+}
+
+/// Writes what a complete archive needs at its end, which we don't write on every step
+fn finish_archive(
+	archive_path: &Path,
+	archive_len: u64,
+	format: ArchiveFormat,
+) -> Result<(), FileSystemJobsError> {
+	let io_error = |e: io::Error| FileIOError::from((archive_path, e));
+
+	let mut archive = open_archive_at(archive_path, archive_len)?;
+
+	match format {
+		// Zip archives are complete after every step, we only have to handle empty selections
+		ArchiveFormat::Zip if archive_len == 0 => {
+			ZipWriter::new(archive)
+				.finish()
+				.map_err(|e| FileSystemJobsError::Zip {
+					path: archive_path.into(),
+					error: e,
+				})?;
+		}
+		ArchiveFormat::Zip => {}
+
+		ArchiveFormat::Tar => {
+			archive
+				.write_all(&[0; TAR_END_OF_ARCHIVE_LEN])
+				.map_err(io_error)?;
+		}
+
+		ArchiveFormat::TarGz => {
+			let mut encoder = GzEncoder::new(archive, Compression::default());
+			encoder
+				.write_all(&[0; TAR_END_OF_ARCHIVE_LEN])
+				.and_then(|()| encoder.finish())
+				.map_err(io_error)?;
+		}
+
+		ArchiveFormat::TarZst => {
+			zstd::stream::write::Encoder::new(archive, 0)
+				.and_then(|mut encoder| {
+					encoder.write_all(&[0; TAR_END_OF_ARCHIVE_LEN])?;
+					encoder.finish()
+				})
+				.map_err(io_error)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Opens the archive positioned at `archive_len`, discarding anything written after it by an
+/// interrupted step, so the step can be safely retried when the job is resumed
+fn open_archive_at(archive_path: &Path, archive_len: u64) -> Result<File, FileIOError> {
+	let io_error = |e: io::Error| FileIOError::from((archive_path, e));
+
+	let mut archive = OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		.truncate(false)
+		.open(archive_path)
+		.map_err(io_error)?;
+
+	archive.set_len(archive_len).map_err(io_error)?;
+	archive.seek(SeekFrom::End(0)).map_err(io_error)?;
+
+	Ok(archive)
+}
+
+/// `tar::Builder` always writes the end of archive marker when it's done, but we append to the
+/// same archive on every step. So we hold back the last bytes written, dropping the marker, and
+/// write it only once when finalizing the job.
+///
+/// Each step is compressed as its own gzip member or zstd frame, and concatenated ones are
+/// decompressed as a single stream by any tool.
+struct WithoutEndOfArchive<W> {
+	inner: W,
+	held_back: Vec<u8>,
+}
+
+impl<W: Write> WithoutEndOfArchive<W> {
+	fn new(inner: W) -> Self {
+		Self {
+			inner,
+			held_back: Vec::with_capacity(TAR_END_OF_ARCHIVE_LEN * 2),
+		}
+	}
+
+	fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<W: Write> Write for WithoutEndOfArchive<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.held_back.extend_from_slice(buf);
+
+		if self.held_back.len() > TAR_END_OF_ARCHIVE_LEN {
+			let to_write = self.held_back.len() - TAR_END_OF_ARCHIVE_LEN;
+			self.inner.write_all(&self.held_back[..to_write])?;
+			self.held_back.drain(..to_write);
+		}
+
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+fn count_archive_entries(
+	archive_path: &Path,
+	format: ArchiveFormat,
+) -> Result<usize, FileSystemJobsError> {
+	let io_error = |e: io::Error| FileIOError::from((archive_path, e));
+
+	let file = BufReader::new(File::open(archive_path).map_err(io_error)?);
+
+	match format {
+		ArchiveFormat::Zip => {
+			ZipArchive::new(file)
+				.map(|zip| zip.len())
+				.map_err(|e| FileSystemJobsError::Zip {
+					path: archive_path.into(),
+					error: e,
+				})
+		}
+
+		ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => {
+			tar_reader(file, format)
+				.and_then(count_tar_entries)
+				.map_err(io_error)
+				.map_err(Into::into)
+		}
+	}
+}
+
+/// Decompressed stream of a `tar` archive, zip archives must be read with [`ZipArchive`] instead
+fn tar_reader(file: BufReader<File>, format: ArchiveFormat) -> io::Result<Box<dyn Read>> {
+	match format {
+		ArchiveFormat::Tar => Ok(Box::new(file)),
+		// Multi member decoder, as we write every step of the compressor job as its own member
+		ArchiveFormat::TarGz => Ok(Box::new(MultiGzDecoder::new(file))),
+		ArchiveFormat::TarZst => Ok(Box::new(zstd::stream::read::Decoder::with_buffer(file)?)),
+		ArchiveFormat::Zip => Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"zip archives aren't tar streams",
+		)),
+	}
+}
+
+fn count_tar_entries(reader: impl Read) -> io::Result<usize> {
+	tar::Archive::new(reader)
+		.entries()?
+		.try_fold(0, |count, entry| entry.map(|_| count + 1))
+}
+
+/// Extracts the entries in the given range, returning how many were extracted and the ones skipped.
+/// Tar archives are always extracted entirely, as they're only ever read in a single step.
+fn extract_entries(
+	archive_path: &Path,
+	format: ArchiveFormat,
+	target_path: &Path,
+	entries: Range<usize>,
+) -> Result<(usize, Vec<String>), FileSystemJobsError> {
+	let io_error = |e: io::Error| FileIOError::from((archive_path, e));
+
+	let file = BufReader::new(File::open(archive_path).map_err(io_error)?);
+
+	let mut errors = Vec::new();
+
+	let extracted = match format {
+		ArchiveFormat::Zip => {
+			extract_zip_entries(file, archive_path, target_path, entries, &mut errors)?
+		}
+
+		ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => extract_tar_entries(
+			tar_reader(file, format).map_err(io_error)?,
+			target_path,
+			&mut errors,
+		)?,
+	};
+
+	Ok((extracted, errors))
+}
+
+fn extract_zip_entries(
+	file: BufReader<File>,
+	archive_path: &Path,
+	target_path: &Path,
+	entries: Range<usize>,
+	errors: &mut Vec<String>,
+) -> Result<usize, FileSystemJobsError> {
+	let zip_error = |e: zip::result::ZipError| FileSystemJobsError::Zip {
+		path: archive_path.into(),
+		error: e,
+	};
+
+	let mut zip = ZipArchive::new(file).map_err(zip_error)?;
+	let mut extracted = 0;
+
+	for idx in entries {
+		let mut entry = match zip.by_index(idx) {
+			Ok(entry) => entry,
+			// Entries using compression methods we don't support are skipped
+			Err(e @ zip::result::ZipError::UnsupportedArchive(_)) => {
+				errors.push(zip_error(e).to_string());
+				continue;
+			}
+			Err(e) => return Err(zip_error(e)),
+		};
+
+		// `enclosed_name` refuses absolute paths and paths that would escape the target directory
+		let Some(entry_path) = entry
+			.enclosed_name()
+			.filter(|entry_path| is_enclosed(entry_path))
+			.map(|entry_path| target_path.join(entry_path))
+		else {
+			errors.push(
+				FileSystemJobsError::UnsafeArchiveEntry(Path::new(entry.name()).into()).to_string(),
+			);
+			continue;
+		};
+
+		if entry.is_dir() {
+			std::fs::create_dir_all(&entry_path)
+				.map_err(|e| FileIOError::from((&entry_path, e)))?;
+		} else {
+			if let Some(parent_path) = entry_path.parent() {
+				std::fs::create_dir_all(parent_path)
+					.map_err(|e| FileIOError::from((parent_path, e)))?;
+			}
+
+			File::create(&entry_path)
+				.and_then(|mut file| io::copy(&mut entry, &mut file))
+				.map_err(|e| FileIOError::from((&entry_path, e)))?;
+		}
+
+		extracted += 1;
+	}
+
+	Ok(extracted)
+}
+
+fn extract_tar_entries(
+	reader: impl Read,
+	target_path: &Path,
+	errors: &mut Vec<String>,
+) -> Result<usize, FileSystemJobsError> {
+	let io_error = |e: io::Error| FileIOError::from((target_path, e));
+
+	let mut archive = tar::Archive::new(reader);
+	let mut extracted = 0;
+
+	for entry in archive.entries().map_err(io_error)? {
+		let mut entry = entry.map_err(io_error)?;
+		let entry_path = entry.path().map_err(io_error)?.into_owned();
+		let entry_type = entry.header().entry_type();
+
+		// Links are skipped, as they could point outside of the target directory
+		if !is_enclosed(&entry_path) || entry_type.is_symlink() || entry_type.is_hard_link() {
+			errors.push(FileSystemJobsError::UnsafeArchiveEntry(entry_path.into()).to_string());
+			continue;
+		}
+
+		// `unpack_in` also refuses to write anything outside of the target directory
+		if entry
+			.unpack_in(target_path)
+			.map_err(|e| FileIOError::from((target_path.join(&entry_path), e)))?
+		{
+			extracted += 1;
+		} else {
+			errors.push(FileSystemJobsError::UnsafeArchiveEntry(entry_path.into()).to_string());
+		}
+	}
+
+	Ok(extracted)
+}
+
+/// Only relative paths without `..` components can be safely joined to the target directory
+fn is_enclosed(path: &Path) -> bool {
+	path.components()
+		.all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn archive_names() {
+		assert_eq!(
+			split_archive_name("photos.zip"),
+			Some(("photos", ArchiveFormat::Zip))
+		);
+		assert_eq!(
+			split_archive_name("backup.2024.TAR.GZ"),
+			Some(("backup.2024", ArchiveFormat::TarGz))
+		);
+		assert_eq!(
+			split_archive_name("src.tzst"),
+			Some(("src", ArchiveFormat::TarZst))
+		);
+		assert_eq!(
+			split_archive_name("docs.tar"),
+			Some(("docs", ArchiveFormat::Tar))
+		);
+		assert_eq!(split_archive_name(".zip"), None);
+		assert_eq!(split_archive_name("notes.txt"), None);
+	}
+
+	#[test]
+	fn tar_round_trip() {
+		let source_dir = tempfile::tempdir().unwrap();
+		std::fs::create_dir(source_dir.path().join("docs")).unwrap();
+		std::fs::write(source_dir.path().join("docs/a.txt"), "first").unwrap();
+		std::fs::write(source_dir.path().join("b.txt"), "second").unwrap();
+
+		let entry = |name: &str, is_dir| ArchiveEntry {
+			source_path: source_dir.path().join(name),
+			name: name.to_string(),
+			is_dir,
+		};
+
+		for format in [
+			ArchiveFormat::Tar,
+			ArchiveFormat::TarGz,
+			ArchiveFormat::TarZst,
+		] {
+			let archive_dir = tempfile::tempdir().unwrap();
+			let archive_path = archive_dir
+				.path()
+				.join(format!("test.{}", format.extension()));
+
+			// Each step is appended on its own, just like the compressor job does, and the
+			// garbage left by an interrupted step must be discarded when resuming
+			let (archive_len, errors) = append_to_archive(
+				&archive_path,
+				0,
+				format,
+				&[entry("docs", true), entry("docs/a.txt", false)],
+			)
+			.unwrap();
+			assert!(errors.is_empty());
+
+			OpenOptions::new()
+				.append(true)
+				.open(&archive_path)
+				.and_then(|mut file| file.write_all(b"interrupted step"))
+				.unwrap();
+
+			let (archive_len, errors) = append_to_archive(
+				&archive_path,
+				archive_len,
+				format,
+				&[entry("b.txt", false), entry("missing.txt", false)],
+			)
+			.unwrap();
+			assert_eq!(errors.len(), 1);
+
+			finish_archive(&archive_path, archive_len, format).unwrap();
+
+			assert_eq!(count_archive_entries(&archive_path, format).unwrap(), 3);
+
+			let target_dir = tempfile::tempdir().unwrap();
+			let (extracted, errors) =
+				extract_entries(&archive_path, format, target_dir.path(), 0..3).unwrap();
+			assert_eq!(extracted, 3);
+			assert!(errors.is_empty());
+
+			assert_eq!(
+				std::fs::read_to_string(target_dir.path().join("docs/a.txt")).unwrap(),
+				"first"
+			);
+			assert_eq!(
+				std::fs::read_to_string(target_dir.path().join("b.txt")).unwrap(),
+				"second"
+			);
+		}
+	}
+
+	#[test]
+	fn enclosed_paths() {
+		assert!(is_enclosed(Path::new("a/b/c.txt")));
+		assert!(is_enclosed(Path::new("./a")));
+		assert!(!is_enclosed(Path::new("../a")));
+		assert!(!is_enclosed(Path::new("a/../../b")));
+		assert!(!is_enclosed(Path::new("/etc/passwd")));
+	}
+}
@@ -0,0 +1,363 @@
+use crate::{
+	api::CoreEvent,
+	invalidate_query,
+	library::Library,
+	location::{
+		archive::ensure_location_is_not_archived, find_location,
+		get_location_path_from_location_id, light_scan_location, location_with_indexer_rules,
+		LocationError,
+	},
+	old_job::JobProgressEvent,
+	Node,
+};
+
+use sd_images::{ConvertibleExtension, DynamicImage};
+use sd_prisma::prisma::{file_path, location};
+use sd_task_system::{
+	ExecStatus, Interrupter, InterruptionKind, IntoAnyTaskOutput, Task, TaskId, TaskOutput,
+	TaskStatus,
+};
+use sd_utils::{db::maybe_missing, error::FileIOError};
+
+use std::{
+	collections::{HashSet, VecDeque},
+	ffi::OsString,
+	mem,
+	sync::Arc,
+};
+
+use chrono::Utc;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{fs, io, spawn, task::spawn_blocking};
+use tracing::{error, info, trace, warn};
+
+use super::{
+	error::FileSystemJobsError, find_available_filename_for_duplicate, get_many_files_datas,
+	FileData,
+};
+
+/// Quality used for lossy formats when none is requested
+const DEFAULT_QUALITY_PERCENTAGE: u8 = 80;
+
+/// What to do when there is already a file with the name of the converted image
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum ConversionConflictPolicy {
+	/// Saves the converted image next to the existing file, adding a ` (n)` suffix to its name
+	Sibling,
+	/// Replaces the existing file with the converted image
+	Overwrite,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum ImageResize {
+	/// Scales both dimensions by this percentage, from 1% to 125%
+	Percentage(u32),
+	/// Downscales the image so its largest dimension is at most this many pixels
+	MaxDimension(u32),
+}
+
+#[derive(Serialize, Deserialize, Type, Debug)]
+pub struct ImageConverterArgs {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	pub desired_extension: ConvertibleExtension,
+	/// Only used by lossy formats, from 1% to 100%
+	pub quality_percentage: Option<u8>,
+	pub resize: Option<ImageResize>,
+	pub conflict_policy: ConversionConflictPolicy,
+	/// If set, we delete the source images after converting them
+	pub delete_src: bool,
+}
+
+/// What an [`ImageConverterTask`] did once it's done
+#[derive(Debug, Default)]
+pub struct ImageConverterOutput {
+	pub converted: usize,
+	/// Images that couldn't be converted, which don't stop the others from being converted
+	pub errors: Vec<String>,
+	/// Materialized paths of the directories where we wrote converted images, to be indexed
+	sub_paths_to_index: HashSet<String>,
+}
+
+/// Converts a batch of images on the task system. Images are converted one at a time, checking
+/// for pauses and cancellations between them, so a paused task resumes from the first image it
+/// hadn't converted yet.
+#[derive(Debug)]
+pub struct ImageConverterTask {
+	id: TaskId,
+	node: Arc<Node>,
+	library: Arc<Library>,
+	args: ImageConverterArgs,
+	pending: VecDeque<FileData>,
+	total: usize,
+	output: ImageConverterOutput,
+}
+
+impl ImageConverterTask {
+	pub async fn new(
+		node: Arc<Node>,
+		library: Arc<Library>,
+		args: ImageConverterArgs,
+	) -> Result<Self, FileSystemJobsError> {
+		let Library { db, .. } = &*library;
+
+		ensure_location_is_not_archived(db, args.location_id).await?;
+
+		let location_path = get_location_path_from_location_id(db, args.location_id).await?;
+
+		let (pending, directories): (VecDeque<_>, Vec<_>) =
+			get_many_files_datas(db, &location_path, &args.file_path_ids)
+				.await?
+				.into_iter()
+				.partition(|file_data| file_data.file_path.is_dir != Some(true));
+
+		let errors = directories
+			.into_iter()
+			.map(|file_data| {
+				format!(
+					"Skipping directory, only images can be converted: {}",
+					file_data.full_path.display()
+				)
+			})
+			.collect();
+
+		Ok(Self {
+			id: TaskId::new_v4(),
+			total: pending.len(),
+			pending,
+			output: ImageConverterOutput {
+				errors,
+				..Default::default()
+			},
+			node,
+			library,
+			args,
+		})
+	}
+
+	/// Dispatches the conversion to the node's task system, reporting how it ended once it does
+	pub async fn dispatch(self) -> TaskId {
+		let (id, node) = (self.id, Arc::clone(&self.node));
+		let handle = node.task_system.dispatch(self).await;
+
+		spawn(async move {
+			match handle.await {
+				Ok(TaskStatus::Done(TaskOutput::Out(output))) => {
+					if let Ok(output) = output.downcast::<ImageConverterOutput>() {
+						info!(
+							"Image conversion <id='{id}'> converted {} images",
+							output.converted
+						);
+						for e in &output.errors {
+							warn!("Image conversion <id='{id}'> error: {e}");
+						}
+					}
+				}
+				Ok(TaskStatus::Done(TaskOutput::Empty)) => {}
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					info!("Image conversion <id='{id}'> was cancelled");
+				}
+				Ok(TaskStatus::Shutdown(task)) => {
+					if let Ok(task) = task.downcast::<Self>() {
+						warn!(
+							"Image conversion <id='{id}'> stopped by shutdown, {} images left",
+							task.pending.len()
+						);
+					}
+				}
+				Ok(TaskStatus::Error(e)) => error!("Image conversion <id='{id}'> failed: {e:#?}"),
+				Err(e) => error!("Task system error on image conversion <id='{id}'>: {e:#?}"),
+			}
+		});
+
+		id
+	}
+
+	fn report_progress(&self, FileData { full_path, .. }: &FileData) {
+		self.node.emit(CoreEvent::JobProgress(JobProgressEvent {
+			id: self.id,
+			library_id: self.library.id,
+			task_count: self.total as i32,
+			completed_task_count: (self.total - self.pending.len()) as i32,
+			phase: "image_converter".to_string(),
+			message: format!(
+				"Converting {}",
+				full_path
+					.file_name()
+					.unwrap_or(full_path.as_os_str())
+					.to_string_lossy()
+			),
+			estimated_completion: Utc::now(),
+		}));
+	}
+
+	/// Indexing the converted images right away instead of waiting for the watcher,
+	/// which also generates their thumbnails and media data
+	async fn index_converted(&self) -> Result<(), FileSystemJobsError> {
+		if self.output.sub_paths_to_index.is_empty() {
+			return Ok(());
+		}
+
+		let location = find_location(&self.library, self.args.location_id)
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await?
+			.ok_or(LocationError::IdNotFound(self.args.location_id))?;
+
+		for sub_path in &self.output.sub_paths_to_index {
+			if let Err(e) = light_scan_location(
+				Arc::clone(&self.node),
+				Arc::clone(&self.library),
+				location.clone(),
+				sub_path,
+			)
+			.await
+			{
+				error!("Failed to index converted images at {sub_path}: {e:#?}");
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[async_trait::async_trait]
+impl Task<FileSystemJobsError> for ImageConverterTask {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, FileSystemJobsError> {
+		while let Some(file_data) = self.pending.front() {
+			match interrupter.try_check_interrupt() {
+				Some(InterruptionKind::Pause) => return Ok(ExecStatus::Paused),
+				Some(InterruptionKind::Cancel) => return Ok(ExecStatus::Canceled),
+				None => {}
+			}
+
+			self.report_progress(file_data);
+
+			match convert(&self.args, file_data).await {
+				Ok(sub_path) => {
+					self.output.converted += 1;
+					self.output.sub_paths_to_index.insert(sub_path);
+				}
+				Err(e) => {
+					error!("Failed to convert image: {e:#?}");
+					self.output.errors.push(e.to_string());
+				}
+			}
+
+			self.pending.pop_front();
+		}
+
+		self.index_converted().await?;
+
+		invalidate_query!(self.library, "search.paths");
+		invalidate_query!(self.library, "search.objects");
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+/// Converts a single image, returning the materialized path of the directory it was written to
+async fn convert(
+	args: &ImageConverterArgs,
+	FileData {
+		file_path,
+		full_path,
+	}: &FileData,
+) -> Result<String, FileSystemJobsError> {
+	let desired_extension = args.desired_extension;
+	let quality = args
+		.quality_percentage
+		.unwrap_or(DEFAULT_QUALITY_PERCENTAGE);
+	let resize = args.resize;
+
+	let bytes = spawn_blocking({
+		let full_path = full_path.clone();
+		move || {
+			sd_images::convert_image(&full_path, &extension_of(desired_extension)).and_then(
+				|image| {
+					sd_images::encode_image(
+						&resize_image(image, resize),
+						desired_extension,
+						quality,
+					)
+				},
+			)
+		}
+	})
+	.await?
+	.map_err(|error| FileSystemJobsError::ImageConversion {
+		path: full_path.clone().into_boxed_path(),
+		error,
+	})?;
+
+	let mut output_path = full_path.with_extension(extension_of(desired_extension));
+
+	match fs::metadata(&output_path).await {
+		Ok(_) if args.conflict_policy == ConversionConflictPolicy::Sibling => {
+			output_path = find_available_filename_for_duplicate(&output_path)
+				.await
+				.map_err(|e| match e {
+					FileSystemJobsError::FailedToFindAvailableName(path) => {
+						FileSystemJobsError::WouldOverwrite(path)
+					}
+					e => e,
+				})?;
+		}
+		Ok(_) => { /* Overwriting it is */ }
+		Err(e) if e.kind() == io::ErrorKind::NotFound => { /* Nothing in our way */ }
+		Err(e) => return Err(FileIOError::from((output_path, e)).into()),
+	}
+
+	trace!(
+		"Writing converted image from {} to {}",
+		full_path.display(),
+		output_path.display()
+	);
+
+	fs::write(&output_path, bytes)
+		.await
+		.map_err(|e| FileIOError::from((&output_path, e)))?;
+
+	// When overwriting an image with the same extension, the output is the source itself
+	if args.delete_src && output_path != *full_path {
+		fs::remove_file(full_path)
+			.await
+			.map_err(|e| FileIOError::from((full_path, e)))?;
+	}
+
+	maybe_missing(&file_path.materialized_path, "file_path.materialized_path")
+		.cloned()
+		.map_err(Into::into)
+}
+
+fn extension_of(desired_extension: ConvertibleExtension) -> OsString {
+	OsString::from(desired_extension.to_string().to_lowercase())
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn resize_image(image: DynamicImage, resize: Option<ImageResize>) -> DynamicImage {
+	match resize {
+		Some(ImageResize::Percentage(percentage)) => {
+			let factor = f64::from(percentage.clamp(1, 125)) / 100.0;
+			image.resize(
+				(f64::from(image.width()) * factor).round().max(1.0) as u32,
+				(f64::from(image.height()) * factor).round().max(1.0) as u32,
+				FilterType::Triangle,
+			)
+		}
+		Some(ImageResize::MaxDimension(max_dimension))
+			if image.width().max(image.height()) > max_dimension =>
+		{
+			let max_dimension = max_dimension.max(1);
+			// `resize` keeps the aspect ratio, fitting the image within these bounds
+			image.resize(max_dimension, max_dimension, FilterType::Triangle)
+		}
+		_ => image,
+	}
+}
@@ -29,6 +29,8 @@ pub enum FileSystemJobsError {
 	FilePath(#[from] FilePathError),
 	#[error("action would overwrite another file: {}", .0.display())]
 	WouldOverwrite(Box<Path>),
+	#[error("file still exists on the other synced directory, so it won't be deleted: {}", .0.display())]
+	StillOnOtherSide(Box<Path>),
 	#[error("missing-field: {0}")]
 	MissingField(#[from] MissingFieldError),
 	#[error("no parent for path, which is supposed to be directory: <path='{}'>", .0.display())]
@@ -41,6 +43,29 @@ pub enum FileSystemJobsError {
 	NonUTF8Path(#[from] NonUtf8PathError),
 	#[error("failed to find an available name to avoid duplication: <path='{}'>", .0.display())]
 	FailedToFindAvailableName(Box<Path>),
+	#[error("failed to convert image: {error} <path='{}'>", .path.display())]
+	ImageConversion {
+		path: Box<Path>,
+		error: sd_images::Error,
+	},
+	#[error("failed to execute converting task: {0}")]
+	ImageConversionTask(#[from] tokio::task::JoinError),
+	#[error("invalid archive name: <name='{0}'>")]
+	InvalidArchiveName(String),
+	#[error("file is not a supported archive: <path='{}'>", .0.display())]
+	UnsupportedArchive(Box<Path>),
+	#[error("skipping archive entry that is a link or would be extracted outside of the target directory: <path='{}'>", .0.display())]
+	UnsafeArchiveEntry(Box<Path>),
+	#[error("zip archive error: {error} <path='{}'>", .path.display())]
+	Zip {
+		path: Box<Path>,
+		error: zip::result::ZipError,
+	},
+	#[error("can't sync a directory with itself or with one of its sub directories: <source='{}', target='{}'>", .source_path.display(), .target_path.display())]
+	OverlappingSyncDirectories {
+		source_path: Box<Path>,
+		target_path: Box<Path>,
+	},
 }
 
 impl From<FileSystemJobsError> for rspc::Error {
@@ -1,4 +1,4 @@
-use crate::location::LocationError;
+use crate::location::{archive::ensure_location_is_not_archived, LocationError};
 
 use sd_file_path_helper::{file_path_with_object, IsolatedFilePathData};
 use sd_prisma::prisma::{file_path, location, PrismaClient};
@@ -22,6 +22,11 @@ pub mod old_erase;
 pub mod old_copy;
 pub mod old_cut;
 
+pub mod archive;
+pub mod convert;
+pub mod reclaim;
+pub mod sync;
+
 // pub mod decrypt;
 // pub mod encrypt;
 
@@ -111,6 +116,8 @@ pub async fn fetch_source_and_target_location_paths(
 	source_location_id: location::id::Type,
 	target_location_id: location::id::Type,
 ) -> Result<(PathBuf, PathBuf), FileSystemJobsError> {
+	ensure_location_is_not_archived(db, target_location_id).await?;
+
 	match db
 		._batch((
 			db.location()
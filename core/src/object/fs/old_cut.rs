@@ -1,6 +1,7 @@
 use crate::{
 	invalidate_query,
 	library::Library,
+	location::archive::ensure_location_is_not_archived,
 	object::fs::{construct_target_filename, error::FileSystemJobsError},
 	old_job::{
 		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobStepOutput, StatefulJob,
@@ -55,6 +56,9 @@ impl StatefulJob for OldFileCutterJobInit {
 		let init = self;
 		let Library { db, .. } = &*ctx.library;
 
+		// Cutting also removes the files from the source location
+		ensure_location_is_not_archived(db, init.source_location_id).await?;
+
 		let (sources_location_path, targets_location_path) =
 			fetch_source_and_target_location_paths(
 				db,
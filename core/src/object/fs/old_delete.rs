@@ -1,7 +1,7 @@
 use crate::{
 	invalidate_query,
 	library::Library,
-	location::get_location_path_from_location_id,
+	location::{archive::ensure_location_is_not_archived, get_location_path_from_location_id},
 	old_job::{
 		CurrentStep, JobError, JobInitOutput, JobResult, JobStepOutput, StatefulJob, WorkerContext,
 	},
@@ -50,6 +50,8 @@ impl StatefulJob for OldFileDeleterJobInit {
 		let init = self;
 		let Library { db, .. } = &*ctx.library;
 
+		ensure_location_is_not_archived(db, init.location_id).await?;
+
 		let steps = get_many_files_datas(
 			db,
 			get_location_path_from_location_id(db, init.location_id).await?,
@@ -0,0 +1,418 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::archive::ensure_location_is_not_archived,
+	object::validation::hash::file_checksum,
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_file_path_helper::{file_path_with_object, IsolatedFilePathData};
+use sd_prisma::{
+	prisma::{file_path, location, object},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{
+	db::{inode_from_db, maybe_missing},
+	error::FileIOError,
+};
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::{fs, io, io::AsyncWriteExt, task::spawn_blocking};
+use tracing::{trace, warn};
+
+use super::{error::FileSystemJobsError, FileData};
+
+/// Which copy of a duplicated file is kept
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum KeepPolicy {
+	Oldest,
+	Newest,
+	/// Keeps the oldest copy in the given location, or the oldest copy overall if there is none there
+	PreferredLocation(location::id::Type),
+}
+
+/// What to do with the redundant copies
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum ReclaimAction {
+	Remove,
+	/// Replaces the copies with hard links to the kept file
+	HardLink,
+	/// Replaces the copies with copy-on-write clones of the kept file, only supported by some
+	/// file systems like APFS, Btrfs and XFS
+	Reflink,
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct OldDuplicateReclaimerJobInit {
+	/// Only redundant copies inside this location are reclaimed, as links can't cross file systems
+	pub location_id: location::id::Type,
+	pub object_ids: Vec<object::id::Type>,
+	pub keep_policy: KeepPolicy,
+	pub action: ReclaimAction,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldDuplicateReclaimerJobData {
+	undo_log_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldDuplicateReclaimerJobRunMetadata {
+	reclaimed_files: usize,
+	reclaimed_bytes: u64,
+}
+
+impl JobRunMetadata for OldDuplicateReclaimerJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.reclaimed_files += new_data.reclaimed_files;
+		self.reclaimed_bytes += new_data.reclaimed_bytes;
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReclaimStep {
+	kept_path: PathBuf,
+	redundant: FileData,
+}
+
+/// A line of the undo log, every reclaimed file can be restored by copying `kept_path` back to
+/// `path`, as long as the kept file wasn't changed since. Entries are written before touching the
+/// files, so a few of them may point to copies which failed to be reclaimed and are still there.
+#[derive(Serialize, Debug)]
+struct UndoLogEntry<'a> {
+	action: ReclaimAction,
+	kept_path: &'a Path,
+	path: &'a Path,
+	checksum: &'a str,
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldDuplicateReclaimerJobInit {
+	type Data = OldDuplicateReclaimerJobData;
+	type Step = ReclaimStep;
+	type RunMetadata = OldDuplicateReclaimerJobRunMetadata;
+
+	const NAME: &'static str = "duplicate_reclaimer";
+
+	fn target_location(&self) -> location::id::Type {
+		self.location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		ensure_location_is_not_archived(db, init.location_id).await?;
+
+		// We can only compare and link files from locations on this device
+		let location_paths = db
+			.location()
+			.find_many(vec![location::instance_id::equals(Some(
+				ctx.library.config().await.instance_id,
+			))])
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|location| location.path.map(|path| (location.id, PathBuf::from(path))))
+			.collect::<HashMap<_, _>>();
+
+		let mut copies_by_object = BTreeMap::<_, Vec<_>>::new();
+
+		for file_path in db
+			.file_path()
+			.find_many(vec![
+				file_path::object_id::in_vec(init.object_ids.clone()),
+				file_path::is_dir::equals(Some(false)),
+			])
+			.include(file_path_with_object::include())
+			.exec()
+			.await?
+		{
+			let (Some(object_id), Some(location_path)) = (
+				file_path.object_id,
+				file_path
+					.location_id
+					.and_then(|location_id| location_paths.get(&location_id)),
+			) else {
+				continue;
+			};
+
+			let full_path = location_path.join(IsolatedFilePathData::try_from(&file_path)?);
+
+			copies_by_object
+				.entry(object_id)
+				.or_default()
+				.push(FileData {
+					file_path,
+					full_path,
+				});
+		}
+
+		let mut steps = vec![];
+
+		for mut copies in copies_by_object.into_values() {
+			if copies.len() < 2 {
+				continue;
+			}
+
+			let kept_idx = keep_idx(&copies, init.keep_policy);
+			let kept = copies.swap_remove(kept_idx);
+
+			steps.extend(
+				copies
+					.into_iter()
+					.filter(|copy| copy.file_path.location_id == Some(init.location_id))
+					.filter(|copy| !is_same_inode(&kept, copy))
+					.map(|redundant| ReclaimStep {
+						kept_path: kept.full_path.clone(),
+						redundant,
+					}),
+			);
+		}
+
+		let undo_log_dir = ctx
+			.node
+			.data_dir
+			.join("undo")
+			.join(ctx.library.id.to_string());
+
+		fs::create_dir_all(&undo_log_dir)
+			.await
+			.map_err(|e| FileIOError::from((&undo_log_dir, e)))?;
+
+		*data = Some(OldDuplicateReclaimerJobData {
+			undo_log_path: undo_log_dir.join(format!(
+				"{}-{}.jsonl",
+				Self::NAME,
+				Utc::now().format("%Y%m%d%H%M%S")
+			)),
+		});
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, .. }: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let Library { db, sync, .. } = ctx.library.as_ref();
+		let ReclaimStep {
+			kept_path,
+			redundant: FileData {
+				file_path,
+				full_path,
+			},
+		} = step;
+
+		ctx.progress_msg(format!("Reclaiming {}", full_path.display()));
+
+		// cas_ids are sampled from large files, so we only touch files which are really the same
+		let checksum = file_checksum(full_path)
+			.await
+			.map_err(|e| FileIOError::from((full_path, e)))?;
+		let kept_checksum = file_checksum(kept_path)
+			.await
+			.map_err(|e| FileIOError::from((kept_path, e)))?;
+
+		if checksum != kept_checksum {
+			return Ok(JobRunErrors(vec![format!(
+				"Skipping {}, its contents differ from the kept copy at {}",
+				full_path.display(),
+				kept_path.display()
+			)])
+			.into());
+		}
+
+		let size = fs::metadata(full_path)
+			.await
+			.map_err(|e| FileIOError::from((full_path, e)))?
+			.len();
+
+		trace!(
+			"Reclaiming {} with {:?}, keeping {}",
+			full_path.display(),
+			self.action,
+			kept_path.display()
+		);
+
+		// Written first, so there's no way to lose track of a reclaimed file
+		write_undo_log_entry(
+			&data.undo_log_path,
+			&UndoLogEntry {
+				action: self.action,
+				kept_path,
+				path: full_path,
+				checksum: &checksum,
+			},
+		)
+		.await?;
+
+		let reclaimed = match self.action {
+			ReclaimAction::Remove => match fs::remove_file(full_path).await {
+				Ok(()) => {
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::file_path::SyncId {
+							pub_id: file_path.pub_id.clone(),
+						}),
+						db.file_path().delete(file_path::id::equals(file_path.id)),
+					)
+					.await?;
+
+					Ok(())
+				}
+				Err(e) => Err(FileSystemJobsError::from(FileIOError::from((full_path, e)))),
+			},
+			ReclaimAction::HardLink => {
+				replace_with_link(kept_path, full_path, |from, to| async move {
+					fs::hard_link(from, to).await
+				})
+				.await
+			}
+			ReclaimAction::Reflink => {
+				replace_with_link(kept_path, full_path, |from, to| async move {
+					spawn_blocking(move || reflink_copy::reflink(from, to))
+						.await
+						.unwrap_or_else(|e| Err(io::Error::new(io::ErrorKind::Other, e)))
+				})
+				.await
+			}
+		};
+
+		// Files can be read-only or in use, links can't cross file systems and not every file
+		// system supports reflinks, which only means this copy can't be reclaimed, while the other
+		// ones still can
+		if let Err(e) = reclaimed {
+			return Ok(JobRunErrors(vec![e.to_string()]).into());
+		}
+
+		Ok(OldDuplicateReclaimerJobRunMetadata {
+			reclaimed_files: 1,
+			reclaimed_bytes: size,
+		}
+		.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		invalidate_query!(ctx.library, "search.paths");
+		invalidate_query!(ctx.library, "search.duplicates");
+
+		Ok(Some(json!({
+			"init": init,
+			"run_metadata": run_metadata,
+			"undo_log_path": data.as_ref().map(|data| &data.undo_log_path),
+		})))
+	}
+}
+
+fn keep_idx(copies: &[FileData], keep_policy: KeepPolicy) -> usize {
+	fn oldest<'a>(copies: impl Iterator<Item = (usize, &'a FileData)>) -> Option<usize> {
+		copies
+			.min_by_key(|(_, copy)| copy.file_path.date_created)
+			.map(|(idx, _)| idx)
+	}
+
+	match keep_policy {
+		KeepPolicy::Oldest => oldest(copies.iter().enumerate()),
+		KeepPolicy::Newest => copies
+			.iter()
+			.enumerate()
+			.max_by_key(|(_, copy)| copy.file_path.date_created)
+			.map(|(idx, _)| idx),
+		KeepPolicy::PreferredLocation(location_id) => oldest(
+			copies
+				.iter()
+				.enumerate()
+				.filter(|(_, copy)| copy.file_path.location_id == Some(location_id)),
+		)
+		.or_else(|| oldest(copies.iter().enumerate())),
+	}
+	.unwrap_or_default()
+}
+
+/// Copies which are already hard links to the kept file don't take any extra space
+fn is_same_inode(kept: &FileData, copy: &FileData) -> bool {
+	kept.file_path.location_id == copy.file_path.location_id
+		&& matches!(
+			(&kept.file_path.inode, &copy.file_path.inode),
+			(Some(kept_inode), Some(inode)) if inode_from_db(kept_inode) == inode_from_db(inode)
+		)
+}
+
+/// Links the kept file next to the redundant one and then renames it over the redundant copy, so
+/// the redundant file is never lost if linking fails midway
+async fn replace_with_link<Fut>(
+	kept_path: &Path,
+	full_path: &Path,
+	link: impl FnOnce(PathBuf, PathBuf) -> Fut,
+) -> Result<(), FileSystemJobsError>
+where
+	Fut: std::future::Future<Output = io::Result<()>>,
+{
+	let file_name = maybe_missing(full_path.file_name(), "full_path.file_name")?;
+	let mut temp_name = file_name.to_os_string();
+	temp_name.push(".sd-reclaim");
+	let temp_path = full_path.with_file_name(temp_name);
+
+	link(kept_path.to_path_buf(), temp_path.clone())
+		.await
+		.map_err(|e| FileIOError::from((&temp_path, e)))?;
+
+	if let Err(e) = fs::rename(&temp_path, full_path).await {
+		if let Err(e) = fs::remove_file(&temp_path).await {
+			warn!(
+				"Failed to remove temporary link {}: {e:#?}",
+				temp_path.display()
+			);
+		}
+
+		return Err(FileIOError::from((full_path, e)).into());
+	}
+
+	Ok(())
+}
+
+async fn write_undo_log_entry(
+	undo_log_path: &Path,
+	entry: &UndoLogEntry<'_>,
+) -> Result<(), FileIOError> {
+	let mut line = serde_json::to_vec(entry).expect("undo log entries are always serializable");
+	line.push(b'\n');
+
+	let mut file = fs::OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(undo_log_path)
+		.await
+		.map_err(|e| FileIOError::from((undo_log_path, e)))?;
+
+	file.write_all(&line)
+		.await
+		.map_err(|e| FileIOError::from((undo_log_path, e)))
+}
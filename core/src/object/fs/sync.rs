@@ -0,0 +1,865 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	location::{
+		archive::ensure_location_is_not_archived, find_location,
+		get_location_path_from_location_id, location_with_indexer_rules, scan_location,
+		scan_location_sub_path, LocationError,
+	},
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+};
+
+use sd_file_path_helper::{
+	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
+	IsolatedFilePathData,
+};
+use sd_prisma::prisma::{file_path, location, PrismaClient};
+use sd_utils::{
+	db::{inode_from_db, maybe_missing},
+	error::FileIOError,
+};
+
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap},
+	path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::{fs, io};
+use tracing::{error, trace};
+
+use super::error::FileSystemJobsError;
+
+file_path::select!(file_path_for_folder_sync {
+	materialized_path
+	is_dir
+	name
+	extension
+	cas_id
+	size_in_bytes_bytes
+	inode
+	date_modified
+});
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum SyncMode {
+	/// Makes the target an exact copy of the source, deleting files missing from the source
+	Mirror,
+	/// Copies the files missing from each side to the other one, without deleting anything
+	TwoWay,
+}
+
+/// What to do with files changed on both sides, only used by [`SyncMode::TwoWay`], as the source
+/// always wins when mirroring
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum SyncConflictPolicy {
+	/// Keeps the most recently modified file, skipping files modified at the same time
+	KeepNewest,
+	KeepSource,
+	KeepTarget,
+	/// Leaves both files untouched
+	Skip,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum SyncSide {
+	Source,
+	Target,
+}
+
+impl SyncSide {
+	const fn other(self) -> Self {
+		match self {
+			Self::Source => Self::Target,
+			Self::Target => Self::Source,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, PartialEq, Eq, Debug)]
+pub enum SyncAction {
+	/// Copies a file from the other side, which doesn't exist on this side yet
+	Copy,
+	/// Replaces the file on this side with the one on the other side
+	Overwrite,
+	/// Renames a file on this side which was renamed on the other side
+	Rename {
+		from: String,
+	},
+	Delete,
+	/// Creates a directory which only exists on the other side, so empty ones are synced too
+	CreateDirectory,
+	/// Deletes a directory missing from the other side, once everything inside it is gone
+	DeleteDirectory,
+}
+
+/// A change to be applied on one of the sides, with paths relative to the synced directories
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+pub struct SyncOperation {
+	pub side: SyncSide,
+	pub action: SyncAction,
+	pub path: String,
+}
+
+#[derive(Serialize, Type, Default, Debug)]
+pub struct FolderSyncPlan {
+	pub operations: Vec<SyncOperation>,
+	/// Files which differ on both sides and will be left untouched
+	pub conflicts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Clone, Debug)]
+pub struct OldFolderSyncJobInit {
+	pub source_location_id: location::id::Type,
+	/// Directory inside the source location to sync, the whole location if missing
+	pub source_sub_path: Option<PathBuf>,
+	pub target_location_id: location::id::Type,
+	/// Directory inside the target location to sync, the whole location if missing
+	pub target_sub_path: Option<PathBuf>,
+	pub mode: SyncMode,
+	pub conflict_policy: SyncConflictPolicy,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OldFolderSyncJobData {
+	source_path: PathBuf,
+	target_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct OldFolderSyncJobRunMetadata {
+	copied: usize,
+	renamed: usize,
+	deleted: usize,
+}
+
+impl JobRunMetadata for OldFolderSyncJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.copied += new_data.copied;
+		self.renamed += new_data.renamed;
+		self.deleted += new_data.deleted;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldFolderSyncJobInit {
+	type Data = OldFolderSyncJobData;
+	type Step = SyncOperation;
+	type RunMetadata = OldFolderSyncJobRunMetadata;
+
+	const NAME: &'static str = "folder_sync";
+
+	fn target_location(&self) -> location::id::Type {
+		self.target_location_id
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let (source, target) = init.sync_roots(db).await?;
+		let plan = plan_folder_sync(init.mode, init.conflict_policy, &source, &target);
+
+		*data = Some(OldFolderSyncJobData {
+			source_path: source.full_path,
+			target_path: target.full_path,
+		});
+
+		Ok((
+			Default::default(),
+			plan.operations,
+			plan.conflicts
+				.into_iter()
+				.map(|path| {
+					format!("Skipping conflicting file, which differs on both sides: {path}")
+				})
+				.collect::<Vec<_>>()
+				.into(),
+		)
+			.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: SyncOperation { side, action, path },
+			..
+		}: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let root_of = |side| match side {
+			SyncSide::Source => &data.source_path,
+			SyncSide::Target => &data.target_path,
+		};
+
+		let full_path = root_of(*side).join(path);
+
+		ctx.progress_msg(format!("Syncing {path}"));
+
+		match action {
+			SyncAction::Copy | SyncAction::Overwrite => {
+				let from = root_of(side.other()).join(path);
+
+				// The index may be outdated, so we make sure we aren't destroying anything new
+				if *action == SyncAction::Copy && fs::metadata(&full_path).await.is_ok() {
+					return Ok(JobRunErrors(vec![FileSystemJobsError::WouldOverwrite(
+						full_path.into_boxed_path(),
+					)
+					.to_string()])
+					.into());
+				}
+
+				create_parent_dir(&full_path).await?;
+
+				trace!("Syncing {} to {}", from.display(), full_path.display());
+
+				fs::copy(&from, &full_path)
+					.await
+					.map_err(|e| FileIOError::from((&full_path, e)))?;
+
+				Ok(OldFolderSyncJobRunMetadata {
+					copied: 1,
+					..Default::default()
+				}
+				.into())
+			}
+
+			SyncAction::Rename { from } => {
+				let from = root_of(*side).join(from);
+
+				if fs::metadata(&full_path).await.is_ok() {
+					return Ok(JobRunErrors(vec![FileSystemJobsError::WouldOverwrite(
+						full_path.into_boxed_path(),
+					)
+					.to_string()])
+					.into());
+				}
+
+				create_parent_dir(&full_path).await?;
+
+				fs::rename(&from, &full_path)
+					.await
+					.map_err(|e| FileIOError::from((&from, e)))?;
+
+				Ok(OldFolderSyncJobRunMetadata {
+					renamed: 1,
+					..Default::default()
+				}
+				.into())
+			}
+
+			SyncAction::CreateDirectory => {
+				fs::create_dir_all(&full_path)
+					.await
+					.map_err(|e| FileIOError::from((&full_path, e)))?;
+
+				Ok(OldFolderSyncJobRunMetadata {
+					copied: 1,
+					..Default::default()
+				}
+				.into())
+			}
+
+			SyncAction::Delete | SyncAction::DeleteDirectory => {
+				// The index may be outdated, so we make sure it's really gone from the other side
+				if fs::symlink_metadata(root_of(side.other()).join(path))
+					.await
+					.is_ok()
+				{
+					return Ok(JobRunErrors(vec![FileSystemJobsError::StillOnOtherSide(
+						full_path.into_boxed_path(),
+					)
+					.to_string()])
+					.into());
+				}
+
+				let res = if *action == SyncAction::Delete {
+					fs::remove_file(&full_path).await
+				} else {
+					fs::remove_dir(&full_path).await
+				};
+
+				match res {
+					Ok(()) => Ok(OldFolderSyncJobRunMetadata {
+						deleted: 1,
+						..Default::default()
+					}
+					.into()),
+					Err(e) if e.kind() == io::ErrorKind::NotFound => {
+						Ok(OldFolderSyncJobRunMetadata::default().into())
+					}
+					// Directories can still hold files that aren't indexed, which we leave alone
+					Err(e) if *action == SyncAction::DeleteDirectory => {
+						Ok(
+							JobRunErrors(vec![FileIOError::from((&full_path, e)).to_string()])
+								.into(),
+						)
+					}
+					Err(e) => Err(FileIOError::from((&full_path, e)).into()),
+				}
+			}
+		}
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		// Indexing the changes right away instead of waiting for the watcher
+		let sides = match init.mode {
+			SyncMode::Mirror => vec![(init.target_location_id, &init.target_sub_path)],
+			SyncMode::TwoWay => vec![
+				(init.source_location_id, &init.source_sub_path),
+				(init.target_location_id, &init.target_sub_path),
+			],
+		};
+
+		for (location_id, sub_path) in sides {
+			let location = find_location(&ctx.library, location_id)
+				.include(location_with_indexer_rules::include())
+				.exec()
+				.await?
+				.ok_or(LocationError::IdNotFound(location_id))?;
+
+			let res = match sub_path {
+				Some(sub_path) if sub_path != Path::new("") => {
+					scan_location_sub_path(&ctx.node, &ctx.library, location, sub_path).await
+				}
+				_ => scan_location(&ctx.node, &ctx.library, location).await,
+			};
+
+			if let Err(e) = res {
+				error!("Failed to index synced files at location <id='{location_id}'>: {e:#?}");
+			}
+		}
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+impl OldFolderSyncJobInit {
+	/// Computes the changes this job would apply, without touching anything
+	pub async fn plan(&self, db: &PrismaClient) -> Result<FolderSyncPlan, FileSystemJobsError> {
+		let (source, target) = self.sync_roots(db).await?;
+
+		Ok(plan_folder_sync(
+			self.mode,
+			self.conflict_policy,
+			&source,
+			&target,
+		))
+	}
+
+	async fn sync_roots(
+		&self,
+		db: &PrismaClient,
+	) -> Result<(SyncRoot, SyncRoot), FileSystemJobsError> {
+		ensure_location_is_not_archived(db, self.target_location_id).await?;
+		if self.mode == SyncMode::TwoWay {
+			ensure_location_is_not_archived(db, self.source_location_id).await?;
+		}
+
+		let source = SyncRoot::fetch(db, self.source_location_id, &self.source_sub_path).await?;
+		let target = SyncRoot::fetch(db, self.target_location_id, &self.target_sub_path).await?;
+
+		if source.full_path.starts_with(&target.full_path)
+			|| target.full_path.starts_with(&source.full_path)
+		{
+			return Err(FileSystemJobsError::OverlappingSyncDirectories {
+				source_path: source.full_path.into_boxed_path(),
+				target_path: target.full_path.into_boxed_path(),
+			});
+		}
+
+		Ok((source, target))
+	}
+}
+
+struct SyncRoot {
+	full_path: PathBuf,
+	/// Files keyed by their paths relative to `full_path`
+	entries: HashMap<String, SyncEntry>,
+	/// Paths of the directories relative to `full_path`
+	directories: BTreeSet<String>,
+}
+
+struct SyncEntry {
+	cas_id: Option<String>,
+	size_in_bytes: u64,
+	inode: Option<u64>,
+	date_modified: Option<DateTime<FixedOffset>>,
+}
+
+impl SyncRoot {
+	async fn fetch(
+		db: &PrismaClient,
+		location_id: location::id::Type,
+		sub_path: &Option<PathBuf>,
+	) -> Result<Self, FileSystemJobsError> {
+		let location_path = get_location_path_from_location_id(db, location_id).await?;
+
+		let (full_path, children_materialized_path) = match sub_path {
+			Some(sub_path) if sub_path != Path::new("") => {
+				let full_path = ensure_sub_path_is_in_location(&location_path, sub_path).await?;
+				ensure_sub_path_is_directory(&location_path, sub_path).await?;
+
+				let sub_iso_file_path =
+					IsolatedFilePathData::new(location_id, &location_path, &full_path, true)?;
+
+				ensure_file_path_exists(
+					sub_path,
+					&sub_iso_file_path,
+					db,
+					FileSystemJobsError::FilePathNotFound,
+				)
+				.await?;
+
+				let children_materialized_path = sub_iso_file_path
+					.materialized_path_for_children()
+					.expect("sub path iso_file_path must be a directory");
+
+				(full_path, children_materialized_path)
+			}
+			_ => (location_path, "/".to_string()),
+		};
+
+		let mut directories = BTreeSet::new();
+
+		let entries = db
+			.file_path()
+			.find_many(vec![
+				file_path::location_id::equals(Some(location_id)),
+				file_path::materialized_path::starts_with(children_materialized_path.clone()),
+			])
+			.select(file_path_for_folder_sync::select())
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|file_path| match file_path.is_dir {
+				Some(false) => Some(file_path),
+				Some(true) => {
+					match (&file_path.materialized_path, &file_path.name) {
+						(Some(materialized_path), Some(name)) => {
+							directories.insert(format!(
+								"{}{name}",
+								&materialized_path[children_materialized_path.len()..]
+							));
+						}
+						_ => error!("Skipping directory missing its materialized path or name"),
+					}
+
+					None
+				}
+				None => None,
+			})
+			.map(|file_path| {
+				let materialized_path =
+					maybe_missing(&file_path.materialized_path, "file_path.materialized_path")?;
+				let name = maybe_missing(&file_path.name, "file_path.name")?;
+
+				let relative_path = match file_path.extension.as_deref() {
+					Some(extension) if !extension.is_empty() => format!(
+						"{}{name}.{extension}",
+						&materialized_path[children_materialized_path.len()..]
+					),
+					_ => format!(
+						"{}{name}",
+						&materialized_path[children_materialized_path.len()..]
+					),
+				};
+
+				Ok((
+					relative_path,
+					SyncEntry {
+						size_in_bytes: file_path
+							.size_in_bytes_bytes
+							.as_deref()
+							.and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+							.map(u64::from_be_bytes)
+							.unwrap_or_default(),
+						inode: file_path.inode.as_deref().map(inode_from_db),
+						cas_id: file_path.cas_id,
+						date_modified: file_path.date_modified,
+					},
+				))
+			})
+			.collect::<Result<_, FileSystemJobsError>>()?;
+
+		Ok(Self {
+			full_path,
+			entries,
+			directories,
+		})
+	}
+}
+
+impl SyncEntry {
+	/// Files with the same size are considered the same if they have the same content id, or are
+	/// the same file on disk (hard links or nested locations) modified at the same time
+	fn is_same_as(&self, other: &Self) -> bool {
+		self.size_in_bytes == other.size_in_bytes
+			&& ((self.cas_id.is_some() && self.cas_id == other.cas_id)
+				|| (self.inode.is_some()
+					&& self.inode == other.inode
+					&& self.date_modified == other.date_modified))
+	}
+}
+
+fn plan_folder_sync(
+	mode: SyncMode,
+	conflict_policy: SyncConflictPolicy,
+	source: &SyncRoot,
+	target: &SyncRoot,
+) -> FolderSyncPlan {
+	let mut plan = FolderSyncPlan::default();
+
+	// Sorted, so plans are stable and parent directories come first
+	let source_entries = source.entries.iter().collect::<BTreeMap<_, _>>();
+	let target_entries = target.entries.iter().collect::<BTreeMap<_, _>>();
+
+	let mut source_only = vec![];
+	for (path, source_entry) in &source_entries {
+		let Some(target_entry) = target_entries.get(path) else {
+			source_only.push(*path);
+			continue;
+		};
+
+		if source_entry.is_same_as(target_entry) {
+			continue;
+		}
+
+		let winner = match (mode, conflict_policy) {
+			(SyncMode::Mirror, _) | (SyncMode::TwoWay, SyncConflictPolicy::KeepSource) => {
+				Some(SyncSide::Source)
+			}
+			(SyncMode::TwoWay, SyncConflictPolicy::KeepTarget) => Some(SyncSide::Target),
+			(SyncMode::TwoWay, SyncConflictPolicy::KeepNewest) => {
+				match (source_entry.date_modified, target_entry.date_modified) {
+					(Some(source_date), Some(target_date)) if source_date > target_date => {
+						Some(SyncSide::Source)
+					}
+					(Some(source_date), Some(target_date)) if target_date > source_date => {
+						Some(SyncSide::Target)
+					}
+					_ => None,
+				}
+			}
+			(SyncMode::TwoWay, SyncConflictPolicy::Skip) => None,
+		};
+
+		if let Some(winner) = winner {
+			plan.operations.push(SyncOperation {
+				side: winner.other(),
+				action: SyncAction::Overwrite,
+				path: path.to_string(),
+			});
+		} else {
+			plan.conflicts.push(path.to_string());
+		}
+	}
+
+	let mut target_only = target_entries
+		.into_iter()
+		.filter(|(path, _)| !source_entries.contains_key(path))
+		.collect::<BTreeMap<_, _>>();
+
+	let directories_only_on = |side: &SyncRoot, other: &SyncRoot| {
+		side.directories
+			.difference(&other.directories)
+			.cloned()
+			.collect::<Vec<_>>()
+	};
+
+	match mode {
+		SyncMode::Mirror => {
+			// Directories holding files are created along with them, but empty ones need their
+			// own operations, just like their deletion once their files are gone
+			plan.operations
+				.extend(directories_only_on(source, target).into_iter().map(|path| {
+					SyncOperation {
+						side: SyncSide::Target,
+						action: SyncAction::CreateDirectory,
+						path,
+					}
+				}));
+
+			let mut renames = vec![];
+			let mut copies = vec![];
+
+			for path in source_only {
+				let source_entry = &source.entries[path];
+
+				// Files missing from the target with the same content as a file only found there
+				// were renamed or moved on the source, so we do the same instead of copying them
+				let renamed_from = source_entry.cas_id.as_ref().and_then(|_| {
+					target_only
+						.iter()
+						.find(|(_, target_entry)| source_entry.is_same_as(target_entry))
+						.map(|(target_path, _)| *target_path)
+				});
+
+				if let Some(from) = renamed_from {
+					target_only.remove(from);
+					renames.push(SyncOperation {
+						side: SyncSide::Target,
+						action: SyncAction::Rename { from: from.clone() },
+						path: path.clone(),
+					});
+				} else {
+					copies.push(SyncOperation {
+						side: SyncSide::Target,
+						action: SyncAction::Copy,
+						path: path.clone(),
+					});
+				}
+			}
+
+			plan.operations.extend(renames);
+			plan.operations.extend(copies);
+			plan.operations
+				.extend(target_only.into_keys().map(|path| SyncOperation {
+					side: SyncSide::Target,
+					action: SyncAction::Delete,
+					path: path.clone(),
+				}));
+			// Sub directories come last in sorted order, and they have to be deleted first
+			plan.operations
+				.extend(
+					directories_only_on(target, source)
+						.into_iter()
+						.rev()
+						.map(|path| SyncOperation {
+							side: SyncSide::Target,
+							action: SyncAction::DeleteDirectory,
+							path,
+						}),
+				);
+		}
+
+		SyncMode::TwoWay => {
+			plan.operations
+				.extend(directories_only_on(source, target).into_iter().map(|path| {
+					SyncOperation {
+						side: SyncSide::Target,
+						action: SyncAction::CreateDirectory,
+						path,
+					}
+				}));
+			plan.operations
+				.extend(directories_only_on(target, source).into_iter().map(|path| {
+					SyncOperation {
+						side: SyncSide::Source,
+						action: SyncAction::CreateDirectory,
+						path,
+					}
+				}));
+			plan.operations
+				.extend(source_only.into_iter().map(|path| SyncOperation {
+					side: SyncSide::Target,
+					action: SyncAction::Copy,
+					path: path.clone(),
+				}));
+			plan.operations
+				.extend(target_only.into_keys().map(|path| SyncOperation {
+					side: SyncSide::Source,
+					action: SyncAction::Copy,
+					path: path.clone(),
+				}));
+		}
+	}
+
+	plan
+}
+
+async fn create_parent_dir(path: &Path) -> Result<(), FileSystemJobsError> {
+	let parent = path.parent().ok_or_else(|| {
+		FileSystemJobsError::MissingParentPath(path.to_path_buf().into_boxed_path())
+	})?;
+
+	fs::create_dir_all(parent)
+		.await
+		.map_err(|e| FileIOError::from((parent, e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(cas_id: &str, size_in_bytes: u64, date_modified: i64) -> SyncEntry {
+		SyncEntry {
+			cas_id: Some(cas_id.to_string()),
+			size_in_bytes,
+			inode: None,
+			date_modified: DateTime::from_timestamp(date_modified, 0).map(Into::into),
+		}
+	}
+
+	fn root(entries: impl IntoIterator<Item = (&'static str, SyncEntry)>) -> SyncRoot {
+		SyncRoot {
+			full_path: PathBuf::new(),
+			entries: entries
+				.into_iter()
+				.map(|(path, entry)| (path.to_string(), entry))
+				.collect(),
+			directories: BTreeSet::new(),
+		}
+	}
+
+	fn with_directories(mut root: SyncRoot, directories: &[&str]) -> SyncRoot {
+		root.directories = directories.iter().map(ToString::to_string).collect();
+		root
+	}
+
+	fn summary(plan: &FolderSyncPlan) -> Vec<(SyncSide, SyncAction, &str)> {
+		plan.operations
+			.iter()
+			.map(|operation| {
+				(
+					operation.side,
+					operation.action.clone(),
+					operation.path.as_str(),
+				)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn mirror() {
+		let source = root([
+			("same.txt", entry("a", 1, 0)),
+			("changed.txt", entry("b", 2, 10)),
+			("new.txt", entry("c", 3, 0)),
+			("docs/moved.txt", entry("d", 4, 0)),
+		]);
+		let target = root([
+			("same.txt", entry("a", 1, 0)),
+			("changed.txt", entry("x", 2, 20)),
+			("moved.txt", entry("d", 4, 0)),
+			("extra.txt", entry("e", 5, 0)),
+		]);
+
+		let plan = plan_folder_sync(SyncMode::Mirror, SyncConflictPolicy::Skip, &source, &target);
+
+		assert_eq!(
+			summary(&plan),
+			vec![
+				(SyncSide::Target, SyncAction::Overwrite, "changed.txt"),
+				(
+					SyncSide::Target,
+					SyncAction::Rename {
+						from: "moved.txt".to_string()
+					},
+					"docs/moved.txt"
+				),
+				(SyncSide::Target, SyncAction::Copy, "new.txt"),
+				(SyncSide::Target, SyncAction::Delete, "extra.txt"),
+			]
+		);
+		assert!(plan.conflicts.is_empty());
+	}
+
+	#[test]
+	fn two_way() {
+		let source = root([
+			("newer_on_source.txt", entry("a", 1, 20)),
+			("newer_on_target.txt", entry("b", 2, 10)),
+			("same_time.txt", entry("c", 3, 10)),
+			("source_only.txt", entry("d", 4, 0)),
+		]);
+		let target = root([
+			("newer_on_source.txt", entry("x", 1, 10)),
+			("newer_on_target.txt", entry("y", 2, 20)),
+			("same_time.txt", entry("z", 3, 10)),
+			("target_only.txt", entry("e", 5, 0)),
+		]);
+
+		let plan = plan_folder_sync(
+			SyncMode::TwoWay,
+			SyncConflictPolicy::KeepNewest,
+			&source,
+			&target,
+		);
+
+		assert_eq!(
+			summary(&plan),
+			vec![
+				(
+					SyncSide::Target,
+					SyncAction::Overwrite,
+					"newer_on_source.txt"
+				),
+				(
+					SyncSide::Source,
+					SyncAction::Overwrite,
+					"newer_on_target.txt"
+				),
+				(SyncSide::Target, SyncAction::Copy, "source_only.txt"),
+				(SyncSide::Source, SyncAction::Copy, "target_only.txt"),
+			]
+		);
+		assert_eq!(plan.conflicts, vec!["same_time.txt".to_string()]);
+	}
+
+	#[test]
+	fn directories() {
+		let source = with_directories(
+			root([("docs/a.txt", entry("a", 1, 0))]),
+			&["docs", "empty", "empty/nested"],
+		);
+		let target = with_directories(root([]), &["old", "old/nested"]);
+
+		let plan = plan_folder_sync(SyncMode::Mirror, SyncConflictPolicy::Skip, &source, &target);
+
+		assert_eq!(
+			summary(&plan),
+			vec![
+				(SyncSide::Target, SyncAction::CreateDirectory, "docs"),
+				(SyncSide::Target, SyncAction::CreateDirectory, "empty"),
+				(
+					SyncSide::Target,
+					SyncAction::CreateDirectory,
+					"empty/nested"
+				),
+				(SyncSide::Target, SyncAction::Copy, "docs/a.txt"),
+				(SyncSide::Target, SyncAction::DeleteDirectory, "old/nested"),
+				(SyncSide::Target, SyncAction::DeleteDirectory, "old"),
+			]
+		);
+
+		let plan = plan_folder_sync(SyncMode::TwoWay, SyncConflictPolicy::Skip, &source, &target);
+
+		assert_eq!(
+			summary(&plan),
+			vec![
+				(SyncSide::Target, SyncAction::CreateDirectory, "docs"),
+				(SyncSide::Target, SyncAction::CreateDirectory, "empty"),
+				(
+					SyncSide::Target,
+					SyncAction::CreateDirectory,
+					"empty/nested"
+				),
+				(SyncSide::Source, SyncAction::CreateDirectory, "old"),
+				(SyncSide::Source, SyncAction::CreateDirectory, "old/nested"),
+				(SyncSide::Target, SyncAction::Copy, "docs/a.txt"),
+			]
+		);
+	}
+}
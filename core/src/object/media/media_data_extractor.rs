@@ -1,12 +1,19 @@
 use crate::old_job::JobRunErrors;
 
-use sd_file_ext::extensions::{Extension, ImageExtension, ALL_IMAGE_EXTENSIONS};
+use sd_file_ext::extensions::{
+	AudioExtension, Extension, ImageExtension, ALL_AUDIO_EXTENSIONS, ALL_IMAGE_EXTENSIONS,
+};
 use sd_file_path_helper::{file_path_for_media_processor, IsolatedFilePathData};
-use sd_media_metadata::ImageMetadata;
+use sd_media_metadata::{AudioMetadata, ImageMetadata, MediaMetadata};
 use sd_prisma::prisma::{location, media_data, PrismaClient};
 use sd_utils::error::FileIOError;
 
-use std::{collections::HashSet, path::Path};
+#[cfg(feature = "ffmpeg")]
+use sd_file_ext::extensions::{VideoExtension, ALL_VIDEO_EXTENSIONS};
+#[cfg(feature = "ffmpeg")]
+use sd_media_metadata::VideoMetadata;
+
+use std::{collections::HashSet, path::Path, str::FromStr};
 
 use futures_concurrency::future::Join;
 use once_cell::sync::Lazy;
@@ -15,7 +22,7 @@ use thiserror::Error;
 use tokio::task::spawn_blocking;
 use tracing::error;
 
-use super::media_data_image_to_query;
+use super::media_data_to_query;
 
 #[derive(Error, Debug)]
 pub enum MediaDataError {
@@ -45,6 +52,42 @@ pub(super) static FILTERED_IMAGE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(||
 		.collect()
 });
 
+pub(super) static FILTERED_AUDIO_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
+	ALL_AUDIO_EXTENSIONS
+		.iter()
+		.cloned()
+		.filter(can_extract_media_data_for_audio)
+		.map(Extension::Audio)
+		.collect()
+});
+
+#[cfg(feature = "ffmpeg")]
+pub(super) static FILTERED_VIDEO_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
+	ALL_VIDEO_EXTENSIONS
+		.iter()
+		.cloned()
+		.filter(can_extract_media_data_for_video)
+		.map(Extension::Video)
+		.collect()
+});
+
+pub(super) static FILTERED_MEDIA_DATA_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
+	#[cfg(feature = "ffmpeg")]
+	return FILTERED_IMAGE_EXTENSIONS
+		.iter()
+		.chain(FILTERED_AUDIO_EXTENSIONS.iter())
+		.chain(FILTERED_VIDEO_EXTENSIONS.iter())
+		.cloned()
+		.collect();
+
+	#[cfg(not(feature = "ffmpeg"))]
+	FILTERED_IMAGE_EXTENSIONS
+		.iter()
+		.chain(FILTERED_AUDIO_EXTENSIONS.iter())
+		.cloned()
+		.collect()
+});
+
 pub const fn can_extract_media_data_for_image(image_extension: &ImageExtension) -> bool {
 	use ImageExtension::*;
 	matches!(
@@ -53,6 +96,21 @@ pub const fn can_extract_media_data_for_image(image_extension: &ImageExtension)
 	)
 }
 
+pub const fn can_extract_media_data_for_audio(audio_extension: &AudioExtension) -> bool {
+	use AudioExtension::*;
+	matches!(
+		audio_extension,
+		Mp3 | Mp2 | M4a | Wav | Aiff | Aif | Flac | Ogg | Oga | Opus | Aac | Adts
+	)
+}
+
+#[cfg(feature = "ffmpeg")]
+pub const fn can_extract_media_data_for_video(video_extension: &VideoExtension) -> bool {
+	use VideoExtension::*;
+	// Raw streams without a container have no metadata worth reading
+	!matches!(video_extension, Swf | Mjpeg | M2v | Hevc)
+}
+
 pub async fn extract_media_data(path: impl AsRef<Path>) -> Result<ImageMetadata, MediaDataError> {
 	let path = path.as_ref().to_path_buf();
 
@@ -62,6 +120,74 @@ pub async fn extract_media_data(path: impl AsRef<Path>) -> Result<ImageMetadata,
 		.map_err(Into::into)
 }
 
+pub async fn extract_audio_media_data(
+	path: impl AsRef<Path>,
+) -> Result<AudioMetadata, MediaDataError> {
+	let path = path.as_ref().to_path_buf();
+
+	// Running in a separated blocking thread as symphonia does sync file reads
+	spawn_blocking(|| AudioMetadata::from_path(path))
+		.await?
+		.map_err(Into::into)
+}
+
+#[cfg(feature = "ffmpeg")]
+pub async fn extract_video_media_data(
+	path: impl AsRef<Path>,
+) -> Result<VideoMetadata, MediaDataError> {
+	let path = path.as_ref().to_path_buf();
+
+	// Running in a separated blocking thread as FFmpeg probing does sync file reads
+	spawn_blocking(|| VideoMetadata::from_path(path))
+		.await?
+		.map_err(Into::into)
+}
+
+/// Extracts media data from a file picking the right extractor from its extension,
+/// returns `Ok(None)` if we don't support media data for this kind of file
+pub async fn extract_media_data_by_extension(
+	path: impl AsRef<Path>,
+	extension: &str,
+) -> Result<Option<MediaMetadata>, MediaDataError> {
+	if let Ok(image_extension) = ImageExtension::from_str(extension) {
+		if can_extract_media_data_for_image(&image_extension) {
+			return extract_media_data(path)
+				.await
+				.map(|mdi| Some(MediaMetadata::Image(Box::new(mdi))));
+		}
+	}
+
+	if let Ok(audio_extension) = AudioExtension::from_str(extension) {
+		if can_extract_media_data_for_audio(&audio_extension) {
+			return extract_audio_media_data(path)
+				.await
+				.map(|mda| Some(MediaMetadata::Audio(Box::new(mda))));
+		}
+	}
+
+	#[cfg(feature = "ffmpeg")]
+	if let Ok(video_extension) = VideoExtension::from_str(extension) {
+		if can_extract_media_data_for_video(&video_extension) {
+			return extract_video_media_data(path)
+				.await
+				.map(|mdv| Some(MediaMetadata::Video(Box::new(mdv))));
+		}
+	}
+
+	Ok(None)
+}
+
+/// Errors that only mean the file has no media data for us, instead of a real failure
+pub const fn is_missing_media_data_error(e: &MediaDataError) -> bool {
+	matches!(
+		e,
+		MediaDataError::MediaData(
+			sd_media_metadata::Error::NoExifDataOnPath(_)
+				| sd_media_metadata::Error::NoAudioTrack(_)
+		)
+	)
+}
+
 pub async fn process(
 	files_paths: &[file_path_for_media_processor::Data],
 	location_id: location::id::Type,
@@ -115,10 +241,17 @@ pub async fn process(
 				IsolatedFilePathData::try_from((location_id, file_path))
 					.map_err(|e| error!("{e:#?}"))
 					.ok()
-					.map(|iso_file_path| (idx, location_path.join(iso_file_path), object_id))
+					.map(|iso_file_path| {
+						(
+							idx,
+							location_path.join(iso_file_path),
+							file_path.extension.as_deref().unwrap_or_default(),
+							object_id,
+						)
+					})
 			})
-			.map(|(idx, path, object_id)| async move {
-				let res = extract_media_data(&path).await;
+			.map(|(idx, path, extension, object_id)| async move {
+				let res = extract_media_data_by_extension(&path, extension).await;
 				ctx_update_fn(idx + 1);
 				(res, path, object_id)
 			})
@@ -133,11 +266,13 @@ pub async fn process(
 			(Vec::with_capacity(total_media_data), Vec::new()),
 			|(mut media_datas, mut errors), (maybe_media_data, path, object_id)| {
 				match maybe_media_data {
-					Ok(media_data) => media_datas.push((media_data, object_id)),
-					Err(MediaDataError::MediaData(sd_media_metadata::Error::NoExifDataOnPath(
-						_,
-					))) => {
-						// No exif data on path, skipping
+					Ok(Some(media_data)) => media_datas.push((media_data, object_id)),
+					Ok(None) => {
+						// Not a kind of file we extract media data from, skipping
+						run_metadata.skipped += 1;
+					}
+					Err(e) if is_missing_media_data_error(&e) => {
+						// No exif data or audio track on path, skipping
 						run_metadata.skipped += 1;
 					}
 					Err(e) => errors.push((e, path)),
@@ -153,7 +288,7 @@ pub async fn process(
 			media_datas
 				.into_iter()
 				.filter_map(|(media_data, object_id)| {
-					media_data_image_to_query(media_data, object_id)
+					media_data_to_query(media_data, object_id)
 						.map_err(|e| error!("{e:#?}"))
 						.ok()
 				})
@@ -3,11 +3,32 @@ pub mod old_media_processor;
 pub mod old_thumbnail;
 
 pub use old_media_processor::OldMediaProcessorJobInit;
-use sd_media_metadata::ImageMetadata;
+use sd_media_metadata::{AudioMetadata, ImageMetadata, MediaMetadata, VideoMetadata};
 use sd_prisma::prisma::media_data::*;
 
 use self::media_data_extractor::MediaDataError;
 
+pub fn media_data_to_query(
+	media_data: MediaMetadata,
+	object_id: object_id::Type,
+) -> Result<CreateUnchecked, MediaDataError> {
+	match media_data {
+		MediaMetadata::Image(mdi) => media_data_image_to_query(*mdi, object_id),
+		MediaMetadata::Audio(mda) => media_data_audio_to_query(*mda, object_id),
+		MediaMetadata::Video(mdv) => media_data_video_to_query(*mdv, object_id),
+	}
+}
+
+pub fn media_data_to_query_params(
+	media_data: MediaMetadata,
+) -> (Vec<(&'static str, rmpv::Value)>, Vec<SetParam>) {
+	match media_data {
+		MediaMetadata::Image(mdi) => media_data_image_to_query_params(*mdi),
+		MediaMetadata::Audio(mda) => media_data_audio_to_query_params(*mda),
+		MediaMetadata::Video(mdv) => media_data_video_to_query_params(*mdv),
+	}
+}
+
 pub fn media_data_image_to_query(
 	mdi: ImageMetadata,
 	object_id: object_id::Type,
@@ -39,7 +60,12 @@ pub fn media_data_image_to_query_params(
 		[
 			option_sync_db_entry!(serde_json::to_vec(&mdi.camera_data).ok(), camera_data),
 			option_sync_db_entry!(serde_json::to_vec(&mdi.date_taken).ok(), media_date),
+			option_sync_db_entry!(serde_json::to_vec(&mdi.resolution).ok(), resolution),
 			option_sync_db_entry!(serde_json::to_vec(&mdi.location).ok(), media_location),
+			option_sync_db_entry!(
+				mdi.date_taken.as_ref().map(|date| date.unix_timestamp()),
+				epoch_time
+			),
 			option_sync_db_entry!(mdi.artist, artist),
 			option_sync_db_entry!(mdi.description, description),
 			option_sync_db_entry!(mdi.copyright, copyright),
@@ -65,6 +91,168 @@ pub fn media_data_image_from_prisma_data(
 	})
 }
 
+pub fn media_data_audio_to_query(
+	mda: AudioMetadata,
+	object_id: object_id::Type,
+) -> Result<CreateUnchecked, MediaDataError> {
+	Ok(CreateUnchecked {
+		object_id,
+		_params: vec![
+			duration::set(mda.duration),
+			audio_codec::set(mda.audio_codec),
+			bitrate::set(mda.bitrate),
+			sample_rate::set(mda.sample_rate),
+			channels::set(mda.channels),
+			bit_depth::set(mda.bit_depth),
+			title::set(mda.title),
+			artist::set(mda.artist),
+			album::set(mda.album),
+			album_artist::set(mda.album_artist),
+			genre::set(mda.genre),
+			track_number::set(mda.track_number),
+			year::set(mda.year),
+			has_cover_art::set(Some(mda.has_cover_art)),
+		],
+	})
+}
+
+pub fn media_data_audio_to_query_params(
+	mda: AudioMetadata,
+) -> (Vec<(&'static str, rmpv::Value)>, Vec<SetParam>) {
+	use sd_sync::{option_sync_db_entry, sync_db_entry};
+	use sd_utils::chain_optional_iter;
+
+	chain_optional_iter(
+		[sync_db_entry!(mda.has_cover_art, has_cover_art)],
+		[
+			option_sync_db_entry!(mda.duration, duration),
+			option_sync_db_entry!(mda.audio_codec, audio_codec),
+			option_sync_db_entry!(mda.bitrate, bitrate),
+			option_sync_db_entry!(mda.sample_rate, sample_rate),
+			option_sync_db_entry!(mda.channels, channels),
+			option_sync_db_entry!(mda.bit_depth, bit_depth),
+			option_sync_db_entry!(mda.title, title),
+			option_sync_db_entry!(mda.artist, artist),
+			option_sync_db_entry!(mda.album, album),
+			option_sync_db_entry!(mda.album_artist, album_artist),
+			option_sync_db_entry!(mda.genre, genre),
+			option_sync_db_entry!(mda.track_number, track_number),
+			option_sync_db_entry!(mda.year, year),
+		],
+	)
+	.into_iter()
+	.unzip()
+}
+
+#[must_use]
+pub fn media_data_audio_from_prisma_data(
+	data: sd_prisma::prisma::media_data::Data,
+) -> AudioMetadata {
+	AudioMetadata {
+		duration: data.duration,
+		audio_codec: data.audio_codec,
+		bitrate: data.bitrate,
+		sample_rate: data.sample_rate,
+		channels: data.channels,
+		bit_depth: data.bit_depth,
+		title: data.title,
+		artist: data.artist,
+		album: data.album,
+		album_artist: data.album_artist,
+		genre: data.genre,
+		track_number: data.track_number,
+		year: data.year,
+		has_cover_art: data.has_cover_art.unwrap_or_default(),
+	}
+}
+
+/// Files with only audio streams have no resolution, so it's left unset for resolution filters to
+/// skip them
+fn video_resolution(mdv: &VideoMetadata) -> Option<Vec<u8>> {
+	(mdv.video_streams > 0)
+		.then(|| serde_json::to_vec(&mdv.resolution).ok())
+		.flatten()
+}
+
+pub fn media_data_video_to_query(
+	mdv: VideoMetadata,
+	object_id: object_id::Type,
+) -> Result<CreateUnchecked, MediaDataError> {
+	Ok(CreateUnchecked {
+		object_id,
+		_params: vec![
+			resolution::set(video_resolution(&mdv)),
+			media_date::set(serde_json::to_vec(&mdv.date_created).ok()),
+			media_location::set(serde_json::to_vec(&mdv.location).ok()),
+			epoch_time::set(mdv.date_created.map(|x| x.unix_timestamp())),
+			duration::set(mdv.duration),
+			bitrate::set(mdv.bitrate),
+			fps::set(mdv.fps),
+			video_codec::set(mdv.video_codec),
+			audio_codec::set(mdv.audio_codec),
+			video_streams::set(Some(mdv.video_streams)),
+			audio_streams::set(Some(mdv.audio_streams)),
+			subtitle_streams::set(Some(mdv.subtitle_streams)),
+			rotation::set(mdv.rotation),
+			hdr_format::set(mdv.hdr_format.map(|hdr| hdr.as_str().to_string())),
+		],
+	})
+}
+
+pub fn media_data_video_to_query_params(
+	mdv: VideoMetadata,
+) -> (Vec<(&'static str, rmpv::Value)>, Vec<SetParam>) {
+	use sd_sync::{option_sync_db_entry, sync_db_entry};
+	use sd_utils::chain_optional_iter;
+
+	chain_optional_iter(
+		[
+			sync_db_entry!(mdv.video_streams, video_streams),
+			sync_db_entry!(mdv.audio_streams, audio_streams),
+			sync_db_entry!(mdv.subtitle_streams, subtitle_streams),
+		],
+		[
+			option_sync_db_entry!(video_resolution(&mdv), resolution),
+			option_sync_db_entry!(serde_json::to_vec(&mdv.date_created).ok(), media_date),
+			option_sync_db_entry!(serde_json::to_vec(&mdv.location).ok(), media_location),
+			option_sync_db_entry!(mdv.date_created.map(|x| x.unix_timestamp()), epoch_time),
+			option_sync_db_entry!(mdv.duration, duration),
+			option_sync_db_entry!(mdv.bitrate, bitrate),
+			option_sync_db_entry!(mdv.fps, fps),
+			option_sync_db_entry!(mdv.video_codec, video_codec),
+			option_sync_db_entry!(mdv.audio_codec, audio_codec),
+			option_sync_db_entry!(mdv.rotation, rotation),
+			option_sync_db_entry!(
+				mdv.hdr_format.map(|hdr| hdr.as_str().to_string()),
+				hdr_format
+			),
+		],
+	)
+	.into_iter()
+	.unzip()
+}
+
+#[must_use]
+pub fn media_data_video_from_prisma_data(
+	data: sd_prisma::prisma::media_data::Data,
+) -> VideoMetadata {
+	VideoMetadata {
+		duration: data.duration,
+		resolution: from_slice_option_to_option(data.resolution).unwrap_or_default(),
+		fps: data.fps,
+		bitrate: data.bitrate,
+		video_codec: data.video_codec,
+		audio_codec: data.audio_codec,
+		video_streams: data.video_streams.unwrap_or_default(),
+		audio_streams: data.audio_streams.unwrap_or_default(),
+		subtitle_streams: data.subtitle_streams.unwrap_or_default(),
+		rotation: data.rotation,
+		hdr_format: data.hdr_format.and_then(|hdr| hdr.parse().ok()),
+		date_created: from_slice_option_to_option(data.media_date),
+		location: from_slice_option_to_option(data.media_location),
+	}
+}
+
 #[must_use]
 fn from_slice_option_to_option<T: serde::Serialize + serde::de::DeserializeOwned>(
 	value: Option<Vec<u8>>,
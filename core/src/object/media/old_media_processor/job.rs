@@ -2,22 +2,26 @@ use crate::{
 	invalidate_query,
 	library::Library,
 	old_job::{
-		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobStepOutput,
-		StatefulJob, WorkerContext,
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunErrors,
+		JobStepOutput, StatefulJob, WorkerContext,
 	},
 	Node,
 };
 
 #[cfg(feature = "ai")]
-use crate::old_job::JobRunErrors;
+use crate::object::tag::rules::apply_label_tag_rules;
 
 use sd_file_ext::extensions::Extension;
 use sd_file_path_helper::{
 	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
 	file_path_for_media_processor, IsolatedFilePathData,
 };
-use sd_prisma::prisma::{location, PrismaClient};
-use sd_utils::db::maybe_missing;
+use sd_prisma::{
+	prisma::{location, object, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{db::maybe_missing, msgpack};
 
 #[cfg(feature = "ai")]
 use sd_ai::old_image_labeler::{BatchToken as ImageLabelerBatchToken, LabelerOutput};
@@ -26,6 +30,7 @@ use sd_ai::old_image_labeler::{BatchToken as ImageLabelerBatchToken, LabelerOutp
 use std::sync::Arc;
 
 use std::{
+	collections::HashMap,
 	hash::Hash,
 	path::{Path, PathBuf},
 	pin::pin,
@@ -43,7 +48,10 @@ use tracing::{debug, error, info, trace, warn};
 
 use super::{
 	media_data_extractor,
-	old_thumbnail::{self, GenerateThumbnailArgs},
+	old_thumbnail::{
+		self, get_indexed_thumbnail_path, perceptual_hash::thumbnail_perceptual_hash,
+		GenerateThumbnailArgs,
+	},
 	process, BatchToProcess, MediaProcessorError, OldMediaProcessorMetadata,
 };
 
@@ -83,6 +91,7 @@ pub struct OldMediaProcessorJobData {
 pub enum OldMediaProcessorJobStep {
 	ExtractMediaData(Vec<file_path_for_media_processor::Data>),
 	WaitThumbnails(usize),
+	ComputePerceptualHashes(Vec<file_path_for_media_processor::Data>),
 	#[cfg(feature = "ai")]
 	WaitLabels(usize),
 }
@@ -170,6 +179,10 @@ impl StatefulJob for OldMediaProcessorJobInit {
 
 		let file_paths = get_files_for_media_data_extraction(db, &iso_file_path).await?;
 
+		let file_paths_for_perceptual_hashing =
+			get_files_for_perceptual_hashing(db, &iso_file_path, self.regenerate_thumbnails)
+				.await?;
+
 		#[cfg(feature = "ai")]
 		let file_paths_for_labeling =
 			get_files_for_labeling(db, &iso_file_path, self.regenerate_labels).await?;
@@ -209,6 +222,14 @@ impl StatefulJob for OldMediaProcessorJobInit {
 				.into_iter()
 				.flatten(),
 			)
+			.chain(
+				file_paths_for_perceptual_hashing
+					.into_iter()
+					.chunks(BATCH_SIZE)
+					.into_iter()
+					.map(|chunk| chunk.collect::<Vec<_>>())
+					.map(OldMediaProcessorJobStep::ComputePerceptualHashes),
+			)
 			.chain(
 				[
 					#[cfg(feature = "ai")]
@@ -324,6 +345,21 @@ impl StatefulJob for OldMediaProcessorJobInit {
 				Ok(None.into())
 			}
 
+			OldMediaProcessorJobStep::ComputePerceptualHashes(file_paths) => {
+				ctx.progress(vec![
+					JobReportUpdate::Phase("perceptual_hashes".to_string()),
+					JobReportUpdate::Message(format!(
+						"Computing perceptual hashes for {} files",
+						file_paths.len()
+					)),
+				]);
+
+				compute_perceptual_hashes(file_paths, &ctx.library, &ctx.node)
+					.await
+					.map(Into::into)
+					.map_err(Into::into)
+			}
+
 			#[cfg(feature = "ai")]
 			OldMediaProcessorJobStep::WaitLabels(total_labels) => {
 				let Some(image_labeller) = ctx.node.old_image_labeller.as_ref() else {
@@ -357,6 +393,7 @@ impl StatefulJob for OldMediaProcessorJobInit {
 				});
 
 				let mut total_labeled = 0;
+				let mut labeled_file_path_ids = Vec::new();
 
 				let mut errors = Vec::new();
 
@@ -376,8 +413,12 @@ impl StatefulJob for OldMediaProcessorJobInit {
 						);
 
 						errors.push(e.to_string());
-					} else if has_new_labels {
-						// invalidate_query!(&ctx.library, "labels.count"); // TODO: This query doesn't exist on main yet
+					} else {
+						labeled_file_path_ids.push(file_path_id);
+
+						if has_new_labels {
+							// invalidate_query!(&ctx.library, "labels.count"); // TODO: This query doesn't exist on main yet
+						}
 					}
 				}
 
@@ -385,6 +426,11 @@ impl StatefulJob for OldMediaProcessorJobInit {
 				invalidate_query!(&ctx.library, "labels.getForObject");
 				invalidate_query!(&ctx.library, "labels.getWithObjects");
 
+				if let Err(e) = apply_label_tag_rules(&ctx.library, labeled_file_path_ids).await {
+					error!("Failed to apply tag rules to labeled objects: {e:#?}");
+					errors.push(e.to_string());
+				}
+
 				if !errors.is_empty() {
 					Ok(JobRunErrors(errors).into())
 				} else {
@@ -413,6 +459,10 @@ impl StatefulJob for OldMediaProcessorJobInit {
 			invalidate_query!(ctx.library, "search.paths");
 		}
 
+		if run_metadata.perceptual_hashes > 0 {
+			invalidate_query!(ctx.library, "search.similarObjects");
+		}
+
 		Ok(Some(json!({"init: ": self, "run_metadata": run_metadata})))
 	}
 }
@@ -493,6 +543,84 @@ async fn dispatch_thumbnails_for_processing(
 	Ok(thumbs_count as u32)
 }
 
+/// Hashes the thumbnails generated for these files, storing the hashes on their objects
+async fn compute_perceptual_hashes(
+	file_paths: &[file_path_for_media_processor::Data],
+	library: &Library,
+	node: &Node,
+) -> Result<(OldMediaProcessorMetadata, JobRunErrors), MediaProcessorError> {
+	let Library { db, sync, id, .. } = library;
+
+	let mut hashes = Vec::with_capacity(file_paths.len());
+	let mut errors = vec![];
+
+	for file_path in file_paths {
+		let (Some(cas_id), Some(object_id)) = (&file_path.cas_id, file_path.object_id) else {
+			continue;
+		};
+
+		// Thumbnail generation may have failed or been skipped for this file
+		if !matches!(library.thumbnail_exists(node, cas_id).await, Ok(true)) {
+			continue;
+		}
+
+		match thumbnail_perceptual_hash(get_indexed_thumbnail_path(node, cas_id, *id)).await {
+			Ok(hash) => hashes.push((object_id, hash)),
+			Err(e) => {
+				error!(
+					"Failed to compute perceptual hash <file_path_id='{}'>: {e:#?}",
+					file_path.id
+				);
+				errors.push(e.to_string());
+			}
+		}
+	}
+
+	let pub_ids = db
+		.object()
+		.find_many(vec![object::id::in_vec(
+			hashes.iter().map(|(object_id, _)| *object_id).collect(),
+		)])
+		.select(object::select!({ id pub_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|object| (object.id, object.pub_id))
+		.collect::<HashMap<_, _>>();
+
+	let (sync_params, db_params): (Vec<_>, Vec<_>) = hashes
+		.into_iter()
+		.filter_map(|(object_id, hash)| {
+			let pub_id = pub_ids.get(&object_id)?;
+			let hash = hash.to_be_bytes().to_vec();
+
+			Some((
+				sync.shared_update(
+					prisma_sync::object::SyncId {
+						pub_id: pub_id.clone(),
+					},
+					object::perceptual_hash::NAME,
+					msgpack!(&hash),
+				),
+				db.object().update(
+					object::id::equals(object_id),
+					vec![object::perceptual_hash::set(Some(hash))],
+				),
+			))
+		})
+		.unzip();
+
+	let perceptual_hashes = sync.write_ops(db, (sync_params, db_params)).await?.len() as u32;
+
+	Ok((
+		OldMediaProcessorMetadata {
+			perceptual_hashes,
+			..Default::default()
+		},
+		JobRunErrors(errors),
+	))
+}
+
 async fn get_files_for_media_data_extraction(
 	db: &PrismaClient,
 	parent_iso_file_path: &IsolatedFilePathData<'_>,
@@ -500,12 +628,61 @@ async fn get_files_for_media_data_extraction(
 	get_all_children_files_by_extensions(
 		db,
 		parent_iso_file_path,
-		&media_data_extractor::FILTERED_IMAGE_EXTENSIONS,
+		&media_data_extractor::FILTERED_MEDIA_DATA_EXTENSIONS,
 	)
 	.await
 	.map_err(Into::into)
 }
 
+async fn get_files_for_perceptual_hashing(
+	db: &PrismaClient,
+	parent_iso_file_path: &IsolatedFilePathData<'_>,
+	regenerate: bool,
+) -> Result<Vec<file_path_for_media_processor::Data>, MediaProcessorError> {
+	// FIXME: Had to use format! macro because PCR doesn't support IN with Vec for SQLite
+	// We have no data coming from the user, so this is sql injection safe
+	let file_paths: Vec<file_path_for_media_processor::Data> = db
+		._query_raw(raw!(
+			&format!(
+				"SELECT id, materialized_path, is_dir, name, extension, cas_id, object_id
+				FROM file_path f
+				WHERE
+					location_id={{}}
+					AND cas_id IS NOT NULL
+					AND object_id IS NOT NULL
+					AND LOWER(extension) IN ({})
+					AND materialized_path LIKE {{}}
+					{}
+				ORDER BY materialized_path ASC",
+				old_thumbnail::PERCEPTUAL_HASHABLE_EXTENSIONS
+					.iter()
+					.map(|ext| format!("LOWER('{ext}')"))
+					.collect::<Vec<_>>()
+					.join(","),
+				if !regenerate {
+					"AND NOT EXISTS (SELECT 1 FROM object WHERE id = f.object_id AND perceptual_hash IS NOT NULL)"
+				} else {
+					""
+				}
+			),
+			PrismaValue::Int(parent_iso_file_path.location_id() as i64),
+			PrismaValue::String(format!(
+				"{}%",
+				parent_iso_file_path
+					.materialized_path_for_children()
+					.expect("sub path iso_file_path must be a directory")
+			))
+		))
+		.exec()
+		.await?;
+
+	// Files with the same object share the same thumbnail, so we only need one of them
+	Ok(file_paths
+		.into_iter()
+		.unique_by(|file_path| file_path.object_id)
+		.collect())
+}
+
 #[cfg(feature = "ai")]
 async fn get_files_for_labeling(
 	db: &PrismaClient,
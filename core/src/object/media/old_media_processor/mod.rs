@@ -40,6 +40,7 @@ pub enum MediaProcessorError {
 pub struct OldMediaProcessorMetadata {
 	media_data: OldMediaDataExtractorMetadata,
 	thumbs_processed: u32,
+	perceptual_hashes: u32,
 	labels_extracted: u32,
 }
 
@@ -48,6 +49,7 @@ impl From<OldMediaDataExtractorMetadata> for OldMediaProcessorMetadata {
 		Self {
 			media_data,
 			thumbs_processed: 0,
+			perceptual_hashes: 0,
 			labels_extracted: 0,
 		}
 	}
@@ -58,6 +60,7 @@ impl JobRunMetadata for OldMediaProcessorMetadata {
 		self.media_data.extracted += new_data.media_data.extracted;
 		self.media_data.skipped += new_data.media_data.skipped;
 		self.thumbs_processed += new_data.thumbs_processed;
+		self.perceptual_hashes += new_data.perceptual_hashes;
 		self.labels_extracted += new_data.labels_extracted;
 	}
 }
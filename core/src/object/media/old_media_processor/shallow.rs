@@ -6,6 +6,9 @@ use crate::{
 	Node,
 };
 
+#[cfg(feature = "ai")]
+use crate::object::tag::rules::apply_label_tag_rules;
+
 use sd_file_ext::extensions::Extension;
 use sd_file_path_helper::{
 	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
@@ -147,9 +150,9 @@ pub async fn old_shallow(
 	{
 		if has_labels {
 			if let Some(labels_rx) = labels_rx {
-				labels_rx
+				let labeled_file_path_ids = labels_rx
 					.await
-					.for_each(
+					.filter_map(
 						|LabelerOutput {
 						     file_path_id,
 						     has_new_labels,
@@ -159,16 +162,25 @@ pub async fn old_shallow(
 								error!(
 								"Failed to generate labels <file_path_id='{file_path_id}'>: {e:#?}"
 							);
-							} else if has_new_labels {
-								// invalidate_query!(library, "labels.count"); // TODO: This query doesn't exist on main yet
+								None
+							} else {
+								if has_new_labels {
+									// invalidate_query!(library, "labels.count"); // TODO: This query doesn't exist on main yet
+								}
+								Some(file_path_id)
 							}
 						},
 					)
+					.collect::<Vec<_>>()
 					.await;
 
 				invalidate_query!(library, "labels.list");
 				invalidate_query!(library, "labels.getForObject");
 				invalidate_query!(library, "labels.getWithObjects");
+
+				if let Err(e) = apply_label_tag_rules(library, labeled_file_path_ids).await {
+					error!("Failed to apply tag rules to labeled objects: {e:#?}");
+				}
 			}
 		}
 	}
@@ -183,7 +195,7 @@ async fn get_files_for_media_data_extraction(
 	get_files_by_extensions(
 		db,
 		parent_iso_file_path,
-		&media_data_extractor::FILTERED_IMAGE_EXTENSIONS,
+		&media_data_extractor::FILTERED_MEDIA_DATA_EXTENSIONS,
 	)
 	.await
 	.map_err(Into::into)
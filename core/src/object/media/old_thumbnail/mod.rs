@@ -1,7 +1,8 @@
 use crate::{library::LibraryId, util::version_manager::VersionManagerError, Node};
 
 use sd_file_ext::extensions::{
-	DocumentExtension, Extension, ImageExtension, ALL_DOCUMENT_EXTENSIONS, ALL_IMAGE_EXTENSIONS,
+	AudioExtension, DocumentExtension, Extension, ImageExtension, ALL_AUDIO_EXTENSIONS,
+	ALL_DOCUMENT_EXTENSIONS, ALL_IMAGE_EXTENSIONS,
 };
 use sd_utils::error::FileIOError;
 
@@ -22,6 +23,7 @@ use tracing::error;
 mod clean_up;
 mod directory;
 pub mod old_actor;
+pub mod perceptual_hash;
 pub mod preferences;
 mod process;
 mod shard;
@@ -63,6 +65,10 @@ pub fn get_indexed_thumbnail_path(node: &Node, cas_id: &str, library_id: Library
 	get_thumbnail_path(node, cas_id, ThumbnailKind::Indexed(library_id))
 }
 
+pub fn get_ephemeral_thumbnail_path(node: &Node, cas_id: &str) -> PathBuf {
+	get_thumbnail_path(node, cas_id, ThumbnailKind::Ephemeral)
+}
+
 /// This does not check if a thumbnail exists, it just returns the path that it would exist at
 fn get_thumbnail_path(node: &Node, cas_id: &str, kind: ThumbnailKind) -> PathBuf {
 	let mut thumb_path = node.config.data_directory();
@@ -125,6 +131,13 @@ pub(super) static THUMBNAILABLE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(||
 				.filter(can_generate_thumbnail_for_document)
 				.map(Extension::Document),
 		)
+		.chain(
+			ALL_AUDIO_EXTENSIONS
+				.iter()
+				.cloned()
+				.filter(can_generate_thumbnail_for_audio)
+				.map(Extension::Audio),
+		)
 		.collect()
 });
 
@@ -140,6 +153,16 @@ pub(super) static ALL_THUMBNAILABLE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new
 	THUMBNAILABLE_EXTENSIONS.clone()
 });
 
+/// Document thumbnails and cover art aren't worth comparing visually, so only images and videos
+/// are hashed
+pub(super) static PERCEPTUAL_HASHABLE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
+	ALL_THUMBNAILABLE_EXTENSIONS
+		.iter()
+		.filter(|extension| !matches!(extension, Extension::Document(_) | Extension::Audio(_)))
+		.cloned()
+		.collect()
+});
+
 #[derive(Error, Debug)]
 pub enum ThumbnailerError {
 	// Internal errors
@@ -151,11 +174,17 @@ pub enum ThumbnailerError {
 	VersionManager(#[from] VersionManagerError<ThumbnailVersion>),
 	#[error("failed to encode webp")]
 	WebPEncoding { path: Box<Path>, reason: String },
+	#[error("failed to decode webp thumbnail: {}", .0.display())]
+	WebPDecoding(Box<Path>),
 	#[error("error while converting the image")]
 	SdImages {
 		path: Box<Path>,
 		error: sd_images::Error,
 	},
+	#[error("failed to read cover art: {0}")]
+	CoverArt(#[from] sd_media_metadata::Error),
+	#[error("failed to decode cover art of {}: {reason}", .path.display())]
+	CoverArtDecoding { path: Box<Path>, reason: String },
 	#[error("failed to execute converting task: {0}")]
 	Task(#[from] task::JoinError),
 	#[cfg(feature = "ffmpeg")]
@@ -194,6 +223,16 @@ pub const fn can_generate_thumbnail_for_image(image_extension: &ImageExtension)
 	)
 }
 
+/// Formats whose tags can embed cover art, which is used as their thumbnail
+pub const fn can_generate_thumbnail_for_audio(audio_extension: &AudioExtension) -> bool {
+	use AudioExtension::*;
+
+	matches!(
+		audio_extension,
+		Mp3 | M4a | Aiff | Aif | Flac | Ogg | Oga | Opus
+	)
+}
+
 pub const fn can_generate_thumbnail_for_document(document_extension: &DocumentExtension) -> bool {
 	use DocumentExtension::*;
 
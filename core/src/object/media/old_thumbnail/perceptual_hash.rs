@@ -0,0 +1,215 @@
+use sd_utils::error::FileIOError;
+
+use std::{collections::HashMap, path::Path};
+
+use image::{imageops::FilterType, DynamicImage};
+use tokio::{fs, task::spawn_blocking};
+use webp::Decoder;
+
+use super::ThumbnailerError;
+
+/// Default Hamming distance for two images to be considered similar, out of the 64 hash bits
+pub const DEFAULT_MAX_DISTANCE: u32 = 10;
+
+/// Computes the difference hash (dHash) of an image, where each bit tells if a pixel is brighter
+/// than its right neighbour on a 9x8 grayscale version of the image. Resized, re-encoded and
+/// slightly edited copies of an image end up with hashes only a few bits apart.
+pub fn dhash(img: &DynamicImage) -> u64 {
+	let img = img.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+
+	let mut hash = 0;
+	for y in 0..8 {
+		for x in 0..8 {
+			hash <<= 1;
+			if img.get_pixel(x, y)[0] > img.get_pixel(x + 1, y)[0] {
+				hash |= 1;
+			}
+		}
+	}
+
+	hash
+}
+
+/// Hashes an already generated thumbnail, which works the same way for images and for video
+/// keyframes, and is way cheaper than decoding the original files again
+pub async fn thumbnail_perceptual_hash(
+	thumbnail_path: impl AsRef<Path>,
+) -> Result<u64, ThumbnailerError> {
+	let thumbnail_path = thumbnail_path.as_ref().to_path_buf();
+
+	let webp = fs::read(&thumbnail_path)
+		.await
+		.map_err(|e| FileIOError::from((&thumbnail_path, e)))?;
+
+	spawn_blocking(move || {
+		Decoder::new(&webp)
+			.decode()
+			.map(|img| dhash(&img.to_image()))
+			.ok_or_else(|| ThumbnailerError::WebPDecoding(thumbnail_path.into_boxed_path()))
+	})
+	.await?
+}
+
+pub const fn hamming_distance(hash1: u64, hash2: u64) -> u32 {
+	(hash1 ^ hash2).count_ones()
+}
+
+/// Groups items whose hashes are at most `max_distance` bits apart, directly or through other
+/// items, returning only groups with more than one item, biggest groups first
+pub fn cluster_similar<T: Copy + Ord>(hashes: &[(T, u64)], max_distance: u32) -> Vec<Vec<T>> {
+	let mut tree = BkTree::default();
+	let mut parents = (0..hashes.len()).collect::<Vec<_>>();
+
+	for (idx, (_, hash)) in hashes.iter().enumerate() {
+		for neighbour_idx in tree.find(*hash, max_distance) {
+			let (root, neighbour_root) = (
+				find_root(&mut parents, idx),
+				find_root(&mut parents, neighbour_idx),
+			);
+			parents[neighbour_root] = root;
+		}
+
+		tree.insert(*hash, idx);
+	}
+
+	let mut clusters = HashMap::<_, Vec<_>>::new();
+	for (idx, (item, _)) in hashes.iter().enumerate() {
+		clusters
+			.entry(find_root(&mut parents, idx))
+			.or_default()
+			.push(*item);
+	}
+
+	let mut clusters = clusters
+		.into_values()
+		.filter(|cluster| cluster.len() > 1)
+		.map(|mut cluster| {
+			cluster.sort_unstable();
+			cluster
+		})
+		.collect::<Vec<_>>();
+
+	clusters.sort_unstable_by(|one, two| two.len().cmp(&one.len()).then_with(|| one.cmp(two)));
+
+	clusters
+}
+
+fn find_root(parents: &mut [usize], mut idx: usize) -> usize {
+	while parents[idx] != idx {
+		parents[idx] = parents[parents[idx]];
+		idx = parents[idx];
+	}
+
+	idx
+}
+
+/// A Burkhard-Keller tree, which lets us find hashes within a Hamming distance without comparing
+/// against every other hash
+#[derive(Default)]
+struct BkTree {
+	nodes: Vec<BkNode>,
+}
+
+struct BkNode {
+	hash: u64,
+	idx: usize,
+	children: HashMap<u32, usize>,
+}
+
+impl BkTree {
+	fn insert(&mut self, hash: u64, idx: usize) {
+		let new_node = BkNode {
+			hash,
+			idx,
+			children: HashMap::new(),
+		};
+
+		if self.nodes.is_empty() {
+			self.nodes.push(new_node);
+			return;
+		}
+
+		let new_node_idx = self.nodes.len();
+		let mut current = 0;
+
+		loop {
+			let distance = hamming_distance(self.nodes[current].hash, hash);
+			if let Some(&child) = self.nodes[current].children.get(&distance) {
+				current = child;
+			} else {
+				self.nodes[current].children.insert(distance, new_node_idx);
+				break;
+			}
+		}
+
+		self.nodes.push(new_node);
+	}
+
+	fn find(&self, hash: u64, max_distance: u32) -> Vec<usize> {
+		let mut found = vec![];
+
+		if self.nodes.is_empty() {
+			return found;
+		}
+
+		let mut to_visit = vec![0];
+		while let Some(current) = to_visit.pop() {
+			let node = &self.nodes[current];
+			let distance = hamming_distance(node.hash, hash);
+
+			if distance <= max_distance {
+				found.push(node.idx);
+			}
+
+			to_visit.extend(
+				node.children
+					.iter()
+					.filter(|(child_distance, _)| {
+						distance.abs_diff(**child_distance) <= max_distance
+					})
+					.map(|(_, child)| *child),
+			);
+		}
+
+		found
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use image::{Rgb, RgbImage};
+
+	fn gradient(width: u32, height: u32) -> DynamicImage {
+		DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+			let value = ((x * 255 / width) ^ (y * 255 / height)) as u8;
+			Rgb([value, value / 2, 255 - value])
+		}))
+	}
+
+	#[test]
+	fn resized_images_are_similar() {
+		let original = dhash(&gradient(640, 480));
+		let resized = dhash(&gradient(320, 240));
+		let other = dhash(&gradient(480, 640).rotate90());
+
+		assert!(hamming_distance(original, resized) <= DEFAULT_MAX_DISTANCE);
+		assert!(hamming_distance(original, other) > DEFAULT_MAX_DISTANCE);
+	}
+
+	#[test]
+	fn clusters() {
+		let hashes = [
+			(1, 0b0000_0000),
+			(2, 0b0000_0011),
+			(3, 0b0000_1111),
+			(4, u64::MAX),
+			(5, u64::MAX ^ 1),
+			(6, 0xF0F0_F0F0_0000_0000),
+		];
+
+		assert_eq!(cluster_similar(&hashes, 2), vec![vec![1, 2, 3], vec![4, 5]]);
+		assert_eq!(cluster_similar(&hashes, 0), Vec::<Vec<i32>>::new());
+	}
+}
@@ -1,8 +1,8 @@
 use crate::api::CoreEvent;
 
-use sd_file_ext::extensions::{DocumentExtension, ImageExtension};
+use sd_file_ext::extensions::{AudioExtension, DocumentExtension, ImageExtension};
 use sd_images::{format_image, scale_dimensions, ConvertibleExtension};
-use sd_media_metadata::image::Orientation;
+use sd_media_metadata::{image::Orientation, AudioMetadata};
 use sd_prisma::prisma::location;
 use sd_utils::error::FileIOError;
 
@@ -30,9 +30,10 @@ use tracing::{debug, error, trace, warn};
 use webp::Encoder;
 
 use super::{
-	can_generate_thumbnail_for_document, can_generate_thumbnail_for_image, get_thumb_key,
-	preferences::ThumbnailerPreferences, shard::get_shard_hex, ThumbnailKind, ThumbnailerError,
-	EPHEMERAL_DIR, TARGET_PX, TARGET_QUALITY, THIRTY_SECS, WEBP_EXTENSION,
+	can_generate_thumbnail_for_audio, can_generate_thumbnail_for_document,
+	can_generate_thumbnail_for_image, get_thumb_key, preferences::ThumbnailerPreferences,
+	shard::get_shard_hex, ThumbnailKind, ThumbnailerError, EPHEMERAL_DIR, TARGET_PX,
+	TARGET_QUALITY, THIRTY_SECS, WEBP_EXTENSION,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -360,6 +361,13 @@ pub(super) async fn generate_thumbnail(
 		if can_generate_thumbnail_for_document(&extension) {
 			generate_image_thumbnail(&path, &output_path).await?;
 		}
+	} else if let Ok(extension) = AudioExtension::from_str(extension) {
+		if can_generate_thumbnail_for_audio(&extension)
+			&& !generate_cover_art_thumbnail(&path, &output_path).await?
+		{
+			trace!("No cover art to use as thumbnail for {}", path.display());
+			return Ok(cas_id);
+		}
 	}
 
 	#[cfg(feature = "ffmpeg")]
@@ -398,23 +406,12 @@ async fn generate_image_thumbnail(
 	let file_path = file_path.as_ref().to_path_buf();
 
 	let webp = spawn_blocking(move || -> Result<_, ThumbnailerError> {
-		let mut img = format_image(&file_path).map_err(|e| ThumbnailerError::SdImages {
-			path: file_path.clone().into_boxed_path(),
-			error: e,
-		})?;
-
-		let (w, h) = img.dimensions();
-		let (w_scaled, h_scaled) = scale_dimensions(w as f32, h as f32, TARGET_PX);
-
-		// Optionally, resize the existing photo and convert back into DynamicImage
-		if w != w_scaled && h != h_scaled {
-			img = DynamicImage::ImageRgba8(imageops::resize(
-				&img,
-				w_scaled,
-				h_scaled,
-				imageops::FilterType::Triangle,
-			));
-		}
+		let mut img = resize_for_thumbnail(format_image(&file_path).map_err(|e| {
+			ThumbnailerError::SdImages {
+				path: file_path.clone().into_boxed_path(),
+				error: e,
+			}
+		})?);
 
 		// this corrects the rotation/flip of the image based on the *available* exif data
 		// not all images have exif data, so we don't error. we also don't rotate HEIF as that's against the spec
@@ -427,22 +424,76 @@ async fn generate_image_thumbnail(
 			}
 		}
 
-		// Create the WebP encoder for the above image
-		let encoder =
-			Encoder::from_image(&img).map_err(|reason| ThumbnailerError::WebPEncoding {
-				path: file_path.into_boxed_path(),
-				reason: reason.to_string(),
-			})?;
-
-		// Type WebPMemory is !Send, which makes the Future in this function !Send,
-		// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
-		// which implies on a unwanted clone...
-		Ok(encoder.encode(TARGET_QUALITY).deref().to_owned())
+		encode_thumbnail(&img, file_path)
 	})
 	.await??;
 
-	let output_path = output_path.as_ref();
+	write_thumbnail(output_path.as_ref(), &webp).await
+}
+
+/// Uses the cover art embedded in an audio file as its thumbnail, returning `false` if it has none
+async fn generate_cover_art_thumbnail(
+	file_path: impl AsRef<Path>,
+	output_path: impl AsRef<Path>,
+) -> Result<bool, ThumbnailerError> {
+	let file_path = file_path.as_ref().to_path_buf();
+
+	let webp = spawn_blocking(move || -> Result<_, ThumbnailerError> {
+		let Some(cover_art) = AudioMetadata::cover_art_from_path(&file_path)? else {
+			return Ok(None);
+		};
+
+		let img = image::load_from_memory(&cover_art.data).map_err(|e| {
+			ThumbnailerError::CoverArtDecoding {
+				path: file_path.clone().into_boxed_path(),
+				reason: e.to_string(),
+			}
+		})?;
+
+		encode_thumbnail(&resize_for_thumbnail(img), file_path).map(Some)
+	})
+	.await??;
+
+	let Some(webp) = webp else {
+		return Ok(false);
+	};
+
+	write_thumbnail(output_path.as_ref(), &webp)
+		.await
+		.map(|()| true)
+}
+
+fn resize_for_thumbnail(img: DynamicImage) -> DynamicImage {
+	let (w, h) = img.dimensions();
+	let (w_scaled, h_scaled) = scale_dimensions(w as f32, h as f32, TARGET_PX);
+
+	// Optionally, resize the existing photo and convert back into DynamicImage
+	if w != w_scaled && h != h_scaled {
+		DynamicImage::ImageRgba8(imageops::resize(
+			&img,
+			w_scaled,
+			h_scaled,
+			imageops::FilterType::Triangle,
+		))
+	} else {
+		img
+	}
+}
+
+fn encode_thumbnail(img: &DynamicImage, file_path: PathBuf) -> Result<Vec<u8>, ThumbnailerError> {
+	// Create the WebP encoder for the above image
+	let encoder = Encoder::from_image(img).map_err(|reason| ThumbnailerError::WebPEncoding {
+		path: file_path.into_boxed_path(),
+		reason: reason.to_string(),
+	})?;
+
+	// Type WebPMemory is !Send, which makes the Future in this function !Send,
+	// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
+	// which implies on a unwanted clone...
+	Ok(encoder.encode(TARGET_QUALITY).deref().to_owned())
+}
 
+async fn write_thumbnail(output_path: &Path, webp: &[u8]) -> Result<(), ThumbnailerError> {
 	if let Some(shard_dir) = output_path.parent() {
 		fs::create_dir_all(shard_dir)
 			.await
@@ -454,7 +505,7 @@ async fn generate_image_thumbnail(
 		);
 	}
 
-	fs::write(output_path, &webp)
+	fs::write(output_path, webp)
 		.await
 		.map_err(|e| FileIOError::from((output_path, e)))
 		.map_err(Into::into)
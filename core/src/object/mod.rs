@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 
 pub mod cas;
+pub mod content_index;
 pub mod fs;
 pub mod media;
 pub mod old_file_identifier;
@@ -18,7 +19,7 @@ pub mod validation;
 // Object selectables!
 object::select!(object_for_file_identifier {
 	pub_id
-	file_paths: select { pub_id cas_id extension is_dir materialized_path name }
+	file_paths: select { pub_id cas_id extension is_dir materialized_path name integrity_checksum location: select { id path instance_id } }
 });
 
 // The response to provide the Explorer when looking at Objects
@@ -1,6 +1,9 @@
 use crate::{
 	library::Library,
-	object::{cas::generate_cas_id, object_for_file_identifier},
+	object::{
+		cas::generate_cas_id, content_index, object_for_file_identifier,
+		tag::rules::apply_tag_rules, validation::hash::file_checksum,
+	},
 	old_job::JobError,
 };
 
@@ -14,14 +17,15 @@ use sd_sync::{CRDTOperation, OperationFactory};
 use sd_utils::{db::maybe_missing, error::FileIOError, msgpack, uuid_to_bytes};
 
 use std::{
+	borrow::Cow,
 	collections::{HashMap, HashSet},
 	fmt::Debug,
-	path::Path,
+	path::{Path, PathBuf},
 };
 
 use futures::future::join_all;
 use tokio::fs;
-use tracing::{error, trace};
+use tracing::{error, trace, warn};
 use uuid::Uuid;
 
 pub mod old_file_identifier_job;
@@ -95,10 +99,12 @@ impl FileMetadata {
 }
 
 async fn identifier_job_step(
-	Library { db, sync, .. }: &Library,
+	library: &Library,
 	location: &location::Data,
 	file_paths: &[file_path_for_file_identifier::Data],
-) -> Result<(usize, usize), JobError> {
+	verify_duplicates: bool,
+) -> Result<(usize, usize, usize), JobError> {
+	let Library { db, sync, .. } = library;
 	let location_path = maybe_missing(&location.path, "location.path").map(Path::new)?;
 
 	let file_paths_metadatas = join_all(
@@ -186,44 +192,43 @@ async fn identifier_job_step(
 		.exec()
 		.await?;
 
-	let existing_object_cas_ids = existing_objects
-		.iter()
-		.flat_map(|object| {
-			object
-				.file_paths
-				.iter()
-				.filter_map(|file_path| file_path.cas_id.as_ref())
-		})
-		.collect::<HashSet<_>>();
-
 	// Attempt to associate each file path with an object that has been
 	// connected to file paths with the same cas_id
-	let updated_file_paths = sync
-		.write_ops(
+	let (matched_objects, total_collisions) = if verify_duplicates {
+		verified_object_matches(
 			db,
+			sync,
+			location,
+			location_path,
+			&file_paths_metadatas,
+			&existing_objects,
+		)
+		.await?
+	} else {
+		(
 			file_paths_metadatas
 				.iter()
-				.filter_map(|(pub_id, (metadata, file_path))| {
+				.filter_map(|(pub_id, (metadata, _))| {
 					// Filtering out files without cas_id due to being empty
-					metadata
-						.cas_id
-						.is_some()
-						.then_some((pub_id, (metadata, file_path)))
-				})
-				.flat_map(|(pub_id, (metadata, _))| {
-					existing_objects
-						.iter()
-						.find(|object| {
-							object
-								.file_paths
-								.iter()
-								.any(|file_path| file_path.cas_id == metadata.cas_id)
-						})
-						.map(|object| (*pub_id, object))
+					metadata.cas_id.as_ref().and_then(|cas_id| {
+						objects_with_cas_id(&existing_objects, cas_id)
+							.next()
+							.map(|object| (*pub_id, object))
+					})
 				})
+				.collect::<HashMap<_, _>>(),
+			0,
+		)
+	};
+
+	let updated_file_paths = sync
+		.write_ops(
+			db,
+			matched_objects
+				.iter()
 				.map(|(pub_id, object)| {
 					let (crdt_op, db_op) = connect_file_path_to_object(
-						pub_id,
+						*pub_id,
 						// SAFETY: This pub_id is generated by the uuid lib, but we have to store bytes in sqlite
 						Uuid::from_slice(&object.pub_id).expect("uuid bytes are invalid"),
 						sync,
@@ -241,15 +246,27 @@ async fn identifier_job_step(
 		existing_objects.len()
 	);
 
+	// Text files and documents get their contents indexed for full-text search once they're linked
+	// to objects, which we only know at the end of this step
+	let files_to_index = file_paths_metadatas
+		.iter()
+		.filter(|(_, (metadata, _))| content_index::is_indexable(metadata.kind))
+		.filter_map(|(pub_id, (_, file_path))| {
+			IsolatedFilePathData::try_from((location.id, *file_path))
+				.map(|iso_file_path| (uuid_to_bytes(*pub_id), location_path.join(iso_file_path)))
+				.ok()
+		})
+		.collect::<HashMap<_, _>>();
+
 	// extract objects that don't already exist in the database
 	let file_paths_requiring_new_object = file_paths_metadatas
 		.into_iter()
-		.filter(|(_, (FileMetadata { cas_id, .. }, _))| {
-			cas_id
-				.as_ref()
-				.map(|cas_id| !existing_object_cas_ids.contains(cas_id))
-				.unwrap_or(true)
-		})
+		.filter(|(pub_id, _)| !matched_objects.contains_key(pub_id))
+		.collect::<Vec<_>>();
+
+	let file_paths_with_new_objects = file_paths_requiring_new_object
+		.iter()
+		.map(|(_, (_, file_path))| file_path.id)
 		.collect::<Vec<_>>();
 
 	let total_created = if !file_paths_requiring_new_object.is_empty() {
@@ -345,7 +362,237 @@ async fn identifier_job_step(
 		0
 	};
 
-	Ok((total_created, updated_file_paths.len()))
+	index_contents(db, files_to_index).await;
+
+	// Failing to apply tag rules shouldn't fail the identification either
+	if total_created > 0 {
+		if let Err(e) = apply_tag_rules(library, file_paths_with_new_objects).await {
+			error!("Failed to apply tag rules to new objects: {e:#?}");
+		}
+	}
+
+	Ok((total_created, updated_file_paths.len(), total_collisions))
+}
+
+/// Failing to index contents shouldn't fail the identification, so we only log errors here
+async fn index_contents(db: &PrismaClient, mut files_to_index: HashMap<Vec<u8>, PathBuf>) {
+	if files_to_index.is_empty() {
+		return;
+	}
+
+	let files = match db
+		.file_path()
+		.find_many(vec![file_path::pub_id::in_vec(
+			files_to_index.keys().cloned().collect(),
+		)])
+		.select(file_path::select!({ pub_id object_id }))
+		.exec()
+		.await
+	{
+		Ok(file_paths) => file_paths
+			.into_iter()
+			.filter_map(|file_path| {
+				file_path
+					.object_id
+					.zip(files_to_index.remove(&file_path.pub_id))
+			})
+			.collect(),
+		Err(e) => {
+			error!("Failed to fetch objects to index their contents: {e:#?}");
+			return;
+		}
+	};
+
+	match content_index::index_new_objects(db, files).await {
+		Ok(total_indexed) => trace!("Indexed contents of {total_indexed} objects"),
+		Err(e) => error!("Failed to index file contents: {e:#?}"),
+	}
+}
+
+fn objects_with_cas_id<'o: 'c, 'c>(
+	objects: &'o [object_for_file_identifier::Data],
+	cas_id: &'c str,
+) -> impl Iterator<Item = &'o object_for_file_identifier::Data> + 'c {
+	objects.iter().filter(move |object| {
+		object
+			.file_paths
+			.iter()
+			.any(|file_path| file_path.cas_id.as_deref() == Some(cas_id))
+	})
+}
+
+/// `cas_id`s are generated from samples of large files, so different files can end up with the
+/// same one. Here we confirm the matches with a full content hash, saved as the files'
+/// `integrity_checksum`, leaving files which don't match any object to get new ones.
+///
+/// Objects without any file we can hash, like the ones from other instances, are trusted.
+async fn verified_object_matches<'o>(
+	db: &PrismaClient,
+	sync: &crate::sync::Manager,
+	location: &location::Data,
+	location_path: &Path,
+	file_paths_metadatas: &HashMap<Uuid, (FileMetadata, &file_path_for_file_identifier::Data)>,
+	existing_objects: &'o [object_for_file_identifier::Data],
+) -> Result<(HashMap<Uuid, &'o object_for_file_identifier::Data>, usize), JobError> {
+	let mut matched_objects = HashMap::new();
+	let mut total_collisions = 0;
+	// Checksums we computed, keyed by file_path pub_id, to be saved in the database
+	let mut new_checksums = HashMap::<Vec<u8>, String>::new();
+
+	for (pub_id, (metadata, file_path)) in file_paths_metadatas {
+		let Some(cas_id) = &metadata.cas_id else {
+			continue;
+		};
+
+		let mut candidates = objects_with_cas_id(existing_objects, cas_id).peekable();
+		if candidates.peek().is_none() {
+			continue;
+		}
+
+		let full_path =
+			location_path.join(IsolatedFilePathData::try_from((location.id, *file_path))?);
+		let checksum = match file_checksum(&full_path).await {
+			Ok(checksum) => checksum,
+			Err(e) => {
+				// Files we can't read right now are linked just like they are without verification
+				warn!(
+					"Failed to hash {} to verify its cas_id, it won't be verified: {e:#?}",
+					full_path.display()
+				);
+				if let Some(object) = candidates.next() {
+					matched_objects.insert(*pub_id, object);
+				}
+				continue;
+			}
+		};
+
+		let mut trusted_object = None;
+		let mut matched_object = None;
+
+		for object in candidates {
+			let mut verified_any = false;
+
+			for existing_file_path in object
+				.file_paths
+				.iter()
+				.filter(|existing_file_path| existing_file_path.cas_id.as_deref() == Some(cas_id))
+			{
+				let existing_checksum = if let Some(checksum) = existing_file_path
+					.integrity_checksum
+					.as_ref()
+					.or_else(|| new_checksums.get(&existing_file_path.pub_id))
+				{
+					Some(checksum.clone())
+				} else {
+					existing_file_checksum(existing_file_path, location.instance_id).await
+				};
+
+				let Some(existing_checksum) = existing_checksum else {
+					continue;
+				};
+
+				verified_any = true;
+				new_checksums
+					.entry(existing_file_path.pub_id.clone())
+					.or_insert_with(|| existing_checksum.clone());
+
+				if existing_checksum == checksum {
+					matched_object = Some(object);
+					break;
+				}
+			}
+
+			if matched_object.is_some() {
+				break;
+			}
+
+			if !verified_any && trusted_object.is_none() {
+				trusted_object = Some(object);
+			}
+		}
+
+		if let Some(object) = matched_object.or(trusted_object) {
+			matched_objects.insert(*pub_id, object);
+		} else {
+			warn!(
+				"Found a cas_id collision, {} has the same cas_id <cas_id='{cas_id}'> as other files \
+				with different contents, so it will get its own object",
+				full_path.display()
+			);
+			total_collisions += 1;
+		}
+
+		new_checksums.insert(uuid_to_bytes(*pub_id), checksum);
+	}
+
+	// Files which already had checksums don't need to be updated
+	let existing_checksums = existing_objects
+		.iter()
+		.flat_map(|object| &object.file_paths)
+		.filter(|file_path| file_path.integrity_checksum.is_some())
+		.map(|file_path| &file_path.pub_id)
+		.collect::<HashSet<_>>();
+
+	sync.write_ops(
+		db,
+		new_checksums
+			.into_iter()
+			.filter(|(pub_id, _)| !existing_checksums.contains(pub_id))
+			.map(|(pub_id, checksum)| {
+				(
+					sync.shared_update(
+						prisma_sync::file_path::SyncId {
+							pub_id: pub_id.clone(),
+						},
+						file_path::integrity_checksum::NAME,
+						msgpack!(&checksum),
+					),
+					db.file_path().update(
+						file_path::pub_id::equals(pub_id),
+						vec![file_path::integrity_checksum::set(Some(checksum))],
+					),
+				)
+			})
+			.unzip::<_, _, Vec<_>, Vec<_>>(),
+	)
+	.await?;
+
+	Ok((matched_objects, total_collisions))
+}
+
+/// Hashes a file already linked to an object, if it's available on this instance. Locations of
+/// other instances have paths on their devices, so their files are never hashed.
+async fn existing_file_checksum(
+	file_path: &object_for_file_identifier::file_paths::Data,
+	instance_id: Option<i32>,
+) -> Option<String> {
+	let location = file_path
+		.location
+		.as_ref()
+		.filter(|location| instance_id.is_some() && location.instance_id == instance_id)?;
+	let location_path = location.path.as_ref()?;
+
+	let (Some(materialized_path), Some(is_dir), Some(name), Some(extension)) = (
+		&file_path.materialized_path,
+		file_path.is_dir,
+		&file_path.name,
+		&file_path.extension,
+	) else {
+		return None;
+	};
+
+	let full_path = Path::new(location_path).join(IsolatedFilePathData::from_db_data(
+		location.id,
+		is_dir,
+		Cow::Borrowed(materialized_path),
+		Cow::Borrowed(name),
+		Cow::Borrowed(extension),
+	));
+
+	file_checksum(&full_path)
+		.await
+		.map_err(|e| trace!("Failed to hash {}: {e:#?}", full_path.display()))
+		.ok()
 }
 
 fn connect_file_path_to_object<'db>(
@@ -383,7 +630,8 @@ async fn process_identifier_file_paths(
 	cursor: file_path::id::Type,
 	library: &Library,
 	orphan_count: usize,
-) -> Result<(usize, usize, file_path::id::Type), JobError> {
+	verify_duplicates: bool,
+) -> Result<(usize, usize, usize, file_path::id::Type), JobError> {
 	trace!(
 		"Processing {:?} orphan Paths. ({} completed of {})",
 		file_paths.len(),
@@ -391,12 +639,13 @@ async fn process_identifier_file_paths(
 		orphan_count
 	);
 
-	let (total_objects_created, total_objects_linked) =
-		identifier_job_step(library, location, file_paths).await?;
+	let (total_objects_created, total_objects_linked, total_collisions) =
+		identifier_job_step(library, location, file_paths, verify_duplicates).await?;
 
 	Ok((
 		total_objects_created,
 		total_objects_linked,
+		total_collisions,
 		// returns a new cursor to the last row of this chunk or the current one
 		file_paths
 			.last()
@@ -16,6 +16,7 @@ use sd_utils::db::maybe_missing;
 use std::{
 	hash::{Hash, Hasher},
 	path::{Path, PathBuf},
+	sync::atomic::Ordering,
 };
 
 use prisma_client_rust::or;
@@ -58,6 +59,8 @@ pub struct OldFileIdentifierJobRunMetadata {
 	total_objects_created: usize,
 	total_objects_linked: usize,
 	total_objects_ignored: usize,
+	/// Files whose cas_id matched other files with different contents, when verifying duplicates
+	total_cas_id_collisions: usize,
 }
 
 impl JobRunMetadata for OldFileIdentifierJobRunMetadata {
@@ -66,6 +69,7 @@ impl JobRunMetadata for OldFileIdentifierJobRunMetadata {
 		self.total_objects_created += new_data.total_objects_created;
 		self.total_objects_linked += new_data.total_objects_linked;
 		self.total_objects_ignored += new_data.total_objects_ignored;
+		self.total_cas_id_collisions += new_data.total_cas_id_collisions;
 		self.cursor = new_data.cursor;
 	}
 }
@@ -208,7 +212,7 @@ impl StatefulJob for OldFileIdentifierJobInit {
 			});
 		}
 
-		let (total_objects_created, total_objects_linked, new_cursor) =
+		let (total_objects_created, total_objects_linked, total_cas_id_collisions, new_cursor) =
 			process_identifier_file_paths(
 				location,
 				&file_paths,
@@ -216,11 +220,13 @@ impl StatefulJob for OldFileIdentifierJobInit {
 				run_metadata.cursor,
 				&ctx.library,
 				run_metadata.total_orphan_paths,
+				ctx.node.verified_dedup_flag.load(Ordering::Relaxed),
 			)
 			.await?;
 
 		new_metadata.total_objects_created = total_objects_created;
 		new_metadata.total_objects_linked = total_objects_linked;
+		new_metadata.total_cas_id_collisions = total_cas_id_collisions;
 		new_metadata.cursor = new_cursor;
 
 		ctx.progress(vec![
@@ -1,4 +1,4 @@
-use crate::{invalidate_query, library::Library, old_job::JobError};
+use crate::{invalidate_query, library::Library, old_job::JobError, Node};
 
 use sd_file_path_helper::{
 	ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
@@ -7,7 +7,10 @@ use sd_file_path_helper::{
 use sd_prisma::prisma::{file_path, location, PrismaClient, SortOrder};
 use sd_utils::db::maybe_missing;
 
-use std::path::{Path, PathBuf};
+use std::{
+	path::{Path, PathBuf},
+	sync::atomic::Ordering,
+};
 
 use prisma_client_rust::or;
 use serde::{Deserialize, Serialize};
@@ -24,6 +27,7 @@ pub struct ShallowFileIdentifierJobState {
 pub async fn old_shallow(
 	location: &location::Data,
 	sub_path: &PathBuf,
+	node: &Node,
 	library: &Library,
 ) -> Result<(), JobError> {
 	let Library { db, .. } = library;
@@ -99,13 +103,14 @@ pub async fn old_shallow(
 		let file_paths =
 			get_orphan_file_paths(&library.db, location.id, *cursor, sub_iso_file_path).await?;
 
-		let (_, _, new_cursor) = process_identifier_file_paths(
+		let (_, _, _, new_cursor) = process_identifier_file_paths(
 			location,
 			&file_paths,
 			step_number,
 			*cursor,
 			library,
 			orphan_count,
+			node.verified_dedup_flag.load(Ordering::Relaxed),
 		)
 		.await?;
 		*cursor = new_cursor;
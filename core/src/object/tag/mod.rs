@@ -1,31 +1,107 @@
 use crate::library::Library;
 
-use sd_prisma::{prisma::tag, prisma_sync};
+use sd_prisma::{
+	prisma::{tag, PrismaClient},
+	prisma_sync,
+};
 use sd_sync::*;
 
 use chrono::{DateTime, FixedOffset, Utc};
 
+use prisma_client_rust::{raw, QueryError};
+use rspc::ErrorCode;
 use sd_utils::msgpack;
 use serde::Deserialize;
 use specta::Type;
 use uuid::Uuid;
 
+pub mod rules;
 pub mod seed;
 
 #[derive(Type, Deserialize, Clone)]
 pub struct TagCreateArgs {
 	pub name: String,
 	pub color: String,
+	#[serde(default)]
+	#[specta(optional)]
+	pub parent_id: Option<tag::id::Type>,
 }
 
 impl TagCreateArgs {
-	pub async fn exec(
+	pub async fn exec(self, library: &Library) -> Result<tag::Data, rspc::Error> {
+		let parent_pub_id = if let Some(parent_id) = self.parent_id {
+			Some(
+				library
+					.db
+					.tag()
+					.find_unique(tag::id::equals(parent_id))
+					.select(tag::select!({ pub_id }))
+					.exec()
+					.await?
+					.ok_or(rspc::Error::new(
+						ErrorCode::NotFound,
+						"Error finding parent tag in db".into(),
+					))?
+					.pub_id,
+			)
+		} else {
+			None
+		};
+
+		self.create(library, parent_pub_id)
+			.await
+			.map_err(Into::into)
+	}
+
+	/// Creates the tag under the parent with the given `pub_id`, which must be the one of
+	/// `self.parent_id`
+	pub(super) async fn create(
 		self,
 		Library { db, sync, .. }: &Library,
+		parent_pub_id: Option<Vec<u8>>,
 	) -> prisma_client_rust::Result<tag::Data> {
 		let pub_id = Uuid::new_v4().as_bytes().to_vec();
 		let date_created: DateTime<FixedOffset> = Utc::now().into();
 
+		let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+			(
+				(tag::name::NAME, msgpack!(&self.name)),
+				tag::name::set(Some(self.name)),
+			),
+			(
+				(tag::color::NAME, msgpack!(&self.color)),
+				tag::color::set(Some(self.color)),
+			),
+			(
+				(tag::is_hidden::NAME, msgpack!(false)),
+				tag::is_hidden::set(Some(false)),
+			),
+			(
+				(
+					tag::date_created::NAME,
+					msgpack!(&date_created.to_rfc3339()),
+				),
+				tag::date_created::set(Some(date_created)),
+			),
+		]
+		.into_iter()
+		.chain(
+			self.parent_id
+				.zip(parent_pub_id)
+				.map(|(parent_id, parent_pub_id)| {
+					(
+						(
+							tag::parent::NAME,
+							msgpack!(prisma_sync::tag::SyncId {
+								pub_id: parent_pub_id
+							}),
+						),
+						tag::parent::connect(tag::id::equals(parent_id)),
+					)
+				}),
+		)
+		.unzip();
+
 		sync.write_ops(
 			db,
 			(
@@ -33,27 +109,48 @@ impl TagCreateArgs {
 					prisma_sync::tag::SyncId {
 						pub_id: pub_id.clone(),
 					},
-					[
-						(tag::name::NAME, msgpack!(&self.name)),
-						(tag::color::NAME, msgpack!(&self.color)),
-						(tag::is_hidden::NAME, msgpack!(false)),
-						(
-							tag::date_created::NAME,
-							msgpack!(&date_created.to_rfc3339()),
-						),
-					],
-				),
-				db.tag().create(
-					pub_id,
-					vec![
-						tag::name::set(Some(self.name)),
-						tag::color::set(Some(self.color)),
-						tag::is_hidden::set(Some(false)),
-						tag::date_created::set(Some(date_created)),
-					],
+					sync_params,
 				),
+				db.tag().create(pub_id, db_params),
 			),
 		)
 		.await
 	}
 }
+
+/// Returns the given tags along with all their descendants, so searching for a parent tag can
+/// include everything nested under it
+pub async fn with_descendants(
+	db: &PrismaClient,
+	tag_ids: Vec<tag::id::Type>,
+) -> Result<Vec<tag::id::Type>, QueryError> {
+	#[derive(Deserialize)]
+	struct TagId {
+		id: tag::id::Type,
+	}
+
+	if tag_ids.is_empty() {
+		return Ok(tag_ids);
+	}
+
+	// We have no data coming from the user, so this is sql injection safe
+	Ok(db
+		._query_raw::<TagId>(raw!(&format!(
+			"WITH RECURSIVE descendants(id) AS (
+				SELECT id FROM tag WHERE id IN ({})
+				UNION
+				SELECT tag.id FROM tag JOIN descendants ON tag.parent_id = descendants.id
+			)
+			SELECT id FROM descendants",
+			tag_ids
+				.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>()
+				.join(",")
+		)))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|tag| tag.id)
+		.collect())
+}
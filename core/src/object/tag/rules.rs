@@ -0,0 +1,423 @@
+//! Rules which automatically tag new objects, applied by the file identifier when objects are
+//! created and by the image labeler when objects get new labels.
+
+use crate::{invalidate_query, library::Library};
+
+use sd_prisma::{
+	prisma::{file_path, tag, tag_on_object, tag_rule},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{
+	db::{maybe_missing, MissingFieldError},
+	msgpack,
+};
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use globset::{Glob, GlobMatcher};
+use prisma_client_rust::QueryError;
+use rmp_serde::{decode, encode};
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tracing::trace;
+use uuid::Uuid;
+
+#[derive(Error, Debug)]
+pub enum TagRuleError {
+	// User errors
+	#[error("glob builder error: {0}")]
+	Glob(#[from] globset::Error),
+	#[error("tag rules must have at least one condition")]
+	NoConditions,
+	#[error("tag not found: {0}")]
+	TagNotFound(tag::id::Type),
+
+	// Internal Errors
+	#[error("tag rule conditions encode error: {0}")]
+	ConditionsRMPEncode(#[from] encode::Error),
+	#[error("tag rule conditions decode error: {0}")]
+	ConditionsRMPDecode(#[from] decode::Error),
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error("missing-field: {0}")]
+	MissingField(#[from] MissingFieldError),
+}
+
+impl From<TagRuleError> for rspc::Error {
+	fn from(err: TagRuleError) -> Self {
+		match err {
+			TagRuleError::Glob(_) | TagRuleError::NoConditions => {
+				rspc::Error::with_cause(ErrorCode::BadRequest, err.to_string(), err)
+			}
+			TagRuleError::TagNotFound(_) => {
+				rspc::Error::with_cause(ErrorCode::NotFound, err.to_string(), err)
+			}
+
+			_ => rspc::Error::with_cause(ErrorCode::InternalServerError, err.to_string(), err),
+		}
+	}
+}
+
+/// A condition a file must meet for a rule to tag its object
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TagRuleCondition {
+	/// Glob matched against the path of the file inside its location, like `Invoices/*.pdf`
+	Glob(String),
+	/// Kind of the object, as in `sd_file_ext::kind::ObjectKind`
+	Kind(i32),
+	/// Pub id of the location holding the file, as ids aren't the same across instances
+	Location(Uuid),
+	/// Name of a label the object has
+	Label(String),
+}
+
+enum CompiledCondition {
+	Glob(GlobMatcher),
+	Kind(i32),
+	Location(Vec<u8>),
+	Label(String),
+}
+
+impl TryFrom<TagRuleCondition> for CompiledCondition {
+	type Error = TagRuleError;
+
+	fn try_from(condition: TagRuleCondition) -> Result<Self, Self::Error> {
+		Ok(match condition {
+			TagRuleCondition::Glob(glob) => Self::Glob(Glob::new(&glob)?.compile_matcher()),
+			TagRuleCondition::Kind(kind) => Self::Kind(kind),
+			TagRuleCondition::Location(pub_id) => Self::Location(pub_id.as_bytes().to_vec()),
+			TagRuleCondition::Label(name) => Self::Label(name),
+		})
+	}
+}
+
+struct CompiledRule {
+	tag_id: tag::id::Type,
+	tag_pub_id: Vec<u8>,
+	conditions: Vec<CompiledCondition>,
+}
+
+file_path::select!(file_path_for_tag_rules {
+	materialized_path
+	name
+	extension
+	location: select { pub_id }
+	object: select {
+		id
+		pub_id
+		kind
+		labels: select { label: select { name } }
+	}
+});
+
+impl CompiledRule {
+	fn matches(&self, file_path: &file_path_for_tag_rules::Data) -> bool {
+		let Some(object) = &file_path.object else {
+			return false;
+		};
+
+		self.conditions.iter().all(|condition| match condition {
+			CompiledCondition::Glob(matcher) => matcher.is_match(relative_path(file_path)),
+			CompiledCondition::Kind(kind) => object.kind == Some(*kind),
+			CompiledCondition::Location(pub_id) => file_path
+				.location
+				.as_ref()
+				.map_or(false, |location| &location.pub_id == pub_id),
+			CompiledCondition::Label(name) => object
+				.labels
+				.iter()
+				.any(|label_on_object| &label_on_object.label.name == name),
+		})
+	}
+}
+
+/// Path of a file inside its location, without the leading separator
+fn relative_path(file_path: &file_path_for_tag_rules::Data) -> String {
+	let materialized_path = file_path.materialized_path.as_deref().unwrap_or("/");
+	let name = file_path.name.as_deref().unwrap_or_default();
+
+	let path = match file_path.extension.as_deref() {
+		Some(extension) if !extension.is_empty() => {
+			format!("{materialized_path}{name}.{extension}")
+		}
+		_ => format!("{materialized_path}{name}"),
+	};
+
+	path.trim_start_matches('/').to_string()
+}
+
+#[derive(Type, Deserialize, Clone, Debug)]
+pub struct TagRuleCreateArgs {
+	pub name: String,
+	pub tag_id: tag::id::Type,
+	pub conditions: Vec<TagRuleCondition>,
+}
+
+impl TagRuleCreateArgs {
+	pub async fn create(
+		self,
+		Library { db, sync, .. }: &Library,
+	) -> Result<tag_rule::Data, TagRuleError> {
+		if self.conditions.is_empty() {
+			return Err(TagRuleError::NoConditions);
+		}
+
+		// Making sure that the conditions are valid before saving them
+		for condition in &self.conditions {
+			CompiledCondition::try_from(condition.clone())?;
+		}
+
+		let tag = db
+			.tag()
+			.find_unique(tag::id::equals(self.tag_id))
+			.select(tag::select!({ pub_id }))
+			.exec()
+			.await?
+			.ok_or(TagRuleError::TagNotFound(self.tag_id))?;
+
+		let pub_id = Uuid::new_v4().as_bytes().to_vec();
+		let date_created: DateTime<FixedOffset> = Utc::now().into();
+		let conditions = rmp_serde::to_vec_named(&self.conditions)?;
+
+		Ok(sync
+			.write_ops(
+				db,
+				(
+					sync.shared_create(
+						prisma_sync::tag_rule::SyncId {
+							pub_id: pub_id.clone(),
+						},
+						[
+							(tag_rule::name::NAME, msgpack!(&self.name)),
+							(tag_rule::conditions::NAME, msgpack!(&conditions)),
+							(
+								tag_rule::tag::NAME,
+								msgpack!(prisma_sync::tag::SyncId { pub_id: tag.pub_id }),
+							),
+							(
+								tag_rule::date_created::NAME,
+								msgpack!(&date_created.to_rfc3339()),
+							),
+						],
+					),
+					db.tag_rule().create(
+						pub_id,
+						vec![
+							tag_rule::name::set(Some(self.name)),
+							tag_rule::conditions::set(Some(conditions)),
+							tag_rule::tag::connect(tag::id::equals(self.tag_id)),
+							tag_rule::date_created::set(Some(date_created)),
+						],
+					),
+				),
+			)
+			.await?)
+	}
+}
+
+pub fn conditions_from_rule(rule: &tag_rule::Data) -> Result<Vec<TagRuleCondition>, TagRuleError> {
+	Ok(rmp_serde::from_slice(maybe_missing(
+		&rule.conditions,
+		"tag_rule.conditions",
+	)?)?)
+}
+
+/// Tags the objects of the given file paths according to the tag rules of the library,
+/// returning how many objects were tagged
+pub async fn apply_tag_rules(
+	library: &Library,
+	file_path_ids: Vec<file_path::id::Type>,
+) -> Result<usize, TagRuleError> {
+	apply_rules(library, file_path_ids, |_| true).await
+}
+
+/// Like [`apply_tag_rules`], but only with the rules depending on labels, for objects which were
+/// just labeled
+pub async fn apply_label_tag_rules(
+	library: &Library,
+	file_path_ids: Vec<file_path::id::Type>,
+) -> Result<usize, TagRuleError> {
+	apply_rules(library, file_path_ids, |rule| {
+		rule.conditions
+			.iter()
+			.any(|condition| matches!(condition, CompiledCondition::Label(_)))
+	})
+	.await
+}
+
+async fn apply_rules(
+	library: &Library,
+	file_path_ids: Vec<file_path::id::Type>,
+	filter: impl Fn(&CompiledRule) -> bool,
+) -> Result<usize, TagRuleError> {
+	let Library { db, sync, .. } = library;
+
+	if file_path_ids.is_empty() {
+		return Ok(0);
+	}
+
+	let rules = db
+		.tag_rule()
+		.find_many(vec![tag_rule::tag_id::not(None)])
+		.include(tag_rule::include!({ tag: select { id pub_id } }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|rule| {
+			let tag = maybe_missing(rule.tag, "tag_rule.tag")?;
+
+			Ok(CompiledRule {
+				tag_id: tag.id,
+				tag_pub_id: tag.pub_id,
+				conditions: rmp_serde::from_slice::<Vec<TagRuleCondition>>(maybe_missing(
+					&rule.conditions,
+					"tag_rule.conditions",
+				)?)?
+				.into_iter()
+				.map(CompiledCondition::try_from)
+				.collect::<Result<_, _>>()?,
+			})
+		})
+		.collect::<Result<Vec<_>, TagRuleError>>()?
+		.into_iter()
+		.filter(filter)
+		.collect::<Vec<_>>();
+
+	if rules.is_empty() {
+		return Ok(0);
+	}
+
+	let file_paths = db
+		.file_path()
+		.find_many(vec![
+			file_path::id::in_vec(file_path_ids),
+			file_path::object_id::not(None),
+		])
+		.select(file_path_for_tag_rules::select())
+		.exec()
+		.await?;
+
+	let mut tagged = HashSet::new();
+	let (sync_ops, db_creates): (Vec<_>, Vec<_>) = file_paths
+		.iter()
+		.flat_map(|file_path| {
+			rules
+				.iter()
+				.filter(|rule| rule.matches(file_path))
+				.filter_map(|rule| file_path.object.as_ref().map(|object| (rule, object)))
+		})
+		.filter(|(rule, object)| tagged.insert((rule.tag_id, object.id)))
+		.map(|(rule, object)| {
+			(
+				sync.relation_create(
+					prisma_sync::tag_on_object::SyncId {
+						tag: prisma_sync::tag::SyncId {
+							pub_id: rule.tag_pub_id.clone(),
+						},
+						object: prisma_sync::object::SyncId {
+							pub_id: object.pub_id.clone(),
+						},
+					},
+					[],
+				),
+				tag_on_object::CreateUnchecked {
+					tag_id: rule.tag_id,
+					object_id: object.id,
+					_params: vec![tag_on_object::date_created::set(Some(Utc::now().into()))],
+				},
+			)
+		})
+		.unzip();
+
+	if db_creates.is_empty() {
+		return Ok(0);
+	}
+
+	let total_tagged = sync
+		.write_ops(
+			db,
+			(
+				sync_ops.into_iter().flatten().collect(),
+				db.tag_on_object().create_many(db_creates).skip_duplicates(),
+			),
+		)
+		.await?;
+
+	trace!("Tag rules tagged {total_tagged} objects");
+
+	if total_tagged > 0 {
+		invalidate_query!(library, "tags.getForObject");
+		invalidate_query!(library, "tags.getWithObjects");
+		invalidate_query!(library, "search.objects");
+	}
+
+	Ok(total_tagged as usize)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn file_path(
+		materialized_path: &str,
+		name: &str,
+		extension: &str,
+	) -> file_path_for_tag_rules::Data {
+		file_path_for_tag_rules::Data {
+			materialized_path: Some(materialized_path.to_string()),
+			name: Some(name.to_string()),
+			extension: Some(extension.to_string()),
+			location: Some(file_path_for_tag_rules::location::Data { pub_id: vec![1] }),
+			object: Some(file_path_for_tag_rules::object::Data {
+				id: 1,
+				pub_id: vec![1],
+				kind: Some(5),
+				labels: vec![],
+			}),
+		}
+	}
+
+	fn rule(conditions: Vec<TagRuleCondition>) -> CompiledRule {
+		CompiledRule {
+			tag_id: 1,
+			tag_pub_id: vec![1],
+			conditions: conditions
+				.into_iter()
+				.map(|condition| CompiledCondition::try_from(condition).unwrap())
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn relative_paths() {
+		assert_eq!(
+			relative_path(&file_path("/Projects/Acme/", "invoice", "pdf")),
+			"Projects/Acme/invoice.pdf"
+		);
+		assert_eq!(relative_path(&file_path("/", "Makefile", "")), "Makefile");
+	}
+
+	#[test]
+	fn matching() {
+		let invoice = file_path("/Projects/Acme/invoices/", "march", "pdf");
+
+		assert!(rule(vec![TagRuleCondition::Glob("**/invoices/*.pdf".into())]).matches(&invoice));
+		assert!(rule(vec![
+			TagRuleCondition::Glob("Projects/**".into()),
+			TagRuleCondition::Kind(5),
+		])
+		.matches(&invoice));
+		assert!(!rule(vec![
+			TagRuleCondition::Glob("Projects/**".into()),
+			TagRuleCondition::Kind(7),
+		])
+		.matches(&invoice));
+		assert!(!rule(vec![TagRuleCondition::Label("Receipt".into())]).matches(&invoice));
+		assert!(!rule(vec![TagRuleCondition::Location(Uuid::nil())]).matches(&invoice));
+	}
+}
@@ -14,10 +14,11 @@ pub async fn file_checksum(path: impl AsRef<Path>) -> Result<String, io::Error>
 	let mut buffer = vec![0; BLOCK_LEN].into_boxed_slice();
 	loop {
 		let read_count = reader.read(&mut buffer).await?;
-		context.update(&buffer[..read_count]);
-		if read_count != BLOCK_LEN {
+		// Reads can return less than a full block before the end of the file
+		if read_count == 0 {
 			break;
 		}
+		context.update(&buffer[..read_count]);
 	}
 	let hex = context.finalize().to_hex();
 
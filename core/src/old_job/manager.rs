@@ -1,10 +1,18 @@
 use crate::{
 	library::Library,
-	location::indexer::old_indexer_job::OldIndexerJobInit,
+	location::{
+		archive::{OldLocationArchiverJobInit, OldLocationReattacherJobInit},
+		indexer::old_indexer_job::OldIndexerJobInit,
+	},
 	object::{
 		fs::{
-			old_copy::OldFileCopierJobInit, old_cut::OldFileCutterJobInit,
-			old_delete::OldFileDeleterJobInit, old_erase::OldFileEraserJobInit,
+			archive::{OldArchiveCompressorJobInit, OldArchiveExtractorJobInit},
+			old_copy::OldFileCopierJobInit,
+			old_cut::OldFileCutterJobInit,
+			old_delete::OldFileDeleterJobInit,
+			old_erase::OldFileEraserJobInit,
+			reclaim::OldDuplicateReclaimerJobInit,
+			sync::OldFolderSyncJobInit,
 		},
 		media::old_media_processor::OldMediaProcessorJobInit,
 		old_file_identifier::old_file_identifier_job::OldFileIdentifierJobInit,
@@ -404,6 +412,12 @@ fn initialize_resumable_job(
 			OldFileCopierJobInit,
 			OldFileDeleterJobInit,
 			OldFileEraserJobInit,
+			OldLocationArchiverJobInit,
+			OldLocationReattacherJobInit,
+			OldArchiveCompressorJobInit,
+			OldArchiveExtractorJobInit,
+			OldFolderSyncJobInit,
+			OldDuplicateReclaimerJobInit,
 		]
 	)
 }
@@ -61,6 +61,14 @@ pub enum P2PEvent {
 	SpacedropRejected {
 		id: Uuid,
 	},
+	// The peer doesn't trust us enough to receive our Spacedrops
+	SpacedropNotAllowed {
+		id: Uuid,
+	},
+	// Pairing with a peer succeeded, it's trusted from now on
+	Paired {
+		identity: RemoteIdentity,
+	},
 }
 
 /// A P2P hook which listens for events and sends them over a channel which can be connected to the frontend.
@@ -1,11 +1,16 @@
 use crate::{
 	node::{
-		config::{self, P2PDiscoveryState, Port},
+		config::{self, NodeConfigError, P2PDiscoveryState, PeerCapability, PeerPermissions, Port},
 		get_hardware_model_name, HardwareModel,
 	},
 	p2p::{
-		libraries::libraries_hook, operations, sync::SyncMessage, Header, OperatingSystem,
-		SPACEDRIVE_APP_ID,
+		libraries::libraries_hook,
+		operations::{
+			self,
+			pairing::{MAX_PAIRING_ATTEMPTS, PAIRING_CODE_TIMEOUT},
+		},
+		sync::SyncMessage,
+		Header, OperatingSystem, SPACEDRIVE_APP_ID,
 	},
 	Node,
 };
@@ -17,7 +22,7 @@ use sd_p2p::{
 	HookId, Libp2pPeerId, Listener, Mdns, Peer, QuicTransport, RelayServerEntry, RemoteIdentity,
 	UnicastStream, P2P,
 };
-use sd_p2p_tunnel::Tunnel;
+use sd_p2p_tunnel::{PairingCode, Tunnel};
 use serde::Serialize;
 use serde_json::json;
 use specta::Type;
@@ -26,7 +31,7 @@ use std::{
 	convert::Infallible,
 	net::SocketAddr,
 	sync::{atomic::AtomicBool, Arc, Mutex, PoisonError},
-	time::Duration,
+	time::{Duration, Instant},
 };
 use tower_service::Service;
 use tracing::error;
@@ -47,6 +52,11 @@ pub struct P2PManager {
 	pub(crate) events: P2PEvents,
 	pub(super) spacedrop_pairing_reqs: Arc<Mutex<HashMap<Uuid, oneshot::Sender<Option<String>>>>>,
 	pub(super) spacedrop_cancellations: Arc<Mutex<HashMap<Uuid, Arc<AtomicBool>>>>,
+	/// Spacedrops which broke mid transfer, with who sent them, where they were being saved and
+	/// when they broke. The sender retrying one of them is accepted without asking the user again.
+	pub(super) spacedrop_interrupted: Arc<Mutex<HashMap<Uuid, (RemoteIdentity, String, Instant)>>>,
+	/// The pairing code currently shown to the user, when it was created and who tried it
+	pairing_code: Mutex<Option<(PairingCode, Instant, HashSet<RemoteIdentity>)>>,
 	pub(crate) node_config: Arc<config::Manager>,
 	pub libraries_hook_id: HookId,
 }
@@ -74,6 +84,8 @@ impl P2PManager {
 			events: P2PEvents::spawn(p2p.clone(), libraries_hook_id),
 			spacedrop_pairing_reqs: Default::default(),
 			spacedrop_cancellations: Default::default(),
+			spacedrop_interrupted: Default::default(),
+			pairing_code: Default::default(),
 			node_config,
 			libraries_hook_id,
 		});
@@ -235,6 +247,167 @@ impl P2PManager {
 			.map(|(_, p)| p.clone())
 	}
 
+	/// Creates a pairing code for another node to pair with this one, replacing the previous one
+	pub fn start_pairing(&self) -> PairingCode {
+		let code = PairingCode::generate();
+		let mut pairing_code = self
+			.pairing_code
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		*pairing_code = Some((code.clone(), Instant::now(), HashSet::new()));
+
+		code
+	}
+
+	/// The pairing code for `identity` to try if it hasn't expired. Each peer gets a single attempt
+	/// and only the first few peers get one, so the code can't be brute forced while a peer racing
+	/// the one it was meant for can't use it up either.
+	pub(super) fn pairing_attempt(&self, identity: RemoteIdentity) -> Option<PairingCode> {
+		let mut pairing_code = self
+			.pairing_code
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		if pairing_code
+			.as_ref()
+			.is_some_and(|(_, created_at, _)| created_at.elapsed() >= PAIRING_CODE_TIMEOUT)
+		{
+			*pairing_code = None;
+		}
+
+		let (code, _, attempts) = pairing_code.as_mut()?;
+		(attempts.len() < MAX_PAIRING_ATTEMPTS && attempts.insert(identity)).then(|| code.clone())
+	}
+
+	/// Forgets the pairing code once a peer paired with it, unless it was replaced meanwhile
+	pub(super) fn end_pairing(&self, code: &PairingCode) {
+		let mut pairing_code = self
+			.pairing_code
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		if pairing_code
+			.as_ref()
+			.is_some_and(|(current, _, _)| current == code)
+		{
+			*pairing_code = None;
+		}
+	}
+
+	/// What a peer is allowed to do, `None` if it isn't trusted
+	pub async fn peer_permissions(&self, identity: &RemoteIdentity) -> Option<PeerPermissions> {
+		self.node_config
+			.get()
+			.await
+			.trusted_peers
+			.get(identity)
+			.map(|peer| peer.permissions.clone())
+	}
+
+	pub async fn allows(&self, identity: &RemoteIdentity, capability: PeerCapability) -> bool {
+		let config = self.node_config.get().await;
+		if config
+			.trusted_peers
+			.get(identity)
+			.is_some_and(|peer| peer.permissions.allows(capability))
+		{
+			return true;
+		}
+
+		// Instances allowed to sync are only tied to the node running them once it advertises them
+		let PeerCapability::Sync(library_id) = capability else {
+			return false;
+		};
+		let Some(instance) = self.advertised_instance(identity, &library_id) else {
+			return false;
+		};
+		if config.sync_instances.get(&instance) != Some(&library_id) {
+			return false;
+		}
+
+		let mut claimed = false;
+		if let Err(err) = self
+			.node_config
+			.write(|c| claimed = c.claim_sync_instance(*identity, library_id, &instance))
+			.await
+		{
+			error!("Failed to save the peer allowed to sync library '{library_id}': {err:?}");
+		}
+
+		claimed
+	}
+
+	/// The identity of the instance of `library_id` the peer says it runs
+	fn advertised_instance(
+		&self,
+		identity: &RemoteIdentity,
+		library_id: &Uuid,
+	) -> Option<RemoteIdentity> {
+		self.p2p
+			.peers()
+			.get(identity)?
+			.metadata()
+			.get(&library_id.to_string())?
+			.parse()
+			.ok()
+	}
+
+	/// The libraries both this node and the peer have loaded
+	pub(super) fn shared_libraries(&self, identity: &RemoteIdentity) -> HashSet<Uuid> {
+		let Some(peer) = self.p2p.peers().get(identity).cloned() else {
+			return HashSet::new();
+		};
+		let ours = self.p2p.metadata();
+
+		peer.metadata()
+			.keys()
+			.filter(|key| ours.contains_key(*key))
+			.filter_map(|key| key.parse().ok())
+			.collect()
+	}
+
+	/// Trusts a peer we just paired with to sync `libraries`, keeping its permissions if it was
+	/// trusted already
+	pub(super) async fn trust(
+		&self,
+		identity: RemoteIdentity,
+		libraries: HashSet<Uuid>,
+	) -> Result<(), NodeConfigError> {
+		self.node_config
+			.write(|c| c.trust(identity, libraries))
+			.await
+			.map(|_| ())
+	}
+
+	/// Returns `false` if the peer isn't trusted
+	pub async fn set_peer_permissions(
+		&self,
+		identity: RemoteIdentity,
+		permissions: PeerPermissions,
+	) -> Result<bool, NodeConfigError> {
+		let mut found = false;
+		self.node_config
+			.write(|c| {
+				if let Some(peer) = c.trusted_peers.get_mut(&identity) {
+					peer.permissions = permissions;
+					found = true;
+				}
+			})
+			.await?;
+
+		Ok(found)
+	}
+
+	/// Forgets a peer, it has to pair again to be trusted. Returns `false` if it wasn't trusted.
+	pub async fn untrust(&self, identity: &RemoteIdentity) -> Result<bool, NodeConfigError> {
+		let mut found = false;
+		self.node_config
+			.write(|c| found = c.trusted_peers.remove(identity).is_some())
+			.await?;
+
+		Ok(found)
+	}
+
 	pub async fn state(&self) -> serde_json::Value {
 		let listeners = self.p2p.listeners();
 		let node_config = self.node_config.get().await;
@@ -301,9 +474,15 @@ async fn start(
 					error!("Failed to handle Spacedrop request");
 				}
 				Header::Sync(library_id) => {
-					let Ok(mut tunnel) = Tunnel::responder(stream).await.map_err(|err| {
-						error!("Failed `Tunnel::responder`: {}", err);
-					}) else {
+					let trusted = this
+						.allows(&stream.remote_identity(), PeerCapability::Sync(library_id))
+						.await;
+					let Ok(mut tunnel) = Tunnel::responder(stream, this.p2p.identity(), trusted)
+						.await
+						.map_err(|err| {
+							error!("Failed `Tunnel::responder`: {}", err);
+						})
+					else {
 						return;
 					};
 
@@ -338,12 +517,21 @@ async fn start(
 				}
 				Header::Http => {
 					let remote = stream.remote_identity();
-					let Err(err) = operations::rspc::receiver(stream, &mut service).await else {
+					let Err(err) = operations::rspc::receiver(&this, stream, &mut service).await
+					else {
 						return;
 					};
 
 					error!("Failed to handling rspc request with '{remote}': {err:?}");
 				}
+				Header::Pair => {
+					let remote = stream.remote_identity();
+					let Err(err) = operations::pairing::receiver(&this, stream).await else {
+						return;
+					};
+
+					error!("Failed to pair with '{remote}': {err}");
+				}
 			};
 		});
 	}
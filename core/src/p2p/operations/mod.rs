@@ -1,6 +1,8 @@
+pub mod pairing;
 pub mod ping;
 pub mod rspc;
 pub mod spacedrop;
 
+pub use pairing::pair;
 pub use rspc::remote_rspc;
 pub use spacedrop::spacedrop;
@@ -0,0 +1,86 @@
+use std::{sync::Arc, time::Duration};
+
+use sd_p2p::{RemoteIdentity, UnicastStream};
+use sd_p2p_tunnel::{PairingCode, Tunnel, TunnelError};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info};
+
+use crate::{
+	node::config::NodeConfigError,
+	p2p::{Header, P2PEvent, P2PManager},
+};
+
+/// How long a pairing code can be used for once it's shown
+pub const PAIRING_CODE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How many peers can try a pairing code before it stops being accepted
+pub const MAX_PAIRING_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum PairingError {
+	#[error("peer not found, has it been discovered?")]
+	PeerNotFound,
+	#[error("failed to connect to the peer: {0}")]
+	Connect(String),
+	#[error("io error: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("this node isn't pairing, or its pairing code expired or was tried too many times")]
+	NotPairing,
+	#[error(transparent)]
+	Tunnel(#[from] TunnelError),
+	#[error("failed to save the trusted peer: {0}")]
+	Config(#[from] NodeConfigError),
+}
+
+/// Pair with the node showing `code`, after which each node trusts the other.
+pub async fn pair(
+	p2p: Arc<P2PManager>,
+	identity: RemoteIdentity,
+	code: PairingCode,
+) -> Result<(), PairingError> {
+	let peer = p2p
+		.p2p
+		.peers()
+		.get(&identity)
+		.ok_or(PairingError::PeerNotFound)?
+		.clone();
+	let mut stream = peer
+		.new_stream()
+		.await
+		.map_err(|err| PairingError::Connect(err.to_string()))?;
+
+	stream.write_all(&Header::Pair.to_bytes()).await?;
+
+	let tunnel = Tunnel::pairing_initiator(stream, p2p.p2p.identity(), &code).await?;
+	paired(&p2p, tunnel.remote_identity()).await
+}
+
+pub(crate) async fn receiver(
+	p2p: &Arc<P2PManager>,
+	stream: UnicastStream,
+) -> Result<(), PairingError> {
+	debug!(
+		"Received pairing request from peer '{}'",
+		stream.remote_identity()
+	);
+
+	let code = p2p
+		.pairing_attempt(stream.remote_identity())
+		.ok_or(PairingError::NotPairing)?;
+
+	let tunnel = Tunnel::pairing_responder(stream, p2p.p2p.identity(), &code).await?;
+	p2p.end_pairing(&code);
+
+	paired(p2p, tunnel.remote_identity()).await
+}
+
+async fn paired(p2p: &P2PManager, identity: RemoteIdentity) -> Result<(), PairingError> {
+	// Libraries the nodes already share sync from now on, like they do for libraries joined later
+	p2p.trust(identity, p2p.shared_libraries(&identity)).await?;
+	info!("Paired with peer '{identity}'");
+
+	p2p.events.send(P2PEvent::Paired { identity }).ok();
+
+	Ok(())
+}
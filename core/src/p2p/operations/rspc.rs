@@ -3,10 +3,14 @@ use std::{error::Error, sync::Arc};
 use axum::{body::Body, http, Router};
 use hyper::{server::conn::Http, Response};
 use sd_p2p::{RemoteIdentity, UnicastStream, P2P};
+use sd_p2p_tunnel::Tunnel;
 use tokio::io::AsyncWriteExt;
 use tracing::debug;
 
-use crate::p2p::Header;
+use crate::{
+	node::config::PeerCapability,
+	p2p::{Header, P2PManager},
+};
 
 /// Transfer an rspc query to a remote node.
 #[allow(unused)]
@@ -24,7 +28,9 @@ pub async fn remote_rspc(
 
 	stream.write_all(&Header::Http.to_bytes()).await?;
 
-	let (mut sender, conn) = hyper::client::conn::handshake(stream).await?;
+	let tunnel = Tunnel::initiator(stream, p2p.identity()).await?;
+
+	let (mut sender, conn) = hyper::client::conn::handshake(tunnel).await?;
 	tokio::task::spawn(async move {
 		if let Err(err) = conn.await {
 			println!("Connection error: {:?}", err);
@@ -35,24 +41,20 @@ pub async fn remote_rspc(
 }
 
 pub(crate) async fn receiver(
+	p2p: &Arc<P2PManager>,
 	stream: UnicastStream,
 	service: &mut Router,
 ) -> Result<(), Box<dyn Error>> {
-	debug!(
-		"Received http request from peer '{}'",
-		stream.remote_identity(),
-	);
+	let remote = stream.remote_identity();
+	debug!("Received http request from peer '{remote}'");
 
-	// TODO: Authentication
-	#[allow(clippy::todo)]
-	if true {
-		todo!("You wouldn't download a car!");
-	}
+	let trusted = p2p.allows(&remote, PeerCapability::RemoteRspc).await;
+	let tunnel = Tunnel::responder(stream, p2p.p2p.identity(), trusted).await?;
 
 	Http::new()
 		.http1_only(true)
 		.http1_keep_alive(true)
-		.serve_connection(stream, service)
+		.serve_connection(tunnel, service)
 		.with_upgrades()
 		.await
 		.map_err(Into::into)
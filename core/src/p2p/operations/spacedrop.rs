@@ -1,6 +1,8 @@
 use std::{
 	borrow::Cow,
-	path::PathBuf,
+	fs::Metadata,
+	io::{self, SeekFrom},
+	path::{Path, PathBuf},
 	sync::{
 		atomic::{AtomicBool, Ordering},
 		Arc, PoisonError,
@@ -8,14 +10,20 @@ use std::{
 	time::Duration,
 };
 
-use crate::p2p::{Header, P2PEvent, P2PManager};
-use futures::future::join_all;
+use crate::{
+	node::config::PeerCapability,
+	p2p::{Header, P2PEvent, P2PManager},
+};
 use sd_p2p::{RemoteIdentity, UnicastStream};
-use sd_p2p_block::{BlockSize, Range, SpaceblockRequest, SpaceblockRequests, Transfer};
+use sd_p2p_block::{
+	block_hashes, BlockSize, Range, SpaceblockRequest, SpaceblockRequests, Transfer, MAX_REQUESTS,
+};
+use serde::{Deserialize, Serialize};
 use tokio::{
-	fs::{create_dir_all, File},
-	io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+	fs::{self, create_dir_all, File, OpenOptions},
+	io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter},
 	sync::oneshot,
+	task::spawn_blocking,
 	time::{sleep, Instant},
 };
 use tracing::{debug, error, info, warn};
@@ -24,6 +32,22 @@ use uuid::Uuid;
 /// The amount of time to wait for a Spacedrop request to be accepted or rejected before it's automatically rejected
 pub(crate) const SPACEDROP_TIMEOUT: Duration = Duration::from_secs(60);
 
+/// Sent back instead of accepting or rejecting when the sender isn't allowed to Spacedrop us
+const SPACEDROP_NOT_ALLOWED: u8 = 2;
+
+/// How long the sender of an interrupted Spacedrop waits for the peer to come back
+const SPACEDROP_RESUME_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How long the receiver of an interrupted Spacedrop accepts it being resumed, a bit longer than
+/// the sender waits so a peer coming back at the last moment can still resume
+const SPACEDROP_INTERRUPTED_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How often the sender of an interrupted Spacedrop checks if the peer is back
+const SPACEDROP_RESUME_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many times a Spacedrop is resumed before giving up on it
+const SPACEDROP_MAX_RESUMES: usize = 5;
+
 // TODO: Proper error handling
 pub async fn spacedrop(
 	p2p: Arc<P2PManager>,
@@ -34,35 +58,57 @@ pub async fn spacedrop(
 		return Err(());
 	}
 
-	let (files, requests): (Vec<_>, Vec<_>) = join_all(paths.into_iter().map(|path| async move {
-		let file = File::open(&path).await?;
-		let metadata = file.metadata().await?;
-		let name = path
-			.file_name()
-			.map(|v| v.to_string_lossy())
-			.unwrap_or(Cow::Borrowed(""))
-			.to_string();
-
-		Ok((
-			(path, file),
-			SpaceblockRequest {
-				name,
-				size: metadata.len(),
-				range: Range::Full,
-			},
-		))
-	}))
-	.await
-	.into_iter()
-	.collect::<Result<Vec<_>, std::io::Error>>()
-	.map_err(|err| {
-		warn!("error opening file: '{err:?}'");
-		// TODO: Proper error type
-	})?
-	.into_iter()
-	.unzip();
-
-	let total_length: u64 = requests.iter().map(|req| req.size).sum();
+	let mut entries = Vec::new();
+	for path in paths {
+		entries.extend(walk(path).await.map_err(|err| {
+			warn!("error opening file: '{err:?}'");
+			// TODO: Proper error type
+		})?);
+	}
+
+	if entries.len() > MAX_REQUESTS as usize {
+		warn!("can't Spacedrop '{}' files at once", entries.len());
+		return Err(());
+	}
+
+	// Blocks only ever hold one file, so the largest one decides how big they need to be
+	let block_size = BlockSize::from_size(
+		entries
+			.iter()
+			.filter(|(_, _, metadata)| !metadata.is_dir())
+			.map(|(_, _, metadata)| metadata.len())
+			.max()
+			.unwrap_or_default(),
+	);
+
+	let mut paths = Vec::with_capacity(entries.len());
+	let mut requests = Vec::with_capacity(entries.len());
+	for (path, name, metadata) in entries {
+		let is_dir = metadata.is_dir();
+
+		// The receiver checks every block against these, which also lets it resume from a partial file
+		let block_hashes = if is_dir {
+			vec![]
+		} else {
+			let mut file = BufReader::new(File::open(&path).await.map_err(|err| {
+				warn!("error opening file '{path:?}': '{err:?}'");
+			})?);
+			block_hashes(&mut file, &block_size).await.map_err(|err| {
+				warn!("error hashing file '{path:?}': '{err:?}'");
+			})?
+		};
+
+		requests.push(SpaceblockRequest {
+			name,
+			size: if is_dir { 0 } else { metadata.len() },
+			range: Range::Full,
+			block_hashes,
+			is_dir,
+			modified: metadata.modified().ok(),
+			permissions: permissions(&metadata),
+		});
+		paths.push(path);
+	}
 
 	let id = Uuid::new_v4();
 	debug!("({id}): starting Spacedrop with peer '{identity}");
@@ -82,80 +128,246 @@ pub async fn spacedrop(
 	})?;
 
 	tokio::spawn(async move {
-		debug!("({id}): connected, sending header");
-		let header = Header::Spacedrop(SpaceblockRequests {
+		let requests = SpaceblockRequests {
 			id,
-			block_size: BlockSize::from_size(total_length),
+			block_size,
 			requests,
-		});
-		if let Err(err) = stream.write_all(&header.to_bytes()).await {
-			debug!("({id}): failed to send header: {err}");
-			return;
-		}
-		let Header::Spacedrop(requests) = header else {
-			unreachable!();
-		};
-
-		debug!("({id}): waiting for response");
-		let result = tokio::select! {
-		  result = stream.read_u8() => result,
-		  // Add 5 seconds incase the user responded on the deadline and slow network
-		   _ = sleep(SPACEDROP_TIMEOUT + Duration::from_secs(5)) => {
-				debug!("({id}): timed out, cancelling");
-				p2p.events.send(P2PEvent::SpacedropTimedOut { id }).ok();
-				return;
-			},
 		};
 
-		match result {
-			Ok(0) => {
-				debug!("({id}): Spacedrop was rejected from peer '{identity}'");
-				p2p.events.send(P2PEvent::SpacedropRejected { id }).ok();
-				return;
-			}
-			Ok(1) => {}                     // Okay
-			Ok(_) => todo!(),               // TODO: Proper error
-			Err(err) => todo!("{:?}", err), // TODO: Proper error
-		}
-
 		let cancelled = Arc::new(AtomicBool::new(false));
 		p2p.spacedrop_cancellations
 			.lock()
 			.unwrap_or_else(PoisonError::into_inner)
 			.insert(id, cancelled.clone());
 
-		debug!("({id}): starting transfer");
 		let i = Instant::now();
+		let mut resumes = 0;
+		loop {
+			match send(&p2p, identity, &mut stream, &requests, &paths, &cancelled).await {
+				Ok(()) => {
+					debug!("({id}): finished; took '{:?}", i.elapsed());
+					return;
+				}
+				Err(Interruption::Disconnected) if resumes < SPACEDROP_MAX_RESUMES => {
+					resumes += 1;
+				}
+				Err(_) => {
+					// TODO: Error to frontend
+					// p2p.events
+					// 	.send(P2PEvent::SpacedropFailed { id })
+					// 	.ok();
+					return;
+				}
+			}
 
-		let mut transfer = Transfer::new(
-			&requests,
-			|percent| {
-				p2p.events
-					.send(P2PEvent::SpacedropProgress { id, percent })
-					.ok();
-			},
-			&cancelled,
-		);
-
-		for (file_id, (path, file)) in files.into_iter().enumerate() {
-			debug!("({id}): transmitting '{file_id}' from '{path:?}'");
-			let file = BufReader::new(file);
-			if let Err(err) = transfer.send(&mut stream, file).await {
-				debug!("({id}): failed to send file '{file_id}': {err}");
-				// TODO: Error to frontend
-				// p2p.events
-				// 	.send(P2PEvent::SpacedropFailed { id, file_id })
-				// 	.ok();
+			debug!("({id}): interrupted, waiting for '{identity}' to resume");
+			let Some(new_stream) = reconnect(&p2p, id, identity, &cancelled).await else {
 				return;
-			}
+			};
+			stream = new_stream;
 		}
-
-		debug!("({id}): finished; took '{:?}", i.elapsed());
 	});
 
 	Ok(id)
 }
 
+/// Lists `path` and everything inside it when it's a directory, with their names relative to the
+/// parent of `path`. Symlinks inside directories are skipped, so a tree can't reach out of itself.
+async fn walk(path: PathBuf) -> io::Result<Vec<(PathBuf, String, Metadata)>> {
+	let name = path
+		.file_name()
+		.map(|v| v.to_string_lossy())
+		.unwrap_or(Cow::Borrowed(""))
+		.to_string();
+	let metadata = fs::metadata(&path).await?;
+
+	let mut entries = Vec::new();
+	let mut to_walk = vec![(path, name, metadata)];
+	while let Some((path, name, metadata)) = to_walk.pop() {
+		if metadata.is_dir() {
+			let mut read_dir = fs::read_dir(&path).await?;
+			while let Some(entry) = read_dir.next_entry().await? {
+				let metadata = fs::symlink_metadata(entry.path()).await?;
+				if metadata.is_symlink() {
+					debug!("skipping symlink '{:?}'", entry.path());
+					continue;
+				}
+
+				let child_name = format!("{name}/{}", entry.file_name().to_string_lossy());
+				to_walk.push((entry.path(), child_name, metadata));
+			}
+		}
+
+		entries.push((path, name, metadata));
+	}
+
+	Ok(entries)
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &Metadata) -> Option<u32> {
+	use std::os::unix::fs::PermissionsExt;
+
+	// Setuid, setgid and sticky bits are never sent, a peer has no business setting them
+	Some(metadata.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn permissions(_: &Metadata) -> Option<u32> {
+	None
+}
+
+#[cfg(unix)]
+async fn set_permissions(path: &Path, mode: u32) -> io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+
+	// Only the permission bits are applied, whatever the sender put in the rest of the mode
+	fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777)).await
+}
+
+#[cfg(not(unix))]
+async fn set_permissions(_: &Path, _: u32) -> io::Result<()> {
+	Ok(())
+}
+
+/// Why an attempt at sending a Spacedrop stopped early
+enum Interruption {
+	/// The connection broke, the receiver kept what it got so it's worth trying again
+	Disconnected,
+	/// Rejected or timed out, trying again wouldn't change that
+	Stopped,
+}
+
+async fn send(
+	p2p: &P2PManager,
+	identity: RemoteIdentity,
+	stream: &mut UnicastStream,
+	requests: &SpaceblockRequests,
+	paths: &[PathBuf],
+	cancelled: &AtomicBool,
+) -> Result<(), Interruption> {
+	let id = requests.id;
+
+	debug!("({id}): connected, sending header");
+	let header = Header::Spacedrop(requests.clone());
+	stream.write_all(&header.to_bytes()).await.map_err(|err| {
+		debug!("({id}): failed to send header: {err}");
+		Interruption::Disconnected
+	})?;
+
+	debug!("({id}): waiting for response");
+	let result = tokio::select! {
+	  result = stream.read_u8() => result,
+	  // Add 5 seconds incase the user responded on the deadline and slow network
+	   _ = sleep(SPACEDROP_TIMEOUT + Duration::from_secs(5)) => {
+			debug!("({id}): timed out, cancelling");
+			p2p.events.send(P2PEvent::SpacedropTimedOut { id }).ok();
+			return Err(Interruption::Stopped);
+		},
+	};
+
+	match result {
+		Ok(0) => {
+			debug!("({id}): Spacedrop was rejected from peer '{identity}'");
+			p2p.events.send(P2PEvent::SpacedropRejected { id }).ok();
+			return Err(Interruption::Stopped);
+		}
+		Ok(1) => {} // Okay
+		Ok(SPACEDROP_NOT_ALLOWED) => {
+			debug!("({id}): peer '{identity}' doesn't allow us to Spacedrop it");
+			p2p.events.send(P2PEvent::SpacedropNotAllowed { id }).ok();
+			return Err(Interruption::Stopped);
+		}
+		Ok(response) => {
+			debug!("({id}): invalid response '{response}' from peer '{identity}'");
+			return Err(Interruption::Stopped);
+		}
+		Err(err) => {
+			debug!("({id}): failed to read response: {err}");
+			return Err(Interruption::Disconnected);
+		}
+	}
+
+	// Along with accepting, the receiver tells us what it's still missing of each file
+	let mut requests = requests.clone();
+	for req in &mut requests.requests {
+		req.range = Range::from_stream(stream).await.map_err(|err| {
+			debug!("({id}): failed to read range of '{}': {err}", req.name);
+			Interruption::Disconnected
+		})?;
+	}
+
+	debug!("({id}): starting transfer");
+	let mut transfer = Transfer::new(
+		&requests,
+		|percent| {
+			p2p.events
+				.send(P2PEvent::SpacedropProgress { id, percent })
+				.ok();
+		},
+		cancelled,
+	);
+
+	for (file_id, (req, path)) in requests.requests.iter().zip(paths).enumerate() {
+		if req.is_dir {
+			transfer.skip().map_err(|err| {
+				debug!("({id}): failed to skip directory '{file_id}': {err}");
+				Interruption::Stopped
+			})?;
+			continue;
+		}
+
+		debug!("({id}): transmitting '{file_id}' from '{path:?}'");
+		let file = File::open(path).await.map_err(|err| {
+			debug!("({id}): failed to open file '{file_id}': {err}");
+			Interruption::Stopped
+		})?;
+
+		transfer
+			.send(stream, BufReader::new(file))
+			.await
+			.map_err(|err| {
+				debug!("({id}): failed to send file '{file_id}': {err}");
+				Interruption::Disconnected
+			})?;
+	}
+
+	Ok(())
+}
+
+/// Waits for the peer of an interrupted Spacedrop to be reachable again
+async fn reconnect(
+	p2p: &P2PManager,
+	id: Uuid,
+	identity: RemoteIdentity,
+	cancelled: &AtomicBool,
+) -> Option<UnicastStream> {
+	let deadline = Instant::now() + SPACEDROP_RESUME_TIMEOUT;
+
+	while Instant::now() < deadline {
+		sleep(SPACEDROP_RESUME_INTERVAL).await;
+
+		if cancelled.load(Ordering::Relaxed) {
+			return None;
+		}
+
+		let Some(peer) = p2p.p2p.peers().get(&identity).cloned() else {
+			continue;
+		};
+
+		match peer.new_stream().await {
+			Ok(stream) => {
+				debug!("({id}): reconnected to '{identity}', resuming");
+				return Some(stream);
+			}
+			Err(err) => debug!("({id}): failed to reconnect to '{identity}': {err:?}"),
+		}
+	}
+
+	debug!("({id}): '{identity}' didn't come back, giving up");
+	None
+}
+
 // TODO: Move these off the manager
 impl P2PManager {
 	pub async fn accept_spacedrop(&self, id: Uuid, path: String) {
@@ -214,6 +426,51 @@ pub(crate) async fn receiver(
 		stream.remote_identity(),
 		req.block_size
 	);
+
+	let permissions = this
+		.peer_permissions(&stream.remote_identity())
+		.await
+		.filter(|permissions| permissions.allows(PeerCapability::Spacedrop));
+	let Some(permissions) = permissions else {
+		info!(
+			"({id}): peer '{}' isn't allowed to Spacedrop, rejecting",
+			stream.remote_identity()
+		);
+
+		stream
+			.write_all(&[SPACEDROP_NOT_ALLOWED])
+			.await
+			.map_err(|err| {
+				error!("({id}): error sending rejection: '{err:?}'");
+			})?;
+		return stream.flush().await.map_err(|err| {
+			error!("({id}): error flushing rejection: '{err:?}'");
+		});
+	};
+
+	let interrupted = this
+		.spacedrop_interrupted
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+		.remove(&id)
+		.filter(|(_, _, interrupted_at)| interrupted_at.elapsed() < SPACEDROP_INTERRUPTED_TTL);
+	if let Some((identity, file_path, _)) = interrupted {
+		if identity == stream.remote_identity() {
+			info!("({id}): resuming, saving to '{file_path:?}'");
+			return receive(this, req, &mut stream, file_path).await;
+		}
+
+		warn!(
+			"({id}): peer '{}' tried to resume a Spacedrop from '{identity}'",
+			stream.remote_identity()
+		);
+	}
+
+	if let Some(file_path) = permissions.spacedrop_auto_accept {
+		info!("({id}): auto accepted saving to '{file_path:?}'");
+		return receive(this, req, &mut stream, file_path).await;
+	}
+
 	this.spacedrop_pairing_reqs
 		.lock()
 		.unwrap_or_else(PoisonError::into_inner)
@@ -263,82 +520,424 @@ pub(crate) async fn receiver(
 				Ok(Some(file_path)) => {
 					info!("({id}): accepted saving to '{:?}'", file_path);
 
-					let cancelled = Arc::new(AtomicBool::new(false));
-					this.spacedrop_cancellations
-						.lock()
-						.unwrap_or_else(PoisonError::into_inner)
-						.insert(id, cancelled.clone());
+					receive(this, req, &mut stream, file_path).await?;
+				}
+				Ok(None) => {
+					info!("({id}): rejected");
+
+					stream.write_all(&[0]).await.map_err(|err| {
+					   error!("({id}): error sending rejection: '{err:?}'");
+					})?;
+					stream.flush().await.map_err(|err| {
+					   error!("({id}): error flushing rejection: '{err:?}'");
+					})?;
+				}
+				Err(_) => {
+					warn!("({id}): error with Spacedrop pairing request receiver!");
+				}
+			}
+		}
+	};
+
+	Ok(())
+}
 
-					stream.write_all(&[1]).await.map_err(|err| {
-						error!("({id}): error sending continuation bit: '{err:?}'");
+/// Receives the files of an accepted Spacedrop, picking up from what an earlier attempt at it left
+async fn receive(
+	this: &Arc<P2PManager>,
+	mut req: SpaceblockRequests,
+	stream: &mut UnicastStream,
+	file_path: String,
+) -> Result<(), ()> {
+	let id = req.id;
+	let identity = stream.remote_identity();
 
-						// TODO: Send error to the frontend
+	let cancelled = Arc::new(AtomicBool::new(false));
+	this.spacedrop_cancellations
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+		.insert(id, cancelled.clone());
 
-						// TODO: make sure the other peer times out or we retry???
-					})?;
+	let files = match prepare(identity, &mut req, Path::new(&file_path)).await {
+		Ok(files) => files,
+		Err(err) => {
+			error!("({id}): error preparing files in '{file_path:?}': '{err:?}'");
+
+			// TODO: Send error to the frontend
+
+			// We can't receive them so we reject the Spacedrop rather than leaving the sender waiting
+			stream.write_all(&[0]).await.map_err(|err| {
+				error!("({id}): error sending rejection: '{err:?}'");
+			})?;
+			stream.flush().await.map_err(|err| {
+				error!("({id}): error flushing rejection: '{err:?}'");
+			})?;
+
+			return Err(());
+		}
+	};
+
+	// The continuation bit is followed by the range we still need of each file
+	let mut reply = vec![1];
+	for request in &req.requests {
+		reply.extend(request.range.to_bytes());
+	}
+	stream.write_all(&reply).await.map_err(|err| {
+		error!("({id}): error sending continuation bit: '{err:?}'");
+
+		// TODO: Send error to the frontend
+	})?;
+	stream.flush().await.map_err(|err| {
+		error!("({id}): error flushing continuation bit: '{err:?}'");
+	})?;
 
-					let names = req.requests.iter().map(|req| req.name.clone()).collect::<Vec<_>>();
-					let mut transfer = Transfer::new(&req, |percent| {
-						this.events.send(P2PEvent::SpacedropProgress { id, percent }).ok();
-					}, &cancelled);
+	let mut transfer = Transfer::new(
+		&req,
+		|percent| {
+			this.events
+				.send(P2PEvent::SpacedropProgress { id, percent })
+				.ok();
+		},
+		&cancelled,
+	);
 
-					let file_path = PathBuf::from(file_path);
-					let names_len = names.len();
-					for file_name in names {
-						 // When transferring more than 1 file we wanna join the incoming file name to the directory provided by the user
-						 let mut path = file_path.clone();
-						 if names_len != 1 {
-							// We know the `file_path` will be a directory so we can just push the file name to it
-							path.push(&file_name);
-						}
+	let (paths, files): (Vec<_>, Vec<_>) = files.into_iter().unzip();
+	for ((request, path), file) in req.requests.iter().zip(&paths).zip(files) {
+		let Some((partial, file)) = file else {
+			transfer.skip().map_err(|err| {
+				error!("({id}): error skipping '{}': '{err:?}'", request.name);
+			})?;
 
-						debug!("({id}): accepting '{file_name}' and saving to '{:?}'", path);
+			// Files an earlier attempt completed may have been interrupted before their metadata
+			if !request.is_dir {
+				apply_metadata(id, path, request).await;
+			}
+			continue;
+		};
 
-						if let Some(parent) = path.parent() {
-						  create_dir_all(&parent).await.map_err(|err| {
-								error!("({id}): error creating parent directory '{parent:?}': '{err:?}'");
+		debug!(
+			"({id}): accepting '{}' and saving to '{:?}'",
+			request.name, path
+		);
 
-								// TODO: Send error to the frontend
+		let mut file = BufWriter::new(file);
+		if let Err(err) = transfer.receive(stream, &mut file).await {
+			error!("({id}): error receiving file '{}': '{err:?}'", request.name);
 
-								// TODO: Send error to remote peer
-							})?;
-						}
+			// TODO: Send error to frontend
 
-						let f = File::create(&path).await.map_err(|err| {
-							error!("({id}): error creating file at '{path:?}': '{err:?}'");
+			// The sender retries once it can reach us again, resuming from what we verified so far.
+			// Senders which never come back are forgotten here, so the map can't grow forever.
+			let mut interrupted = this
+				.spacedrop_interrupted
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner);
+			interrupted.retain(|_, (_, _, interrupted_at)| {
+				interrupted_at.elapsed() < SPACEDROP_INTERRUPTED_TTL
+			});
+			interrupted.insert(id, (identity, file_path, Instant::now().into_std()));
 
-							// TODO: Send error to the frontend
+			return Err(());
+		}
 
-							// TODO: Send error to remote peer
-						})?;
-						let f = BufWriter::new(f);
-						if let Err(err) = transfer.receive(&mut stream, f).await {
-							error!("({id}): error receiving file '{file_name}': '{err:?}'");
+		// Cancelling from either side stops the transfer early without an error
+		if !partial.is_complete(&mut file, request.size).await {
+			info!("({id}): cancelled");
+			return Ok(());
+		}
 
-							// TODO: Send error to frontend
+		partial.complete().await.map_err(|err| {
+			error!(
+				"({id}): error moving '{}' to '{:?}': '{err:?}'",
+				request.name, path
+			);
+		})?;
 
-							break;
-						}
-					}
+		apply_metadata(id, path, request).await;
+	}
 
-					info!("({id}): complete");
-				}
-				Ok(None) => {
-					info!("({id}): rejected");
+	// Directories go last and deepest first, as writing into them changes their modification time
+	for (request, path) in req.requests.iter().zip(&paths).rev() {
+		if request.is_dir {
+			apply_metadata(id, path, request).await;
+		}
+	}
 
-					stream.write_all(&[0]).await.map_err(|err| {
-					   error!("({id}): error sending rejection: '{err:?}'");
-					})?;
-					stream.flush().await.map_err(|err| {
-					   error!("({id}): error flushing rejection: '{err:?}'");
-					})?;
-				}
-				Err(_) => {
-					warn!("({id}): error with Spacedrop pairing request receiver!");
+	info!("({id}): complete");
+
+	Ok(())
+}
+
+/// Works out where each file and directory of a Spacedrop goes, creating the directories and
+/// opening the partial files the others are received into. Files already at their destination,
+/// like the ones an earlier attempt at the Spacedrop completed, are requested with an empty range.
+async fn prepare(
+	identity: RemoteIdentity,
+	req: &mut SpaceblockRequests,
+	base_path: &Path,
+) -> io::Result<Vec<(PathBuf, Option<(PartialFile, File)>)>> {
+	let names_len = req.requests.len();
+	let mut files = Vec::with_capacity(names_len);
+
+	for request in &mut req.requests {
+		// When transferring more than 1 file we wanna join the incoming file name to the directory provided by the user
+		let path = if names_len == 1 {
+			base_path.to_path_buf()
+		} else {
+			// We know the `file_path` will be a directory so we can just join the relative path to it
+			request.path_in(base_path).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!(
+						"'{}' would be saved outside of the destination",
+						request.name
+					),
+				)
+			})?
+		};
+
+		if request.is_dir {
+			create_dir_all(&path).await?;
+			files.push((path, None));
+			continue;
+		}
+
+		let partial = PartialFile::new(path.clone());
+		if partial.is_received(request, &req.block_size).await? {
+			request.range = Range::Partial(request.size..request.size);
+			files.push((path, None));
+			continue;
+		}
+
+		let (file, range) = partial.open(identity, request, &req.block_size).await?;
+		request.range = range;
+		files.push((path, Some((partial, file))));
+	}
+
+	Ok(files)
+}
+
+/// Applies the modification time and permissions the sender had. Failing to only loses that
+/// metadata, so errors are just logged.
+async fn apply_metadata(id: Uuid, path: &Path, request: &SpaceblockRequest) {
+	// Before the permissions, which could make the file read only
+	if let Some(modified) = request.modified {
+		let (file_path, is_dir) = (path.to_path_buf(), request.is_dir);
+		let result = spawn_blocking(move || {
+			// Directories can't be opened for writing, but setting their times doesn't need it on Unix
+			std::fs::File::options()
+				.read(is_dir)
+				.write(!is_dir)
+				.open(file_path)?
+				.set_modified(modified)
+		})
+		.await;
+
+		match result {
+			Ok(Ok(())) => {}
+			Ok(Err(err)) => {
+				debug!("({id}): error setting modification time of '{path:?}': '{err:?}'")
+			}
+			Err(err) => error!("({id}): error joining modification time task: '{err:?}'"),
+		}
+	}
+
+	if let Some(mode) = request.permissions {
+		if let Err(err) = set_permissions(path, mode).await {
+			debug!("({id}): error setting permissions of '{path:?}': '{err:?}'");
+		}
+	}
+}
+
+/// A file being received, written next to its destination until all of it arrived. A sidecar
+/// records what it belongs to, so a later attempt at sending the same file can resume it.
+struct PartialFile {
+	path: PathBuf,
+	data_path: PathBuf,
+	state_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+struct PartialFileState {
+	identity: String,
+	name: String,
+	size: u64,
+	block_size: u32,
+	/// BLAKE3 hash over the hashes of every block of the file
+	hashes: String,
+}
+
+impl PartialFile {
+	fn new(path: PathBuf) -> Self {
+		let with_suffix = |suffix: &str| {
+			let mut path = path.clone().into_os_string();
+			path.push(suffix);
+			PathBuf::from(path)
+		};
+
+		Self {
+			data_path: with_suffix(".sdpart"),
+			state_path: with_suffix(".sdpart.json"),
+			path,
+		}
+	}
+
+	/// Opens the partial file where the transfer should continue from, along with the range to
+	/// request from the sender
+	async fn open(
+		&self,
+		identity: RemoteIdentity,
+		req: &SpaceblockRequest,
+		block_size: &BlockSize,
+	) -> io::Result<(File, Range)> {
+		if let Some(parent) = self.path.parent() {
+			create_dir_all(parent).await?;
+		}
+
+		let mut hasher = blake3::Hasher::new();
+		for hash in &req.block_hashes {
+			hasher.update(hash.as_bytes());
+		}
+		let state = PartialFileState {
+			identity: identity.to_string(),
+			name: req.name.clone(),
+			size: req.size,
+			block_size: block_size.size(),
+			hashes: hasher.finalize().to_hex().to_string(),
+		};
+
+		let resumable = match fs::read(&self.state_path).await {
+			Ok(bytes) => serde_json::from_slice::<PartialFileState>(&bytes)
+				.is_ok_and(|existing| existing == state),
+			Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+			Err(err) => return Err(err),
+		};
+
+		if resumable {
+			match OpenOptions::new()
+				.read(true)
+				.write(true)
+				.open(&self.data_path)
+				.await
+			{
+				Ok(mut file) => {
+					// Anything after the last block matching its hash is received again
+					let offset = req.resume_offset(&mut file, block_size).await?;
+					file.set_len(offset).await?;
+					file.seek(SeekFrom::Start(offset)).await?;
+
+					return Ok((file, Range::Partial(offset..req.size)));
 				}
+				Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+				Err(err) => return Err(err),
 			}
 		}
-	};
 
-	Ok(())
+		let file = File::create(&self.data_path).await?;
+		fs::write(&self.state_path, serde_json::to_vec(&state)?).await?;
+
+		Ok((file, Range::Full))
+	}
+
+	/// Whether the destination already holds the whole file, every block matching its hash
+	async fn is_received(
+		&self,
+		req: &SpaceblockRequest,
+		block_size: &BlockSize,
+	) -> io::Result<bool> {
+		let file = match File::open(&self.path).await {
+			Ok(file) => file,
+			Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+			Err(err) => return Err(err),
+		};
+
+		let metadata = file.metadata().await?;
+		if !metadata.is_file() || metadata.len() != req.size {
+			return Ok(false);
+		}
+
+		Ok(req
+			.resume_offset(&mut BufReader::new(file), block_size)
+			.await? == req.size)
+	}
+
+	async fn is_complete(&self, file: &mut BufWriter<File>, size: u64) -> bool {
+		file.flush().await.is_ok()
+			&& fs::metadata(&self.data_path)
+				.await
+				.is_ok_and(|metadata| metadata.len() == size)
+	}
+
+	/// Moves the fully received file to its destination
+	async fn complete(&self) -> io::Result<()> {
+		fs::rename(&self.data_path, &self.path).await?;
+		fs::remove_file(&self.state_path).await
+	}
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::panic)]
+mod tests {
+	use std::io::Cursor;
+
+	use sd_p2p::Identity;
+
+	use super::*;
+
+	async fn request(name: &str, data: &[u8], block_size: &BlockSize) -> SpaceblockRequest {
+		SpaceblockRequest {
+			name: name.to_string(),
+			size: data.len() as u64,
+			range: Range::Full,
+			block_hashes: block_hashes(&mut Cursor::new(data), block_size)
+				.await
+				.unwrap(),
+			is_dir: false,
+			modified: None,
+			permissions: None,
+		}
+	}
+
+	#[tokio::test]
+	async fn resuming_skips_received_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let identity = Identity::new().to_remote_identity();
+		let block_size = BlockSize::from_size(0);
+		let block = block_size.size() as usize;
+		let (first, second) = (vec![1; block + 42], vec![2; 2 * block + 42]);
+
+		let mut req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size: block_size.clone(),
+			requests: vec![
+				request("first", &first, &block_size).await,
+				request("second", &second, &block_size).await,
+			],
+		};
+
+		// The first attempt receives the first file and a block of the second before breaking
+		let mut attempt = req.clone();
+		let mut files = prepare(identity, &mut attempt, dir.path()).await.unwrap();
+		let (_, Some((partial, mut file))) = files.remove(0) else {
+			panic!("the first file should be received");
+		};
+		file.write_all(&first).await.unwrap();
+		file.flush().await.unwrap();
+		partial.complete().await.unwrap();
+		let (_, Some((_, mut file))) = files.remove(0) else {
+			panic!("the second file should be received");
+		};
+		file.write_all(&second[..block]).await.unwrap();
+		file.flush().await.unwrap();
+
+		let files = prepare(identity, &mut req, dir.path()).await.unwrap();
+		assert!(files[0].1.is_none());
+		assert!(req.requests[0].range.bytes(first.len() as u64).is_empty());
+		assert!(files[1].1.is_some());
+		assert_eq!(
+			req.requests[1].range,
+			Range::Partial(block as u64..second.len() as u64)
+		);
+		assert_eq!(fs::read(dir.path().join("first")).await.unwrap(), first);
+	}
 }
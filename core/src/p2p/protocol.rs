@@ -13,6 +13,8 @@ pub enum Header {
 	Sync(Uuid),
 	// A HTTP server used for rspc requests and streaming files
 	Http,
+	// Pairing with a node showing a pairing code, so it trusts us from now on
+	Pair,
 }
 
 #[derive(Debug, Error)]
@@ -45,6 +47,7 @@ impl Header {
 					.map_err(HeaderError::SyncRequest)?,
 			)),
 			5 => Ok(Self::Http),
+			6 => Ok(Self::Pair),
 			d => Err(HeaderError::DiscriminatorInvalid(d)),
 		}
 	}
@@ -63,6 +66,7 @@ impl Header {
 				bytes
 			}
 			Self::Http => vec![5],
+			Self::Pair => vec![6],
 		}
 	}
 }
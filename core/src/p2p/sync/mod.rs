@@ -21,7 +21,7 @@ pub use proto::*;
 
 pub use originator::run as originator;
 mod originator {
-	use crate::p2p::Header;
+	use crate::{node::config::PeerCapability, p2p::Header};
 
 	use super::*;
 	use responder::tx as rx;
@@ -88,7 +88,16 @@ mod originator {
 				continue;
 			};
 
+			// The peer gets our operations, so it must be one we sync this library with
+			if !p2p
+				.allows(&remote_identity, PeerCapability::Sync(library_id))
+				.await
+			{
+				continue;
+			}
+
 			let sync = sync.clone();
+			let identity = p2p.p2p.identity().clone();
 
 			tokio::spawn(async move {
 				debug!(
@@ -102,7 +111,13 @@ mod originator {
 					.await
 					.unwrap();
 
-				let mut tunnel = Tunnel::initiator(stream).await.unwrap();
+				let mut tunnel = match Tunnel::initiator(stream, &identity).await {
+					Ok(tunnel) => tunnel,
+					Err(err) => {
+						debug!("Failed to open tunnel with peer '{remote_identity:?}': {err}");
+						return;
+					}
+				};
 
 				tunnel
 					.write_all(&SyncMessage::NewOperations.to_bytes())
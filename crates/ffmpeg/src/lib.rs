@@ -9,11 +9,13 @@ use std::path::Path;
 mod error;
 mod film_strip;
 mod movie_decoder;
+mod probe;
 mod thumbnailer;
 mod utils;
 mod video_frame;
 
 pub use error::Error;
+pub use probe::{probe, HdrFormat, MediaInfo, VideoStreamInfo};
 pub use thumbnailer::{Thumbnailer, ThumbnailerBuilder};
 
 /// Helper function to generate a thumbnail file from a video file with reasonable defaults
@@ -0,0 +1,258 @@
+use crate::{
+	error::{Error, FfmpegError},
+	utils::from_path,
+};
+
+use ffmpeg_sys_next::{
+	av_dict_get, av_display_rotation_get, av_find_best_stream, av_stream_get_side_data,
+	avcodec_get_name, avformat_close_input, avformat_find_stream_info, avformat_open_input,
+	AVColorTransferCharacteristic, AVDictionary, AVFormatContext, AVMediaType,
+	AVPacketSideDataType, AVRational, AVStream, AV_DICT_IGNORE_SUFFIX, AV_DISPOSITION_ATTACHED_PIC,
+	AV_TIME_BASE,
+};
+use std::{
+	collections::HashMap,
+	ffi::{CStr, CString},
+	path::Path,
+	time::Duration,
+};
+
+/// Container and stream information, read from the file headers without decoding any frames
+#[derive(Debug, Clone, Default)]
+pub struct MediaInfo {
+	pub duration: Option<Duration>,
+	/// Overall bitrate of the container in bits per second
+	pub bitrate: Option<i64>,
+	pub video_streams: u32,
+	pub audio_streams: u32,
+	pub subtitle_streams: u32,
+	/// The stream FFmpeg considers the main video stream of the file
+	pub video: Option<VideoStreamInfo>,
+	pub audio_codec: Option<String>,
+	/// Container tags, with lowercased keys (e.g. `creation_time`, `location`)
+	pub tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VideoStreamInfo {
+	pub codec: Option<String>,
+	pub width: i32,
+	pub height: i32,
+	pub frame_rate: Option<f64>,
+	/// Clockwise rotation in degrees that must be applied for correct playback
+	pub rotation: Option<i32>,
+	pub hdr: Option<HdrFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrFormat {
+	/// SMPTE ST 2084 (PQ) transfer, as used by HDR10 and HDR10+
+	Pq,
+	/// ARIB STD-B67, Hybrid Log-Gamma
+	Hlg,
+	DolbyVision,
+}
+
+struct InputContext(*mut AVFormatContext);
+
+impl Drop for InputContext {
+	fn drop(&mut self) {
+		if !self.0.is_null() {
+			unsafe { avformat_close_input(&mut self.0) };
+		}
+	}
+}
+
+/// Reads container and stream information from a media file
+pub fn probe(path: impl AsRef<Path>) -> Result<MediaInfo, Error> {
+	let path_cstring = from_path(path)?;
+	let mut input = InputContext(std::ptr::null_mut());
+
+	match unsafe {
+		avformat_open_input(
+			&mut input.0,
+			path_cstring.as_ptr(),
+			std::ptr::null_mut(),
+			std::ptr::null_mut(),
+		)
+	} {
+		0 => {}
+		e => {
+			return Err(Error::FfmpegWithReason(
+				FfmpegError::from(e),
+				"Failed to open input".to_string(),
+			))
+		}
+	}
+
+	match unsafe { avformat_find_stream_info(input.0, std::ptr::null_mut()) } {
+		e if e < 0 => {
+			return Err(Error::FfmpegWithReason(
+				FfmpegError::from(e),
+				"Failed to get stream info".to_string(),
+			))
+		}
+		_ => {}
+	}
+
+	let format_context = unsafe { &*input.0 };
+
+	let mut info = MediaInfo {
+		duration: u64::try_from(format_context.duration)
+			.ok()
+			.filter(|duration| *duration > 0)
+			.map(|duration| {
+				Duration::from_micros(duration * 1_000_000 / u64::from(AV_TIME_BASE.unsigned_abs()))
+			}),
+		bitrate: (format_context.bit_rate > 0).then_some(format_context.bit_rate),
+		tags: read_dictionary(format_context.metadata),
+		..Default::default()
+	};
+
+	let best_video_stream_idx = unsafe {
+		av_find_best_stream(
+			input.0,
+			AVMediaType::AVMEDIA_TYPE_VIDEO,
+			-1,
+			-1,
+			std::ptr::null_mut(),
+			0,
+		)
+	};
+
+	for stream_idx in 0..format_context.nb_streams {
+		let stream = unsafe { &**format_context.streams.offset(stream_idx.try_into()?) };
+		let codec_params = unsafe { &*stream.codecpar };
+
+		match codec_params.codec_type {
+			AVMediaType::AVMEDIA_TYPE_VIDEO => {
+				// Embedded cover art is exposed as a single frame video stream, it isn't a real video
+				if stream.disposition & AV_DISPOSITION_ATTACHED_PIC != 0 {
+					continue;
+				}
+
+				info.video_streams += 1;
+
+				if i32::try_from(stream_idx)? == best_video_stream_idx || info.video.is_none() {
+					info.video = Some(VideoStreamInfo {
+						codec: codec_name(codec_params.codec_id),
+						width: codec_params.width,
+						height: codec_params.height,
+						frame_rate: rational_to_f64(stream.avg_frame_rate)
+							.or_else(|| rational_to_f64(stream.r_frame_rate)),
+						rotation: stream_rotation(stream),
+						hdr: stream_hdr_format(stream, codec_params.color_trc),
+					});
+				}
+			}
+			AVMediaType::AVMEDIA_TYPE_AUDIO => {
+				info.audio_streams += 1;
+				if info.audio_codec.is_none() {
+					info.audio_codec = codec_name(codec_params.codec_id);
+				}
+			}
+			AVMediaType::AVMEDIA_TYPE_SUBTITLE => info.subtitle_streams += 1,
+			_ => {}
+		}
+	}
+
+	Ok(info)
+}
+
+fn read_dictionary(dictionary: *const AVDictionary) -> HashMap<String, String> {
+	let mut entries = HashMap::new();
+
+	if dictionary.is_null() {
+		return entries;
+	}
+
+	let empty_cstring = CString::default();
+	let mut tag = std::ptr::null_mut();
+	loop {
+		tag = unsafe {
+			av_dict_get(
+				dictionary,
+				empty_cstring.as_ptr(),
+				tag,
+				AV_DICT_IGNORE_SUFFIX,
+			)
+		};
+
+		if tag.is_null() {
+			break;
+		}
+
+		// WARNING: NEVER use CString with foreign raw pointer (causes double-free)
+		let (key, value) = unsafe { (CStr::from_ptr((*tag).key), CStr::from_ptr((*tag).value)) };
+		if let (Ok(key), Ok(value)) = (key.to_str(), value.to_str()) {
+			entries.insert(key.to_lowercase(), value.to_string());
+		}
+	}
+
+	entries
+}
+
+fn codec_name(codec_id: ffmpeg_sys_next::AVCodecID) -> Option<String> {
+	let name = unsafe { avcodec_get_name(codec_id) };
+	if name.is_null() {
+		return None;
+	}
+
+	unsafe { CStr::from_ptr(name) }
+		.to_str()
+		.ok()
+		.filter(|name| *name != "none")
+		.map(ToString::to_string)
+}
+
+fn rational_to_f64(rational: AVRational) -> Option<f64> {
+	(rational.num > 0 && rational.den > 0)
+		.then(|| f64::from(rational.num) / f64::from(rational.den))
+}
+
+#[allow(clippy::cast_ptr_alignment, clippy::cast_possible_truncation)]
+fn stream_rotation(stream: &AVStream) -> Option<i32> {
+	let matrix = unsafe {
+		av_stream_get_side_data(
+			stream,
+			AVPacketSideDataType::AV_PKT_DATA_DISPLAYMATRIX,
+			std::ptr::null_mut(),
+		)
+	} as *const i32;
+
+	if matrix.is_null() {
+		// Older files carry the rotation as a plain stream tag
+		return read_dictionary(stream.metadata)
+			.get("rotate")
+			.and_then(|rotate| rotate.parse::<i32>().ok())
+			.map(|rotate| rotate.rem_euclid(360));
+	}
+
+	// FFmpeg gives us the counterclockwise rotation of the display matrix,
+	// we want the clockwise rotation to be applied on playback, like the `rotate` tag
+	let angle = unsafe { av_display_rotation_get(matrix) };
+	(!angle.is_nan()).then(|| (-angle.round() as i32).rem_euclid(360))
+}
+
+fn stream_hdr_format(
+	stream: &AVStream,
+	color_transfer: AVColorTransferCharacteristic,
+) -> Option<HdrFormat> {
+	let dolby_vision_config = unsafe {
+		av_stream_get_side_data(
+			stream,
+			AVPacketSideDataType::AV_PKT_DATA_DOVI_CONF,
+			std::ptr::null_mut(),
+		)
+	};
+
+	if !dolby_vision_config.is_null() {
+		return Some(HdrFormat::DolbyVision);
+	}
+
+	match color_transfer {
+		AVColorTransferCharacteristic::AVCOL_TRC_SMPTE2084 => Some(HdrFormat::Pq),
+		AVColorTransferCharacteristic::AVCOL_TRC_ARIB_STD_B67 => Some(HdrFormat::Hlg),
+		_ => None,
+	}
+}
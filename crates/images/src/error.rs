@@ -33,6 +33,8 @@ pub enum Error {
 	Pixbuf,
 	#[error("error while loading the image (via the `image` crate): {0}")]
 	Image(#[from] image::ImageError),
+	#[error("error while encoding the image as webp: {0}")]
+	WebPEncoding(String),
 	// #[error("error while converting from raw")] // not enough rust support for it to be feasible
 	// RawConversion,
 	#[error("error while parsing integers")]
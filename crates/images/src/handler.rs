@@ -1,14 +1,15 @@
 use crate::{
-	consts,
+	consts::{self, ConvertibleExtension},
 	error::{Error, Result},
 	generic::GenericHandler,
 	pdf::PdfHandler,
 	svg::SvgHandler,
 	ImageHandler,
 };
-use image::DynamicImage;
+use image::{DynamicImage, ImageOutputFormat};
 use std::{
 	ffi::{OsStr, OsString},
+	io::Cursor,
 	path::Path,
 };
 
@@ -25,6 +26,62 @@ pub fn convert_image(path: impl AsRef<Path>, desired_ext: &OsStr) -> Result<Dyna
 	match_to_handler(path.extension())?.convert_image(match_to_handler(Some(desired_ext))?, path)
 }
 
+/// Encodes an image in the format of the desired extension.
+///
+/// The `quality` goes from 1 to 100 and is only used by lossy formats (JPEG and WebP).
+pub fn encode_image(
+	image: &DynamicImage,
+	desired_ext: ConvertibleExtension,
+	quality: u8,
+) -> Result<Vec<u8>> {
+	let quality = quality.clamp(1, 100);
+
+	let format = match desired_ext {
+		ConvertibleExtension::Webp => {
+			return webp::Encoder::from_image(image)
+				.map(|encoder| encoder.encode(f32::from(quality)).to_vec())
+				.map_err(|e| Error::WebPEncoding(e.to_string()));
+		}
+		ConvertibleExtension::Jpg | ConvertibleExtension::Jpeg => {
+			// JPEG doesn't support an alpha channel, so we have to drop it first
+			let mut bytes = Cursor::new(Vec::new());
+			DynamicImage::ImageRgb8(image.to_rgb8())
+				.write_to(&mut bytes, ImageOutputFormat::Jpeg(quality))?;
+			return Ok(bytes.into_inner());
+		}
+		ConvertibleExtension::Bmp | ConvertibleExtension::Dib => ImageOutputFormat::Bmp,
+		ConvertibleExtension::Ff => ImageOutputFormat::Farbfeld,
+		ConvertibleExtension::Gif => ImageOutputFormat::Gif,
+		ConvertibleExtension::Ico => ImageOutputFormat::Ico,
+		ConvertibleExtension::Png => ImageOutputFormat::Png,
+		ConvertibleExtension::Pnm => {
+			ImageOutputFormat::Pnm(image::codecs::pnm::PnmSubtype::ArbitraryMap)
+		}
+		ConvertibleExtension::Qoi => ImageOutputFormat::Qoi,
+		ConvertibleExtension::Tga
+		| ConvertibleExtension::Icb
+		| ConvertibleExtension::Vda
+		| ConvertibleExtension::Vst => ImageOutputFormat::Tga,
+		ConvertibleExtension::Tiff | ConvertibleExtension::Tif => ImageOutputFormat::Tiff,
+		// We can read these, but we don't have encoders for them
+		ConvertibleExtension::Hif
+		| ConvertibleExtension::Heif
+		| ConvertibleExtension::Heifs
+		| ConvertibleExtension::Heic
+		| ConvertibleExtension::Heics
+		| ConvertibleExtension::Avif
+		| ConvertibleExtension::Avci
+		| ConvertibleExtension::Avcs
+		| ConvertibleExtension::Svg
+		| ConvertibleExtension::Svgz
+		| ConvertibleExtension::Pdf => return Err(Error::Unsupported),
+	};
+
+	let mut bytes = Cursor::new(Vec::new());
+	image.write_to(&mut bytes, format)?;
+	Ok(bytes.into_inner())
+}
+
 #[allow(clippy::useless_let_if_seq)]
 fn match_to_handler(ext: Option<&OsStr>) -> Result<Box<dyn ImageHandler>> {
 	let ext = ext.map(OsStr::to_ascii_lowercase).unwrap_or_default();
@@ -36,8 +36,9 @@ use consts::MAXIMUM_FILE_SIZE;
 // Re-exports
 pub use consts::{all_compatible_extensions, ConvertibleExtension};
 pub use error::{Error, Result};
-pub use handler::{convert_image, format_image};
+pub use handler::{convert_image, encode_image, format_image};
 pub use image::DynamicImage;
+pub use pdf::extract_pdf_text;
 
 pub trait ImageHandler {
 	#[inline]
@@ -76,14 +76,38 @@ static LANDSCAPE_CONFIG: Lazy<PdfRenderConfig> = Lazy::new(|| {
 	thumbnail_config(PdfRenderConfig::new().set_target_width(PDF_LANDSCAPE_RENDER_WIDTH))
 });
 
+fn load_pdfium() -> Result<Pdfium> {
+	Ok(Pdfium::new(
+		Pdfium::bind_to_library(PDFIUM_LIB.as_str()).or_else(|err| {
+			error!("{err:#?}");
+			Pdfium::bind_to_system_library()
+		})?,
+	))
+}
+
+/// Extracts the text of a PDF page by page, stopping once it has at least `max_len` bytes
+pub fn extract_pdf_text(path: &Path, max_len: usize) -> Result<String> {
+	let pdfium = load_pdfium()?;
+	let pdf = pdfium.load_pdf_from_file(path, None)?;
+
+	let mut text = String::new();
+	for page in pdf.pages().iter() {
+		text.push_str(&page.text()?.all());
+		text.push('\n');
+
+		if text.len() >= max_len {
+			break;
+		}
+	}
+
+	Ok(text)
+}
+
 pub struct PdfHandler {}
 
 impl ImageHandler for PdfHandler {
 	fn handle_image(&self, path: &Path) -> Result<DynamicImage> {
-		let pdfium = Pdfium::new(Pdfium::bind_to_library(PDFIUM_LIB.as_str()).or_else(|err| {
-			error!("{err:#?}");
-			Pdfium::bind_to_system_library()
-		})?);
+		let pdfium = load_pdfium()?;
 
 		let pdf = pdfium.load_pdf_from_file(path, None)?;
 		let first_page = pdf.pages().first()?;
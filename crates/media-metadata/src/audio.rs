@@ -1,19 +1,284 @@
-use std::path::Path;
+use std::{fs::File, path::Path};
 
-use crate::Result;
+use symphonia::core::{
+	codecs::{
+		CodecType, CODEC_TYPE_ATRAC3, CODEC_TYPE_ATRAC3PLUS, CODEC_TYPE_EAC3,
+		CODEC_TYPE_MONKEYS_AUDIO, CODEC_TYPE_MUSEPACK, CODEC_TYPE_OPUS, CODEC_TYPE_SPEEX,
+		CODEC_TYPE_TTA, CODEC_TYPE_WAVPACK, CODEC_TYPE_WMA,
+	},
+	formats::FormatOptions,
+	io::{MediaSourceStream, MediaSourceStreamOptions},
+	meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value},
+	probe::{Hint, ProbeResult},
+	units::TimeBase,
+};
+
+use crate::{Error, Result};
 
 #[derive(
 	Default, Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type,
 )]
 pub struct AudioMetadata {
-	duration: Option<i32>, // can't use `Duration` due to bigint
-	audio_codec: Option<String>,
+	/// Duration in milliseconds
+	pub duration: Option<i32>, // can't use `Duration` due to bigint
+	pub audio_codec: Option<String>,
+	/// Average bitrate in bits per second, derived from the file size and duration
+	pub bitrate: Option<i32>,
+	pub sample_rate: Option<i32>,
+	pub channels: Option<i32>,
+	pub bit_depth: Option<i32>,
+	pub title: Option<String>,
+	pub artist: Option<String>,
+	pub album: Option<String>,
+	pub album_artist: Option<String>,
+	pub genre: Option<String>,
+	pub track_number: Option<i32>,
+	pub year: Option<i32>,
+	pub has_cover_art: bool,
+}
+
+/// Embedded cover art, as found in `ID3v2` `APIC`, FLAC `PICTURE` or MP4 `covr` blocks.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CoverArt {
+	pub media_type: String,
+	pub data: Vec<u8>,
 }
 
 impl AudioMetadata {
-	#[allow(clippy::missing_errors_doc)]
-	#[allow(clippy::missing_panics_doc)]
-	pub fn from_path(_path: impl AsRef<Path>) -> Result<Self> {
-		todo!()
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+		let path = path.as_ref();
+		let file_size = path
+			.metadata()
+			.map_err(|e| Error::Io(e, path.to_path_buf().into_boxed_path()))?
+			.len();
+
+		let mut probed = probe(path)?;
+
+		let params = probed
+			.format
+			.default_track()
+			.ok_or_else(|| Error::NoAudioTrack(path.to_path_buf()))?
+			.codec_params
+			.clone();
+
+		let mut data = Self {
+			duration: params
+				.time_base
+				.zip(params.n_frames)
+				.and_then(|(time_base, n_frames)| duration_to_millis(time_base, n_frames)),
+			audio_codec: codec_name(params.codec),
+			sample_rate: params.sample_rate.and_then(|x| i32::try_from(x).ok()),
+			channels: params
+				.channels
+				.and_then(|channels| i32::try_from(channels.count()).ok()),
+			bit_depth: params.bits_per_sample.and_then(|x| i32::try_from(x).ok()),
+			..Default::default()
+		};
+
+		data.bitrate = data
+			.duration
+			.and_then(|millis| u64::try_from(millis).ok())
+			.filter(|millis| *millis > 0)
+			.and_then(|millis| i32::try_from(file_size * 8 * 1000 / millis).ok());
+
+		// Tags can live outside of the container (e.g. ID3v2 in front of an MP3 stream),
+		// which symphonia reports during probing, or inside of it (Vorbis comments, MP4 atoms).
+		// Container tags take precedence as they're usually the most up to date.
+		if let Some(mut metadata) = probed.metadata.get() {
+			if let Some(revision) = metadata.skip_to_latest() {
+				data.read_revision(revision);
+			}
+		}
+
+		if let Some(revision) = probed.format.metadata().skip_to_latest() {
+			data.read_revision(revision);
+		}
+
+		Ok(data)
+	}
+
+	/// Returns the first embedded picture in the file, preferring the front cover if there are many.
+	pub fn cover_art_from_path(path: impl AsRef<Path>) -> Result<Option<CoverArt>> {
+		use symphonia::core::meta::StandardVisualKey;
+
+		let mut probed = probe(path.as_ref())?;
+
+		let mut visuals = Vec::new();
+		if let Some(mut metadata) = probed.metadata.get() {
+			if let Some(revision) = metadata.skip_to_latest() {
+				visuals.extend_from_slice(revision.visuals());
+			}
+		}
+		if let Some(revision) = probed.format.metadata().skip_to_latest() {
+			visuals.extend_from_slice(revision.visuals());
+		}
+
+		let front_cover_idx = visuals
+			.iter()
+			.position(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+			.unwrap_or_default();
+
+		Ok((!visuals.is_empty()).then(|| {
+			let visual = visuals.swap_remove(front_cover_idx);
+			CoverArt {
+				media_type: visual.media_type,
+				data: visual.data.into_vec(),
+			}
+		}))
+	}
+
+	fn read_revision(&mut self, revision: &MetadataRevision) {
+		for tag in revision.tags() {
+			let Some(std_key) = tag.std_key else {
+				continue;
+			};
+
+			match std_key {
+				StandardTagKey::TrackTitle => {
+					set_if_some(&mut self.title, value_to_string(&tag.value));
+				}
+				StandardTagKey::Artist => {
+					set_if_some(&mut self.artist, value_to_string(&tag.value));
+				}
+				StandardTagKey::Album => set_if_some(&mut self.album, value_to_string(&tag.value)),
+				StandardTagKey::AlbumArtist => {
+					set_if_some(&mut self.album_artist, value_to_string(&tag.value));
+				}
+				StandardTagKey::Genre => set_if_some(&mut self.genre, value_to_string(&tag.value)),
+				StandardTagKey::TrackNumber => {
+					set_if_some(&mut self.track_number, value_to_number(&tag.value));
+				}
+				// `Date` is the most specific tag, so we don't let the other ones override it
+				StandardTagKey::Date => set_if_some(&mut self.year, value_to_year(&tag.value)),
+				StandardTagKey::ReleaseDate | StandardTagKey::OriginalDate
+					if self.year.is_none() =>
+				{
+					self.year = value_to_year(&tag.value);
+				}
+				_ => {}
+			}
+		}
+
+		self.has_cover_art |= !revision.visuals().is_empty();
+	}
+}
+
+fn probe(path: &Path) -> Result<ProbeResult> {
+	let file = File::open(path).map_err(|e| Error::Io(e, path.to_path_buf().into_boxed_path()))?;
+
+	let mut hint = Hint::new();
+	if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+		hint.with_extension(extension);
+	}
+
+	symphonia::default::get_probe()
+		.format(
+			&hint,
+			MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default()),
+			&FormatOptions::default(),
+			&MetadataOptions::default(),
+		)
+		.map_err(Into::into)
+}
+
+fn codec_name(codec: CodecType) -> Option<String> {
+	if let Some(descriptor) = symphonia::default::get_codecs().get_codec(codec) {
+		return Some(descriptor.short_name.to_string());
+	}
+
+	// Codecs that symphonia can identify in a container but has no decoder registered for
+	match codec {
+		CODEC_TYPE_OPUS => Some("opus"),
+		CODEC_TYPE_SPEEX => Some("speex"),
+		CODEC_TYPE_MUSEPACK => Some("musepack"),
+		CODEC_TYPE_ATRAC3 => Some("atrac3"),
+		CODEC_TYPE_ATRAC3PLUS => Some("atrac3plus"),
+		CODEC_TYPE_EAC3 => Some("eac3"),
+		CODEC_TYPE_WMA => Some("wma"),
+		CODEC_TYPE_WAVPACK => Some("wavpack"),
+		CODEC_TYPE_MONKEYS_AUDIO => Some("ape"),
+		CODEC_TYPE_TTA => Some("tta"),
+		_ => None,
+	}
+	.map(ToString::to_string)
+}
+
+fn duration_to_millis(time_base: TimeBase, n_frames: u64) -> Option<i32> {
+	// Doing it in integer math to avoid lossy float conversions, 128 bits as it can overflow 64
+	(u128::from(n_frames) * u128::from(time_base.numer) * 1000)
+		.checked_div(u128::from(time_base.denom))
+		.and_then(|millis| i32::try_from(millis).ok())
+}
+
+fn set_if_some<T>(field: &mut Option<T>, value: Option<T>) {
+	if value.is_some() {
+		*field = value;
+	}
+}
+
+fn value_to_string(value: &Value) -> Option<String> {
+	match value {
+		Value::Binary(_) | Value::Flag => None,
+		value => {
+			let value = value.to_string();
+			let trimmed = value.trim();
+			(!trimmed.is_empty()).then(|| trimmed.to_string())
+		}
+	}
+}
+
+/// Track numbers are frequently stored as `3/12` (track 3 of 12)
+fn value_to_number(value: &Value) -> Option<i32> {
+	match value {
+		Value::SignedInt(x) => i32::try_from(*x).ok(),
+		Value::UnsignedInt(x) => i32::try_from(*x).ok(),
+		value => value_to_string(value)?
+			.split('/')
+			.next()?
+			.trim()
+			.parse()
+			.ok(),
+	}
+}
+
+/// Dates come in all shapes (`2004`, `2004-03-12`, `2004-03-12T10:00:00`), but always start with the year
+fn value_to_year(value: &Value) -> Option<i32> {
+	match value {
+		Value::SignedInt(x) => i32::try_from(*x).ok(),
+		Value::UnsignedInt(x) => i32::try_from(*x).ok(),
+		value => value_to_string(value)?.get(..4)?.parse().ok(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn track_numbers() {
+		assert_eq!(value_to_number(&Value::String("3/12".into())), Some(3));
+		assert_eq!(value_to_number(&Value::String(" 7 ".into())), Some(7));
+		assert_eq!(value_to_number(&Value::UnsignedInt(4)), Some(4));
+		assert_eq!(value_to_number(&Value::String("side A".into())), None);
+	}
+
+	#[test]
+	fn years() {
+		assert_eq!(value_to_year(&Value::String("2004".into())), Some(2004));
+		assert_eq!(
+			value_to_year(&Value::String("2004-03-12".into())),
+			Some(2004)
+		);
+		assert_eq!(value_to_year(&Value::String("04".into())), None);
+		assert_eq!(value_to_year(&Value::Flag), None);
+	}
+
+	#[test]
+	fn millis() {
+		assert_eq!(
+			duration_to_millis(TimeBase::new(1, 44_100), 44_100 * 61 + 11_025),
+			Some(61_250)
+		);
+		assert_eq!(duration_to_millis(TimeBase::default(), 1), None);
 	}
 }
@@ -23,6 +23,16 @@ pub enum Error {
 	NoExifDataOnPath(PathBuf),
 	#[error("the slice provided contains no exif data")]
 	NoExifDataOnSlice,
+	#[error("error from the symphonia crate: {0}")]
+	Symphonia(#[from] symphonia::core::errors::Error),
+	#[error("the file provided at ({0}) contains no audio track")]
+	NoAudioTrack(PathBuf),
+	#[cfg(feature = "ffmpeg")]
+	#[error("error from ffmpeg: {0}")]
+	Ffmpeg(#[from] sd_ffmpeg::Error),
+	#[cfg(not(feature = "ffmpeg"))]
+	#[error("can't read video metadata from ({0}) without the ffmpeg feature")]
+	VideoUnsupported(PathBuf),
 
 	#[error("serde error {0}")]
 	Serde(#[from] serde_json::Error),
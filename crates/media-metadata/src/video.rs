@@ -1,20 +1,209 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path, str::FromStr};
 
-use crate::Result;
+use chrono::{DateTime, NaiveDateTime};
 
-#[derive(
-	Default, Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type,
-)]
+use crate::{
+	image::{MediaDate, MediaLocation, Resolution},
+	Error, Result,
+};
+
+#[derive(Default, Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize, specta::Type)]
 pub struct VideoMetadata {
-	duration: Option<i32>, // bigint
-	video_codec: Option<String>,
-	audio_codec: Option<String>,
+	/// Duration in milliseconds
+	pub duration: Option<i32>, // bigint
+	pub resolution: Resolution,
+	pub fps: Option<f64>,
+	/// Overall bitrate of the container in bits per second
+	pub bitrate: Option<i32>,
+	pub video_codec: Option<String>,
+	pub audio_codec: Option<String>,
+	pub video_streams: i32,
+	pub audio_streams: i32,
+	pub subtitle_streams: i32,
+	/// Clockwise rotation in degrees that players apply to the video
+	pub rotation: Option<i32>,
+	pub hdr_format: Option<HdrFormat>,
+	pub date_created: Option<MediaDate>,
+	pub location: Option<MediaLocation>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type)]
+pub enum HdrFormat {
+	Hdr10,
+	Hlg,
+	DolbyVision,
+}
+
+impl HdrFormat {
+	#[must_use]
+	pub const fn as_str(&self) -> &'static str {
+		match self {
+			Self::Hdr10 => "hdr10",
+			Self::Hlg => "hlg",
+			Self::DolbyVision => "dolby_vision",
+		}
+	}
+}
+
+impl FromStr for HdrFormat {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		match s {
+			"hdr10" => Ok(Self::Hdr10),
+			"hlg" => Ok(Self::Hlg),
+			"dolby_vision" => Ok(Self::DolbyVision),
+			_ => Err(Error::Conversion),
+		}
+	}
 }
 
+/// Tags used by different muxers and phone vendors for the recording date, in order of preference
+const DATE_TAGS: [&str; 3] = ["com.apple.quicktime.creationdate", "creation_time", "date"];
+
+/// Tags used for the recording location, all of them in ISO 6709 format
+const LOCATION_TAGS: [&str; 3] = [
+	"com.apple.quicktime.location.iso6709",
+	"location",
+	"location-eng",
+];
+
 impl VideoMetadata {
-	#[allow(clippy::missing_errors_doc)]
-	#[allow(clippy::missing_panics_doc)]
-	pub fn from_path(_path: impl AsRef<Path>) -> Result<Self> {
-		todo!()
+	#[cfg(feature = "ffmpeg")]
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+		sd_ffmpeg::probe(path).map(Into::into).map_err(Into::into)
+	}
+
+	#[cfg(not(feature = "ffmpeg"))]
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+		Err(Error::VideoUnsupported(path.as_ref().to_path_buf()))
+	}
+
+	/// Reads the recording date and location from container tags (with lowercased keys)
+	pub fn read_container_tags(&mut self, tags: &HashMap<String, String>) {
+		self.date_created = DATE_TAGS
+			.iter()
+			.find_map(|tag| tags.get(*tag).and_then(|date| parse_date(date)));
+		self.location = LOCATION_TAGS
+			.iter()
+			.find_map(|tag| tags.get(*tag).and_then(|location| parse_iso6709(location)));
+	}
+}
+
+#[cfg(feature = "ffmpeg")]
+impl From<sd_ffmpeg::MediaInfo> for VideoMetadata {
+	fn from(info: sd_ffmpeg::MediaInfo) -> Self {
+		let video = info.video.unwrap_or_default();
+
+		let mut data = Self {
+			duration: info
+				.duration
+				.and_then(|duration| i32::try_from(duration.as_millis()).ok()),
+			resolution: Resolution::new(video.width, video.height),
+			fps: video.frame_rate,
+			bitrate: info.bitrate.and_then(|bitrate| i32::try_from(bitrate).ok()),
+			video_codec: video.codec,
+			audio_codec: info.audio_codec,
+			video_streams: i32::try_from(info.video_streams).unwrap_or(i32::MAX),
+			audio_streams: i32::try_from(info.audio_streams).unwrap_or(i32::MAX),
+			subtitle_streams: i32::try_from(info.subtitle_streams).unwrap_or(i32::MAX),
+			rotation: video.rotation,
+			hdr_format: video.hdr.map(|hdr| match hdr {
+				sd_ffmpeg::HdrFormat::Pq => HdrFormat::Hdr10,
+				sd_ffmpeg::HdrFormat::Hlg => HdrFormat::Hlg,
+				sd_ffmpeg::HdrFormat::DolbyVision => HdrFormat::DolbyVision,
+			}),
+			..Default::default()
+		};
+
+		data.read_container_tags(&info.tags);
+
+		data
+	}
+}
+
+/// Container dates are usually RFC 3339 (`2023-09-26T22:04:37.000000Z`), but Apple writes
+/// the offset without a colon (`2023-09-26T22:04:37+0100`), and some muxers leave it out entirely
+fn parse_date(date: &str) -> Option<MediaDate> {
+	let date = date.trim();
+
+	DateTime::parse_from_rfc3339(date)
+		.or_else(|_| DateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%z"))
+		.map(MediaDate::Utc)
+		.or_else(|_| {
+			NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f")
+				.or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S"))
+				.map(MediaDate::Naive)
+		})
+		.ok()
+}
+
+/// Parses an ISO 6709 point in decimal degrees, e.g. `+37.7749-122.4194+010.000/`
+fn parse_iso6709(location: &str) -> Option<MediaLocation> {
+	let location = location.trim().trim_end_matches('/');
+
+	// Every component starts with its sign, so we split right before each of them
+	let mut components = Vec::with_capacity(3);
+	let mut start = 0;
+	for (idx, c) in location.char_indices().skip(1) {
+		if c == '+' || c == '-' {
+			components.push(&location[start..idx]);
+			start = idx;
+		}
+	}
+	components.push(&location[start..]);
+
+	let mut components = components.into_iter();
+
+	let latitude = components.next()?.parse::<f64>().ok()?;
+	let longitude = components.next()?.parse::<f64>().ok()?;
+	let altitude = components
+		.next()
+		.and_then(|altitude| altitude.split('.').next())
+		.and_then(|altitude| altitude.parse().ok());
+
+	(latitude.is_finite() && longitude.is_finite())
+		.then(|| MediaLocation::new(latitude, longitude, altitude, None))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn iso6709() {
+		assert_eq!(
+			parse_iso6709("+37.7749-122.4194+010.000/"),
+			Some(MediaLocation::new(37.7749, -122.4194, Some(10), None))
+		);
+		assert_eq!(
+			parse_iso6709("-33.8688+151.2093/"),
+			Some(MediaLocation::new(-33.8688, 151.2093, None, None))
+		);
+		assert_eq!(parse_iso6709("garbage"), None);
+	}
+
+	#[test]
+	fn dates() {
+		assert!(matches!(
+			parse_date("2023-09-26T22:04:37.000000Z"),
+			Some(MediaDate::Utc(_))
+		));
+		assert!(matches!(
+			parse_date("2023-09-26T22:04:37+0100"),
+			Some(MediaDate::Utc(_))
+		));
+		assert!(matches!(
+			parse_date("2023-09-26 22:04:37"),
+			Some(MediaDate::Naive(_))
+		));
+		assert_eq!(parse_date("yesterday"), None);
+	}
+
+	#[test]
+	fn hdr_format_round_trip() {
+		for hdr in [HdrFormat::Hdr10, HdrFormat::Hlg, HdrFormat::DolbyVision] {
+			assert_eq!(hdr.as_str().parse::<HdrFormat>().ok(), Some(hdr));
+		}
 	}
 }
@@ -0,0 +1,170 @@
+use std::{
+	io::{self, SeekFrom},
+	pin::Pin,
+	sync::atomic::AtomicBool,
+	task::{Context, Poll},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sd_p2p_block::{BlockSize, Range, SpaceblockRequest, SpaceblockRequests, Transfer};
+use tokio::{
+	io::{AsyncBufRead, AsyncRead, AsyncSeek, ReadBuf},
+	runtime::Runtime,
+};
+use uuid::Uuid;
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+const GIB: u64 = 1024 * MIB;
+
+/// Number of files and size of each of them
+const SMALL_FILES: [(usize, u64); 2] = [(1000, 4 * KIB), (100, 64 * KIB)];
+const LARGE_FILES: [u64; 2] = [256 * MIB, 4 * GIB];
+
+const STREAM_BUFFER: usize = 64 * KIB as usize;
+
+static ZEROES: [u8; 64 * 1024] = [0; 64 * 1024];
+
+/// A file full of zeroes, so multi-GB transfers don't need the memory or disk for them
+struct Zeroes {
+	len: u64,
+	pos: u64,
+}
+
+impl Zeroes {
+	fn available(&self) -> &'static [u8] {
+		let len = (ZEROES.len() as u64).min(self.len - self.pos);
+		&ZEROES[..len as usize]
+	}
+}
+
+impl AsyncRead for Zeroes {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		_: &mut Context<'_>,
+		buf: &mut ReadBuf<'_>,
+	) -> Poll<io::Result<()>> {
+		let available = self.available();
+		let len = available.len().min(buf.remaining());
+		buf.put_slice(&available[..len]);
+		self.pos += len as u64;
+
+		Poll::Ready(Ok(()))
+	}
+}
+
+impl AsyncBufRead for Zeroes {
+	fn poll_fill_buf(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+		Poll::Ready(Ok(self.get_mut().available()))
+	}
+
+	fn consume(mut self: Pin<&mut Self>, amt: usize) {
+		self.pos += amt as u64;
+	}
+}
+
+impl AsyncSeek for Zeroes {
+	fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+		let SeekFrom::Start(pos) = position else {
+			return Err(io::ErrorKind::Unsupported.into());
+		};
+		self.pos = pos.min(self.len);
+
+		Ok(())
+	}
+
+	fn poll_complete(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<u64>> {
+		Poll::Ready(Ok(self.pos))
+	}
+}
+
+fn requests(sizes: &[u64]) -> SpaceblockRequests {
+	let block_size = BlockSize::from_size(sizes.iter().copied().max().unwrap_or_default());
+	let block_len = u64::from(block_size.size());
+	let full_block = blake3::hash(&vec![0u8; block_len as usize]);
+
+	SpaceblockRequests {
+		id: Uuid::new_v4(),
+		requests: sizes
+			.iter()
+			.enumerate()
+			.map(|(i, &size)| SpaceblockRequest {
+				name: format!("{i}.bin"),
+				size,
+				range: Range::Full,
+				block_hashes: (0..size.div_ceil(block_len))
+					.map(|block| match size - block * block_len {
+						rest if rest >= block_len => full_block,
+						rest => blake3::hash(&vec![0u8; rest as usize]),
+					})
+					.collect(),
+				is_dir: false,
+				modified: None,
+				permissions: None,
+			})
+			.collect(),
+		block_size,
+	}
+}
+
+async fn transfer(req: &SpaceblockRequests) {
+	let (mut client, mut server) = tokio::io::duplex(STREAM_BUFFER);
+	let cancelled = AtomicBool::new(false);
+
+	tokio::join!(
+		async {
+			let mut transfer = Transfer::new(req, |_| {}, &cancelled);
+			for file in &req.requests {
+				let file = Zeroes {
+					len: file.size,
+					pos: 0,
+				};
+				transfer.send(&mut client, file).await.unwrap();
+			}
+		},
+		async {
+			let mut transfer = Transfer::new(req, |_| {}, &cancelled);
+			for _ in &req.requests {
+				transfer
+					.receive(&mut server, tokio::io::sink())
+					.await
+					.unwrap();
+			}
+		}
+	);
+}
+
+fn bench(c: &mut Criterion) {
+	let rt = Runtime::new().unwrap();
+	let mut group = c.benchmark_group("transfer");
+	group.sample_size(10);
+
+	for (count, size) in SMALL_FILES {
+		let req = requests(&vec![size; count]);
+
+		group.throughput(Throughput::Bytes(count as u64 * size));
+		group.bench_function(
+			BenchmarkId::new("small_files", format!("{count}x{size}")),
+			|b| b.iter(|| rt.block_on(transfer(&req))),
+		);
+	}
+
+	for size in LARGE_FILES {
+		let req = requests(&[size]);
+
+		group.throughput(Throughput::Bytes(size));
+		group.bench_function(BenchmarkId::new("large_file", size), |b| {
+			b.iter(|| rt.block_on(transfer(&req)));
+		});
+	}
+
+	group.finish();
+}
+
+criterion_group!(
+	name = benches;
+	config = Criterion::default();
+	targets = bench
+);
+
+criterion_main!(benches);
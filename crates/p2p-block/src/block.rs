@@ -1,6 +1,8 @@
 use std::io::{self, ErrorKind};
 
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::BlockSize;
 
 /// TODO
 #[derive(Debug, PartialEq, Eq)]
@@ -54,12 +56,45 @@ impl<'a> Block<'a> {
 	}
 }
 
+/// Fills `buf` from `file`, only returning less than its length at the end of the file so blocks
+/// always line up with their hashes
+pub(crate) async fn read_block(
+	file: &mut (impl AsyncRead + Unpin),
+	buf: &mut [u8],
+) -> Result<usize, io::Error> {
+	let mut read = 0;
+	while read < buf.len() {
+		match file.read(&mut buf[read..]).await? {
+			0 => break,
+			n => read += n,
+		}
+	}
+
+	Ok(read)
+}
+
+/// Hashes every block of `file` with BLAKE3 so the receiver can verify each of them as they arrive
+pub async fn block_hashes(
+	file: &mut (impl AsyncRead + Unpin),
+	block_size: &BlockSize,
+) -> Result<Vec<blake3::Hash>, io::Error> {
+	let mut buf = vec![0u8; block_size.size() as usize];
+	let mut hashes = Vec::new();
+
+	loop {
+		let read = read_block(file, &mut buf).await?;
+		if read == 0 {
+			return Ok(hashes);
+		}
+
+		hashes.push(blake3::hash(&buf[..read]));
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use std::io::Cursor;
 
-	use crate::BlockSize;
-
 	use super::*;
 
 	#[tokio::test]
@@ -96,4 +131,19 @@ mod tests {
 		assert_eq!(req, req2);
 		assert_eq!(data, data2);
 	}
+
+	#[tokio::test]
+	async fn test_block_hashes() {
+		let data = b"Spacedrive, but in blocks".to_vec();
+		let block_size = BlockSize::dangerously_new(10);
+
+		let hashes = block_hashes(&mut Cursor::new(&data), &block_size)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			hashes,
+			data.chunks(10).map(blake3::hash).collect::<Vec<_>>()
+		);
+	}
 }
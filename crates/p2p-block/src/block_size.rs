@@ -2,15 +2,30 @@ use std::io;
 
 use tokio::io::{AsyncRead, AsyncReadExt};
 
-/// TODO
+/// Block size of files up to 250 MiB
+pub const MIN_BLOCK_SIZE: u32 = 128 * 1024;
+
+/// Block size of files of 16 GiB and more
+pub const MAX_BLOCK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Block sizes double until files are split in fewer blocks than this
+const DESIRED_BLOCKS_PER_FILE: u64 = 2000;
+
+/// Size of the blocks files are split into, a power of two between [`MIN_BLOCK_SIZE`] and [`MAX_BLOCK_SIZE`]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BlockSize(u32); // Max block size is gonna be 3.9GB which is stupidly overkill
 
 impl BlockSize {
-	// TODO: Validating `BlockSize` are multiple of 2, i think. Idk why but BEP does it.
-
 	pub async fn from_stream(stream: &mut (impl AsyncRead + Unpin)) -> io::Result<Self> {
-		stream.read_u32_le().await.map(Self)
+		let size = stream.read_u32_le().await?;
+		if !size.is_power_of_two() || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&size) {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("Invalid block size '{size}'"),
+			));
+		}
+
+		Ok(Self(size))
 	}
 
 	#[must_use]
@@ -18,10 +33,18 @@ impl BlockSize {
 		self.0.to_le_bytes()
 	}
 
+	/// Picks the block size of a file of `size` bytes like Syncthing does, so big files don't pay for
+	/// an acknowledgement every 128 KiB while small ones don't need huge buffers.
+	/// <https://docs.syncthing.net/specs/bep-v1.html#selection-of-block-size>
 	#[must_use]
 	pub fn from_size(size: u64) -> Self {
-		// TODO: Something like: https://docs.syncthing.net/specs/bep-v1.html#selection-of-block-size
-		Self(131_072) // 128 KiB
+		let mut block_size = MIN_BLOCK_SIZE;
+		while block_size < MAX_BLOCK_SIZE && size / u64::from(block_size) >= DESIRED_BLOCKS_PER_FILE
+		{
+			block_size *= 2;
+		}
+
+		Self(block_size)
 	}
 
 	/// This is super dangerous as it doesn't enforce any assumptions of the protocol and is designed just for tests.
@@ -43,13 +66,53 @@ mod tests {
 
 	use super::*;
 
+	const KIB: u64 = 1024;
+	const MIB: u64 = 1024 * KIB;
+	const GIB: u64 = 1024 * MIB;
+
 	#[tokio::test]
 	async fn test_block_size() {
-		let req = BlockSize::dangerously_new(5);
+		let req = BlockSize::from_size(42069);
 		let bytes = req.to_bytes();
 		let req2 = BlockSize::from_stream(&mut Cursor::new(bytes))
 			.await
 			.unwrap();
 		assert_eq!(req, req2);
+
+		for invalid in [
+			0,
+			5,
+			MIN_BLOCK_SIZE / 2,
+			MIN_BLOCK_SIZE + 1,
+			MAX_BLOCK_SIZE * 2,
+		] {
+			let bytes = BlockSize::dangerously_new(invalid).to_bytes();
+			assert!(BlockSize::from_stream(&mut Cursor::new(bytes))
+				.await
+				.is_err());
+		}
+	}
+
+	#[test]
+	fn test_from_size() {
+		for (size, block_size) in [
+			(0, 128 * KIB),
+			(4 * KIB, 128 * KIB),
+			(249 * MIB, 128 * KIB),
+			(300 * MIB, 256 * KIB),
+			(700 * MIB, 512 * KIB),
+			(1536 * MIB, MIB),
+			(3 * GIB, 2 * MIB),
+			(6 * GIB, 4 * MIB),
+			(12 * GIB, 8 * MIB),
+			(20 * GIB, 16 * MIB),
+			(4096 * GIB, 16 * MIB),
+		] {
+			assert_eq!(
+				u64::from(BlockSize::from_size(size).size()),
+				block_size,
+				"size {size}"
+			);
+		}
 	}
 }
@@ -7,7 +7,7 @@
 #![allow(unused)] // TODO: This module is still in heavy development!
 
 use std::{
-	io,
+	io::{self, SeekFrom},
 	marker::PhantomData,
 	path::{Path, PathBuf},
 	string::FromUtf8Error,
@@ -20,7 +20,10 @@ use std::{
 use thiserror::Error;
 use tokio::{
 	fs::File,
-	io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+	io::{
+		AsyncBufRead, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+		BufReader,
+	},
 };
 use tracing::debug;
 
@@ -31,6 +34,7 @@ mod block;
 mod block_size;
 mod sb_request;
 
+use block::read_block;
 pub use block::*;
 pub use block_size::*;
 pub use sb_request::*;
@@ -86,28 +90,66 @@ impl<'a, F> Transfer<'a, F>
 where
 	F: Fn(u8) + 'a,
 {
-	// TODO: Handle `req.range` correctly in this code
-
 	pub fn new(req: &'a SpaceblockRequests, on_progress: F, cancelled: &'a AtomicBool) -> Self {
 		Self {
 			reqs: req,
 			on_progress,
 			total_offset: 0,
-			total_bytes: req.requests.iter().map(|req| req.size).sum(),
+			total_bytes: req
+				.requests
+				.iter()
+				.map(|req| {
+					let range = req.range.bytes(req.size);
+					range.end - range.start
+				})
+				.sum(),
 			i: 0,
 			cancelled,
 		}
 	}
 
+	fn next_request(&mut self) -> Result<&'a SpaceblockRequest, io::Error> {
+		let req = self.reqs.requests.get(self.i).ok_or_else(|| {
+			debug!("Vector read out of bounds!");
+			io::Error::new(io::ErrorKind::Other, "More files than requested")
+		})?;
+		self.i += 1;
+
+		Ok(req)
+	}
+
+	/// Moves past the next request without transferring anything, which is all directories need
+	pub fn skip(&mut self) -> Result<(), io::Error> {
+		let req = self.next_request()?;
+		if !req.range.bytes(req.size).is_empty() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Only empty requests can be skipped",
+			));
+		}
+
+		Ok(())
+	}
+
 	// TODO: Should `new` take in the streams too cause this means we `Stream` `SpaceblockRequest` could get outta sync.
 	pub async fn send(
 		&mut self,
 		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
-		mut file: (impl AsyncBufRead + Unpin),
+		mut file: (impl AsyncBufRead + AsyncSeek + Unpin),
 	) -> Result<(), io::Error> {
+		let req = self.next_request()?;
+		let range = req.range.bytes(req.size);
+		if range.is_empty() {
+			return Ok(());
+		}
+
+		if range.start != 0 {
+			file.seek(SeekFrom::Start(range.start)).await?;
+		}
+
 		// We manually implement what is basically a `BufReader` so we have more control
 		let mut buf = vec![0u8; self.reqs.block_size.size() as usize];
-		let mut offset: u64 = 0;
+		let mut offset = range.start;
 
 		loop {
 			if self.cancelled.load(Ordering::Relaxed) {
@@ -116,24 +158,22 @@ where
 				return Ok(());
 			}
 
-			let read = file.read(&mut buf[..]).await?;
+			// Blocks must be full so they match the hashes the receiver checks them against
+			let len = buf.len().min((range.end - offset) as usize);
+			let read = read_block(&mut file, &mut buf[..len]).await?;
+			if read == 0 {
+				// TODO: Send error to remote
+				return Err(io::Error::new(
+					io::ErrorKind::UnexpectedEof,
+					"File is shorter than requested, it may have been modified while sending",
+				));
+			}
+
 			self.total_offset += read as u64;
 			(self.on_progress)(
 				((self.total_offset as f64 / self.total_bytes as f64) * 100.0) as u8,
 			); // SAFETY: Percent must be between 0 and 100
 
-			if read == 0 {
-				#[allow(clippy::panic)] // TODO: Remove panic
-						// The file may have been modified during sender on the sender and we don't account for that.
-						// TODO: Error handling + send error to remote
-				assert!(
-					(offset + read as u64) == self.reqs.requests[self.i].size,
-					"File sending has stopped but it doesn't match the expected length!"
-				);
-
-				return Ok(());
-			}
-
 			let block = Block {
 				offset,
 				size: read as u64,
@@ -158,11 +198,18 @@ where
 				}
 				// Transfer complete
 				2 => return Ok(()),
-				_ => todo!(),
+				_ => {
+					return Err(io::Error::new(
+						io::ErrorKind::InvalidData,
+						"Invalid Spaceblock acknowledgement",
+					))
+				}
 			}
 		}
 	}
 
+	/// Receives the next file into `file`, which must already be positioned at the start of the
+	/// request's range. Blocks failing their integrity check are never written.
 	// TODO: Timeout on receiving/sending
 	pub async fn receive(
 		&mut self,
@@ -170,15 +217,24 @@ where
 		mut file: (impl AsyncWrite + Unpin),
 		// TODO: Proper error type
 	) -> Result<(), io::Error> {
-		// We manually implement what is basically a `BufReader` so we have more control
-		let mut data_buf = vec![0u8; self.reqs.block_size.size() as usize];
-		let mut offset: u64 = 0;
-
-		if self.reqs.requests[self.i].size == 0 {
-			self.i += 1;
+		let req = self.next_request()?;
+		let range = req.range.bytes(req.size);
+		if range.is_empty() {
 			return Ok(());
 		}
 
+		let block_size = u64::from(self.reqs.block_size.size());
+		if range.start % block_size != 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Ranges must start on a block boundary to be verified",
+			));
+		}
+
+		// We manually implement what is basically a `BufReader` so we have more control
+		let mut data_buf = vec![0u8; block_size as usize];
+		let mut offset = range.start;
+
 		// TODO: Prevent loop being a DOS vector
 		loop {
 			if self.cancelled.load(Ordering::Relaxed) {
@@ -191,6 +247,26 @@ where
 			let msg = Msg::from_stream(stream, &mut data_buf).await?;
 			match msg {
 				Msg::Block(block) => {
+					if block.offset != offset || offset + block.size > range.end {
+						return Err(io::Error::new(
+							io::ErrorKind::InvalidData,
+							format!(
+								"Unexpected block at offset {} of size {}",
+								block.offset, block.size
+							),
+						));
+					}
+
+					let data = &data_buf[..block.size as usize];
+					if req.block_hashes.get((offset / block_size) as usize)
+						!= Some(&blake3::hash(data))
+					{
+						return Err(io::Error::new(
+							io::ErrorKind::InvalidData,
+							format!("Block at offset {offset} failed its integrity check"),
+						));
+					}
+
 					self.total_offset += block.size;
 					(self.on_progress)(
 						((self.total_offset as f64 / self.total_bytes as f64) * 100.0) as u8,
@@ -202,14 +278,9 @@ where
 					);
 					offset += block.size;
 
-					file.write_all(&data_buf[..block.size as usize]).await?;
+					file.write_all(data).await?;
 
-					let req = self.reqs.requests.get(self.i).ok_or_else(|| {
-						debug!("Vector read out of bounds!");
-						io::ErrorKind::Other
-					})?;
-					// TODO: Should this be `read == 0`
-					if offset == req.size {
+					if offset == range.end {
 						break;
 					}
 
@@ -228,7 +299,6 @@ where
 		stream.write_u8(2).await?;
 		stream.flush().await?;
 		file.flush().await?;
-		self.i += 1;
 
 		Ok(())
 	}
@@ -249,13 +319,21 @@ mod tests {
 
 		// This is sent out of band of Spaceblock
 		let data = b"Spacedrive".to_vec();
+		let block_size = BlockSize::from_size(data.len() as u64);
+		let hashes = block_hashes(&mut Cursor::new(&data), &block_size)
+			.await
+			.unwrap();
 		let req = SpaceblockRequests {
 			id: Uuid::new_v4(),
-			block_size: BlockSize::from_size(data.len() as u64),
+			block_size,
 			requests: vec![SpaceblockRequest {
 				name: "Demo".to_string(),
 				size: data.len() as u64,
 				range: Range::Full,
+				block_hashes: hashes,
+				is_dir: false,
+				modified: None,
+				permissions: None,
 			}],
 		};
 
@@ -290,6 +368,9 @@ mod tests {
 		let block_size = 131_072_u32;
 		let data = vec![0u8; block_size as usize * 4]; // Let's pacman some RAM
 		let block_size = BlockSize::dangerously_new(block_size);
+		let hashes = block_hashes(&mut Cursor::new(&data), &block_size)
+			.await
+			.unwrap();
 
 		let req = SpaceblockRequests {
 			id: Uuid::new_v4(),
@@ -298,6 +379,10 @@ mod tests {
 				name: "Demo".to_string(),
 				size: data.len() as u64,
 				range: Range::Full,
+				block_hashes: hashes,
+				is_dir: false,
+				modified: None,
+				permissions: None,
 			}],
 		};
 
@@ -323,6 +408,64 @@ mod tests {
 		assert_eq!(result, data);
 	}
 
+	#[tokio::test]
+	async fn test_spaceblock_many_small_files() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let files = (0..100u8)
+			.map(|i| vec![i; usize::from(i) * 7])
+			.collect::<Vec<_>>();
+		let block_size = BlockSize::from_size(files.iter().map(Vec::len).max().unwrap() as u64);
+
+		let mut requests = Vec::with_capacity(files.len());
+		for (i, data) in files.iter().enumerate() {
+			requests.push(SpaceblockRequest {
+				name: format!("Demo{i}"),
+				size: data.len() as u64,
+				range: Range::Full,
+				block_hashes: block_hashes(&mut Cursor::new(data), &block_size)
+					.await
+					.unwrap(),
+				is_dir: false,
+				modified: None,
+				permissions: None,
+			});
+		}
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size,
+			requests,
+		};
+
+		let (tx, rx) = oneshot::channel();
+		tokio::spawn({
+			let req = req.clone();
+			let files = files.clone();
+			async move {
+				tx.send(()).unwrap();
+				let cancelled = AtomicBool::default();
+				let mut transfer = Transfer::new(&req, |_| {}, &cancelled);
+				for data in files {
+					transfer
+						.send(&mut client, BufReader::new(Cursor::new(data)))
+						.await
+						.unwrap();
+				}
+			}
+		});
+
+		rx.await.unwrap();
+
+		let cancelled = AtomicBool::default();
+		let mut transfer = Transfer::new(&req, |_| {}, &cancelled);
+		for data in files {
+			let mut result = Vec::new();
+			transfer.receive(&mut server, &mut result).await.unwrap();
+			assert_eq!(result, data);
+		}
+	}
+
 	#[tokio::test]
 	async fn test_transfer_receiver_cancelled() {
 		let (mut client, mut server) = tokio::io::duplex(64);
@@ -331,6 +474,9 @@ mod tests {
 		let block_size = 25u32;
 		let data = vec![0u8; block_size as usize];
 		let block_size = BlockSize::dangerously_new(block_size); // TODO: Determine it using proper algo instead of hardcoding it
+		let hashes = block_hashes(&mut Cursor::new(&data), &block_size)
+			.await
+			.unwrap();
 
 		let req = SpaceblockRequests {
 			id: Uuid::new_v4(),
@@ -339,6 +485,10 @@ mod tests {
 				name: "Demo".to_string(),
 				size: data.len() as u64,
 				range: Range::Full,
+				block_hashes: hashes,
+				is_dir: false,
+				modified: None,
+				permissions: None,
 			}],
 		};
 
@@ -373,6 +523,9 @@ mod tests {
 		let block_size = 25u32;
 		let data = vec![0u8; block_size as usize];
 		let block_size = BlockSize::dangerously_new(block_size); // TODO: Determine it using proper algo instead of hardcoding it
+		let hashes = block_hashes(&mut Cursor::new(&data), &block_size)
+			.await
+			.unwrap();
 
 		let req = SpaceblockRequests {
 			id: Uuid::new_v4(),
@@ -381,6 +534,10 @@ mod tests {
 				name: "Demo".to_string(),
 				size: data.len() as u64,
 				range: Range::Full,
+				block_hashes: hashes,
+				is_dir: false,
+				modified: None,
+				permissions: None,
 			}],
 		};
 
@@ -424,6 +581,10 @@ mod tests {
 				name: "Demo".to_string(),
 				size: data.len() as u64,
 				range: Range::Full,
+				block_hashes: vec![],
+				is_dir: false,
+				modified: None,
+				permissions: None,
 			}],
 		};
 
@@ -450,6 +611,102 @@ mod tests {
 		assert_eq!(result, Vec::<u8>::new()); // Cancelled by sender so no data
 	}
 
+	#[tokio::test]
+	async fn test_spaceblock_resume() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let data = b"Spacedrive, but resumed in blocks".to_vec();
+		let block_size = BlockSize::dangerously_new(10);
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size: block_size.clone(),
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				size: data.len() as u64,
+				range: Range::Partial(20..data.len() as u64),
+				block_hashes: block_hashes(&mut Cursor::new(&data), &block_size)
+					.await
+					.unwrap(),
+				is_dir: false,
+				modified: None,
+				permissions: None,
+			}],
+		};
+
+		let (tx, rx) = oneshot::channel();
+		tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				tx.send(()).unwrap();
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await;
+			}
+		});
+
+		rx.await.unwrap();
+
+		let mut result = data[..20].to_vec();
+		Transfer::new(&req, |_| {}, &Default::default())
+			.receive(&mut server, &mut result)
+			.await
+			.unwrap();
+		assert_eq!(result, data);
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_corrupted_block() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let data = b"Spacedrive, but in blocks".to_vec();
+		let block_size = BlockSize::dangerously_new(10);
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size: block_size.clone(),
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				size: data.len() as u64,
+				range: Range::Full,
+				block_hashes: block_hashes(&mut Cursor::new(&data), &block_size)
+					.await
+					.unwrap(),
+				is_dir: false,
+				modified: None,
+				permissions: None,
+			}],
+		};
+
+		// The sender reads something else than what was hashed from the second block on
+		let mut corrupted = data.clone();
+		corrupted[12] = b'!';
+
+		let (tx, rx) = oneshot::channel();
+		tokio::spawn({
+			let req = req.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(corrupted));
+				tx.send(()).unwrap();
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await;
+			}
+		});
+
+		rx.await.unwrap();
+
+		let mut result = Vec::new();
+		let err = Transfer::new(&req, |_| {}, &Default::default())
+			.receive(&mut server, &mut result)
+			.await
+			.unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+		assert_eq!(result, data[..10]); // Only the verified block was written
+	}
+
 	#[tokio::test]
 	async fn test_msg() {
 		let block = Block {
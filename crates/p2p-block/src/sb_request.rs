@@ -1,4 +1,8 @@
-use std::io;
+use std::{
+	io,
+	path::{Component, Path, PathBuf},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt};
@@ -6,7 +10,7 @@ use uuid::Uuid;
 
 use sd_p2p_proto::{decode, encode};
 
-use super::BlockSize;
+use super::{block::read_block, BlockSize};
 
 /// TODO
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -34,6 +38,15 @@ impl Range {
 		}
 	}
 
+	/// The bytes of a file of `size` bytes covered by this range
+	#[must_use]
+	pub fn bytes(&self, size: u64) -> std::ops::Range<u64> {
+		match self {
+			Self::Full => 0..size,
+			Self::Partial(range) => range.start.min(size)..range.end.min(size),
+		}
+	}
+
 	#[must_use]
 	pub fn to_bytes(&self) -> Vec<u8> {
 		let mut buf = Vec::new();
@@ -50,6 +63,9 @@ impl Range {
 	}
 }
 
+/// Maximum number of files and directories in one Spacedrop
+pub const MAX_REQUESTS: u32 = 100_000;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpaceblockRequests {
 	pub id: Uuid,
@@ -82,14 +98,19 @@ impl SpaceblockRequests {
 			.map_err(SpaceblockRequestsError::BlockSize)?;
 
 		let size = stream
-			// Max of 255 files in one request
-			.read_u8()
+			.read_u32_le()
 			.await
 			.map_err(SpaceblockRequestsError::InvalidLen)?;
+		if size > MAX_REQUESTS {
+			return Err(SpaceblockRequestsError::InvalidLen(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("Can't Spacedrop more than {MAX_REQUESTS} files at once"),
+			)));
+		}
 
 		let mut requests = Vec::new();
 		for i in 0..size {
-			requests.push(SpaceblockRequest::from_stream(stream).await?);
+			requests.push(SpaceblockRequest::from_stream(stream, &block_size).await?);
 		}
 
 		Ok(Self {
@@ -108,14 +129,14 @@ impl SpaceblockRequests {
 		} = self;
 		#[allow(clippy::panic)] // TODO: Remove this panic
 		assert!(
-			requests.len() <= 255,
-			"Can't Spacedrop more than 255 files at once!"
+			requests.len() <= MAX_REQUESTS as usize,
+			"Can't Spacedrop more than {MAX_REQUESTS} files at once!"
 		);
 
 		let mut buf = vec![];
 		encode::uuid(&mut buf, id);
 		buf.append(&mut block_size.to_bytes().to_vec());
-		buf.push(requests.len() as u8);
+		buf.extend_from_slice(&(requests.len() as u32).to_le_bytes());
 		for request in requests {
 			buf.extend_from_slice(&request.to_bytes());
 		}
@@ -123,13 +144,20 @@ impl SpaceblockRequests {
 	}
 }
 
-/// TODO
+/// A file or directory of a Spacedrop
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpaceblockRequest {
+	/// Path relative to the root of the Spacedrop, with `/` separators
 	pub name: String,
 	pub size: u64,
-	// TODO: Include file permissions
 	pub range: Range,
+	/// BLAKE3 hash of each block of the file, checked by the receiver as blocks arrive
+	pub block_hashes: Vec<blake3::Hash>,
+	/// Directories have no content, they are only created so empty ones make it to the receiver
+	pub is_dir: bool,
+	pub modified: Option<SystemTime>,
+	/// Unix mode bits, the receiver only applies them on Unix too
+	pub permissions: Option<u32>,
 }
 
 #[derive(Debug, Error)]
@@ -141,11 +169,16 @@ pub enum SpaceblockRequestError {
 	// TODO: From outside. Probs remove?
 	#[error("SpaceblockRequestError::RangeError({0:?})")]
 	RangeError(io::Error),
+	#[error("SpaceblockRequestError::BlockHashes({0})")]
+	BlockHashes(io::Error),
+	#[error("SpaceblockRequestError::Metadata({0})")]
+	Metadata(io::Error),
 }
 
 impl SpaceblockRequest {
 	pub async fn from_stream(
 		stream: &mut (impl AsyncRead + Unpin),
+		block_size: &BlockSize,
 	) -> Result<Self, SpaceblockRequestError> {
 		let name = decode::string(stream)
 			.await
@@ -156,25 +189,171 @@ impl SpaceblockRequest {
 			.await
 			.map_err(SpaceblockRequestError::Size)?;
 
+		let range = Range::from_stream(stream)
+			.await
+			.map_err(SpaceblockRequestError::Size)?;
+
+		let hashes_len = stream
+			.read_u64_le()
+			.await
+			.map_err(SpaceblockRequestError::BlockHashes)?;
+		// Every block must be verifiable, so a sender can't opt out of the integrity checks
+		let expected_hashes = size.div_ceil(u64::from(block_size.size()));
+		if hashes_len != expected_hashes {
+			return Err(SpaceblockRequestError::BlockHashes(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("Expected {expected_hashes} block hashes, got {hashes_len}"),
+			)));
+		}
+
+		// The length comes from the remote so we only grow the vector as hashes actually arrive
+		let mut block_hashes = Vec::with_capacity(hashes_len.min(1024) as usize);
+		for _ in 0..hashes_len {
+			let mut hash = [0u8; blake3::OUT_LEN];
+			stream
+				.read_exact(&mut hash)
+				.await
+				.map_err(SpaceblockRequestError::BlockHashes)?;
+			block_hashes.push(blake3::Hash::from(hash));
+		}
+
+		let is_dir = stream
+			.read_u8()
+			.await
+			.map_err(SpaceblockRequestError::Metadata)?
+			== 1;
+		if is_dir && size != 0 {
+			return Err(SpaceblockRequestError::Metadata(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"Directories can't have a size",
+			)));
+		}
+
+		let modified = match stream
+			.read_u8()
+			.await
+			.map_err(SpaceblockRequestError::Metadata)?
+		{
+			0 => None,
+			_ => {
+				let secs = stream
+					.read_u64_le()
+					.await
+					.map_err(SpaceblockRequestError::Metadata)?;
+				let nanos = stream
+					.read_u32_le()
+					.await
+					.map_err(SpaceblockRequestError::Metadata)?;
+				UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+			}
+		};
+
+		let permissions = match stream
+			.read_u8()
+			.await
+			.map_err(SpaceblockRequestError::Metadata)?
+		{
+			0 => None,
+			_ => Some(
+				stream
+					.read_u32_le()
+					.await
+					.map_err(SpaceblockRequestError::Metadata)?,
+			),
+		};
+
 		Ok(Self {
 			name,
 			size,
-			range: Range::from_stream(stream)
-				.await
-				.map_err(SpaceblockRequestError::Size)?,
+			range,
+			block_hashes,
+			is_dir,
+			modified,
+			permissions,
 		})
 	}
 
 	#[must_use]
 	pub fn to_bytes(&self) -> Vec<u8> {
-		let Self { name, size, range } = self;
+		let Self {
+			name,
+			size,
+			range,
+			block_hashes,
+			is_dir,
+			modified,
+			permissions,
+		} = self;
 		let mut buf = Vec::new();
 
 		encode::string(&mut buf, name);
 		buf.extend_from_slice(&self.size.to_le_bytes());
 		buf.extend_from_slice(&self.range.to_bytes());
+		buf.extend_from_slice(&(block_hashes.len() as u64).to_le_bytes());
+		for hash in block_hashes {
+			buf.extend_from_slice(hash.as_bytes());
+		}
+		buf.push(u8::from(*is_dir));
+
+		// Times before the Unix epoch aren't worth a sign bit, they are just left out
+		match modified.and_then(|modified| modified.duration_since(UNIX_EPOCH).ok()) {
+			Some(modified) => {
+				buf.push(1);
+				buf.extend_from_slice(&modified.as_secs().to_le_bytes());
+				buf.extend_from_slice(&modified.subsec_nanos().to_le_bytes());
+			}
+			None => buf.push(0),
+		}
+
+		match permissions {
+			Some(permissions) => {
+				buf.push(1);
+				buf.extend_from_slice(&permissions.to_le_bytes());
+			}
+			None => buf.push(0),
+		}
 		buf
 	}
+
+	/// Where this request goes inside `base`. `None` when its name would escape `base`, through
+	/// `..`, an absolute path or a drive prefix, so a remote peer can't write anywhere else.
+	#[must_use]
+	pub fn path_in(&self, base: &Path) -> Option<PathBuf> {
+		let mut path = base.to_path_buf();
+		for segment in self.name.split('/') {
+			// Checking each segment on its own also catches `\` separators and prefixes on Windows
+			let mut components = Path::new(segment).components();
+			match (components.next(), components.next()) {
+				(Some(Component::Normal(segment)), None) => path.push(segment),
+				_ => return None,
+			}
+		}
+
+		Some(path)
+	}
+
+	/// How much of this file `partial` already holds, counting the blocks matching `block_hashes`
+	/// from its start. A transfer can pick up from there with a [`Range::Partial`].
+	pub async fn resume_offset(
+		&self,
+		partial: &mut (impl AsyncRead + Unpin),
+		block_size: &BlockSize,
+	) -> io::Result<u64> {
+		let mut buf = vec![0u8; block_size.size() as usize];
+		let mut offset = 0;
+
+		for hash in &self.block_hashes {
+			let expected = u64::from(block_size.size()).min(self.size.saturating_sub(offset));
+			let read = read_block(partial, &mut buf[..expected as usize]).await?;
+			if read as u64 != expected || blake3::hash(&buf[..read]) != *hash {
+				break;
+			}
+
+			offset += expected;
+		}
+
+		Ok(offset)
+	}
 }
 
 #[cfg(test)]
@@ -194,6 +373,9 @@ mod tests {
 		let bytes = req.to_bytes();
 		let req2 = Range::from_stream(&mut Cursor::new(bytes)).await.unwrap();
 		assert_eq!(req, req2);
+
+		assert_eq!(Range::Full.bytes(42), 0..42);
+		assert_eq!(Range::Partial(10..420).bytes(42), 10..42);
 	}
 
 	#[tokio::test]
@@ -220,6 +402,10 @@ mod tests {
 				name: "Demo".to_string(),
 				size: 42069,
 				range: Range::Full,
+				block_hashes: vec![blake3::hash(b"Demo")],
+				is_dir: false,
+				modified: None,
+				permissions: None,
 			}],
 		};
 
@@ -233,13 +419,31 @@ mod tests {
 			name: "Demo".to_string(),
 			size: 42069,
 			range: Range::Partial(0..420),
+			block_hashes: vec![blake3::hash(b"Spacedrive"), blake3::hash(b"Demo")],
+			is_dir: false,
+			modified: None,
+			permissions: None,
 		};
 
+		let block_size = BlockSize::dangerously_new(32768);
 		let bytes = req.to_bytes();
-		let req2 = SpaceblockRequest::from_stream(&mut Cursor::new(bytes))
+		let req2 = SpaceblockRequest::from_stream(&mut Cursor::new(bytes), &block_size)
 			.await
 			.unwrap();
 		assert_eq!(req, req2);
+
+		// Senders can't skip the integrity checks by leaving hashes out
+		for block_hashes in [vec![], vec![blake3::hash(b"Spacedrive")]] {
+			let req = SpaceblockRequest {
+				block_hashes,
+				..req.clone()
+			};
+			assert!(
+				SpaceblockRequest::from_stream(&mut Cursor::new(req.to_bytes()), &block_size)
+					.await
+					.is_err()
+			);
+		}
 	}
 
 	#[tokio::test]
@@ -252,11 +456,53 @@ mod tests {
 					name: "Demo".to_string(),
 					size: 42069,
 					range: Range::Full,
+					block_hashes: vec![blake3::hash(b"Demo")],
+					is_dir: false,
+					modified: None,
+					permissions: None,
 				},
 				SpaceblockRequest {
 					name: "Demo2".to_string(),
 					size: 420,
 					range: Range::Full,
+					block_hashes: vec![blake3::hash(b"Demo2")],
+					is_dir: false,
+					modified: None,
+					permissions: None,
+				},
+			],
+		};
+
+		let bytes = req.to_bytes();
+		let req2 = SpaceblockRequests::from_stream(&mut Cursor::new(bytes))
+			.await
+			.unwrap();
+		assert_eq!(req, req2);
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_request_metadata() {
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size: BlockSize::from_size(42069),
+			requests: vec![
+				SpaceblockRequest {
+					name: "Photos".to_string(),
+					size: 0,
+					range: Range::Full,
+					block_hashes: vec![],
+					is_dir: true,
+					modified: Some(UNIX_EPOCH + Duration::new(1_700_000_000, 420)),
+					permissions: Some(0o755),
+				},
+				SpaceblockRequest {
+					name: "Photos/2024/Demo.jpg".to_string(),
+					size: 42069,
+					range: Range::Full,
+					block_hashes: vec![blake3::hash(b"Spacedrive")],
+					is_dir: false,
+					modified: None,
+					permissions: Some(0o644),
 				},
 			],
 		};
@@ -266,5 +512,82 @@ mod tests {
 			.await
 			.unwrap();
 		assert_eq!(req, req2);
+
+		let mut dir = req.requests[0].clone();
+		dir.size = 420;
+		dir.block_hashes = vec![blake3::hash(b"Photos")];
+		assert!(
+			SpaceblockRequest::from_stream(&mut Cursor::new(dir.to_bytes()), &req.block_size)
+				.await
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn test_path_in() {
+		let base = Path::new("Downloads");
+		let path_in = |name: &str| {
+			SpaceblockRequest {
+				name: name.to_string(),
+				size: 0,
+				range: Range::Full,
+				block_hashes: vec![],
+				is_dir: false,
+				modified: None,
+				permissions: None,
+			}
+			.path_in(base)
+		};
+
+		assert_eq!(path_in("Demo"), Some(base.join("Demo")));
+		assert_eq!(
+			path_in("Photos/2024/Demo.jpg"),
+			Some(base.join("Photos").join("2024").join("Demo.jpg"))
+		);
+
+		for name in [
+			"",
+			".",
+			"..",
+			"../Demo",
+			"Photos/../../Demo",
+			"Photos//Demo",
+			"/etc/passwd",
+			"Photos/",
+		] {
+			assert_eq!(path_in(name), None, "{name:?}");
+		}
+	}
+
+	#[tokio::test]
+	async fn test_resume_offset() {
+		let data = b"Spacedrive, but in blocks".to_vec();
+		let block_size = BlockSize::dangerously_new(10);
+		let req = SpaceblockRequest {
+			name: "Demo".to_string(),
+			size: data.len() as u64,
+			range: Range::Full,
+			block_hashes: data.chunks(10).map(blake3::hash).collect(),
+			is_dir: false,
+			modified: None,
+			permissions: None,
+		};
+
+		let mut corrupted = data.clone();
+		corrupted[12] = b'!';
+
+		for (partial, expected) in [
+			(vec![], 0),
+			// Incomplete blocks are received again
+			(data[..15].to_vec(), 10),
+			(data.clone(), data.len() as u64),
+			(corrupted, 10),
+		] {
+			let offset = req
+				.resume_offset(&mut Cursor::new(partial), &block_size)
+				.await
+				.unwrap();
+			assert_eq!(offset, expected);
+		}
 	}
 }
@@ -1,6 +1,8 @@
 //! A system for creating encrypted tunnels between peers over untrusted connections.
 
+mod pairing;
 mod tunnel;
 
+pub use pairing::*;
 pub use sd_p2p::{Identity, IdentityErr, RemoteIdentity};
 pub use tunnel::*;
@@ -0,0 +1,112 @@
+use std::{fmt, str::FromStr};
+
+use rand_core::{OsRng, RngCore};
+use thiserror::Error;
+
+/// Crockford's base32 alphabet, which leaves out letters easily mistaken for digits
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Number of characters in a pairing code, 40 bits of entropy
+pub const PAIRING_CODE_LEN: usize = 8;
+
+const PAIRING_CONTEXT: &str = "spacedrive 2024-05-02 sd-p2p-tunnel pairing code";
+
+#[derive(Debug, Error)]
+#[error("invalid pairing code")]
+pub struct InvalidPairingCode;
+
+/// A short code shown on one node and typed on the other to pair them. The initiator of a
+/// pairing tunnel proves it knows the code, so only someone who saw it can get a node to trust
+/// them. Codes should only be accepted once and for a short while, as they are easy to guess
+/// given enough attempts.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PairingCode([u8; PAIRING_CODE_LEN]);
+
+impl PairingCode {
+	#[must_use]
+	pub fn generate() -> Self {
+		let mut code = [0u8; PAIRING_CODE_LEN];
+		OsRng.fill_bytes(&mut code);
+		// 256 is a multiple of 32 so this doesn't favour any character
+		Self(code.map(|byte| ALPHABET[usize::from(byte % 32)]))
+	}
+
+	/// Proof of knowing the code, bound to a single handshake
+	pub(crate) fn proof(&self, transcript: &blake3::Hash) -> blake3::Hash {
+		blake3::keyed_hash(
+			&blake3::derive_key(PAIRING_CONTEXT, &self.0),
+			transcript.as_bytes(),
+		)
+	}
+}
+
+impl FromStr for PairingCode {
+	type Err = InvalidPairingCode;
+
+	/// Accepts codes the way people type them, ignoring case, separators and the letters
+	/// Crockford's base32 reads as digits
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut code = [0u8; PAIRING_CODE_LEN];
+		let mut len = 0;
+
+		for c in s.chars().filter(|c| *c != '-' && !c.is_whitespace()) {
+			let c = match c.to_ascii_uppercase() {
+				'O' => '0',
+				'I' | 'L' => '1',
+				c => c,
+			};
+
+			if len == PAIRING_CODE_LEN || !c.is_ascii() || !ALPHABET.contains(&(c as u8)) {
+				return Err(InvalidPairingCode);
+			}
+			code[len] = c as u8;
+			len += 1;
+		}
+
+		if len != PAIRING_CODE_LEN {
+			return Err(InvalidPairingCode);
+		}
+
+		Ok(Self(code))
+	}
+}
+
+impl fmt::Display for PairingCode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (first, second) = self.0.split_at(PAIRING_CODE_LEN / 2);
+		write!(
+			f,
+			"{}-{}",
+			String::from_utf8_lossy(first),
+			String::from_utf8_lossy(second)
+		)
+	}
+}
+
+// Codes are secrets, so they stay out of logs
+impl fmt::Debug for PairingCode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("PairingCode(..)")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_pairing_code() {
+		let code = PairingCode::generate();
+		let typed = code.to_string().to_lowercase().replace('-', " ");
+		assert_eq!(typed.parse::<PairingCode>().unwrap(), code);
+
+		assert_eq!(
+			"oi1l-abcd".parse::<PairingCode>().unwrap(),
+			"0111ABCD".parse::<PairingCode>().unwrap()
+		);
+
+		for invalid in ["", "0123456", "012345678", "0123456U", "0123456é"] {
+			assert!(invalid.parse::<PairingCode>().is_err(), "{invalid:?}");
+		}
+	}
+}
@@ -1,77 +1,625 @@
+//! Tunnels start with a handshake where both peers sign a transcript of ephemeral X25519 keys
+//! with their [`Identity`], which proves they own it and that nobody sits between them. The
+//! result of the key exchange is turned into a key per direction, and everything after the
+//! handshake is sent as ChaCha20-Poly1305 frames.
+//!
+//! ```text
+//! initiator -> responder: 'T' | version | mode | initiator ephemeral key
+//! responder -> initiator: responder ephemeral key | responder signature
+//! initiator -> responder: initiator signature | pairing proof (only when pairing)
+//! responder -> initiator: status
+//! ```
+
 use std::{
-	io,
+	fmt, io,
 	pin::Pin,
-	task::{Context, Poll},
+	task::{ready, Context, Poll},
 };
 
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use rand_core::OsRng;
+use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use sd_p2p::{Identity, RemoteIdentity, UnicastStream, SIGNATURE_LEN};
+
+use crate::PairingCode;
+
+const DISCRIMINATOR: u8 = b'T';
+const VERSION: u8 = 1;
+
+const MODE_NORMAL: u8 = 0;
+const MODE_PAIRING: u8 = 1;
+
+const STATUS_ACCEPTED: u8 = 0;
+const STATUS_UNTRUSTED: u8 = 1;
+const STATUS_INVALID_PAIRING_CODE: u8 = 2;
+const STATUS_UNEXPECTED_MODE: u8 = 3;
+
+const TRANSCRIPT_CONTEXT: &str = "spacedrive 2024-05-02 sd-p2p-tunnel transcript";
+const INITIATOR_KEY_CONTEXT: &str = "spacedrive 2024-05-02 sd-p2p-tunnel initiator to responder";
+const RESPONDER_KEY_CONTEXT: &str = "spacedrive 2024-05-02 sd-p2p-tunnel responder to initiator";
+
+/// Each side signs the transcript prefixed with its role, so a signature can't be reflected back
+const INITIATOR_ROLE: &[u8] = b"initiator";
+const RESPONDER_ROLE: &[u8] = b"responder";
 
-use sd_p2p::UnicastStream;
+const EPHEMERAL_KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const FRAME_HEADER_LEN: usize = 4;
 
-#[derive(Debug)]
-pub struct Tunnel {
-	stream: UnicastStream,
+/// Largest amount of plaintext in a single frame
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum TunnelError {
+	#[error("io error: {0}")]
+	Io(#[from] io::Error),
+	#[error("invalid discriminator, is this stream actually a tunnel?")]
+	InvalidDiscriminator,
+	#[error("unsupported tunnel version '{0}'")]
+	UnsupportedVersion(u8),
+	#[error("invalid tunnel mode '{0}'")]
+	InvalidMode(u8),
+	#[error("the remote peer failed to prove its identity")]
+	InvalidSignature,
+	#[error("the key exchange didn't produce a usable key")]
+	WeakKey,
+	#[error("the remote peer doesn't trust us with this, it must be paired and allow it first")]
+	Untrusted,
+	#[error("invalid pairing code")]
+	InvalidPairingCode,
+	#[error("the remote peer wasn't expecting to pair, or was only expecting to")]
+	UnexpectedMode,
+	#[error("invalid handshake status '{0}'")]
+	InvalidStatus(u8),
+}
+
+/// Who a responder lets through
+#[derive(Clone, Copy)]
+enum Admission<'a> {
+	Trusted,
+	Untrusted,
+	Pairing(&'a PairingCode),
+}
+
+/// An authenticated and encrypted stream with a peer
+pub struct Tunnel<S = UnicastStream> {
+	stream: S,
+	remote_identity: RemoteIdentity,
+	send: ChaCha20Poly1305,
+	send_nonce: u64,
+	recv: ChaCha20Poly1305,
+	recv_nonce: u64,
+	/// Encrypted frame still being written to the stream
+	write_buf: Vec<u8>,
+	write_pos: usize,
+	/// Bytes of the frame being read from the stream
+	read_buf: Vec<u8>,
+	/// Decrypted data not read yet
+	plaintext: Vec<u8>,
+	plaintext_pos: usize,
 }
 
 impl Tunnel {
-	// TODO: Proper errors
-	pub async fn initiator(mut stream: UnicastStream) -> Result<Self, &'static str> {
-		stream
-			.write_all(&[b'T'])
-			.await
-			.map_err(|_| "Error writing discriminator")?;
-
-		// TODO: Do pairing + authentication
-
-		Ok(Self { stream })
-	}
-
-	// TODO: Proper errors
-	pub async fn responder(mut stream: UnicastStream) -> Result<Self, &'static str> {
-		let discriminator = stream
-			.read_u8()
-			.await
-			.map_err(|_| "Error reading discriminator. Is this stream actually a tunnel?")?;
-		if discriminator != b'T' {
-			return Err("Invalid discriminator. Is this stream actually a tunnel?");
+	/// Opens a tunnel with the peer at the other end of `stream`, which must be trusting us
+	pub async fn initiator(
+		stream: UnicastStream,
+		identity: &Identity,
+	) -> Result<Self, TunnelError> {
+		let remote = stream.remote_identity();
+		initiate(stream, identity, remote, None).await
+	}
+
+	/// Accepts a tunnel from the peer at the other end of `stream` if it's `trusted`
+	pub async fn responder(
+		stream: UnicastStream,
+		identity: &Identity,
+		trusted: bool,
+	) -> Result<Self, TunnelError> {
+		let remote = stream.remote_identity();
+		let admission = if trusted {
+			Admission::Trusted
+		} else {
+			Admission::Untrusted
+		};
+
+		respond(stream, identity, remote, admission).await
+	}
+
+	/// Opens a tunnel with a peer showing `code`, which starts trusting us once it succeeds
+	pub async fn pairing_initiator(
+		stream: UnicastStream,
+		identity: &Identity,
+		code: &PairingCode,
+	) -> Result<Self, TunnelError> {
+		let remote = stream.remote_identity();
+		initiate(stream, identity, remote, Some(code)).await
+	}
+
+	/// Accepts a tunnel from any peer proving it knows `code`
+	pub async fn pairing_responder(
+		stream: UnicastStream,
+		identity: &Identity,
+		code: &PairingCode,
+	) -> Result<Self, TunnelError> {
+		let remote = stream.remote_identity();
+		respond(stream, identity, remote, Admission::Pairing(code)).await
+	}
+}
+
+impl<S> Tunnel<S> {
+	fn new(stream: S, remote_identity: RemoteIdentity, send: [u8; 32], recv: [u8; 32]) -> Self {
+		Self {
+			stream,
+			remote_identity,
+			send: ChaCha20Poly1305::new(Key::from_slice(&send)),
+			send_nonce: 0,
+			recv: ChaCha20Poly1305::new(Key::from_slice(&recv)),
+			recv_nonce: 0,
+			write_buf: Vec::new(),
+			write_pos: 0,
+			read_buf: Vec::new(),
+			plaintext: Vec::new(),
+			plaintext_pos: 0,
 		}
+	}
 
-		// TODO: Do pairing + authentication
+	/// The peer at the other end, which proved it owns this identity during the handshake
+	pub fn remote_identity(&self) -> RemoteIdentity {
+		self.remote_identity
+	}
+}
 
-		Ok(Self { stream })
+fn transcript(
+	mode: u8,
+	initiator_ephemeral: &[u8; EPHEMERAL_KEY_LEN],
+	responder_ephemeral: &[u8; EPHEMERAL_KEY_LEN],
+	initiator: &RemoteIdentity,
+	responder: &RemoteIdentity,
+) -> blake3::Hash {
+	let mut hasher = blake3::Hasher::new_derive_key(TRANSCRIPT_CONTEXT);
+	hasher.update(&[VERSION, mode]);
+	hasher.update(initiator_ephemeral);
+	hasher.update(responder_ephemeral);
+	hasher.update(&initiator.get_bytes());
+	hasher.update(&responder.get_bytes());
+	hasher.finalize()
+}
+
+fn signed_message(role: &[u8], transcript: &blake3::Hash) -> Vec<u8> {
+	[role, transcript.as_bytes()].concat()
+}
+
+/// Derives the initiator to responder and responder to initiator keys
+fn session_keys(
+	secret: EphemeralSecret,
+	remote_ephemeral: [u8; EPHEMERAL_KEY_LEN],
+	transcript: &blake3::Hash,
+) -> Result<([u8; 32], [u8; 32]), TunnelError> {
+	let shared = secret.diffie_hellman(&PublicKey::from(remote_ephemeral));
+	if !shared.was_contributory() {
+		return Err(TunnelError::WeakKey);
+	}
+
+	let material = [shared.as_bytes().as_slice(), transcript.as_bytes()].concat();
+	Ok((
+		blake3::derive_key(INITIATOR_KEY_CONTEXT, &material),
+		blake3::derive_key(RESPONDER_KEY_CONTEXT, &material),
+	))
+}
+
+async fn initiate<S: AsyncRead + AsyncWrite + Unpin>(
+	mut stream: S,
+	identity: &Identity,
+	remote: RemoteIdentity,
+	pairing: Option<&PairingCode>,
+) -> Result<Tunnel<S>, TunnelError> {
+	let mode = if pairing.is_some() {
+		MODE_PAIRING
+	} else {
+		MODE_NORMAL
+	};
+	let secret = EphemeralSecret::random_from_rng(OsRng);
+	let ephemeral = PublicKey::from(&secret);
+
+	let mut hello = vec![DISCRIMINATOR, VERSION, mode];
+	hello.extend_from_slice(ephemeral.as_bytes());
+	stream.write_all(&hello).await?;
+	stream.flush().await?;
+
+	let mut remote_ephemeral = [0u8; EPHEMERAL_KEY_LEN];
+	stream.read_exact(&mut remote_ephemeral).await?;
+	let mut signature = [0u8; SIGNATURE_LEN];
+	stream.read_exact(&mut signature).await?;
+
+	let transcript = transcript(
+		mode,
+		ephemeral.as_bytes(),
+		&remote_ephemeral,
+		&identity.to_remote_identity(),
+		&remote,
+	);
+	if !remote.verify(&signed_message(RESPONDER_ROLE, &transcript), &signature) {
+		return Err(TunnelError::InvalidSignature);
+	}
+
+	let mut reply = identity
+		.sign(&signed_message(INITIATOR_ROLE, &transcript))
+		.to_vec();
+	if let Some(code) = pairing {
+		reply.extend_from_slice(code.proof(&transcript).as_bytes());
 	}
+	stream.write_all(&reply).await?;
+	stream.flush().await?;
+
+	match stream.read_u8().await? {
+		STATUS_ACCEPTED => {}
+		STATUS_UNTRUSTED => return Err(TunnelError::Untrusted),
+		STATUS_INVALID_PAIRING_CODE => return Err(TunnelError::InvalidPairingCode),
+		STATUS_UNEXPECTED_MODE => return Err(TunnelError::UnexpectedMode),
+		status => return Err(TunnelError::InvalidStatus(status)),
+	}
+
+	let (send, recv) = session_keys(secret, remote_ephemeral, &transcript)?;
+	Ok(Tunnel::new(stream, remote, send, recv))
 }
 
-impl AsyncRead for Tunnel {
+async fn respond<S: AsyncRead + AsyncWrite + Unpin>(
+	mut stream: S,
+	identity: &Identity,
+	remote: RemoteIdentity,
+	admission: Admission<'_>,
+) -> Result<Tunnel<S>, TunnelError> {
+	if stream.read_u8().await? != DISCRIMINATOR {
+		return Err(TunnelError::InvalidDiscriminator);
+	}
+
+	let version = stream.read_u8().await?;
+	if version != VERSION {
+		return Err(TunnelError::UnsupportedVersion(version));
+	}
+
+	let mode = stream.read_u8().await?;
+	if mode != MODE_NORMAL && mode != MODE_PAIRING {
+		return Err(TunnelError::InvalidMode(mode));
+	}
+
+	let mut remote_ephemeral = [0u8; EPHEMERAL_KEY_LEN];
+	stream.read_exact(&mut remote_ephemeral).await?;
+
+	let secret = EphemeralSecret::random_from_rng(OsRng);
+	let ephemeral = PublicKey::from(&secret);
+	let transcript = transcript(
+		mode,
+		&remote_ephemeral,
+		ephemeral.as_bytes(),
+		&remote,
+		&identity.to_remote_identity(),
+	);
+
+	let mut hello = ephemeral.as_bytes().to_vec();
+	hello.extend_from_slice(&identity.sign(&signed_message(RESPONDER_ROLE, &transcript)));
+	stream.write_all(&hello).await?;
+	stream.flush().await?;
+
+	let mut signature = [0u8; SIGNATURE_LEN];
+	stream.read_exact(&mut signature).await?;
+	let proof = if mode == MODE_PAIRING {
+		let mut proof = [0u8; blake3::OUT_LEN];
+		stream.read_exact(&mut proof).await?;
+		Some(blake3::Hash::from(proof))
+	} else {
+		None
+	};
+
+	if !remote.verify(&signed_message(INITIATOR_ROLE, &transcript), &signature) {
+		return Err(TunnelError::InvalidSignature);
+	}
+
+	let status = match (admission, proof) {
+		(Admission::Trusted, None) => STATUS_ACCEPTED,
+		(Admission::Untrusted, None) => STATUS_UNTRUSTED,
+		// `blake3::Hash` compares in constant time
+		(Admission::Pairing(code), Some(proof)) if code.proof(&transcript) == proof => {
+			STATUS_ACCEPTED
+		}
+		(Admission::Pairing(_), Some(_)) => STATUS_INVALID_PAIRING_CODE,
+		_ => STATUS_UNEXPECTED_MODE,
+	};
+	stream.write_u8(status).await?;
+	stream.flush().await?;
+
+	match status {
+		STATUS_ACCEPTED => {}
+		STATUS_UNTRUSTED => return Err(TunnelError::Untrusted),
+		STATUS_INVALID_PAIRING_CODE => return Err(TunnelError::InvalidPairingCode),
+		_ => return Err(TunnelError::UnexpectedMode),
+	}
+
+	let (recv, send) = session_keys(secret, remote_ephemeral, &transcript)?;
+	Ok(Tunnel::new(stream, remote, send, recv))
+}
+
+fn next_nonce(counter: &mut u64) -> io::Result<Nonce> {
+	let mut nonce = Nonce::default();
+	nonce[..8].copy_from_slice(&counter.to_le_bytes());
+	*counter = counter
+		.checked_add(1)
+		.ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Tunnel ran out of nonces"))?;
+
+	Ok(nonce)
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Tunnel<S> {
+	/// Number of bytes missing from `read_buf` to complete the current frame
+	fn missing(&self) -> usize {
+		match self.read_buf.get(..FRAME_HEADER_LEN) {
+			Some(header) => {
+				let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+				FRAME_HEADER_LEN + len as usize - self.read_buf.len()
+			}
+			None => FRAME_HEADER_LEN - self.read_buf.len(),
+		}
+	}
+
+	/// Decrypts the frame in `read_buf` once all of it arrived
+	fn take_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+		let Some(header) = self.read_buf.get(..FRAME_HEADER_LEN) else {
+			return Ok(None);
+		};
+
+		let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+		if len > MAX_FRAME_LEN + TAG_LEN {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"Tunnel frame is too large",
+			));
+		}
+
+		if self.read_buf.len() < FRAME_HEADER_LEN + len {
+			return Ok(None);
+		}
+
+		let nonce = next_nonce(&mut self.recv_nonce)?;
+		let plaintext = self
+			.recv
+			.decrypt(&nonce, &self.read_buf[FRAME_HEADER_LEN..])
+			.map_err(|_| {
+				io::Error::new(io::ErrorKind::InvalidData, "Tunnel frame failed to decrypt")
+			})?;
+		self.read_buf.clear();
+
+		Ok(Some(plaintext))
+	}
+
+	/// Writes out what's left of the current frame
+	fn poll_write_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		while self.write_pos < self.write_buf.len() {
+			let written = ready!(
+				Pin::new(&mut self.stream).poll_write(cx, &self.write_buf[self.write_pos..])
+			)?;
+			if written == 0 {
+				return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+			}
+			self.write_pos += written;
+		}
+
+		self.write_buf.clear();
+		self.write_pos = 0;
+
+		Poll::Ready(Ok(()))
+	}
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for Tunnel<S> {
 	fn poll_read(
 		self: Pin<&mut Self>,
 		cx: &mut Context<'_>,
 		buf: &mut ReadBuf<'_>,
 	) -> Poll<io::Result<()>> {
-		// TODO: Do decryption
+		let this = self.get_mut();
+
+		while this.plaintext_pos == this.plaintext.len() {
+			if let Some(plaintext) = this.take_frame()? {
+				this.plaintext = plaintext;
+				this.plaintext_pos = 0;
+				continue;
+			}
+
+			// Only ever read up to the end of the current frame, so `read_buf` holds a single one
+			let filled = this.read_buf.len();
+			this.read_buf.resize(filled + this.missing(), 0);
+			let mut read_buf = ReadBuf::new(&mut this.read_buf[filled..]);
+			let result = Pin::new(&mut this.stream).poll_read(cx, &mut read_buf);
+			let read = read_buf.filled().len();
+			this.read_buf.truncate(filled + read);
+			ready!(result)?;
+
+			if read == 0 {
+				return Poll::Ready(if filled == 0 {
+					Ok(())
+				} else {
+					Err(io::ErrorKind::UnexpectedEof.into())
+				});
+			}
+		}
+
+		let len = buf
+			.remaining()
+			.min(this.plaintext.len() - this.plaintext_pos);
+		buf.put_slice(&this.plaintext[this.plaintext_pos..this.plaintext_pos + len]);
+		this.plaintext_pos += len;
 
-		Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+		Poll::Ready(Ok(()))
 	}
 }
 
-impl AsyncWrite for Tunnel {
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for Tunnel<S> {
 	fn poll_write(
 		self: Pin<&mut Self>,
 		cx: &mut Context<'_>,
 		buf: &[u8],
 	) -> Poll<io::Result<usize>> {
-		// TODO: Do encryption
+		let this = self.get_mut();
+		ready!(this.poll_write_frame(cx))?;
 
-		Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+		if buf.is_empty() {
+			return Poll::Ready(Ok(0));
+		}
+
+		let len = buf.len().min(MAX_FRAME_LEN);
+		let nonce = next_nonce(&mut this.send_nonce)?;
+		let ciphertext = this
+			.send
+			.encrypt(&nonce, &buf[..len])
+			.map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to encrypt tunnel frame"))?;
+
+		this.write_buf
+			.extend_from_slice(&(ciphertext.len() as u32).to_le_bytes());
+		this.write_buf.extend_from_slice(&ciphertext);
+
+		// The frame is ours to write from now on, `poll_flush` finishes it if the stream is busy
+		if let Poll::Ready(Err(err)) = this.poll_write_frame(cx) {
+			return Poll::Ready(Err(err));
+		}
+
+		Poll::Ready(Ok(len))
 	}
 
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+		let this = self.get_mut();
+		ready!(this.poll_write_frame(cx))?;
+		Pin::new(&mut this.stream).poll_flush(cx)
 	}
 
 	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+		let this = self.get_mut();
+		ready!(this.poll_write_frame(cx))?;
+		Pin::new(&mut this.stream).poll_shutdown(cx)
+	}
+}
+
+impl<S> fmt::Debug for Tunnel<S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Tunnel")
+			.field("remote_identity", &self.remote_identity)
+			.finish_non_exhaustive()
 	}
 }
 
-// TODO: Unit tests
+#[cfg(test)]
+mod tests {
+	use tokio::io::{duplex, DuplexStream};
+
+	use super::*;
+
+	async fn handshake(
+		initiator: &Identity,
+		responder: &Identity,
+		pairing: Option<&PairingCode>,
+		admission: Admission<'_>,
+	) -> (
+		Result<Tunnel<DuplexStream>, TunnelError>,
+		Result<Tunnel<DuplexStream>, TunnelError>,
+	) {
+		let (a, b) = duplex(64);
+
+		tokio::join!(
+			initiate(a, initiator, responder.to_remote_identity(), pairing),
+			respond(b, responder, initiator.to_remote_identity(), admission),
+		)
+	}
+
+	#[tokio::test]
+	async fn test_tunnel() {
+		let (initiator, responder) = (Identity::new(), Identity::new());
+		let (a, b) = handshake(&initiator, &responder, None, Admission::Trusted).await;
+		let (mut a, mut b) = (a.unwrap(), b.unwrap());
+		assert_eq!(a.remote_identity(), responder.to_remote_identity());
+		assert_eq!(b.remote_identity(), initiator.to_remote_identity());
+
+		// Spans many frames, in both directions at once
+		let data = (0..MAX_FRAME_LEN * 3 + 42)
+			.map(|i| i as u8)
+			.collect::<Vec<_>>();
+
+		let (sent, received) = tokio::join!(
+			async {
+				a.write_all(&data).await.unwrap();
+				a.flush().await.unwrap();
+				let mut reply = vec![0u8; 10];
+				a.read_exact(&mut reply).await.unwrap();
+				reply
+			},
+			async {
+				let mut received = vec![0u8; data.len()];
+				b.read_exact(&mut received).await.unwrap();
+				b.write_all(b"Spacedrive").await.unwrap();
+				b.flush().await.unwrap();
+				received
+			}
+		);
+		assert_eq!(sent, b"Spacedrive");
+		assert_eq!(received, data);
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_untrusted() {
+		let (initiator, responder) = (Identity::new(), Identity::new());
+		let (a, b) = handshake(&initiator, &responder, None, Admission::Untrusted).await;
+		assert!(matches!(a, Err(TunnelError::Untrusted)));
+		assert!(matches!(b, Err(TunnelError::Untrusted)));
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_impersonation() {
+		let (initiator, responder, impostor) = (Identity::new(), Identity::new(), Identity::new());
+		let (a, b) = duplex(64);
+
+		// The initiator expects `responder`, but `impostor` answers
+		let (a, _) = tokio::join!(
+			initiate(a, &initiator, responder.to_remote_identity(), None),
+			respond(
+				b,
+				&impostor,
+				initiator.to_remote_identity(),
+				Admission::Trusted
+			),
+		);
+		assert!(matches!(a, Err(TunnelError::InvalidSignature)));
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_pairing() {
+		let (initiator, responder) = (Identity::new(), Identity::new());
+		let code = PairingCode::generate();
+
+		let (a, b) = handshake(
+			&initiator,
+			&responder,
+			Some(&code),
+			Admission::Pairing(&code),
+		)
+		.await;
+		assert!(a.is_ok());
+		assert!(b.is_ok());
+
+		let wrong_code = PairingCode::generate();
+		let (a, b) = handshake(
+			&initiator,
+			&responder,
+			Some(&wrong_code),
+			Admission::Pairing(&code),
+		)
+		.await;
+		assert!(matches!(a, Err(TunnelError::InvalidPairingCode)));
+		assert!(matches!(b, Err(TunnelError::InvalidPairingCode)));
+
+		// Pairing doesn't get an untrusted peer through a regular responder
+		let (a, b) = handshake(&initiator, &responder, Some(&code), Admission::Untrusted).await;
+		assert!(matches!(a, Err(TunnelError::UnexpectedMode)));
+		assert!(matches!(b, Err(TunnelError::UnexpectedMode)));
+	}
+}
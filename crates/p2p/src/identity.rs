@@ -6,7 +6,7 @@ use std::{
 };
 
 use base64::{engine::general_purpose, Engine};
-use ed25519_dalek::{VerifyingKey, SECRET_KEY_LENGTH};
+use ed25519_dalek::{Signature, Signer, VerifyingKey, SECRET_KEY_LENGTH, SIGNATURE_LENGTH};
 use rand_core::OsRng;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -14,6 +14,7 @@ use thiserror::Error;
 use zeroize::ZeroizeOnDrop;
 
 pub const REMOTE_IDENTITY_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = SIGNATURE_LENGTH;
 
 #[derive(Debug, Error)]
 #[error(transparent)]
@@ -63,6 +64,12 @@ impl Identity {
 	pub fn to_remote_identity(&self) -> RemoteIdentity {
 		RemoteIdentity(self.0.verifying_key())
 	}
+
+	/// Signs `message` so anyone with our [`RemoteIdentity`] can check it came from us
+	#[must_use]
+	pub fn sign(&self, message: &[u8]) -> [u8; SIGNATURE_LEN] {
+		self.0.sign(message).to_bytes()
+	}
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Type)]
@@ -163,6 +170,14 @@ impl RemoteIdentity {
 	pub fn verifying_key(&self) -> VerifyingKey {
 		self.0
 	}
+
+	/// Whether `signature` was made over `message` by the [`Identity`] behind this
+	#[must_use]
+	pub fn verify(&self, message: &[u8], signature: &[u8; SIGNATURE_LEN]) -> bool {
+		self.0
+			.verify_strict(message, &Signature::from_bytes(signature))
+			.is_ok()
+	}
 }
 
 impl From<ed25519_dalek::SigningKey> for Identity {
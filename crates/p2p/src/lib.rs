@@ -13,6 +13,7 @@ mod stream;
 pub use hooks::{HookEvent, HookId, ListenerId, ShutdownGuard};
 pub use identity::{
 	Identity, IdentityErr, IdentityOrRemoteIdentity, IdentityOrRemoteIdentityErr, RemoteIdentity,
+	SIGNATURE_LEN,
 };
 pub use mdns::Mdns;
 pub use p2p::{Listener, P2P};
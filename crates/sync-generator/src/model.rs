@@ -85,10 +85,30 @@ pub fn module((model, sync_type): ModelWithSyncType) -> Module {
 					let relation_model_name_snake =
 						snake_ident(relation_field.related_model().name());
 
+					// Optional relations are cleared by syncing nil, which we apply by unsetting the
+					// foreign key, as `disconnect` isn't accepted when the upsert creates the record
+					let clear = relation_field
+						.referencing_fields()
+						.into_iter()
+						.flatten()
+						.next()
+						.filter(|scalar_field| !scalar_field.is_in_required_relation())
+						.map(|scalar_field| {
+							let scalar_field_name_snake = snake_ident(scalar_field.name());
+
+							quote! {
+								if val.is_nil() {
+									return Some(#model_name_snake::#scalar_field_name_snake::set(None));
+								}
+							}
+						});
+
 					match relation_field.referenced_fields() {
 						Some(i) => {
 							if i.count() == 1 {
 								Some(quote! {{
+									#clear
+
 									let val: std::collections::HashMap<String, rmpv::Value> = ::rmpv::ext::from_value(val).unwrap();
 									let val = val.into_iter().next().unwrap();
 
@@ -27,6 +27,35 @@ pub fn from_bytes_to_uuid(bytes: &[u8]) -> Uuid {
 	Uuid::from_slice(bytes).expect("corrupted uuid in database")
 }
 
+/// Parses sizes like `512`, `10MB` or `1.5GiB`, where `KB` is 1000 bytes and `KiB` is 1024 bytes
+#[must_use]
+pub fn parse_size(value: &str) -> Option<u64> {
+	let unit_start = value
+		.find(|c: char| !(c.is_ascii_digit() || c == '.'))
+		.unwrap_or(value.len());
+	let (number, unit) = value.split_at(unit_start);
+
+	let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+		"" | "b" => 1,
+		"k" | "kb" => 1_000,
+		"m" | "mb" => 1_000_000,
+		"g" | "gb" => 1_000_000_000,
+		"t" | "tb" => 1_000_000_000_000,
+		"kib" => 1 << 10,
+		"mib" => 1 << 20,
+		"gib" => 1 << 30,
+		"tib" => 1 << 40,
+		_ => return None,
+	};
+
+	if let Ok(number) = number.parse::<u64>() {
+		number.checked_mul(multiplier)
+	} else {
+		let number = number.parse::<f64>().ok()?;
+		(number.is_finite() && number >= 0.0).then(|| (number * multiplier as f64).round() as u64)
+	}
+}
+
 #[macro_export]
 macro_rules! msgpack {
 	(null) => {
@@ -36,3 +65,19 @@ macro_rules! msgpack {
 		::rmpv::ext::to_value(&$e).expect("failed to serialize msgpack")
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sizes() {
+		assert_eq!(parse_size("512"), Some(512));
+		assert_eq!(parse_size("1.5GB"), Some(1_500_000_000));
+		assert_eq!(parse_size("2KiB"), Some(2048));
+		assert_eq!(parse_size("10mb"), Some(10_000_000));
+		assert_eq!(parse_size("10XB"), None);
+		assert_eq!(parse_size("MB"), None);
+		assert_eq!(parse_size("-1"), None);
+	}
+}
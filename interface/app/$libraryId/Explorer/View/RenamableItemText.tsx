@@ -81,6 +81,7 @@ export const RenamableItemText = ({ allowHighlight = true, ...props }: Props) =>
 							generate_preview_media: null,
 							sync_preview_media: null,
 							hidden: null,
+							respect_ignore_files: null,
 							indexer_rules_ids: []
 						});
 
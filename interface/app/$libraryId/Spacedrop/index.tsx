@@ -37,6 +37,8 @@ export function SpacedropProvider() {
 		} else if (data.type === 'SpacedropRejected') {
 			// TODO: Add more information to this like peer name, etc in future
 			toast.warning(t('spacedrop_rejected'));
+		} else if (data.type === 'SpacedropNotAllowed') {
+			toast.warning(t('spacedrop_not_allowed'));
 		}
 	});
 
@@ -34,6 +34,7 @@ const schema = z.object({
 	name: z.string().min(1).nullable(),
 	path: z.string().min(1).nullable(),
 	hidden: z.boolean().nullable(),
+	respectIgnoreFiles: z.boolean().nullable(),
 	indexerRulesIds: z.array(z.number()),
 	locationType: z.string(),
 	syncPreviewMedia: z.boolean().nullable(),
@@ -68,6 +69,7 @@ const EditLocationForm = () => {
 			name: locationData?.name ?? '',
 			path: locationData?.path ?? '',
 			hidden: locationData?.hidden ?? false,
+			respectIgnoreFiles: locationData?.respect_ignore_files ?? false,
 			syncPreviewMedia: locationData?.sync_preview_media ?? false,
 			generatePreviewMedia: locationData?.generate_preview_media ?? false
 		}
@@ -89,6 +91,7 @@ const EditLocationForm = () => {
 			path: data.path,
 			name: data.name,
 			hidden: data.hidden,
+			respect_ignore_files: data.respectIgnoreFiles,
 			indexer_rules_ids: data.indexerRulesIds,
 			sync_preview_media: data.syncPreviewMedia,
 			generate_preview_media: data.generatePreviewMedia
@@ -173,6 +176,15 @@ const EditLocationForm = () => {
 					</ToggleSection>
 				</div>
 				<Divider />
+				<ToggleSection className="mb-2">
+					<Label className="grow">
+						{t('respect_ignore_files')}{' '}
+						<Tooltip label={t('respect_ignore_files_label')}>
+							<Info className="inline" />
+						</Tooltip>
+					</Label>
+					<SwitchField {...form.register('respectIgnoreFiles')} size="sm" />
+				</ToggleSection>
 				<Controller
 					name="indexerRulesIds"
 					render={({ field }) => (
@@ -21,7 +21,16 @@ const ruleKinds: UnionToTuple<RuleKind> = [
 	'AcceptFilesByGlob',
 	'RejectFilesByGlob',
 	'AcceptIfChildrenDirectoriesArePresent',
-	'RejectIfChildrenDirectoriesArePresent'
+	'RejectIfChildrenDirectoriesArePresent',
+	'AcceptFilesByXattr',
+	'RejectFilesByXattr',
+	'AcceptFilesBySize',
+	'RejectFilesBySize',
+	'AcceptFilesByModifiedAge',
+	'RejectFilesByModifiedAge',
+	'AcceptFilesByPermissions',
+	'RejectFilesByPermissions',
+	'RejectFilesByIgnoreFiles'
 ];
 const ruleKindEnum = z.enum(ruleKinds);
 
@@ -3,6 +3,10 @@
 
 export type Procedures = {
     queries: 
+        { key: "albums.get", input: LibraryArgs<number>, result: Album | null } | 
+        { key: "albums.getForObject", input: LibraryArgs<number>, result: Album[] } | 
+        { key: "albums.list", input: LibraryArgs<null>, result: AlbumWithThumbnails[] } | 
+        { key: "albums.objects", input: LibraryArgs<number>, result: NormalisedResults<ExplorerItem> } | 
         { key: "auth.me", input: never, result: { id: string; email: string } } | 
         { key: "backups.getAll", input: never, result: GetAll } | 
         { key: "buildInfo", input: never, result: BuildInfo } | 
@@ -15,6 +19,7 @@ export type Procedures = {
         { key: "files.getConvertableImageExtensions", input: never, result: string[] } | 
         { key: "files.getMediaData", input: LibraryArgs<number>, result: MediaMetadata } | 
         { key: "files.getPath", input: LibraryArgs<number>, result: string | null } | 
+        { key: "files.planFolderSync", input: LibraryArgs<OldFolderSyncJobInit>, result: FolderSyncPlan } | 
         { key: "invalidation.test-invalidate", input: never, result: number } | 
         { key: "jobs.isActive", input: LibraryArgs<null>, result: boolean } | 
         { key: "jobs.reports", input: LibraryArgs<null>, result: JobGroup[] } | 
@@ -29,9 +34,12 @@ export type Procedures = {
         { key: "library.statistics", input: LibraryArgs<null>, result: StatisticsResponse } | 
         { key: "locations.get", input: LibraryArgs<number>, result: { item: Reference<Location>; nodes: CacheNode[] } | null } | 
         { key: "locations.getWithRules", input: LibraryArgs<number>, result: { item: Reference<LocationWithIndexerRule>; nodes: CacheNode[] } | null } | 
+        { key: "locations.indexer_rules.dryRun", input: LibraryArgs<RuleSetDryRunArgs>, result: RuleSetDryRun } | 
+        { key: "locations.indexer_rules.export", input: LibraryArgs<number[]>, result: string } | 
         { key: "locations.indexer_rules.get", input: LibraryArgs<number>, result: NormalisedResult<IndexerRule> } | 
         { key: "locations.indexer_rules.list", input: LibraryArgs<null>, result: NormalisedResults<IndexerRule> } | 
         { key: "locations.indexer_rules.listForLocation", input: LibraryArgs<number>, result: NormalisedResults<IndexerRule> } | 
+        { key: "locations.indexer_rules.validate", input: string, result: RuleSet } | 
         { key: "locations.list", input: LibraryArgs<null>, result: NormalisedResults<Location> } | 
         { key: "locations.systemLocations", input: never, result: SystemLocations } | 
         { key: "models.image_detection.list", input: never, result: string[] } | 
@@ -41,21 +49,37 @@ export type Procedures = {
         { key: "notifications.dismissAll", input: never, result: null } | 
         { key: "notifications.get", input: never, result: Notification[] } | 
         { key: "p2p.state", input: never, result: JsonValue } | 
+        { key: "p2p.trustedPeers", input: never, result: ([RemoteIdentity, TrustedPeer])[] } | 
         { key: "preferences.get", input: LibraryArgs<null>, result: LibraryPreferences } | 
+        { key: "search.content", input: LibraryArgs<ContentSearchArgs>, result: ContentSearchData } | 
+        { key: "search.duplicates", input: LibraryArgs<DuplicatesSearchArgs>, result: DuplicatesSearchData } | 
         { key: "search.objects", input: LibraryArgs<ObjectSearchArgs>, result: SearchData<ExplorerItem> } | 
         { key: "search.objectsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: number } | 
+        { key: "search.parseQuery", input: LibraryArgs<string>, result: ParsedSearchQuery } | 
         { key: "search.paths", input: LibraryArgs<FilePathSearchArgs>, result: SearchData<ExplorerItem> } | 
         { key: "search.pathsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: number } | 
         { key: "search.saved.get", input: LibraryArgs<number>, result: { id: number; pub_id: number[]; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null } | null } | 
         { key: "search.saved.list", input: LibraryArgs<null>, result: SavedSearch[] } | 
+        { key: "search.similarObjects", input: LibraryArgs<SimilarObjectsSearchArgs>, result: SimilarObjectsSearchData } | 
+        { key: "spaces.get", input: LibraryArgs<number>, result: SpaceWithLayout | null } | 
+        { key: "spaces.list", input: LibraryArgs<null>, result: Space[] } | 
+        { key: "spaces.objects", input: LibraryArgs<number>, result: NormalisedResults<ExplorerItem> } | 
         { key: "sync.enabled", input: LibraryArgs<null>, result: boolean } | 
         { key: "sync.messages", input: LibraryArgs<null>, result: CRDTOperation[] } | 
         { key: "tags.get", input: LibraryArgs<number>, result: { item: Reference<Tag>; nodes: CacheNode[] } | null } | 
         { key: "tags.getForObject", input: LibraryArgs<number>, result: NormalisedResults<Tag> } | 
         { key: "tags.getWithObjects", input: LibraryArgs<number[]>, result: { [key in number]: ({ date_created: string | null; object: { id: number } })[] } } | 
         { key: "tags.list", input: LibraryArgs<null>, result: NormalisedResults<Tag> } | 
+        { key: "tags.listRules", input: LibraryArgs<null>, result: TagRuleItem[] } | 
         { key: "volumes.list", input: never, result: NormalisedResults<Volume> },
     mutations: 
+        { key: "albums.addObjects", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.create", input: LibraryArgs<AlbumCreateArgs>, result: Album } | 
+        { key: "albums.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "albums.removeObjects", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.rename", input: LibraryArgs<AlbumRenameArgs>, result: null } | 
+        { key: "albums.reorder", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.setCover", input: LibraryArgs<AlbumSetCoverArgs>, result: null } | 
         { key: "api.sendFeedback", input: Feedback, result: null } | 
         { key: "auth.logout", input: never, result: null } | 
         { key: "backups.backup", input: LibraryArgs<null>, result: string } | 
@@ -73,16 +97,20 @@ export type Procedures = {
         { key: "ephemeralFiles.cutFiles", input: LibraryArgs<EphemeralFileSystemOps>, result: null } | 
         { key: "ephemeralFiles.deleteFiles", input: LibraryArgs<string[]>, result: null } | 
         { key: "ephemeralFiles.renameFile", input: LibraryArgs<EphemeralRenameFileArgs>, result: null } | 
-        { key: "files.convertImage", input: LibraryArgs<ConvertImageArgs>, result: null } | 
+        { key: "files.compressFiles", input: LibraryArgs<OldArchiveCompressorJobInit>, result: null } | 
+        { key: "files.convertImage", input: LibraryArgs<ImageConverterArgs>, result: null } | 
         { key: "files.copyFiles", input: LibraryArgs<OldFileCopierJobInit>, result: null } | 
         { key: "files.createFolder", input: LibraryArgs<CreateFolderArgs>, result: string } | 
         { key: "files.cutFiles", input: LibraryArgs<OldFileCutterJobInit>, result: null } | 
         { key: "files.deleteFiles", input: LibraryArgs<OldFileDeleterJobInit>, result: null } | 
         { key: "files.eraseFiles", input: LibraryArgs<OldFileEraserJobInit>, result: null } | 
+        { key: "files.extractArchive", input: LibraryArgs<OldArchiveExtractorJobInit>, result: null } | 
+        { key: "files.reclaimDuplicates", input: LibraryArgs<OldDuplicateReclaimerJobInit>, result: null } | 
         { key: "files.removeAccessTime", input: LibraryArgs<number[]>, result: null } | 
         { key: "files.renameFile", input: LibraryArgs<RenameFileArgs>, result: null } | 
         { key: "files.setFavorite", input: LibraryArgs<SetFavoriteArgs>, result: null } | 
         { key: "files.setNote", input: LibraryArgs<SetNoteArgs>, result: null } | 
+        { key: "files.syncFolders", input: LibraryArgs<OldFolderSyncJobInit>, result: null } | 
         { key: "files.updateAccessTime", input: LibraryArgs<number[]>, result: null } | 
         { key: "invalidation.test-invalidate-mutation", input: LibraryArgs<null>, result: null } | 
         { key: "jobs.cancel", input: LibraryArgs<string>, result: null } | 
@@ -101,11 +129,14 @@ export type Procedures = {
         { key: "library.startActor", input: LibraryArgs<string>, result: null } | 
         { key: "library.stopActor", input: LibraryArgs<string>, result: null } | 
         { key: "locations.addLibrary", input: LibraryArgs<LocationCreateArgs>, result: number | null } | 
+        { key: "locations.archive", input: LibraryArgs<number>, result: null } | 
         { key: "locations.create", input: LibraryArgs<LocationCreateArgs>, result: number | null } | 
         { key: "locations.delete", input: LibraryArgs<number>, result: null } | 
         { key: "locations.fullRescan", input: LibraryArgs<FullRescanArgs>, result: null } | 
         { key: "locations.indexer_rules.create", input: LibraryArgs<IndexerRuleCreateArgs>, result: null } | 
         { key: "locations.indexer_rules.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "locations.indexer_rules.import", input: LibraryArgs<RuleSetImportArgs>, result: number[] } | 
+        { key: "locations.reattach", input: LibraryArgs<ReattachLocationArgs>, result: null } | 
         { key: "locations.relink", input: LibraryArgs<string>, result: number } | 
         { key: "locations.subPathRescan", input: LibraryArgs<RescanArgs>, result: null } | 
         { key: "locations.update", input: LibraryArgs<LocationUpdateArgs>, result: null } | 
@@ -114,15 +145,32 @@ export type Procedures = {
         { key: "p2p.acceptSpacedrop", input: [string, string | null], result: null } | 
         { key: "p2p.cancelSpacedrop", input: string, result: null } | 
         { key: "p2p.debugConnect", input: RemoteIdentity, result: string } | 
+        { key: "p2p.pair", input: PairingPayload, result: null } | 
+        { key: "p2p.setPeerPermissions", input: SetPeerPermissionsArgs, result: null } | 
         { key: "p2p.spacedrop", input: SpacedropArgs, result: string } | 
+        { key: "p2p.startPairing", input: never, result: PairingPayload } | 
+        { key: "p2p.untrustPeer", input: RemoteIdentity, result: null } | 
         { key: "preferences.update", input: LibraryArgs<LibraryPreferences>, result: null } | 
         { key: "search.saved.create", input: LibraryArgs<{ name: string; search?: string | null; filters?: string | null; description?: string | null; icon?: string | null }>, result: null } | 
+        { key: "search.saved.copy", input: LibraryArgs<SavedSearchCopyArgs>, result: null } | 
         { key: "search.saved.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "search.saved.export", input: LibraryArgs<SavedSearchExportArgs>, result: null } | 
         { key: "search.saved.update", input: LibraryArgs<[number, Args]>, result: null } | 
+        { key: "spaces.addObjects", input: LibraryArgs<SpaceObjectsArgs>, result: null } | 
+        { key: "spaces.create", input: LibraryArgs<SpaceCreateArgs>, result: Space } | 
+        { key: "spaces.createLink", input: LibraryArgs<SpaceLinkCreateArgs>, result: SpaceLink } | 
+        { key: "spaces.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "spaces.deleteLink", input: LibraryArgs<number>, result: null } | 
+        { key: "spaces.removeObjects", input: LibraryArgs<SpaceObjectsArgs>, result: null } | 
+        { key: "spaces.update", input: LibraryArgs<SpaceUpdateArgs>, result: null } | 
+        { key: "spaces.updateLayout", input: LibraryArgs<SpaceLayoutArgs>, result: null } | 
         { key: "sync.enable", input: LibraryArgs<null>, result: null } | 
         { key: "tags.assign", input: LibraryArgs<{ targets: Target[]; tag_id: number; unassign: boolean }>, result: null } | 
         { key: "tags.create", input: LibraryArgs<TagCreateArgs>, result: Tag } | 
+        { key: "tags.createRule", input: LibraryArgs<TagRuleCreateArgs>, result: null } | 
         { key: "tags.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "tags.deleteRule", input: LibraryArgs<number>, result: null } | 
+        { key: "tags.setParent", input: LibraryArgs<TagSetParentArgs>, result: null } | 
         { key: "tags.update", input: LibraryArgs<TagUpdateArgs>, result: null } | 
         { key: "toggleFeatureFlag", input: BackendFeature, result: null },
     subscriptions: 
@@ -136,19 +184,50 @@ export type Procedures = {
         { key: "notifications.listen", input: never, result: Notification } | 
         { key: "p2p.events", input: never, result: P2PEvent } | 
         { key: "search.ephemeralPaths", input: LibraryArgs<EphemeralPathSearchArgs>, result: EphemeralPathsResultItem } | 
+        { key: "search.saved.changes", input: LibraryArgs<number>, result: SavedSearchChanges } | 
         { key: "sync.newMessage", input: LibraryArgs<null>, result: null }
 };
 
+export type Album = { id: number; pub_id: number[]; name: string | null; is_hidden: boolean | null; cover_id: number | null; date_created: string | null; date_modified: string | null }
+
+export type AlbumCreateArgs = { name: string; object_ids?: number[] }
+
+export type AlbumObjectsArgs = { album_id: number; object_ids: number[] }
+
+export type AlbumRenameArgs = { id: number; name: string }
+
+export type AlbumSetCoverArgs = { id: number; object_id: number | null }
+
+export type AlbumWithThumbnails = { album: Album; object_count: number; 
+/**
+ * Thumbnail of the cover object, or of the first objects when there's no cover
+ */
+thumbnails: string[][] }
+
+export type ArchiveFormat = "Zip" | "Tar" | "TarGz" | "TarZst"
+
 export type Args = { search?: string | null; filters?: string | null; name?: string | null; icon?: string | null; description?: string | null }
 
-export type AudioMetadata = { duration: number | null; audio_codec: string | null }
+export type AudioMetadata = { 
+/**
+ * Duration in milliseconds
+ */
+duration: number | null; audio_codec: string | null; 
+/**
+ * Average bitrate in bits per second, derived from the file size and duration
+ */
+bitrate: number | null; sample_rate: number | null; channels: number | null; bit_depth: number | null; title: string | null; artist: string | null; album: string | null; album_artist: string | null; genre: string | null; track_number: number | null; year: number | null; has_cover_art: boolean }
 
 /**
  * All of the feature flags provided by the core itself. The frontend has it's own set of feature flags!
  * 
  * If you want a variant of this to show up on the frontend it must be added to `backendFeatures` in `useFeatureFlag.tsx`
  */
-export type BackendFeature = "filesOverP2P" | "cloudSync"
+export type BackendFeature = "filesOverP2P" | "cloudSync" | 
+/**
+ * Confirms `cas_id` matches with a full content hash before linking files to the same object
+ */
+"verifiedDedup"
 
 export type Backup = ({ id: string; timestamp: string; library_id: string; library_name: string }) & { path: string }
 
@@ -196,7 +275,28 @@ export type Composite =
  */
 export type ConnectionMethod = "Relay" | "Local" | "Disconnected"
 
-export type ConvertImageArgs = { location_id: number; file_path_id: number; delete_src: boolean; desired_extension: ConvertibleExtension; quality_percentage: number | null }
+export type ContentSearchArgs = { query: string; take?: number | null; filters?: SearchFilterArgs[] }
+
+export type ContentSearchData = { items: ContentSearchItem[]; nodes: CacheNode[] }
+
+export type ContentSearchItem = { item: Reference<ExplorerItem>; 
+/**
+ * Part of the contents around the match, with the matched words highlighted
+ */
+snippet: SnippetPart[] }
+
+/**
+ * What to do when there is already a file with the name of the converted image
+ */
+export type ConversionConflictPolicy = 
+/**
+ * Saves the converted image next to the existing file, adding a ` (n)` suffix to its name
+ */
+"Sibling" | 
+/**
+ * Replaces the existing file with the converted image
+ */
+"Overwrite"
 
 export type ConvertibleExtension = "bmp" | "dib" | "ff" | "gif" | "ico" | "jpg" | "jpeg" | "png" | "pnm" | "qoi" | "tga" | "icb" | "vda" | "vst" | "tiff" | "tif" | "hif" | "heif" | "heifs" | "heic" | "heics" | "avif" | "avci" | "avcs" | "svg" | "svgz" | "pdf" | "webp"
 
@@ -220,13 +320,24 @@ export type DiskType = "SSD" | "HDD" | "Removable"
 
 export type DoubleClickAction = "openFile" | "quickPreview"
 
+export type DuplicateGroup = { objectId: number; sizeInBytes: string; wastedBytes: string; filePaths: Reference<ExplorerItem>[] }
+
+/**
+ * Sizes are strings as Javascript numbers can't hold every `u64`
+ */
+export type DuplicateSizeFilter = { minSizeInBytes?: string | null; maxSizeInBytes?: string | null }
+
+export type DuplicatesSearchArgs = { take?: number | null; skip?: number; filters?: SearchFilterArgs[]; size?: DuplicateSizeFilter }
+
+export type DuplicatesSearchData = { groups: DuplicateGroup[]; totalGroups: number; totalWastedBytes: string; nodes: CacheNode[] }
+
 export type EditLibraryArgs = { id: string; name: LibraryName | null; description: MaybeUndefined<string> }
 
 export type EphemeralFileSystemOps = { sources: string[]; target_dir: string }
 
 export type EphemeralPathOrder = { field: "name"; value: SortOrder } | { field: "sizeInBytes"; value: SortOrder } | { field: "dateCreated"; value: SortOrder } | { field: "dateModified"; value: SortOrder }
 
-export type EphemeralPathSearchArgs = { path: string; withHiddenFiles: boolean; order?: EphemeralPathOrder | null }
+export type EphemeralPathSearchArgs = { path: string; withHiddenFiles: boolean; withIgnoreFiles?: boolean; order?: EphemeralPathOrder | null }
 
 export type EphemeralPathsResultItem = { entries: Reference<ExplorerItem>[]; errors: Error[]; nodes: CacheNode[] }
 
@@ -259,15 +370,27 @@ export type FilePathCursor = { isDir: boolean; variant: FilePathCursorVariant }
 
 export type FilePathCursorVariant = "none" | { name: CursorOrderItem<string> } | { sizeInBytes: SortOrder } | { dateCreated: CursorOrderItem<string> } | { dateModified: CursorOrderItem<string> } | { dateIndexed: CursorOrderItem<string> } | { object: FilePathObjectCursor }
 
-export type FilePathFilterArgs = { locations: InOrNotIn<number> } | { path: { location_id: number; path: string; include_descendants: boolean } } | { name: TextMatch } | { extension: InOrNotIn<string> } | { createdAt: Range<string> } | { modifiedAt: Range<string> } | { indexedAt: Range<string> } | { hidden: boolean }
+export type FilePathFilterArgs = { locations: InOrNotIn<number> } | { path: { location_id: number; path: string; include_descendants: boolean } } | { name: TextMatch } | { extension: InOrNotIn<string> } | { createdAt: Range<string> } | { modifiedAt: Range<string> } | { indexedAt: Range<string> } | { hidden: boolean } | 
+/**
+ * Sizes are strings as Javascript numbers can't hold every `u64`
+ */
+{ sizeInBytes: Range<string> } | 
+/**
+ * Words or quoted phrases which must all appear in the file's contents
+ */
+{ content: string }
 
 export type FilePathObjectCursor = { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
 
 export type FilePathOrder = { field: "name"; value: SortOrder } | { field: "sizeInBytes"; value: SortOrder } | { field: "dateCreated"; value: SortOrder } | { field: "dateModified"; value: SortOrder } | { field: "dateIndexed"; value: SortOrder } | { field: "object"; value: ObjectOrder }
 
-export type FilePathSearchArgs = { take?: number | null; orderAndPagination?: OrderAndPagination<number, FilePathOrder, FilePathCursor> | null; filters?: SearchFilterArgs[]; groupDirectories?: boolean }
+export type FilePathSearchArgs = { take?: number | null; orderAndPagination?: OrderAndPagination<number, FilePathOrder, FilePathCursor> | null; filters?: SearchFilterArgs[]; 
+/**
+ * Text query like `kind:image ext:!raw`, parsed into more filters
+ */
+query?: string | null; groupDirectories?: boolean }
 
-export type FilePathWithObject = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null } | null }
+export type FilePathWithObject = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; perceptual_hash: number[] | null } | null }
 
 export type Flash = { 
 /**
@@ -314,6 +437,12 @@ export type FlashMode =
  */
 "Forced"
 
+export type FolderSyncPlan = { operations: SyncOperation[]; 
+/**
+ * Files which differ on both sides and will be left untouched
+ */
+conflicts: string[] }
+
 export type FromPattern = { pattern: string; replace_all: boolean }
 
 export type FullRescanArgs = { location_id: number; reidentify_objects: boolean }
@@ -322,14 +451,42 @@ export type GenerateLabelsForLocationArgs = { id: number; path: string; regenera
 
 export type GenerateThumbsForLocationArgs = { id: number; path: string; regenerate?: boolean }
 
+/**
+ * Bounding box in degrees, wrapping around the antimeridian when `min_longitude` is greater than
+ * `max_longitude`
+ */
+export type GeoBoundingBox = { minLatitude: number; maxLatitude: number; minLongitude: number; maxLongitude: number }
+
 export type GetAll = { backups: Backup[]; directory: string }
 
 export type HardwareModel = "Other" | "MacStudio" | "MacBookAir" | "MacBookPro" | "MacBook" | "MacMini" | "MacPro" | "IMac" | "IMacPro" | "IPad" | "IPhone" | "Simulator" | "Android"
 
+export type HdrFormat = "Hdr10" | "Hlg" | "DolbyVision"
+
 export type IdentifyUniqueFilesArgs = { id: number; path: string }
 
+export type ImageConverterArgs = { location_id: number; file_path_ids: number[]; desired_extension: ConvertibleExtension; 
+/**
+ * Only used by lossy formats, from 1% to 100%
+ */
+quality_percentage: number | null; resize: ImageResize | null; conflict_policy: ConversionConflictPolicy; 
+/**
+ * If set, we delete the source images after converting them
+ */
+delete_src: boolean }
+
 export type ImageMetadata = { resolution: Resolution; date_taken: MediaDate | null; location: MediaLocation | null; camera_data: CameraData; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null }
 
+export type ImageResize = 
+/**
+ * Scales both dimensions by this percentage, from 1% to 125%
+ */
+{ Percentage: number } | 
+/**
+ * Downscales the image so its largest dimension is at most this many pixels
+ */
+{ MaxDimension: number }
+
 export type InOrNotIn<T> = { in: T[] } | { notIn: T[] }
 
 export type IndexerRule = { id: number; pub_id: number[]; name: string | null; default: boolean | null; rules_per_kind: number[] | null; date_created: string | null; date_modified: string | null }
@@ -343,6 +500,10 @@ export type IndexerRule = { id: number; pub_id: number[]; name: string | null; d
  * 
  * In case of `RuleKind::AcceptIfChildrenDirectoriesArePresent` or `RuleKind::RejectIfChildrenDirectoriesArePresent` the
  * `parameters` field must be a vector of strings containing the names of the directories.
+ * 
+ * The remaining kinds take the formats described in the `metadata` module: extended attributes as
+ * `name` or `name=value`, sizes and modification ages as `min..max` ranges (`1MB..`, `..30d`),
+ * permissions as `uid:1000`, `gid:20` or `mode:002`, and ignore files by their names (`.gitignore`).
  */
 export type IndexerRuleCreateArgs = { name: string; dry_run: boolean; rules: ([RuleKind, string[]])[] }
 
@@ -358,6 +519,15 @@ export type JobStatus = "Queued" | "Running" | "Completed" | "Canceled" | "Faile
 
 export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
 
+/**
+ * Which copy of a duplicated file is kept
+ */
+export type KeepPolicy = "Oldest" | "Newest" | 
+/**
+ * Keeps the oldest copy in the given location, or the oldest copy overall if there is none there
+ */
+{ PreferredLocation: number }
+
 export type KindStatistic = { kind: number; name: string; count: number; total_bytes: string }
 
 export type KindStatistics = { statistics: KindStatistic[] }
@@ -393,7 +563,7 @@ instance_id: number;
  */
 cloud_id?: string | null; generate_sync_operations?: boolean; version: LibraryConfigVersion }
 
-export type LibraryConfigVersion = "V0" | "V1" | "V2" | "V3" | "V4" | "V5" | "V6" | "V7" | "V8" | "V9"
+export type LibraryConfigVersion = "V0" | "V1" | "V2" | "V3" | "V4" | "V5" | "V6" | "V7" | "V8" | "V9" | "V10"
 
 export type LibraryConfigWrapped = { uuid: string; instance_id: string; instance_public_key: RemoteIdentity; config: LibraryConfig }
 
@@ -405,7 +575,7 @@ export type LightScanArgs = { location_id: number; sub_path: string }
 
 export type Listener2 = { id: string; name: string; addrs: string[] }
 
-export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; instance_id: number | null }
+export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; respect_ignore_files: boolean | null; date_created: string | null; instance_id: number | null }
 
 /**
  * `LocationCreateArgs` is the argument received from the client using `rspc` to create a new location.
@@ -424,13 +594,23 @@ export type LocationSettings = { explorer: ExplorerSettings<FilePathOrder> }
  * It is important to note that only the indexer rule ids in this vector will be used from now on.
  * Old rules that aren't in this vector will be purged.
  */
-export type LocationUpdateArgs = { id: number; name: string | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; indexer_rules_ids: number[]; path: string | null }
+export type LocationUpdateArgs = { id: number; name: string | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; respect_ignore_files: boolean | null; indexer_rules_ids: number[]; path: string | null }
 
-export type LocationWithIndexerRule = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; instance_id: number | null; indexer_rules: Reference<IndexerRule>[] }
+export type LocationWithIndexerRule = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; respect_ignore_files: boolean | null; date_created: string | null; instance_id: number | null; indexer_rules: Reference<IndexerRule>[] }
 
 export type MaybeUndefined<T> = null | T
 
-export type MediaDataOrder = { field: "epochTime"; value: SortOrder }
+export type MediaDataFilterArgs = 
+/**
+ * Width times height, in millions of pixels
+ */
+{ megapixels: Range<number> } | { cameraMake: TextMatch } | { cameraModel: TextMatch } | 
+/**
+ * When the photo or video was taken
+ */
+{ takenAt: Range<string> } | { location: GeoBoundingBox } | { artist: TextMatch }
+
+export type MediaDataOrder = { field: "epochTime"; value: SortOrder } | { field: "duration"; value: SortOrder } | { field: "artist"; value: SortOrder } | { field: "album"; value: SortOrder } | { field: "trackNumber"; value: SortOrder } | { field: "bitrate"; value: SortOrder } | { field: "fps"; value: SortOrder }
 
 /**
  * This can be either naive with no TZ (`YYYY-MM-DD HH-MM-SS`) or UTC (`YYYY-MM-DD HH-MM-SS ±HHMM`),
@@ -485,23 +665,45 @@ export type NotificationId = { type: "library"; id: [string, number] } | { type:
 
 export type NotificationKind = "info" | "success" | "error" | "warning"
 
-export type Object = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null }
+export type Object = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; perceptual_hash: number[] | null }
 
 export type ObjectCursor = "none" | { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
 
-export type ObjectFilterArgs = { favorite: boolean } | { hidden: ObjectHiddenFilter } | { kind: InOrNotIn<number> } | { tags: InOrNotIn<number> } | { labels: InOrNotIn<number> } | { dateAccessed: Range<string> }
+export type ObjectFilterArgs = { favorite: boolean } | { hidden: ObjectHiddenFilter } | { kind: InOrNotIn<number> } | { tags: InOrNotIn<number> } | 
+/**
+ * Like `Tags`, but also matching the tags nested under the given ones
+ */
+{ tagsWithDescendants: InOrNotIn<number> } | { labels: InOrNotIn<number> } | { albums: InOrNotIn<number> } | { dateAccessed: Range<string> } | { mediaData: MediaDataFilterArgs }
 
 export type ObjectHiddenFilter = "exclude" | "include"
 
 export type ObjectOrder = { field: "dateAccessed"; value: SortOrder } | { field: "kind"; value: SortOrder } | { field: "mediaData"; value: MediaDataOrder }
 
-export type ObjectSearchArgs = { take: number; orderAndPagination?: OrderAndPagination<number, ObjectOrder, ObjectCursor> | null; filters?: SearchFilterArgs[] }
+export type ObjectSearchArgs = { take: number; orderAndPagination?: OrderAndPagination<number, ObjectOrder, ObjectCursor> | null; filters?: SearchFilterArgs[]; 
+/**
+ * Text query like `kind:image ext:!raw`, parsed into more filters
+ */
+query?: string | null }
 
 export type ObjectValidatorArgs = { id: number; path: string }
 
-export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: FilePath[] }
+export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; perceptual_hash: number[] | null; file_paths: FilePath[] }
 
-export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: Reference<FilePath>[] }
+export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; perceptual_hash: number[] | null; file_paths: Reference<FilePath>[] }
+
+export type OldArchiveCompressorJobInit = { location_id: number; file_path_ids: number[]; 
+/**
+ * Name of the archive without extension, defaults to the name of the first selected file
+ */
+name: string | null; format: ArchiveFormat }
+
+export type OldArchiveExtractorJobInit = { location_id: number; file_path_id: number }
+
+export type OldDuplicateReclaimerJobInit = { 
+/**
+ * Only redundant copies inside this location are reclaimed, as links can't cross file systems
+ */
+location_id: number; object_ids: number[]; keep_policy: KeepPolicy; action: ReclaimAction }
 
 export type OldFileCopierJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
 
@@ -511,6 +713,16 @@ export type OldFileDeleterJobInit = { location_id: number; file_path_ids: number
 
 export type OldFileEraserJobInit = { location_id: number; file_path_ids: number[]; passes: string }
 
+export type OldFolderSyncJobInit = { source_location_id: number; 
+/**
+ * Directory inside the source location to sync, the whole location if missing
+ */
+source_sub_path: string | null; target_location_id: number; 
+/**
+ * Directory inside the target location to sync, the whole location if missing
+ */
+target_sub_path: string | null; mode: SyncMode; conflict_policy: SyncConflictPolicy }
+
 /**
  * Represents the operating system which the remote peer is running.
  * This is not used internally and predominantly is designed to be used for display purposes by the embedding application.
@@ -523,10 +735,38 @@ export type Orientation = "Normal" | "CW90" | "CW180" | "CW270" | "MirroredVerti
 
 export type P2PDiscoveryState = "Everyone" | "ContactsOnly" | "Disabled"
 
-export type P2PEvent = { type: "PeerChange"; identity: RemoteIdentity; connection: ConnectionMethod; discovery: DiscoveryMethod; metadata: PeerMetadata } | { type: "PeerDelete"; identity: RemoteIdentity } | { type: "SpacedropRequest"; id: string; identity: RemoteIdentity; peer_name: string; files: string[] } | { type: "SpacedropProgress"; id: string; percent: number } | { type: "SpacedropTimedOut"; id: string } | { type: "SpacedropRejected"; id: string }
+export type P2PEvent = { type: "PeerChange"; identity: RemoteIdentity; connection: ConnectionMethod; discovery: DiscoveryMethod; metadata: PeerMetadata } | { type: "PeerDelete"; identity: RemoteIdentity } | { type: "SpacedropRequest"; id: string; identity: RemoteIdentity; peer_name: string; files: string[] } | { type: "SpacedropProgress"; id: string; percent: number } | { type: "SpacedropTimedOut"; id: string } | { type: "SpacedropRejected"; id: string } | { type: "SpacedropNotAllowed"; id: string } | { type: "Paired"; identity: RemoteIdentity }
+
+/**
+ * What a node shows to be paired with, either as text or as a QR code of its JSON
+ */
+export type PairingPayload = { identity: RemoteIdentity; code: string }
+
+export type ParsedSearchQuery = { filters: SearchFilterArgs[] } | { error: { message: string; span: Span } }
 
 export type PeerMetadata = { name: string; operating_system: OperatingSystem | null; device_model: HardwareModel | null; version: string | null }
 
+/**
+ * What a trusted peer is allowed to do with this node
+ */
+export type PeerPermissions = { 
+/**
+ * Send files to this node with Spacedrop
+ */
+spacedrop: boolean; 
+/**
+ * Folder its Spacedrops are saved to without asking, if any
+ */
+spacedrop_auto_accept: string | null; 
+/**
+ * Query this node over rspc and stream files from it
+ */
+remote_rspc: boolean; 
+/**
+ * Libraries it can sync with this node
+ */
+sync_libraries: string[] }
+
 export type PlusCode = string
 
 export type Port = null | number
@@ -541,6 +781,22 @@ export type Range<T> = { from: T } | { to: T }
  * 
  * If you use a `Reference` in a query, you *must* ensure the corresponding `CacheNode` is also in the query.
  */
+export type ReattachLocationArgs = { location_id: number; path: string }
+
+/**
+ * What to do with the redundant copies
+ */
+export type ReclaimAction = "Remove" | 
+/**
+ * Replaces the copies with hard links to the kept file
+ */
+"HardLink" | 
+/**
+ * Replaces the copies with copy-on-write clones of the kept file, only supported by some
+ * file systems like APFS, Btrfs and XFS
+ */
+"Reflink"
+
 export type Reference<T> = { __type: string; __id: string; "#type": T }
 
 export type RemoteIdentity = string
@@ -559,10 +815,38 @@ export type Resolution = { width: number; height: number }
 
 export type Response = { Start: { user_code: string; verification_url: string; verification_url_complete: string } } | "Complete" | { Error: string }
 
-export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent"
+export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent" | "AcceptFilesByXattr" | "RejectFilesByXattr" | "AcceptFilesBySize" | "RejectFilesBySize" | "AcceptFilesByModifiedAge" | "RejectFilesByModifiedAge" | "AcceptFilesByPermissions" | "RejectFilesByPermissions" | "RejectFilesByIgnoreFiles"
+
+export type RuleSet = { version: number; rules: RuleSetEntry[] }
+
+/**
+ * How many paths of a location the indexer would index with a rule set and how many it would skip
+ */
+export type RuleSetDryRun = { accepted: number; rejected: number; 
+/**
+ * The location has more paths than a dry run walks, so counts only cover part of it
+ */
+truncated: boolean }
+
+export type RuleSetDryRunArgs = { location_id: number; rule_set: string }
+
+/**
+ * A single indexer rule, named as it will show up in the library importing it
+ */
+export type RuleSetEntry = { name: string; rules: RuleSetRule[] }
+
+export type RuleSetImportArgs = { rule_set: string; skip_existing: boolean }
+
+export type RuleSetRule = { kind: RuleKind; parameters?: string[] }
 
 export type SavedSearch = { id: number; pub_id: number[]; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null }
 
+export type SavedSearchChanges = { added: Reference<ExplorerItem>[]; removed: number[]; nodes: CacheNode[] }
+
+export type SavedSearchCopyArgs = { id: number; targetLocationId: number; targetLocationRelativeDirectoryPath: string }
+
+export type SavedSearchExportArgs = { id: number; targetDir: string }
+
 export type SearchData<T> = { cursor: number[] | null; items: Reference<T>[]; nodes: CacheNode[] }
 
 export type SearchFilterArgs = { filePath: FilePathFilterArgs } | { object: ObjectFilterArgs }
@@ -571,29 +855,152 @@ export type SetFavoriteArgs = { id: number; favorite: boolean }
 
 export type SetNoteArgs = { id: number; note: string | null }
 
+export type SetPeerPermissionsArgs = { identity: RemoteIdentity; permissions: PeerPermissions }
+
+export type SimilarObjectsSearchArgs = { 
+/**
+ * How many of the 64 perceptual hash bits can differ for objects to be similar
+ */
+maxDistance?: number | null; take?: number | null; skip?: number; filters?: SearchFilterArgs[] }
+
+export type SimilarObjectsSearchData = { clusters: Reference<ExplorerItem>[][]; totalClusters: number; nodes: CacheNode[] }
+
 export type SingleInvalidateOperationEvent = { 
 /**
  * This fields are intentionally private.
  */
 key: string; arg: JsonValue; result: JsonValue | null }
 
+export type SnippetPart = { text: string; highlighted: boolean }
+
 export type SortOrder = "Asc" | "Desc"
 
+export type Space = { id: number; pub_id: number[]; name: string | null; description: string | null; date_created: string | null; date_modified: string | null }
+
+export type SpaceCreateArgs = { name: string; description?: string | null }
+
+/**
+ * Layout of an item on the canvas, fields left as `None` are kept as they are
+ */
+export type SpaceItemLayout = { object_id: number; x?: number | null; y?: number | null; width?: number | null; height?: number | null; z_index?: number | null }
+
+export type SpaceLayoutArgs = { space_id: number; items: SpaceItemLayout[] }
+
+export type SpaceLink = { id: number; pub_id: number[]; label: string | null; space_id: number | null; from_id: number | null; to_id: number | null; date_created: string | null }
+
+export type SpaceLinkCreateArgs = { space_id: number; from_object_id: number; to_object_id: number; label?: string | null }
+
+export type SpaceObjectsArgs = { space_id: number; object_ids: number[] }
+
+export type SpaceUpdateArgs = { id: number; name?: string | null; 
+/**
+ * An empty description clears it
+ */
+description?: string | null }
+
+export type SpaceWithLayout = { space: Space; items: { object_id: number; x: number | null; y: number | null; width: number | null; height: number | null; z_index: number | null }[]; links: SpaceLink[] }
+
 export type SpacedropArgs = { identity: RemoteIdentity; file_path: string[] }
 
+/**
+ * Position in the query, counted in characters
+ */
+export type Span = { start: number; end: number }
+
 export type Statistics = { id: number; date_captured: string; total_object_count: number; library_db_size: string; total_bytes_used: string; total_bytes_capacity: string; total_unique_bytes: string; total_bytes_free: string; preview_media_bytes: string }
 
 export type StatisticsResponse = { statistics: Statistics | null }
 
+export type SyncAction = 
+/**
+ * Copies a file from the other side, which doesn't exist on this side yet
+ */
+"Copy" | 
+/**
+ * Replaces the file on this side with the one on the other side
+ */
+"Overwrite" | 
+/**
+ * Renames a file on this side which was renamed on the other side
+ */
+{ Rename: { from: string } } | "Delete" | 
+/**
+ * Creates a directory which only exists on the other side, so empty ones are synced too
+ */
+"CreateDirectory" | 
+/**
+ * Deletes a directory missing from the other side, once everything inside it is gone
+ */
+"DeleteDirectory"
+
+/**
+ * What to do with files changed on both sides, only used by [`SyncMode::TwoWay`], as the source
+ * always wins when mirroring
+ */
+export type SyncConflictPolicy = 
+/**
+ * Keeps the most recently modified file, skipping files modified at the same time
+ */
+"KeepNewest" | "KeepSource" | "KeepTarget" | 
+/**
+ * Leaves both files untouched
+ */
+"Skip"
+
+export type SyncMode = 
+/**
+ * Makes the target an exact copy of the source, deleting files missing from the source
+ */
+"Mirror" | 
+/**
+ * Copies the files missing from each side to the other one, without deleting anything
+ */
+"TwoWay"
+
+/**
+ * A change to be applied on one of the sides, with paths relative to the synced directories
+ */
+export type SyncOperation = { side: SyncSide; action: SyncAction; path: string }
+
+export type SyncSide = "Source" | "Target"
+
 export type SystemLocations = { desktop: string | null; documents: string | null; downloads: string | null; pictures: string | null; music: string | null; videos: string | null }
 
-export type Tag = { id: number; pub_id: number[]; name: string | null; color: string | null; is_hidden: boolean | null; date_created: string | null; date_modified: string | null }
+export type Tag = { id: number; pub_id: number[]; name: string | null; color: string | null; is_hidden: boolean | null; parent_id: number | null; date_created: string | null; date_modified: string | null }
+
+export type TagCreateArgs = { name: string; color: string; parent_id?: number | null }
+
+export type TagRuleCondition = 
+/**
+ * Glob matched against the path of the file inside its location, like `Invoices/*.pdf`
+ */
+{ glob: string } | 
+/**
+ * Kind of the object, as in `sd_file_ext::kind::ObjectKind`
+ */
+{ kind: number } | 
+/**
+ * Pub id of the location holding the file, as ids aren't the same across instances
+ */
+{ location: string } | 
+/**
+ * Name of a label the object has
+ */
+{ label: string }
 
-export type TagCreateArgs = { name: string; color: string }
+export type TagRuleCreateArgs = { name: string; tag_id: number; conditions: TagRuleCondition[] }
+
+export type TagRuleItem = { id: number; name: string | null; tag_id: number | null; conditions: TagRuleCondition[] }
+
+export type TagSetParentArgs = { id: number; parent_id: number | null }
 
 export type TagUpdateArgs = { id: number; name: string | null; color: string | null }
 
-export type Target = { Object: number } | { FilePath: number }
+export type Target = { Object: number } | { FilePath: number } | 
+/**
+ * Everything currently matched by a saved search
+ */
+{ SavedSearch: number }
 
 export type TestingParams = { id: string; path: string }
 
@@ -601,8 +1008,22 @@ export type TextMatch = { contains: string } | { startsWith: string } | { endsWi
 
 export type ThumbnailerPreferences = { background_processing_percentage: number }
 
+export type TrustedPeer = { paired_at: string; permissions: PeerPermissions }
+
 export type UpdateThumbnailerPreferences = { background_processing_percentage: number }
 
-export type VideoMetadata = { duration: number | null; video_codec: string | null; audio_codec: string | null }
+export type VideoMetadata = { 
+/**
+ * Duration in milliseconds
+ */
+duration: number | null; resolution: Resolution; fps: number | null; 
+/**
+ * Overall bitrate of the container in bits per second
+ */
+bitrate: number | null; video_codec: string | null; audio_codec: string | null; video_streams: number; audio_streams: number; subtitle_streams: number; 
+/**
+ * Clockwise rotation in degrees that players apply to the video
+ */
+rotation: number | null; hdr_format: HdrFormat | null; date_created: MediaDate | null; location: MediaLocation | null }
 
 export type Volume = { name: string; mount_points: string[]; total_capacity: string; available_capacity: string; disk_type: DiskType; file_system: string | null; is_root_filesystem: boolean }
@@ -15,7 +15,7 @@ export const features = [
 
 // This defines which backend feature flags show up in the UI.
 // This is kinda a hack to not having the runtime array of possible features as Specta only exports the types.
-export const backendFeatures: BackendFeature[] = ['filesOverP2P', 'cloudSync'];
+export const backendFeatures: BackendFeature[] = ['filesOverP2P', 'cloudSync', 'verifiedDedup'];
 
 export type FeatureFlag = (typeof features)[number] | BackendFeature;
 